@@ -0,0 +1,128 @@
+use crate::object::{Binding, Object, ObjectError, Reader, Writer};
+
+mod test;
+
+/// Magic number at the start of every archive
+pub const ARCHIVE_MAGIC: &[u8; 4] = b"AARC";
+
+/// Version of the archive layout
+pub const ARCHIVE_VERSION: u16 = 2;
+
+/// Object stored in an archive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Member {
+    /// File name the object was added from (`strlen.o`)
+    pub name: String,
+    pub object: Object,
+}
+
+/// Static library: a collection of objects with an index of the global symbols they define.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Archive {
+    pub members: Vec<Member>,
+    /// Global symbol and the index of the member defining it, in member order.
+    /// Only the first definition of a name is indexed.
+    pub index: Vec<(String, usize)>,
+}
+
+impl Archive {
+    /// Bundles objects and builds the symbol index.
+    pub fn new(members: Vec<Member>) -> Archive {
+        let mut index: Vec<(String, usize)> = Vec::new();
+
+        for (member, Member { object, .. }) in members.iter().enumerate() {
+            for symbol in &object.symbols {
+                let defined = symbol.binding == Binding::Global && symbol.section.is_some();
+                if defined && !index.iter().any(|(name, _)| *name == symbol.name) {
+                    index.push((symbol.name.clone(), member));
+                }
+            }
+        }
+
+        Archive { members, index }
+    }
+
+    /// Member defining the global symbol `name`
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.index
+            .iter()
+            .find(|(symbol, _)| symbol == name)
+            .map(|(_, member)| *member)
+    }
+
+    /// Serializes the archive: magic, version, the symbol index, then every member as its
+    /// name and a length-prefixed object file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes.extend(ARCHIVE_MAGIC);
+        writer.u16(ARCHIVE_VERSION);
+
+        writer.u32(self.index.len() as u32);
+        for (name, member) in &self.index {
+            writer.string(name);
+            writer.u32(*member as u32);
+        }
+
+        writer.u32(self.members.len() as u32);
+        for member in &self.members {
+            writer.string(&member.name);
+            let bytes = member.object.to_bytes();
+            writer.u32(bytes.len() as u32);
+            writer.bytes.extend(bytes);
+        }
+
+        writer.bytes
+    }
+
+    /// Parses and validates an archive produced by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Archive, ObjectError> {
+        let mut reader = Reader { bytes, position: 0 };
+
+        if reader.take(4)? != ARCHIVE_MAGIC {
+            return Err(ObjectError::new("Not an asmrs archive.".to_string()));
+        }
+
+        let version = reader.u16()?;
+        if version != ARCHIVE_VERSION {
+            return Err(ObjectError::new(format!(
+                "Unsupported archive version {}.",
+                version
+            )));
+        }
+
+        let mut index = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = reader.string()?;
+            let member = reader.u32()? as usize;
+            index.push((name, member));
+        }
+
+        let mut members = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = reader.string()?;
+            let length = reader.u32()? as usize;
+            let object = Object::from_bytes(reader.take(length)?)?;
+            members.push(Member { name, object });
+        }
+
+        if let Some((name, _)) = index.iter().find(|(_, member)| *member >= members.len()) {
+            return Err(ObjectError::new(format!(
+                "Index entry '{}' points past the last member.",
+                name
+            )));
+        }
+
+        if reader.position != bytes.len() {
+            return Err(ObjectError::new(
+                "Trailing bytes after archive.".to_string(),
+            ));
+        }
+
+        Ok(Archive { members, index })
+    }
+}
+
+/// Whether `bytes` start like an archive rather than an object
+pub fn is_archive(bytes: &[u8]) -> bool {
+    bytes.starts_with(ARCHIVE_MAGIC)
+}
@@ -0,0 +1,73 @@
+#[cfg(test)]
+fn member(name: &str, symbols: &[&str]) -> crate::archive::Member {
+    use crate::{
+        archive::Member,
+        object::{Binding, Object, Section, Symbol},
+    };
+
+    let object = Object {
+        name: name.replace(".o", ".asm"),
+        sections: vec![Section {
+            name: ".text".to_string(),
+            alignment: 1,
+            data: vec![0xc3; symbols.len()],
+            size: symbols.len() as u32,
+        }],
+        symbols: symbols
+            .iter()
+            .enumerate()
+            .map(|(index, symbol)| Symbol {
+                name: symbol.to_string(),
+                binding: Binding::Global,
+                section: Some(0),
+                value: index as u32,
+            })
+            .collect(),
+        relocations: vec![],
+    };
+
+    Member {
+        name: name.to_string(),
+        object,
+    }
+}
+
+#[test]
+fn archive_index() {
+    use crate::archive::Archive;
+
+    let archive = Archive::new(vec![
+        member("string.o", &["strlen", "strcpy"]),
+        member("bios.o", &["putc", "strlen"]),
+    ]);
+
+    assert_eq!(
+        archive.index,
+        vec![
+            ("strlen".to_string(), 0),
+            ("strcpy".to_string(), 0),
+            ("putc".to_string(), 1),
+        ]
+    );
+    assert_eq!(archive.find("putc"), Some(1));
+    assert_eq!(archive.find("getc"), None);
+}
+
+#[test]
+fn archive_round_trip() {
+    use crate::archive::{is_archive, Archive};
+
+    let archive = Archive::new(vec![
+        member("string.o", &["strlen"]),
+        member("bios.o", &["putc"]),
+    ]);
+    let bytes = archive.to_bytes();
+
+    assert!(is_archive(&bytes));
+    assert_eq!(Archive::from_bytes(&bytes).unwrap(), archive);
+
+    let mut truncated = bytes.clone();
+    truncated.pop();
+    assert!(Archive::from_bytes(&truncated).is_err());
+    assert!(Archive::from_bytes(&archive.members[0].object.to_bytes()).is_err());
+}
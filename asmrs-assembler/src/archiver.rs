@@ -0,0 +1,78 @@
+use asmrs_assembler::{
+    archive::{Archive, Member},
+    object::{Binding, Object},
+};
+use std::{fs, path::Path};
+
+/// Usage text of the `archive` subcommand.
+pub const USAGE: &str = "Usage: asmrs-assembler archive create <output> <object>...\n       \
+asmrs-assembler archive list <archive>";
+
+/// Runs `archive create` or `archive list` (arguments after `archive`).
+/// Returns the text to print.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<String, String> {
+    let command = args.next().ok_or_else(|| "Expected command.".to_string())?;
+    let path = args
+        .next()
+        .ok_or_else(|| "Expected archive file.".to_string())?;
+
+    match command.as_str() {
+        "create" => {
+            let mut members = Vec::new();
+            for input in args {
+                let bytes = fs::read(&input)
+                    .map_err(|error| format!("Could not read '{}': {}", input, error))?;
+                let object =
+                    Object::from_bytes(&bytes).map_err(|error| format!("{}: {}", input, error))?;
+                let name = Path::new(&input)
+                    .file_name()
+                    .map_or(input.clone(), |name| name.to_string_lossy().into_owned());
+                members.push(Member { name, object });
+            }
+
+            if members.is_empty() {
+                return Err("Expected object files.".to_string());
+            }
+
+            let archive = Archive::new(members);
+            fs::write(&path, archive.to_bytes())
+                .map_err(|error| format!("Could not write '{}': {}", path, error))?;
+
+            Ok(String::new())
+        }
+        "list" => {
+            if let Some(arg) = args.next() {
+                return Err(format!("Unexpected argument: '{}'", arg));
+            }
+
+            let bytes =
+                fs::read(&path).map_err(|error| format!("Could not read '{}': {}", path, error))?;
+            let archive =
+                Archive::from_bytes(&bytes).map_err(|error| format!("{}: {}", path, error))?;
+
+            Ok(list(&archive))
+        }
+        _ => Err(format!("Unknown archive command: '{}'", command)),
+    }
+}
+
+/// Lists every member by name with the global symbols it defines and the ones it needs.
+fn list(archive: &Archive) -> String {
+    let mut output = String::new();
+
+    for (index, member) in archive.members.iter().enumerate() {
+        output.push_str(&format!("{}:\n", member.name));
+
+        for (name, _) in archive.index.iter().filter(|(_, member)| *member == index) {
+            output.push_str(&format!("    T {}\n", name));
+        }
+
+        for symbol in &member.object.symbols {
+            if symbol.binding == Binding::Extern {
+                output.push_str(&format!("    U {}\n", symbol.name));
+            }
+        }
+    }
+
+    output
+}
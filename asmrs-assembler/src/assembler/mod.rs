@@ -0,0 +1,964 @@
+use crate::encoder::{encode, Encoding, Instruction, Memory, Operand, Value};
+use crate::format::{Executable, FormatError};
+use crate::object::{self, Binding, Object, Relocation, RelocationKind, Symbol};
+use asmrs_parser::lexer::token::{
+    Cpu, DirectiveType, InstructionType, OperatorType, RegisterType, Token, TokenType,
+};
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+mod test;
+
+/// Section statements go to before the first `section` directive
+pub const DEFAULT_SECTION: &str = ".text";
+
+/// Output the program is assembled for
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// One image loaded at `origin`, which `org` overrides
+    Flat { origin: u16 },
+    /// Paragraph aligned sections, each addressed through its own segment (.exe)
+    Segmented,
+    /// Relocatable sections starting at 0; references the linker has to resolve become
+    /// relocations
+    Object,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// Processor the program has to run on
+    pub cpu: Cpu,
+    pub target: Target,
+}
+
+/// Section of the assembled program
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    /// Segment the section is addressed through, relative to the image start
+    pub segment: u16,
+    /// Offset of the first byte within the segment
+    pub address: u16,
+    /// Initialized contents
+    pub data: Vec<u8>,
+    /// Total size; bytes beyond `data` are reserved (`resb`, `resw`)
+    pub size: u32,
+}
+
+/// Label and where it was defined
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Label {
+    /// Name; local labels (`.loop`) are prefixed with the label before them
+    pub name: String,
+    /// Index of the defining section
+    pub section: usize,
+    /// Offset within the section
+    pub offset: u32,
+    /// Visible outside its file: exported with `global` in an object, any label but local
+    /// ones (`.loop`) otherwise
+    pub global: bool,
+}
+
+/// Statement and the bytes it was assembled to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Placement {
+    /// First token of the statement, for its source position
+    pub token: Token,
+    /// Index of the section the statement is in
+    pub section: usize,
+    /// Offset within the section
+    pub offset: u32,
+    pub bytes: Vec<u8>,
+    /// Symbols the statement refers to
+    pub references: Vec<String>,
+}
+
+/// Assembled program
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Assembly {
+    /// Offset the image is loaded at
+    pub origin: u16,
+    /// Sections in the order of their first use
+    pub sections: Vec<Section>,
+    pub labels: Vec<Label>,
+    /// Locations (segment:offset) of words holding a segment value, for the MZ relocations
+    pub segment_fixups: Vec<(u16, u16)>,
+    /// Segment:offset set by `entry`
+    pub entry: Option<(u16, u16)>,
+    /// Symbols imported with `extern`
+    pub externs: Vec<String>,
+    /// Fixups left to the linker; symbols index the labels followed by the externs
+    pub relocations: Vec<Relocation>,
+    /// Every statement in source order
+    pub placements: Vec<Placement>,
+}
+
+impl Assembly {
+    /// Sections placed back to back from the origin. Space reserved at the very end is
+    /// left out, reserved space between sections is zero filled.
+    pub fn image(&self) -> Vec<u8> {
+        let mut image = Vec::new();
+
+        for section in self
+            .sections
+            .iter()
+            .filter(|section| !section.data.is_empty())
+        {
+            image.resize(self.position(section) as usize, 0);
+            image.extend(&section.data);
+        }
+
+        image
+    }
+
+    /// MZ layout of a program assembled for `Target::Segmented`. The stack is the `.stack`
+    /// section if there is one.
+    pub fn executable(&self) -> Result<Executable, FormatError> {
+        let entry = self
+            .entry
+            .ok_or_else(|| FormatError::new("No entry point; set one with 'entry'.".to_string()))?;
+        let size = self
+            .sections
+            .last()
+            .map_or(0, |section| self.position(section) + section.size);
+        let stack = self
+            .sections
+            .iter()
+            .find(|section| section.name == ".stack")
+            .map(|section| (section.segment, section.size));
+
+        Ok(Executable::new(
+            self.image(),
+            size,
+            self.segment_fixups.clone(),
+            entry,
+            stack,
+        ))
+    }
+
+    /// Relocatable object of a program assembled for `Target::Object`. Every label becomes
+    /// a symbol, global if it is exported.
+    pub fn object(&self, name: &str) -> Object {
+        let sections = self
+            .sections
+            .iter()
+            .map(|section| object::Section {
+                name: section.name.clone(),
+                alignment: 1,
+                data: section.data.clone(),
+                size: section.size,
+            })
+            .collect();
+
+        let labels = self.labels.iter().map(|label| Symbol {
+            name: label.name.clone(),
+            binding: if label.global {
+                Binding::Global
+            } else {
+                Binding::Local
+            },
+            section: Some(label.section),
+            value: label.offset,
+        });
+        let externs = self.externs.iter().map(|name| Symbol {
+            name: name.clone(),
+            binding: Binding::Extern,
+            section: None,
+            value: 0,
+        });
+
+        Object {
+            name: name.to_string(),
+            sections,
+            symbols: labels.chain(externs).collect(),
+            relocations: self.relocations.clone(),
+        }
+    }
+
+    /// Position of a section in the image
+    fn position(&self, section: &Section) -> u32 {
+        section.segment as u32 * 16 + section.address as u32 - self.origin as u32
+    }
+
+    /// Address of a label within the segment
+    pub fn address(&self, label: &Label) -> u16 {
+        self.sections[label.section].address + label.offset as u16
+    }
+}
+
+/// Statement of a source line
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Kind {
+    Label(String),
+    Instruction(Instruction),
+    /// `db` and `dw`, encoded while parsing
+    Data(Encoding),
+    /// `resb` and `resw`, in bytes
+    Reserve(u32),
+    Org(u16),
+    Section(String),
+    Entry(String),
+    Global(Vec<String>),
+    Extern(Vec<String>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Statement {
+    kind: Kind,
+    /// First token, for error positions
+    token: Token,
+}
+
+/// Assembles `tokens` into machine code. Jumps are short where the target is in range and
+/// grow to near jumps otherwise, unless their distance is given explicitly.
+pub fn assemble(tokens: &[Token], options: &Options) -> Result<Assembly, AssembleError> {
+    let statements = parse(tokens)?;
+
+    let segmented = options.target == Target::Segmented;
+    let relocatable = options.target == Target::Object;
+    let mut origin = match options.target {
+        Target::Flat { origin } => origin,
+        Target::Segmented | Target::Object => 0,
+    };
+    let mut origins = statements
+        .iter()
+        .filter_map(|statement| match statement.kind {
+            Kind::Org(origin) => Some((origin, &statement.token)),
+            _ => None,
+        });
+    if let Some((value, token)) = origins.next() {
+        if segmented || relocatable {
+            return Err(AssembleError::at(
+                "'org' needs flat output.".to_string(),
+                token,
+            ));
+        }
+        origin = value;
+    }
+    if let Some((_, token)) = origins.next() {
+        return Err(AssembleError::at("Duplicate 'org'.".to_string(), token));
+    }
+
+    let mut entries = statements
+        .iter()
+        .filter_map(|statement| match &statement.kind {
+            Kind::Entry(name) => Some((name, &statement.token)),
+            _ => None,
+        });
+    let entry = entries.next();
+    if let Some((_, token)) = entry.filter(|_| !segmented) {
+        return Err(AssembleError::at(
+            "'entry' needs exe output.".to_string(),
+            token,
+        ));
+    }
+    if let Some((_, token)) = entries.next() {
+        return Err(AssembleError::at("Duplicate 'entry'.".to_string(), token));
+    }
+
+    // symbols shared with other objects
+    let mut globals = Vec::<(&String, &Token)>::new();
+    let mut externs = Vec::<(&String, &Token)>::new();
+    for statement in &statements {
+        let (names, shared) = match &statement.kind {
+            Kind::Global(names) => (names, &mut globals),
+            Kind::Extern(names) => (names, &mut externs),
+            _ => continue,
+        };
+        for name in names {
+            if !shared.iter().any(|(other, _)| *other == name) {
+                shared.push((name, &statement.token));
+            }
+        }
+    }
+
+    // sections in the order of first use, statements go to the last one named
+    let mut names = Vec::<(String, &Token)>::new();
+    let mut current = None;
+    let mut placement = Vec::with_capacity(statements.len());
+    for statement in &statements {
+        if let Kind::Section(name) = &statement.kind {
+            current = Some(section_index(&mut names, name, &statement.token));
+        } else if current.is_none()
+            && !matches!(
+                statement.kind,
+                Kind::Org(_) | Kind::Entry(_) | Kind::Global(_) | Kind::Extern(_)
+            )
+        {
+            current = Some(section_index(&mut names, DEFAULT_SECTION, &statement.token));
+        }
+        placement.push(current.unwrap_or_default());
+    }
+    if let (true, Some(statement)) = (names.is_empty(), statements.first()) {
+        section_index(&mut names, DEFAULT_SECTION, &statement.token);
+    }
+
+    let mut near = vec![false; statements.len()];
+
+    loop {
+        let mut encodings = Vec::with_capacity(statements.len());
+        for (statement, near) in statements.iter().zip(&near) {
+            encodings.push(match &statement.kind {
+                Kind::Instruction(instruction) => encode(instruction, options.cpu, *near)
+                    .map_err(|message| AssembleError::at(message, &statement.token))?,
+                Kind::Data(encoding) => encoding.clone(),
+                _ => Encoding::default(),
+            });
+        }
+
+        // offsets of statements and labels within their sections
+        let mut sizes = vec![0u32; names.len()];
+        let mut offsets = Vec::with_capacity(statements.len());
+        let mut labels = Vec::<Label>::new();
+        let mut indices = HashMap::new();
+        for (index, statement) in statements.iter().enumerate() {
+            let section = placement[index];
+            offsets.push(sizes[section]);
+
+            match &statement.kind {
+                Kind::Label(name) => {
+                    if indices.insert(name.clone(), labels.len()).is_some() {
+                        return Err(AssembleError::at(
+                            format!("Duplicate label: '{}'", name),
+                            &statement.token,
+                        ));
+                    }
+                    labels.push(Label {
+                        name: name.clone(),
+                        section,
+                        offset: sizes[section],
+                        global: if relocatable {
+                            globals.iter().any(|(global, _)| *global == name)
+                        } else {
+                            !name.contains('.')
+                        },
+                    });
+                }
+                Kind::Reserve(size) => sizes[section] += size,
+                _ => sizes[section] += encodings[index].bytes.len() as u32,
+            }
+        }
+
+        // flat: back to back from the origin; segmented: every section starts a paragraph;
+        // object: every section starts at 0
+        let mut sections = Vec::with_capacity(names.len());
+        let mut address = origin as u32;
+        let mut segment = 0u32;
+        for ((name, token), size) in names.iter().zip(&sizes) {
+            if segmented {
+                segment += address.div_ceil(16);
+                address = 0;
+            } else if relocatable {
+                address = 0;
+            }
+            if address + size > 0x10000 || segment * 16 + size > 0x100000 {
+                return Err(AssembleError::at(
+                    format!(
+                        "Section '{}' ends beyond the 64 KiB segment at {:#x}.",
+                        name,
+                        address + size
+                    ),
+                    token,
+                ));
+            }
+            sections.push(Section {
+                name: name.clone(),
+                segment: segment as u16,
+                address: address as u16,
+                data: Vec::new(),
+                size: *size,
+            });
+            address += size;
+        }
+
+        let mut grown = false;
+        // only final once no jump grows, as growing moves the code behind it
+        let mut out_of_range = None;
+        let mut segment_fixups = Vec::new();
+        let mut relocations = Vec::new();
+        for (index, statement) in statements.iter().enumerate() {
+            let encoding = &mut encodings[index];
+            let section = &sections[placement[index]];
+            let address = section.address as u32 + offsets[index];
+
+            for fixup in &encoding.fixups {
+                // objects resolve jumps within a section, everything else is up to the linker
+                if let (true, Some(symbol)) = (relocatable, &fixup.symbol) {
+                    let relative = matches!(
+                        fixup.kind,
+                        RelocationKind::Relative8 | RelocationKind::Relative16
+                    );
+                    let label = indices.get(symbol).copied();
+                    let symbol = match label {
+                        Some(label) if relative && labels[label].section == placement[index] => {
+                            None
+                        }
+                        Some(label) => Some(label),
+                        None => externs
+                            .iter()
+                            .position(|(name, _)| *name == symbol)
+                            .map(|position| labels.len() + position),
+                    };
+
+                    if let Some(symbol) = symbol {
+                        if fixup.kind == RelocationKind::Relative8 && is_growable(statement) {
+                            near[index] = true;
+                            grown = true;
+                            continue;
+                        }
+                        relocations.push(Relocation {
+                            section: placement[index],
+                            offset: offsets[index] + fixup.offset as u32,
+                            symbol,
+                            kind: fixup.kind,
+                            addend: fixup.addend,
+                        });
+                        continue;
+                    }
+                }
+
+                let (segment, target) = match &fixup.symbol {
+                    Some(symbol) => {
+                        let label = indices.get(symbol).map(|index| &labels[*index]);
+                        let label = label.ok_or_else(|| {
+                            AssembleError::at(
+                                format!("Undefined symbol: '{}'", symbol),
+                                &statement.token,
+                            )
+                        })?;
+                        let target = &sections[label.section];
+                        (target.segment, target.address as i32 + label.offset as i32)
+                    }
+                    None => (section.segment, 0),
+                };
+                let target = target + fixup.addend;
+
+                if segment != section.segment
+                    && matches!(
+                        fixup.kind,
+                        RelocationKind::Relative8 | RelocationKind::Relative16
+                    )
+                {
+                    return Err(AssembleError::at(
+                        format!(
+                            "Jump to '{}' crosses segments.",
+                            fixup.symbol.as_deref().unwrap_or_default()
+                        ),
+                        &statement.token,
+                    ));
+                }
+
+                let field = &mut encoding.bytes[fixup.offset..];
+                match fixup.kind {
+                    RelocationKind::Absolute => {
+                        field[..2].copy_from_slice(&(target as u16).to_le_bytes())
+                    }
+                    RelocationKind::Relative8 => {
+                        // offsets wrap around within the 64 KiB segment
+                        let next = address as i32 + fixup.offset as i32 + 1;
+                        let displacement = (target - next) as u16 as i16 as i32;
+                        match i8::try_from(displacement) {
+                            Ok(displacement) => field[0] = displacement as u8,
+                            Err(_) if is_growable(statement) => {
+                                near[index] = true;
+                                grown = true;
+                            }
+                            Err(_) => {
+                                out_of_range.get_or_insert_with(|| {
+                                    AssembleError::at(
+                                        format!(
+                                            "Short jump target is out of range by {} bytes.",
+                                            displacement.abs()
+                                                - if displacement < 0 { 128 } else { 127 }
+                                        ),
+                                        &statement.token,
+                                    )
+                                });
+                            }
+                        }
+                    }
+                    RelocationKind::Relative16 => {
+                        let displacement = target - (address as i32 + fixup.offset as i32 + 2);
+                        field[..2].copy_from_slice(&(displacement as u16).to_le_bytes())
+                    }
+                    RelocationKind::Segment if segmented => {
+                        field[..2].copy_from_slice(&segment.to_le_bytes());
+                        segment_fixups
+                            .push((section.segment, (address + fixup.offset as u32) as u16));
+                    }
+                    RelocationKind::Segment => {
+                        return Err(AssembleError::at(
+                            "'seg' needs exe output.".to_string(),
+                            &statement.token,
+                        ))
+                    }
+                }
+            }
+        }
+
+        if grown {
+            continue;
+        }
+        if let Some(error) = out_of_range {
+            return Err(error);
+        }
+
+        for (index, encoding) in encodings.iter().enumerate() {
+            if encoding.bytes.is_empty() {
+                continue;
+            }
+            let data = &mut sections[placement[index]].data;
+            data.resize(offsets[index] as usize, 0);
+            data.extend(&encoding.bytes);
+        }
+
+        let placements = statements
+            .iter()
+            .zip(encodings)
+            .enumerate()
+            .map(|(index, (statement, encoding))| Placement {
+                token: statement.token.clone(),
+                section: placement[index],
+                offset: offsets[index],
+                references: encoding
+                    .fixups
+                    .iter()
+                    .filter_map(|fixup| fixup.symbol.clone())
+                    .collect(),
+                bytes: encoding.bytes,
+            })
+            .collect();
+
+        for (name, token) in &globals {
+            if !indices.contains_key(*name) {
+                return Err(AssembleError::at(
+                    format!("Undefined symbol: '{}'", name),
+                    token,
+                ));
+            }
+        }
+        for (name, token) in &externs {
+            if indices.contains_key(*name) {
+                return Err(AssembleError::at(
+                    format!("Symbol '{}' is both defined and extern.", name),
+                    token,
+                ));
+            }
+        }
+
+        let entry = match entry {
+            Some((name, token)) => {
+                let label = indices.get(name).map(|index| &labels[*index]);
+                let label = label.ok_or_else(|| {
+                    AssembleError::at(format!("Undefined symbol: '{}'", name), token)
+                })?;
+                let section = &sections[label.section];
+                Some((section.segment, section.address + label.offset as u16))
+            }
+            None => None,
+        };
+
+        return Ok(Assembly {
+            origin,
+            sections,
+            labels,
+            segment_fixups,
+            entry,
+            externs: externs.iter().map(|(name, _)| name.to_string()).collect(),
+            relocations,
+            placements,
+        });
+    }
+}
+
+/// Jumps without an explicit distance become near jumps when the target is out of range.
+fn is_growable(statement: &Statement) -> bool {
+    matches!(
+        &statement.kind,
+        Kind::Instruction(Instruction {
+            r#type: InstructionType::Jmp,
+            distance: None,
+            ..
+        })
+    )
+}
+
+/// Index of section `name`, which is added when `token` uses it first.
+fn section_index<'a>(names: &mut Vec<(String, &'a Token)>, name: &str, token: &'a Token) -> usize {
+    names
+        .iter()
+        .position(|(other, _)| other == name)
+        .unwrap_or_else(|| {
+            names.push((name.to_string(), token));
+            names.len() - 1
+        })
+}
+
+/// Splits `tokens` into statements, line by line.
+fn parse(tokens: &[Token]) -> Result<Vec<Statement>, AssembleError> {
+    let mut statements = Vec::new();
+    let mut scope = None::<String>;
+
+    for line in tokens.chunk_by(|first, second| first.line_index() == second.line_index()) {
+        let mut rest = line;
+
+        while let [token, remaining @ ..] = rest {
+            let TokenType::Label(name) = token.r#type() else {
+                break;
+            };
+            let name = name.trim_end_matches(':');
+            if !name.starts_with('.') {
+                scope = Some(name.to_string());
+            }
+
+            statements.push(Statement {
+                kind: Kind::Label(qualify(name, &scope)),
+                token: token.clone(),
+            });
+            rest = remaining;
+        }
+
+        let [token, operands @ ..] = rest else {
+            continue;
+        };
+
+        let kind = match token.r#type() {
+            TokenType::Instruction(r#type) => {
+                Kind::Instruction(parse_instruction(*r#type, operands, token, &scope)?)
+            }
+            TokenType::Directive(directive) => {
+                parse_directive(*directive, operands, token, &scope)?
+            }
+            // es lodsb
+            TokenType::Register(RegisterType::Segment(segment)) => {
+                let r#type = match operands.first().map(Token::r#type) {
+                    Some(TokenType::Instruction(r#type)) => *r#type,
+                    _ => {
+                        return Err(AssembleError::at(
+                            "Expected instruction after segment override.".to_string(),
+                            token,
+                        ))
+                    }
+                };
+                let mut instruction =
+                    parse_instruction(r#type, &operands[1..], &operands[0], &scope)?;
+                if instruction.segment.is_some() {
+                    return Err(AssembleError::at("Repeated prefix.".to_string(), token));
+                }
+                instruction.segment = Some(*segment);
+                Kind::Instruction(instruction)
+            }
+            _ => {
+                return Err(AssembleError::at(
+                    "Expected instruction, directive or label.".to_string(),
+                    token,
+                ))
+            }
+        };
+
+        statements.push(Statement {
+            kind,
+            token: token.clone(),
+        });
+    }
+
+    Ok(statements)
+}
+
+fn parse_instruction(
+    r#type: InstructionType,
+    tokens: &[Token],
+    token: &Token,
+    scope: &Option<String>,
+) -> Result<Instruction, AssembleError> {
+    let mut instruction = Instruction {
+        prefix: None,
+        segment: None,
+        r#type,
+        distance: None,
+        operands: Vec::new(),
+    };
+    let mut tokens = tokens;
+
+    // rep es movsb
+    if let [next, rest @ ..] = tokens {
+        if let (TokenType::Register(RegisterType::Segment(segment)), Some(following)) =
+            (next.r#type(), rest.first())
+        {
+            if matches!(following.r#type(), TokenType::Instruction(_)) {
+                instruction.segment = Some(*segment);
+                tokens = rest;
+            }
+        }
+    }
+
+    // rep movsb, lock xchg [bx], ax
+    if let [next, rest @ ..] = tokens {
+        if let TokenType::Instruction(next) = next.r#type() {
+            if !matches!(
+                r#type,
+                InstructionType::Rep | InstructionType::Repne | InstructionType::Lock
+            ) {
+                return Err(AssembleError::at(
+                    "Expected one instruction per line.".to_string(),
+                    token,
+                ));
+            }
+            instruction.prefix = Some(r#type);
+            instruction.r#type = *next;
+            tokens = rest;
+        }
+    }
+
+    if let [next, rest @ ..] = tokens {
+        if let TokenType::Operator(
+            distance @ (OperatorType::Far | OperatorType::Near | OperatorType::Short),
+        ) = next.r#type()
+        {
+            instruction.distance = Some(*distance);
+            tokens = rest;
+        }
+    }
+
+    for group in operands(tokens, token)? {
+        instruction.operands.push(
+            parse_operand(group, scope).map_err(|message| AssembleError::at(message, &group[0]))?,
+        );
+    }
+
+    Ok(instruction)
+}
+
+fn parse_operand(tokens: &[Token], scope: &Option<String>) -> Result<Operand, String> {
+    let types = tokens.iter().map(Token::r#type).collect::<Vec<_>>();
+
+    let operand = match types[..] {
+        [TokenType::Register(register)] => Operand::Register(*register),
+        [TokenType::Constant(constant)] => Operand::Immediate(Value::constant(*constant as i32)),
+        [TokenType::Identifier(symbol)] => Operand::Immediate(Value {
+            symbol: Some(qualify(symbol, scope)),
+            ..Value::default()
+        }),
+        [TokenType::Operator(OperatorType::Seg), TokenType::Identifier(symbol)] => {
+            Operand::Immediate(Value {
+                symbol: Some(qualify(symbol, scope)),
+                constant: 0,
+                segment: true,
+            })
+        }
+        [TokenType::String(string)] => Operand::Immediate(Value::constant(character(string)?)),
+        [TokenType::Constant(segment), TokenType::Colon, TokenType::Constant(offset)] => {
+            Operand::Far {
+                segment: *segment,
+                offset: *offset,
+            }
+        }
+        [TokenType::MemoryLocation(address)] => Operand::Memory(Memory {
+            displacement: Value::constant(*address as i32),
+            ..Memory::default()
+        }),
+        [TokenType::Size(size), TokenType::MemoryLocation(address)] => Operand::Memory(Memory {
+            size: Some(*size),
+            displacement: Value::constant(*address as i32),
+            ..Memory::default()
+        }),
+        [TokenType::Memory(memory)] | [TokenType::Size(_), TokenType::Memory(memory)] => {
+            Operand::Memory(Memory {
+                size: match types[0] {
+                    TokenType::Size(size) => Some(*size),
+                    _ => None,
+                },
+                segment: memory.segment,
+                base: memory.base,
+                index: memory.index,
+                displacement: Value {
+                    symbol: memory.symbol.as_ref().map(|symbol| qualify(symbol, scope)),
+                    constant: memory.displacement,
+                    segment: false,
+                },
+            })
+        }
+        _ => return Err("Expected register, memory, constant or symbol operand.".to_string()),
+    };
+
+    Ok(operand)
+}
+
+fn parse_directive(
+    directive: DirectiveType,
+    tokens: &[Token],
+    token: &Token,
+    scope: &Option<String>,
+) -> Result<Kind, AssembleError> {
+    let single = || match tokens {
+        [single] => Ok(single.r#type()),
+        _ => Err(AssembleError::at(
+            format!("Expected one operand after '{:?}'.", directive).to_lowercase(),
+            token,
+        )),
+    };
+
+    let kind = match directive {
+        DirectiveType::Db | DirectiveType::Dw => {
+            let wide = directive == DirectiveType::Dw;
+            let mut encoding = Encoding::default();
+
+            for group in operands(tokens, token)? {
+                let value = match group.iter().map(Token::r#type).collect::<Vec<_>>()[..] {
+                    [TokenType::String(string)] => {
+                        encoding.bytes.extend(string.bytes());
+                        if wide && string.len() % 2 == 1 {
+                            encoding.byte(0);
+                        }
+                        continue;
+                    }
+                    [TokenType::Constant(constant)] => Value::constant(*constant as i32),
+                    [TokenType::Identifier(symbol)] => Value {
+                        symbol: Some(qualify(symbol, scope)),
+                        ..Value::default()
+                    },
+                    [TokenType::Operator(OperatorType::Seg), TokenType::Identifier(symbol)] => {
+                        Value {
+                            symbol: Some(qualify(symbol, scope)),
+                            constant: 0,
+                            segment: true,
+                        }
+                    }
+                    _ => {
+                        return Err(AssembleError::at(
+                            "Expected constant, string or symbol.".to_string(),
+                            &group[0],
+                        ))
+                    }
+                };
+                encoding
+                    .value(&value, wide)
+                    .map_err(|message| AssembleError::at(message, &group[0]))?;
+            }
+
+            Kind::Data(encoding)
+        }
+        DirectiveType::Resb | DirectiveType::Resw => match single()? {
+            TokenType::Constant(count) => Kind::Reserve(
+                *count as u32
+                    * if directive == DirectiveType::Resw {
+                        2
+                    } else {
+                        1
+                    },
+            ),
+            _ => return Err(AssembleError::at("Expected count.".to_string(), token)),
+        },
+        DirectiveType::Org => match single()? {
+            TokenType::Constant(origin) => Kind::Org(*origin),
+            _ => return Err(AssembleError::at("Expected origin.".to_string(), token)),
+        },
+        DirectiveType::Section => match single()? {
+            TokenType::Identifier(name) => Kind::Section(name.clone()),
+            _ => {
+                return Err(AssembleError::at(
+                    "Expected section name.".to_string(),
+                    token,
+                ))
+            }
+        },
+        DirectiveType::Entry => match single()? {
+            TokenType::Identifier(name) => Kind::Entry(qualify(name, scope)),
+            _ => {
+                return Err(AssembleError::at(
+                    "Expected entry label.".to_string(),
+                    token,
+                ))
+            }
+        },
+        DirectiveType::Global | DirectiveType::Extern => {
+            let mut names = Vec::new();
+            for group in operands(tokens, token)? {
+                match group.iter().map(Token::r#type).collect::<Vec<_>>()[..] {
+                    [TokenType::Identifier(name)] => names.push(qualify(name, scope)),
+                    _ => return Err(AssembleError::at("Expected symbol.".to_string(), &group[0])),
+                }
+            }
+            if names.is_empty() {
+                return Err(AssembleError::at("Expected symbol.".to_string(), token));
+            }
+
+            if directive == DirectiveType::Global {
+                Kind::Global(names)
+            } else {
+                Kind::Extern(names)
+            }
+        }
+    };
+
+    Ok(kind)
+}
+
+/// Splits operands at commas; `token` is the mnemonic or directive they belong to.
+fn operands<'a>(tokens: &'a [Token], token: &Token) -> Result<Vec<&'a [Token]>, AssembleError> {
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let groups = tokens
+        .split(|token| *token.r#type() == TokenType::Comma)
+        .collect::<Vec<_>>();
+    if groups.iter().any(|group| group.is_empty()) {
+        return Err(AssembleError::at("Expected operand.".to_string(), token));
+    }
+
+    Ok(groups)
+}
+
+/// Prefixes local labels (`.loop`) with the label they belong to.
+fn qualify(name: &str, scope: &Option<String>) -> String {
+    match scope {
+        Some(scope) if name.starts_with('.') => format!("{}{}", scope, name),
+        _ => name.to_string(),
+    }
+}
+
+/// Value of a character constant ('A', 'ab'); the first character is the low byte.
+fn character(string: &str) -> Result<i32, String> {
+    match string.as_bytes() {
+        [low] => Ok(*low as i32),
+        [low, high] => Ok(u16::from_le_bytes([*low, *high]) as i32),
+        _ => Err(format!(
+            "Expected one or two characters in '{}' as operand.",
+            string
+        )),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AssembleError {
+    message: String,
+    line_index: usize,
+    char_index: usize,
+}
+
+impl AssembleError {
+    /// Creates a new Assemble Error with the given message, line number and column number.
+    pub fn new(message: String, line_index: usize, char_index: usize) -> AssembleError {
+        Self {
+            message,
+            line_index,
+            char_index,
+        }
+    }
+
+    fn at(message: String, token: &Token) -> AssembleError {
+        Self::new(message, token.line_index(), token.char_index())
+    }
+}
+
+impl Display for AssembleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Assemble Error: at line: {}, column: {}: {}",
+            self.line_index, self.char_index, self.message
+        )
+    }
+}
+
+impl Error for AssembleError {}
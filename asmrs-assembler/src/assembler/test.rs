@@ -0,0 +1,315 @@
+#[cfg(test)]
+fn assemble_flat(source: &str) -> Result<crate::assembler::Assembly, String> {
+    use crate::assembler::{assemble, Options, Target};
+    use asmrs_parser::lexer::{token::Cpu, tokenize};
+
+    let tokens = tokenize(source.to_string()).map_err(|error| error.to_string())?;
+    let options = Options {
+        cpu: Cpu::I8086,
+        target: Target::Flat { origin: 0 },
+    };
+    assemble(&tokens, &options).map_err(|error| error.to_string())
+}
+
+#[test]
+fn assemble_program() {
+    use crate::assembler::test::assemble_flat;
+
+    let source = "\
+org 0x100
+start:
+    mov ax, 0x1234
+    mov bl, [bx+si+4]
+    mov [es:bx], al
+    add ax, 5
+    add ax, 0x1000
+    cmp byte [bp], 1
+    rep movsb
+    jmp start
+    int 0x21
+    mov dx, message // data behind the code
+message: db \"Hi\", 0
+";
+
+    let assembly = assemble_flat(source).unwrap();
+    assert_eq!(assembly.origin, 0x100);
+    assert_eq!(
+        assembly.image(),
+        [
+            0xb8, 0x34, 0x12, 0x8a, 0x58, 0x04, 0x26, 0x88, 0x07, 0x83, 0xc0, 0x05, 0x05, 0x00,
+            0x10, 0x80, 0x7e, 0x00, 0x01, 0xf3, 0xa4, 0xeb, 0xe9, 0xcd, 0x21, 0xba, 0x1c, 0x01,
+            b'H', b'i', 0x00
+        ]
+    );
+    assert_eq!(assembly.address(&assembly.labels[1]), 0x11c);
+}
+
+#[test]
+fn assemble_jumps() {
+    use crate::assembler::test::assemble_flat;
+
+    // out of range jumps grow to near jumps, reserved space between is zero filled
+    let assembly = assemble_flat("jmp end\nresb 0x100\nend: hlt").unwrap();
+    let mut image = vec![0xe9, 0x00, 0x01];
+    image.extend([0; 0x100]);
+    image.push(0xf4);
+    assert_eq!(assembly.image(), image);
+
+    // local labels belong to the label before them
+    let source = "a:\n.loop: dec cx\n    jnz .loop\nb:\n.loop: jmp .loop";
+    let assembly = assemble_flat(source).unwrap();
+    assert_eq!(assembly.image(), [0x49, 0x75, 0xfd, 0xeb, 0xfe]);
+    let names = assembly
+        .labels
+        .iter()
+        .map(|label| label.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["a", "a.loop", "b", "b.loop"]);
+
+    // the range of later jumps is checked once the code behind a grown jump has moved
+    let assembly = assemble_flat("jmp 0x200\njz 0x84").unwrap();
+    assert_eq!(assembly.image(), [0xe9, 0xfd, 0x01, 0x74, 0x7f]);
+
+    // offsets wrap around within the segment
+    let assembly = assemble_flat("nop\njmp 0xff83").unwrap();
+    assert_eq!(assembly.image(), [0x90, 0xeb, 0x80]);
+
+    assert_eq!(
+        assemble_flat("jz end\nresb 0x100\nend: hlt").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: Short jump target is out of range by 129 bytes."
+    );
+}
+
+#[test]
+fn assemble_errors() {
+    use crate::assembler::test::assemble_flat;
+
+    assert_eq!(
+        assemble_flat("start: nop\nstart: nop").unwrap_err(),
+        "Assemble Error: at line: 1, column: 0: Duplicate label: 'start'"
+    );
+    assert_eq!(
+        assemble_flat("call print").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: Undefined symbol: 'print'"
+    );
+    assert_eq!(
+        assemble_flat("mov al, 0x100").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: Constant 0x100 does not fit into a byte."
+    );
+    assert_eq!(
+        assemble_flat("mov [bx], 1").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: Operand size required, e.g. 'byte [bx]' or 'word [bx]'."
+    );
+    assert_eq!(
+        assemble_flat("cs mov ax, bx").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: 'mov' has no memory operand for segment override 'cs'."
+    );
+    assert_eq!(
+        assemble_flat("es mov [ds:bx], ax").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: Conflicting segment overrides."
+    );
+}
+
+#[test]
+fn assemble_segment_override() {
+    use crate::assembler::test::assemble_flat;
+
+    let assembly = assemble_flat("cs lodsb\nrep es movsb\nss xlat\nds mov ax, [bp]").unwrap();
+    assert_eq!(
+        assembly.image(),
+        [0x2e, 0xac, 0xf3, 0x26, 0xa4, 0x36, 0xd7, 0x3e, 0x8b, 0x46, 0x00]
+    );
+}
+
+#[test]
+fn assemble_floating_point() {
+    use crate::assembler::test::assemble_flat;
+
+    let source = "\
+fld dword [0x600]
+fild word [bx+0x4]
+fmulp st(1), st
+fsqrt
+fistp word [0x606]
+fcom st(2)
+fcompp
+fnstsw [0x608]
+fsub st(3), st
+fdiv st, st(2)
+fstp tword [bp+si]
+finit
+";
+    let assembly = assemble_flat(source).unwrap();
+    assert_eq!(
+        assembly.image(),
+        [
+            0xd9, 0x06, 0x00, 0x06, 0xdf, 0x47, 0x04, 0xde, 0xc9, 0xd9, 0xfa, 0xdf, 0x1e, 0x06,
+            0x06, 0xd8, 0xd2, 0xde, 0xd9, 0xdd, 0x3e, 0x08, 0x06, 0xdc, 0xeb, 0xd8, 0xf2, 0xdb,
+            0x3a, 0x9b, 0xdb, 0xe3
+        ]
+    );
+
+    assert_eq!(
+        assemble_flat("fld [0x600]").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: Operand size required for 'fld'."
+    );
+    assert_eq!(
+        assemble_flat("fiadd qword [0x600]").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: Expected word or dword memory after 'fiadd'."
+    );
+}
+
+#[test]
+fn assemble_segmented() {
+    use crate::assembler::{assemble, test::assemble_flat, Options, Target};
+    use asmrs_parser::lexer::{token::Cpu, tokenize};
+
+    let assemble_segmented = |source: &str| {
+        let tokens = tokenize(source.to_string()).map_err(|error| error.to_string())?;
+        let options = Options {
+            cpu: Cpu::I8086,
+            target: Target::Segmented,
+        };
+        assemble(&tokens, &options).map_err(|error| error.to_string())
+    };
+
+    let source = "\
+entry start
+section .text
+start:
+    mov ax, seg message
+    mov ds, ax
+    mov dx, message
+    hlt
+section .data
+message: db \"Hi\"
+    dw seg start
+section .stack
+    resb 0x100
+";
+    let assembly = assemble_segmented(source).unwrap();
+    // every section starts a paragraph and is addressed from offset 0
+    assert_eq!(
+        assembly.image(),
+        [
+            0xb8, 0x01, 0x00, 0x8e, 0xd8, 0xba, 0x00, 0x00, 0xf4, 0, 0, 0, 0, 0, 0, 0, b'H', b'i',
+            0x00, 0x00
+        ]
+    );
+    assert_eq!(assembly.segment_fixups, [(0, 1), (1, 2)]);
+    assert_eq!(assembly.entry, Some((0, 0)));
+
+    let executable = assembly.executable().unwrap();
+    assert_eq!(executable.entry, (0, 0));
+    assert_eq!(executable.stack, (2, 0x100));
+    assert_eq!(executable.min_alloc, 0x10);
+
+    assert_eq!(
+        assemble_segmented("nop")
+            .unwrap()
+            .executable()
+            .unwrap_err()
+            .to_string(),
+        "Format Error: No entry point; set one with 'entry'."
+    );
+    assert_eq!(
+        assemble_segmented("entry main\nnop").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: Undefined symbol: 'main'"
+    );
+    assert_eq!(
+        assemble_segmented("org 0x100\nnop").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: 'org' needs flat output."
+    );
+    assert_eq!(
+        assemble_segmented("jmp there\nsection .far\nthere: ret").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: Jump to 'there' crosses segments."
+    );
+    assert_eq!(
+        assemble_flat("mov ax, seg start\nstart: ret").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: 'seg' needs exe output."
+    );
+    assert_eq!(
+        assemble_flat("entry start\nstart: ret").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: 'entry' needs exe output."
+    );
+}
+
+#[test]
+fn assemble_object() {
+    use crate::assembler::{assemble, test::assemble_flat, Options, Target};
+    use crate::object::{Binding, Relocation, RelocationKind};
+    use asmrs_parser::lexer::{token::Cpu, tokenize};
+
+    let assemble_object = |source: &str| {
+        let tokens = tokenize(source.to_string()).map_err(|error| error.to_string())?;
+        let options = Options {
+            cpu: Cpu::I8086,
+            target: Target::Object,
+        };
+        assemble(&tokens, &options).map_err(|error| error.to_string())
+    };
+
+    let source = "\
+global start
+extern print
+start:
+    call print
+    mov dx, message
+    jmp print
+.loop: jmp .loop
+section .data
+message: db \"hi\"
+";
+    let object = assemble_object(source).unwrap().object("main.asmrs");
+    assert_eq!(object.name, "main.asmrs");
+    // jumps within the section are resolved, the one to 'print' grows to near
+    assert_eq!(
+        object.sections[0].data,
+        [0xe8, 0x00, 0x00, 0xba, 0x00, 0x00, 0xe9, 0x00, 0x00, 0xeb, 0xfe]
+    );
+    assert_eq!(object.sections[1].data, b"hi");
+
+    let symbols = object
+        .symbols
+        .iter()
+        .map(|symbol| (symbol.name.as_str(), symbol.binding, symbol.section))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        symbols,
+        [
+            ("start", Binding::Global, Some(0)),
+            ("start.loop", Binding::Local, Some(0)),
+            ("message", Binding::Local, Some(1)),
+            ("print", Binding::Extern, None),
+        ]
+    );
+
+    let relocation = |offset, symbol, kind| Relocation {
+        section: 0,
+        offset,
+        symbol,
+        kind,
+        addend: 0,
+    };
+    assert_eq!(
+        object.relocations,
+        [
+            relocation(1, 3, RelocationKind::Relative16),
+            relocation(4, 2, RelocationKind::Absolute),
+            relocation(7, 3, RelocationKind::Relative16),
+        ]
+    );
+
+    assert_eq!(
+        assemble_object("global main\nstart: ret").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: Undefined symbol: 'main'"
+    );
+    assert_eq!(
+        assemble_object("extern start\nstart: ret").unwrap_err(),
+        "Assemble Error: at line: 0, column: 0: Symbol 'start' is both defined and extern."
+    );
+    assert_eq!(
+        assemble_flat("extern print\ncall print").unwrap_err(),
+        "Assemble Error: at line: 1, column: 0: Undefined symbol: 'print'"
+    );
+}
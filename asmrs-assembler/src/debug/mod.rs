@@ -0,0 +1,401 @@
+use crate::assembler::Assembly;
+use asmrs_parser::lexer::token::Token;
+use std::{error::Error, fmt::Display};
+
+mod test;
+
+/// First line of every debug file, followed by the format version
+pub const DEBUG_HEADER: &str = "asmrs-debug";
+
+/// Version of the debug file layout
+pub const DEBUG_VERSION: u16 = 1;
+
+/// Bytes emitted for one source position
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineEntry {
+    pub segment: u16,
+    pub offset: u16,
+    /// Number of bytes emitted
+    pub length: u16,
+    /// Index into `DebugInfo::files`
+    pub file: usize,
+    /// Line within the file (0-based, like `Token`)
+    pub line_index: usize,
+    /// Column within the line (0-based, like `Token`)
+    pub char_index: usize,
+}
+
+impl LineEntry {
+    /// Whether the entry covers the byte at `segment:offset`
+    pub fn contains(&self, segment: u16, offset: u16) -> bool {
+        self.segment == segment && offset >= self.offset && (offset - self.offset) < self.length
+    }
+}
+
+/// Address range belonging to a label: from the label up to the next label of the same
+/// level. Local labels (`.loop`) are nested into the preceding global label.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Scope {
+    pub name: String,
+    pub segment: u16,
+    pub start: u16,
+    /// First offset after the scope
+    pub end: u16,
+    /// Index of the enclosing scope
+    pub parent: Option<usize>,
+}
+
+/// Named address with the scope it is visible in
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugSymbol {
+    pub name: String,
+    pub segment: u16,
+    pub offset: u16,
+    /// Index of the scope the symbol is local to, `None` for global symbols
+    pub scope: Option<usize>,
+}
+
+/// Debug sidecar (.dbg) mapping emitted bytes back to their source.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DebugInfo {
+    pub files: Vec<String>,
+    pub lines: Vec<LineEntry>,
+    pub scopes: Vec<Scope>,
+    pub symbols: Vec<DebugSymbol>,
+}
+
+impl DebugInfo {
+    pub fn new() -> DebugInfo {
+        Self::default()
+    }
+
+    /// Index of `file`, adding it if it is new
+    pub fn file(&mut self, file: &str) -> usize {
+        match self.files.iter().position(|name| name == file) {
+            Some(index) => index,
+            None => {
+                self.files.push(file.to_string());
+                self.files.len() - 1
+            }
+        }
+    }
+
+    /// Records the bytes emitted at `segment:offset` for the instruction starting at `token`.
+    pub fn push_line(
+        &mut self,
+        file: usize,
+        token: &Token,
+        segment: u16,
+        offset: u16,
+        length: u16,
+    ) {
+        self.lines.push(LineEntry {
+            segment,
+            offset,
+            length,
+            file,
+            line_index: token.line_index(),
+            char_index: token.char_index(),
+        });
+    }
+
+    /// Opens a scope and returns its index.
+    pub fn push_scope(&mut self, scope: Scope) -> usize {
+        self.scopes.push(scope);
+        self.scopes.len() - 1
+    }
+
+    pub fn push_symbol(&mut self, symbol: DebugSymbol) {
+        self.symbols.push(symbol);
+    }
+
+    /// Collects the debug information of `assembly`, assembled from the source file `file`.
+    /// Every label opens a scope; local labels (`.loop`) are nested into the label they
+    /// belong to.
+    pub fn from_assembly(assembly: &Assembly, file: &str) -> DebugInfo {
+        let mut debug = DebugInfo::new();
+        let file = debug.file(file);
+
+        for placement in &assembly.placements {
+            if placement.bytes.is_empty() {
+                continue;
+            }
+            let section = &assembly.sections[placement.section];
+            debug.push_line(
+                file,
+                &placement.token,
+                section.segment,
+                section.address.wrapping_add(placement.offset as u16),
+                placement.bytes.len() as u16,
+            );
+        }
+
+        let mut parent = None;
+        for (index, label) in assembly.labels.iter().enumerate() {
+            let section = &assembly.sections[label.section];
+            let local = label
+                .name
+                .split_once('.')
+                .filter(|(parent, _)| !parent.is_empty());
+
+            // up to the next label of the same level, nested ones end at any label
+            let end = assembly.labels[index + 1..]
+                .iter()
+                .find(|other| {
+                    other.section == label.section && (local.is_some() || !other.name.contains('.'))
+                })
+                .map_or(
+                    (section.address as u32 + section.size).min(0xffff) as u16,
+                    |other| assembly.address(other),
+                );
+
+            let (name, scope) = match local {
+                Some((_, name)) => (format!(".{}", name), parent),
+                None => (label.name.clone(), None),
+            };
+            let segment = section.segment;
+            let start = assembly.address(label);
+
+            let index = debug.push_scope(Scope {
+                name: name.clone(),
+                segment,
+                start,
+                end,
+                parent: scope,
+            });
+            if local.is_none() {
+                parent = Some(index);
+            }
+
+            debug.push_symbol(DebugSymbol {
+                name,
+                segment,
+                offset: start,
+                scope,
+            });
+        }
+
+        debug
+    }
+
+    /// Moves every address by `segment` paragraphs, for programs whose segments are only
+    /// known once they are loaded (.com, .exe).
+    pub fn relocate(&mut self, segment: u16) {
+        for line in &mut self.lines {
+            line.segment = line.segment.wrapping_add(segment);
+        }
+        for scope in &mut self.scopes {
+            scope.segment = scope.segment.wrapping_add(segment);
+        }
+        for symbol in &mut self.symbols {
+            symbol.segment = symbol.segment.wrapping_add(segment);
+        }
+    }
+
+    /// Source position of the byte at `segment:offset`
+    pub fn line_at(&self, segment: u16, offset: u16) -> Option<&LineEntry> {
+        self.lines
+            .iter()
+            .find(|line| line.contains(segment, offset))
+    }
+
+    /// First address emitted for a source line (0-based), e.g. to set a breakpoint
+    pub fn address_of(&self, file: &str, line_index: usize) -> Option<(u16, u16)> {
+        let file = self.files.iter().position(|name| name == file)?;
+        self.lines
+            .iter()
+            .find(|line| line.file == file && line.line_index == line_index)
+            .map(|line| (line.segment, line.offset))
+    }
+
+    /// Innermost scope containing `segment:offset`
+    pub fn scope_at(&self, segment: u16, offset: u16) -> Option<usize> {
+        // nested scopes are opened after their parent
+        self.scopes.iter().rposition(|scope| {
+            scope.segment == segment && offset >= scope.start && offset < scope.end
+        })
+    }
+
+    /// Symbol named `name` as seen from `segment:offset`: local symbols of the enclosing
+    /// scopes take precedence over global ones.
+    pub fn symbol(&self, name: &str, segment: u16, offset: u16) -> Option<&DebugSymbol> {
+        let mut scope = self.scope_at(segment, offset);
+
+        while let Some(index) = scope {
+            let local = self
+                .symbols
+                .iter()
+                .find(|symbol| symbol.name == name && symbol.scope == Some(index));
+            if local.is_some() {
+                return local;
+            }
+            scope = self.scopes[index].parent;
+        }
+
+        self.symbols
+            .iter()
+            .find(|symbol| symbol.name == name && symbol.scope.is_none())
+    }
+
+    /// Parses a debug file written by `to_string`.
+    pub fn parse(text: &str) -> Result<DebugInfo, DebugError> {
+        let mut debug = DebugInfo::new();
+        let mut lines = text.lines().enumerate();
+
+        let header = format!("{} {}", DEBUG_HEADER, DEBUG_VERSION);
+        if lines.next().map(|(_, line)| line) != Some(header.as_str()) {
+            return Err(DebugError::new("Not an asmrs debug file.".to_string(), 0));
+        }
+
+        for (line_index, line) in lines {
+            let error = |message: &str| DebugError::new(message.to_string(), line_index);
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+
+            match fields.as_slice() {
+                [] => {}
+                ["file", name] => debug.files.push(name.to_string()),
+                ["line", address, length, file, line, column] => {
+                    let (segment, offset) =
+                        parse_address(address).ok_or_else(|| error("Invalid address."))?;
+                    let entry = LineEntry {
+                        segment,
+                        offset,
+                        length: parse_hex(length).ok_or_else(|| error("Invalid length."))?,
+                        file: file.parse().map_err(|_| error("Invalid file index."))?,
+                        line_index: line.parse().map_err(|_| error("Invalid line."))?,
+                        char_index: column.parse().map_err(|_| error("Invalid column."))?,
+                    };
+                    if entry.file >= debug.files.len() {
+                        return Err(error("Unknown file index."));
+                    }
+                    debug.lines.push(entry);
+                }
+                ["scope", name, start, end, parent] => {
+                    let (segment, start) =
+                        parse_address(start).ok_or_else(|| error("Invalid address."))?;
+                    let end = parse_hex(end).ok_or_else(|| error("Invalid address."))?;
+                    let parent = parse_scope(parent, debug.scopes.len())
+                        .ok_or_else(|| error("Invalid parent scope."))?;
+                    debug.scopes.push(Scope {
+                        name: name.to_string(),
+                        segment,
+                        start,
+                        end,
+                        parent,
+                    });
+                }
+                ["symbol", name, address, scope] => {
+                    let (segment, offset) =
+                        parse_address(address).ok_or_else(|| error("Invalid address."))?;
+                    let scope = parse_scope(scope, debug.scopes.len())
+                        .ok_or_else(|| error("Invalid scope."))?;
+                    debug.symbols.push(DebugSymbol {
+                        name: name.to_string(),
+                        segment,
+                        offset,
+                        scope,
+                    });
+                }
+                _ => return Err(error("Unknown record.")),
+            }
+        }
+
+        Ok(debug)
+    }
+}
+
+/// One record per line: `file NAME`, `line SEG:OFF LENGTH FILE LINE COLUMN`,
+/// `scope NAME SEG:START END PARENT` and `symbol NAME SEG:OFF SCOPE`. Addresses are
+/// hexadecimal, indices decimal and `-` stands for no scope.
+impl Display for DebugInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} {}", DEBUG_HEADER, DEBUG_VERSION)?;
+
+        for file in &self.files {
+            writeln!(f, "file {}", file)?;
+        }
+
+        for line in &self.lines {
+            writeln!(
+                f,
+                "line {:04X}:{:04X} {:04X} {} {} {}",
+                line.segment, line.offset, line.length, line.file, line.line_index, line.char_index
+            )?;
+        }
+
+        for scope in &self.scopes {
+            writeln!(
+                f,
+                "scope {} {:04X}:{:04X} {:04X} {}",
+                scope.name,
+                scope.segment,
+                scope.start,
+                scope.end,
+                scope_name(scope.parent)
+            )?;
+        }
+
+        for symbol in &self.symbols {
+            writeln!(
+                f,
+                "symbol {} {:04X}:{:04X} {}",
+                symbol.name,
+                symbol.segment,
+                symbol.offset,
+                scope_name(symbol.scope)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn scope_name(scope: Option<usize>) -> String {
+    scope.map_or("-".to_string(), |scope| scope.to_string())
+}
+
+/// Parses a scope index, which has to refer to an earlier scope.
+fn parse_scope(text: &str, scopes: usize) -> Option<Option<usize>> {
+    if text == "-" {
+        return Some(None);
+    }
+    text.parse().ok().filter(|scope| *scope < scopes).map(Some)
+}
+
+fn parse_address(text: &str) -> Option<(u16, u16)> {
+    let (segment, offset) = text.split_once(':')?;
+    Some((parse_hex(segment)?, parse_hex(offset)?))
+}
+
+fn parse_hex(text: &str) -> Option<u16> {
+    u16::from_str_radix(text, 16).ok()
+}
+
+#[derive(Clone, Debug)]
+pub struct DebugError {
+    message: String,
+    line_index: usize,
+}
+
+impl DebugError {
+    /// Creates a new Debug Error with the given message and line index.
+    pub fn new(message: String, line_index: usize) -> DebugError {
+        Self {
+            message,
+            line_index,
+        }
+    }
+}
+
+impl Display for DebugError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Debug Error: at line: {}: {}",
+            self.line_index + 1,
+            self.message
+        )
+    }
+}
+
+impl Error for DebugError {}
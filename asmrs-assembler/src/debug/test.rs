@@ -0,0 +1,130 @@
+#[cfg(test)]
+fn debug_info() -> crate::debug::DebugInfo {
+    use crate::debug::{DebugInfo, DebugSymbol, Scope};
+    use asmrs_parser::lexer::token::{InstructionType, Token, TokenType};
+
+    let mut debug = DebugInfo::new();
+    let file = debug.file("main.asmrs");
+
+    // start:
+    //     mov cx, 10
+    // .loop:
+    //     loop .loop
+    //     ret
+    let mov = Token::new(TokenType::Instruction(InstructionType::Mov), 1, 4, 3);
+    let r#loop = Token::new(TokenType::Instruction(InstructionType::Loop), 3, 4, 4);
+    let ret = Token::new(TokenType::Instruction(InstructionType::Ret), 4, 4, 3);
+    debug.push_line(file, &mov, 0, 0x100, 3);
+    debug.push_line(file, &r#loop, 0, 0x103, 2);
+    debug.push_line(file, &ret, 0, 0x105, 1);
+
+    let start = debug.push_scope(Scope {
+        name: "start".to_string(),
+        segment: 0,
+        start: 0x100,
+        end: 0x106,
+        parent: None,
+    });
+    let local = debug.push_scope(Scope {
+        name: ".loop".to_string(),
+        segment: 0,
+        start: 0x103,
+        end: 0x106,
+        parent: Some(start),
+    });
+    debug.push_symbol(DebugSymbol {
+        name: "start".to_string(),
+        segment: 0,
+        offset: 0x100,
+        scope: None,
+    });
+    debug.push_symbol(DebugSymbol {
+        name: ".loop".to_string(),
+        segment: 0,
+        offset: 0x103,
+        scope: Some(start),
+    });
+    debug.push_symbol(DebugSymbol {
+        name: "count".to_string(),
+        segment: 0,
+        offset: 0x200,
+        scope: Some(local),
+    });
+
+    debug
+}
+
+#[test]
+fn debug_lookup() {
+    let debug = debug_info();
+
+    let line = debug.line_at(0, 0x104).unwrap();
+    assert_eq!((line.line_index, line.char_index), (3, 4));
+    assert!(debug.line_at(0, 0x106).is_none());
+
+    assert_eq!(debug.address_of("main.asmrs", 4), Some((0, 0x105)));
+    assert_eq!(debug.address_of("other.asmrs", 4), None);
+
+    assert_eq!(debug.scope_at(0, 0x101), Some(0));
+    assert_eq!(debug.scope_at(0, 0x104), Some(1));
+    assert_eq!(debug.scope_at(0, 0x106), None);
+
+    assert_eq!(debug.symbol(".loop", 0, 0x104).unwrap().offset, 0x103);
+    assert_eq!(debug.symbol("count", 0, 0x104).unwrap().offset, 0x200);
+    assert!(debug.symbol("count", 0, 0x101).is_none());
+    assert_eq!(debug.symbol("start", 0, 0x300).unwrap().offset, 0x100);
+}
+
+#[test]
+fn debug_round_trip() {
+    use crate::debug::DebugInfo;
+
+    let debug = debug_info();
+    let text = debug.to_string();
+
+    assert_eq!(
+        text.lines().take(3).collect::<Vec<_>>(),
+        [
+            "asmrs-debug 1",
+            "file main.asmrs",
+            "line 0000:0100 0003 0 1 4"
+        ]
+    );
+    assert_eq!(DebugInfo::parse(&text).unwrap(), debug);
+
+    let error = DebugInfo::parse("asmrs-debug 1\nline 0000:0100 0003 0 1 4").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Debug Error: at line: 2: Unknown file index."
+    );
+}
+
+#[test]
+fn debug_assembly() {
+    use crate::{
+        assembler::{assemble, Options, Target},
+        debug::{test::debug_info, DebugInfo},
+    };
+    use asmrs_parser::lexer::{token::Cpu, tokenize};
+
+    let source = "start:\n    mov cx, 10\n.loop:\n    loop .loop\n    ret\n";
+    let options = Options {
+        cpu: Cpu::I8086,
+        target: Target::Flat { origin: 0x100 },
+    };
+    let assembly = assemble(&tokenize(source.to_string()).unwrap(), &options).unwrap();
+
+    let mut debug = DebugInfo::from_assembly(&assembly, "main.asmrs");
+    let mut expected = debug_info();
+    expected.symbols.pop();
+    assert_eq!(debug, expected);
+
+    // a .com loaded at 1000h
+    debug.relocate(0x1000);
+    assert_eq!(debug.line_at(0x1000, 0x104).unwrap().line_index, 3);
+    assert_eq!(
+        debug.symbol(".loop", 0x1000, 0x104).unwrap().segment,
+        0x1000
+    );
+    assert_eq!(debug.scope_at(0x1000, 0x101), Some(0));
+}
@@ -0,0 +1,1154 @@
+use crate::object::RelocationKind;
+use asmrs_parser::lexer::token::{
+    Condition, Cpu, GeneralPurposeRegister, InstructionType, OperandSize, OperatorType,
+    RegisterType, SegmentRegister, SpecialPurposeRegister,
+};
+
+mod test;
+
+/// Instruction with its operands, as written in the source
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    /// Repeat or lock prefix
+    pub prefix: Option<InstructionType>,
+    /// Segment override written before the mnemonic (`es lodsb`)
+    pub segment: Option<SegmentRegister>,
+    pub r#type: InstructionType,
+    /// `short`, `near` or `far` written after the mnemonic
+    pub distance: Option<OperatorType>,
+    pub operands: Vec<Operand>,
+}
+
+/// Operand of an instruction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Register(RegisterType),
+    /// Constant, symbol or jump target
+    Immediate(Value),
+    Memory(Memory),
+    /// Direct far pointer segment:offset
+    Far {
+        segment: u16,
+        offset: u16,
+    },
+}
+
+/// Constant, optionally relative to a symbol
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Value {
+    pub symbol: Option<String>,
+    pub constant: i32,
+    /// Segment of the symbol instead of its offset (`seg symbol`)
+    pub segment: bool,
+}
+
+impl Value {
+    pub fn constant(constant: i32) -> Value {
+        Value {
+            constant,
+            ..Value::default()
+        }
+    }
+}
+
+/// Memory operand
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Memory {
+    /// Explicit size (`word [bx]`)
+    pub size: Option<OperandSize>,
+    /// Segment override
+    pub segment: Option<SegmentRegister>,
+    /// bx or bp
+    pub base: Option<RegisterType>,
+    /// si or di
+    pub index: Option<RegisterType>,
+    pub displacement: Value,
+}
+
+/// Field whose value depends on a symbol, left for the assembler or linker to fill in
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fixup {
+    /// Position of the field within the encoded bytes
+    pub offset: usize,
+    pub kind: RelocationKind,
+    /// Referenced symbol, `None` for jumps to the absolute offset in `addend`
+    pub symbol: Option<String>,
+    /// Constant added to the symbol value
+    pub addend: i32,
+}
+
+/// Machine code of an instruction or data directive
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Encoding {
+    pub bytes: Vec<u8>,
+    pub fixups: Vec<Fixup>,
+}
+
+impl Encoding {
+    pub fn byte(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    pub fn word(&mut self, word: u16) {
+        self.bytes.extend(word.to_le_bytes());
+    }
+
+    /// Appends a byte or word holding `value`; symbols get a fixup and a zero placeholder.
+    pub fn value(&mut self, value: &Value, wide: bool) -> Result<(), String> {
+        let Some(symbol) = &value.symbol else {
+            if value.segment {
+                return Err("Expected symbol after 'seg'.".to_string());
+            }
+            return if wide {
+                self.word(word(value.constant)?);
+                Ok(())
+            } else {
+                self.byte(byte(value.constant)?);
+                Ok(())
+            };
+        };
+
+        if !wide {
+            return Err(format!("Symbol '{}' does not fit into a byte.", symbol));
+        }
+
+        self.fixups.push(Fixup {
+            offset: self.bytes.len(),
+            kind: if value.segment {
+                RelocationKind::Segment
+            } else {
+                RelocationKind::Absolute
+            },
+            symbol: Some(symbol.clone()),
+            addend: value.constant,
+        });
+        self.word(0);
+        Ok(())
+    }
+
+    /// Appends a relative displacement to `target`, measured from the end of the field.
+    fn relative(&mut self, target: &Value, wide: bool) -> Result<(), String> {
+        if target.segment {
+            return Err("Jump targets cannot be segments.".to_string());
+        }
+
+        self.fixups.push(Fixup {
+            offset: self.bytes.len(),
+            kind: if wide {
+                RelocationKind::Relative16
+            } else {
+                RelocationKind::Relative8
+            },
+            symbol: target.symbol.clone(),
+            addend: target.constant,
+        });
+
+        if wide {
+            self.word(0);
+        } else {
+            self.byte(0);
+        }
+        Ok(())
+    }
+
+    /// Appends a ModRM byte with `reg` and the register or memory operand `rm`, followed
+    /// by its displacement.
+    fn modrm(&mut self, reg: u8, rm: &Operand) -> Result<(), String> {
+        let memory = match rm {
+            Operand::Register(register) => {
+                let (code, _) = general_purpose(*register)?;
+                self.byte(0b11 << 6 | reg << 3 | code);
+                return Ok(());
+            }
+            Operand::Memory(memory) => memory,
+            _ => return Err("Expected register or memory operand.".to_string()),
+        };
+
+        let bx = Some(RegisterType::GeneralPurpose(GeneralPurposeRegister::Bx));
+        let bp = Some(RegisterType::SpecialPurpose(SpecialPurposeRegister::Bp));
+        let si = Some(RegisterType::SpecialPurpose(SpecialPurposeRegister::Si));
+        let di = Some(RegisterType::SpecialPurpose(SpecialPurposeRegister::Di));
+
+        let rm = match (memory.base, memory.index) {
+            (None, None) => {
+                self.byte(reg << 3 | 0b110);
+                return self.value(&memory.displacement, true);
+            }
+            (base, index) if base == bx && index == si => 0,
+            (base, index) if base == bx && index == di => 1,
+            (base, index) if base == bp && index == si => 2,
+            (base, index) if base == bp && index == di => 3,
+            (None, index) if index == si => 4,
+            (None, index) if index == di => 5,
+            (base, None) if base == bp => 6,
+            (base, None) if base == bx => 7,
+            _ => return Err("Invalid base and index registers.".to_string()),
+        };
+
+        let displacement = &memory.displacement;
+        if displacement.symbol.is_some() {
+            self.byte(0b10 << 6 | reg << 3 | rm);
+            return self.value(displacement, true);
+        }
+
+        // [bp] has no encoding without displacement
+        let value = word(displacement.constant)? as i16;
+        if value == 0 && rm != 6 {
+            self.byte(reg << 3 | rm);
+        } else if let Ok(value) = i8::try_from(value) {
+            self.byte(0b01 << 6 | reg << 3 | rm);
+            self.byte(value as u8);
+        } else {
+            self.byte(0b10 << 6 | reg << 3 | rm);
+            self.word(value as u16);
+        }
+
+        Ok(())
+    }
+}
+
+/// Arithmetic/logic operations in the order of their encoding
+const ARITHMETIC: [InstructionType; 8] = [
+    InstructionType::Add,
+    InstructionType::Or,
+    InstructionType::Adc,
+    InstructionType::Sbb,
+    InstructionType::And,
+    InstructionType::Sub,
+    InstructionType::Xor,
+    InstructionType::Cmp,
+];
+
+/// 8087 arithmetic in the order of the reg field of D8h
+const FLOATING_POINT_ARITHMETIC: [InstructionType; 8] = [
+    InstructionType::Fadd,
+    InstructionType::Fmul,
+    InstructionType::Fcom,
+    InstructionType::Fcomp,
+    InstructionType::Fsub,
+    InstructionType::Fsubr,
+    InstructionType::Fdiv,
+    InstructionType::Fdivr,
+];
+
+/// 8087 integer arithmetic, in the same order
+const INTEGER_ARITHMETIC: [InstructionType; 8] = [
+    InstructionType::Fiadd,
+    InstructionType::Fimul,
+    InstructionType::Ficom,
+    InstructionType::Ficomp,
+    InstructionType::Fisub,
+    InstructionType::Fisubr,
+    InstructionType::Fidiv,
+    InstructionType::Fidivr,
+];
+
+/// Encodes an instruction for `cpu`. Jumps without an explicit distance are short unless
+/// `near` is set; symbols and jump targets are left as fixups.
+pub fn encode(instruction: &Instruction, cpu: Cpu, near: bool) -> Result<Encoding, String> {
+    use InstructionType as I;
+
+    let mut encoding = Encoding::default();
+
+    if let Some(prefix) = instruction.prefix {
+        encoding.byte(prefix_byte(prefix).ok_or_else(|| format!("'{}' is not a prefix.", prefix))?);
+    }
+
+    // segment overrides precede the opcode
+    let memory = instruction
+        .operands
+        .iter()
+        .find_map(|operand| match operand {
+            Operand::Memory(memory) => Some(memory),
+            _ => None,
+        });
+    let segment = match (instruction.segment, memory) {
+        (Some(segment), Some(memory)) if memory.segment.is_some_and(|other| other != segment) => {
+            return Err("Conflicting segment overrides.".to_string())
+        }
+        (Some(segment), None) if !instruction.r#type.has_implicit_memory() => {
+            return Err(format!(
+                "'{}' has no memory operand for segment override '{}'.",
+                instruction.r#type,
+                RegisterType::Segment(segment)
+            ))
+        }
+        (Some(segment), _) => Some(segment),
+        (None, memory) => memory.and_then(|memory| memory.segment),
+    };
+    if let Some(segment) = segment {
+        encoding.byte(0x26 | segment_code(segment) << 3);
+    }
+
+    let operands = instruction.operands.as_slice();
+    let r#type = instruction.r#type;
+
+    if let Some(distance) = instruction.distance {
+        let (name, allowed) = match distance {
+            OperatorType::Far => ("far", matches!(r#type, I::Jmp | I::Call)),
+            OperatorType::Near => ("near", matches!(r#type, I::Jmp | I::Call)),
+            OperatorType::Short => (
+                "short",
+                matches!(
+                    r#type,
+                    I::Jmp | I::Jcc(_) | I::Jcxz | I::Loop | I::Loope | I::Loopne
+                ),
+            ),
+            OperatorType::Seg => ("seg", false),
+        };
+        if !allowed {
+            return Err(format!("'{}' cannot be used with '{}'.", r#type, name));
+        }
+    }
+
+    if let Some(byte) = simple(r#type) {
+        expect_operands(operands, 0)?;
+        encoding.bytes.extend(byte);
+        return Ok(encoding);
+    }
+
+    if let Some(index) = ARITHMETIC.iter().position(|other| *other == r#type) {
+        arithmetic(&mut encoding, index as u8, operands)?;
+        return Ok(encoding);
+    }
+
+    if r#type.is_floating_point() {
+        floating_point(&mut encoding, r#type, operands)?;
+        return Ok(encoding);
+    }
+
+    match (r#type, operands) {
+        (I::Aam | I::Aad, _) => {
+            encoding.byte(if r#type == I::Aam { 0xd4 } else { 0xd5 });
+            match operands {
+                [] => encoding.byte(10),
+                [Operand::Immediate(base)] => encoding.value(base, false)?,
+                _ => return Err(format!("Expected base after '{}'.", r#type)),
+            }
+        }
+        (I::Ret | I::Retf, []) => encoding.byte(if r#type == I::Ret { 0xc3 } else { 0xcb }),
+        (I::Ret | I::Retf, [Operand::Immediate(count)]) => {
+            encoding.byte(if r#type == I::Ret { 0xc2 } else { 0xca });
+            encoding.value(count, true)?;
+        }
+        (I::Int, [Operand::Immediate(vector)]) => {
+            encoding.byte(0xcd);
+            encoding.value(vector, false)?;
+        }
+        (I::Esc, [Operand::Immediate(opcode), rm]) => {
+            let opcode = byte(opcode.constant)?;
+            if opcode > 63 || matches!(rm, Operand::Immediate(_) | Operand::Far { .. }) {
+                return Err("Expected 'esc 0-63, register or memory'.".to_string());
+            }
+            encoding.byte(0xd8 | opcode >> 3);
+            encoding.modrm(opcode & 0b111, rm)?;
+        }
+        (I::Test, [first, second]) => {
+            let (rm, other) = match (first, second) {
+                (Operand::Memory(_), _) | (_, Operand::Immediate(_)) => (first, second),
+                _ => (second, first),
+            };
+            let wide = width(first, second)?;
+            match other {
+                Operand::Register(register) => {
+                    let (reg, _) = general_purpose(*register)?;
+                    encoding.byte(0x84 | wide as u8);
+                    encoding.modrm(reg, rm)?;
+                }
+                Operand::Immediate(value) if is_accumulator(rm) => {
+                    encoding.byte(0xa8 | wide as u8);
+                    encoding.value(value, wide)?;
+                }
+                Operand::Immediate(value) => {
+                    encoding.byte(0xf6 | wide as u8);
+                    encoding.modrm(0, rm)?;
+                    encoding.value(value, wide)?;
+                }
+                _ => return Err(invalid(r#type)),
+            }
+        }
+        (I::Mov, [destination, source]) => mov(&mut encoding, destination, source)?,
+        (I::Xchg, [first, second]) => {
+            let wide = width(first, second)?;
+            match (first, second) {
+                (Operand::Register(register), other) | (other, Operand::Register(register))
+                    if wide && is_accumulator(other) && matches!(other, Operand::Register(_)) =>
+                {
+                    let (code, _) = general_purpose(*register)?;
+                    encoding.byte(0x90 | code);
+                }
+                (rm, Operand::Register(register)) | (Operand::Register(register), rm) => {
+                    let (reg, _) = general_purpose(*register)?;
+                    encoding.byte(0x86 | wide as u8);
+                    encoding.modrm(reg, rm)?;
+                }
+                _ => return Err(invalid(r#type)),
+            }
+        }
+        (I::Inc | I::Dec, [operand]) => {
+            let reg = (r#type == I::Dec) as u8;
+            match operand {
+                Operand::Register(register) if general_purpose(*register)?.1 => {
+                    encoding.byte(0x40 | reg << 3 | general_purpose(*register)?.0);
+                }
+                _ => {
+                    let wide = size(operand)?;
+                    encoding.byte(0xfe | wide as u8);
+                    encoding.modrm(reg, operand)?;
+                }
+            }
+        }
+        (I::Push | I::Pop, [operand]) => {
+            let push = r#type == I::Push;
+            match operand {
+                Operand::Register(RegisterType::Segment(segment)) => {
+                    if !push && *segment == SegmentRegister::Cs {
+                        return Err("Cannot pop into cs.".to_string());
+                    }
+                    encoding.byte(0x06 | segment_code(*segment) << 3 | !push as u8);
+                }
+                Operand::Register(register) => {
+                    let (code, wide) = general_purpose(*register)?;
+                    if !wide {
+                        return Err(format!("Cannot {} a byte register.", r#type));
+                    }
+                    encoding.byte(if push { 0x50 } else { 0x58 } | code);
+                }
+                Operand::Memory(memory) => {
+                    if memory.size.is_some_and(|size| size != OperandSize::Word) {
+                        return Err(format!("Can only {} words.", r#type));
+                    }
+                    encoding.byte(if push { 0xff } else { 0x8f });
+                    encoding.modrm(if push { 6 } else { 0 }, operand)?;
+                }
+                Operand::Immediate(value) if push => {
+                    require(cpu, Cpu::I80186, "push with an immediate")?;
+                    match sign_extended(value) {
+                        Some(value) => {
+                            encoding.byte(0x6a);
+                            encoding.byte(value);
+                        }
+                        None => {
+                            encoding.byte(0x68);
+                            encoding.value(value, true)?;
+                        }
+                    }
+                }
+                _ => return Err(invalid(r#type)),
+            }
+        }
+        (I::Not | I::Neg | I::Mul | I::Imul | I::Div | I::Idiv, [operand]) => {
+            let reg = [I::Not, I::Neg, I::Mul, I::Imul, I::Div, I::Idiv]
+                .iter()
+                .position(|other| *other == r#type)
+                .unwrap() as u8
+                + 2;
+            let wide = size(operand)?;
+            encoding.byte(0xf6 | wide as u8);
+            encoding.modrm(reg, operand)?;
+        }
+        (I::Imul, [Operand::Register(register), rest @ ..]) if !rest.is_empty() => {
+            require(cpu, Cpu::I80186, "imul with an immediate")?;
+            let (reg, wide) = general_purpose(*register)?;
+            let (rm, value) = match rest {
+                [Operand::Immediate(value)] => (&operands[0], value),
+                [rm, Operand::Immediate(value)] => (rm, value),
+                _ => return Err(invalid(r#type)),
+            };
+            if !wide || (rm != &operands[0] && size(rm).is_ok_and(|wide| !wide)) {
+                return Err("imul with an immediate needs word operands.".to_string());
+            }
+            match sign_extended(value) {
+                Some(byte) => {
+                    encoding.byte(0x6b);
+                    encoding.modrm(reg, rm)?;
+                    encoding.byte(byte);
+                }
+                None => {
+                    encoding.byte(0x69);
+                    encoding.modrm(reg, rm)?;
+                    encoding.value(value, true)?;
+                }
+            }
+        }
+        (
+            I::Rol | I::Ror | I::Rcl | I::Rcr | I::Shl | I::Sal | I::Shr | I::Sar,
+            [operand, count],
+        ) => {
+            let reg = match r#type {
+                I::Rol => 0,
+                I::Ror => 1,
+                I::Rcl => 2,
+                I::Rcr => 3,
+                I::Shl | I::Sal => 4,
+                I::Shr => 5,
+                _ => 7,
+            };
+            let wide = size(operand)?;
+            match count {
+                Operand::Register(RegisterType::GeneralPurpose(GeneralPurposeRegister::Cl)) => {
+                    encoding.byte(0xd2 | wide as u8);
+                    encoding.modrm(reg, operand)?;
+                }
+                Operand::Immediate(Value {
+                    symbol: None,
+                    constant: 1,
+                    ..
+                }) => {
+                    encoding.byte(0xd0 | wide as u8);
+                    encoding.modrm(reg, operand)?;
+                }
+                Operand::Immediate(count) => {
+                    require(cpu, Cpu::I80186, "shifts by an immediate other than 1")?;
+                    encoding.byte(0xc0 | wide as u8);
+                    encoding.modrm(reg, operand)?;
+                    encoding.value(count, false)?;
+                }
+                _ => return Err(format!("Expected 1, cl or a count after '{}'.", r#type)),
+            }
+        }
+        (I::Lea | I::Lds | I::Les | I::Bound, [Operand::Register(register), memory]) => {
+            let (reg, wide) = general_purpose(*register)?;
+            if !wide || !matches!(memory, Operand::Memory(_)) {
+                return Err(format!(
+                    "Expected 16-bit register and memory after '{}'.",
+                    r#type
+                ));
+            }
+            encoding.byte(match r#type {
+                I::Lea => 0x8d,
+                I::Lds => 0xc5,
+                I::Les => 0xc4,
+                _ => 0x62,
+            });
+            encoding.modrm(reg, memory)?;
+        }
+        (I::Jcc(_) | I::Jcxz | I::Loop | I::Loope | I::Loopne, [Operand::Immediate(target)]) => {
+            encoding.byte(match r#type {
+                I::Jcc(condition) => 0x70 | condition_code(condition),
+                I::Jcxz => 0xe3,
+                I::Loop => 0xe2,
+                I::Loope => 0xe1,
+                _ => 0xe0,
+            });
+            encoding.relative(target, false)?;
+        }
+        (I::Jmp | I::Call, [Operand::Far { segment, offset }]) => {
+            encoding.byte(if r#type == I::Call { 0x9a } else { 0xea });
+            encoding.word(*offset);
+            encoding.word(*segment);
+        }
+        (I::Jmp | I::Call, [Operand::Immediate(target)]) => {
+            if instruction.distance == Some(OperatorType::Far) {
+                return Err("Far jumps need a segment:offset or memory operand.".to_string());
+            }
+            match (r#type, instruction.distance) {
+                (I::Call, Some(OperatorType::Short)) => unreachable!(),
+                (I::Call, _) => {
+                    encoding.byte(0xe8);
+                    encoding.relative(target, true)?;
+                }
+                (_, Some(OperatorType::Short)) => {
+                    encoding.byte(0xeb);
+                    encoding.relative(target, false)?;
+                }
+                (_, Some(OperatorType::Near)) => {
+                    encoding.byte(0xe9);
+                    encoding.relative(target, true)?;
+                }
+                _ => {
+                    encoding.byte(if near { 0xe9 } else { 0xeb });
+                    encoding.relative(target, near)?;
+                }
+            }
+        }
+        (I::Jmp | I::Call, [operand @ (Operand::Register(_) | Operand::Memory(_))]) => {
+            let far = instruction.distance == Some(OperatorType::Far);
+            if far && !matches!(operand, Operand::Memory(_)) {
+                return Err("Far jumps through a register are not possible.".to_string());
+            }
+            if matches!(operand, Operand::Register(register) if !general_purpose(*register)?.1) {
+                return Err(format!("Expected 16-bit register after '{}'.", r#type));
+            }
+            encoding.byte(0xff);
+            let reg = if r#type == I::Call { 2 } else { 4 };
+            encoding.modrm(reg + far as u8, operand)?;
+        }
+        (I::In, [accumulator, port]) | (I::Out, [port, accumulator]) => {
+            if !is_accumulator(accumulator) || !matches!(accumulator, Operand::Register(_)) {
+                return Err(format!("Expected al or ax with '{}'.", r#type));
+            }
+            let wide = size(accumulator)?;
+            let out = (r#type == I::Out) as u8;
+            match port {
+                Operand::Register(RegisterType::GeneralPurpose(GeneralPurposeRegister::Dx)) => {
+                    encoding.byte(0xec | out << 1 | wide as u8);
+                }
+                Operand::Immediate(port) => {
+                    encoding.byte(0xe4 | out << 1 | wide as u8);
+                    encoding.value(port, false)?;
+                }
+                _ => return Err(format!("Expected port number or dx with '{}'.", r#type)),
+            }
+        }
+        (I::Enter, [Operand::Immediate(size), Operand::Immediate(level)]) => {
+            encoding.byte(0xc8);
+            encoding.value(size, true)?;
+            encoding.value(level, false)?;
+        }
+        (I::Lgdt | I::Lidt | I::Sgdt | I::Sidt, [operand @ Operand::Memory(_)]) => {
+            encoding.bytes.extend([0x0f, 0x01]);
+            let reg = match r#type {
+                I::Sgdt => 0,
+                I::Sidt => 1,
+                I::Lgdt => 2,
+                _ => 3,
+            };
+            encoding.modrm(reg, operand)?;
+        }
+        (I::Smsw | I::Lmsw, [operand]) => {
+            if !size(operand)? {
+                return Err(format!("Expected word operand after '{}'.", r#type));
+            }
+            encoding.bytes.extend([0x0f, 0x01]);
+            encoding.modrm(if r#type == I::Smsw { 4 } else { 6 }, operand)?;
+        }
+        _ => return Err(invalid(r#type)),
+    }
+
+    Ok(encoding)
+}
+
+/// Opcode bytes of instructions without operands
+fn simple(r#type: InstructionType) -> Option<Vec<u8>> {
+    use InstructionType as I;
+
+    let bytes = match r#type {
+        I::Aaa => vec![0x37],
+        I::Aas => vec![0x3f],
+        I::Daa => vec![0x27],
+        I::Das => vec![0x2f],
+        I::Cbw => vec![0x98],
+        I::Cwd => vec![0x99],
+        I::Clc => vec![0xf8],
+        I::Cld => vec![0xfc],
+        I::Cli => vec![0xfa],
+        I::Clts => vec![0x0f, 0x06],
+        I::Cmc => vec![0xf5],
+        I::Cmpsb => vec![0xa6],
+        I::Cmpsw => vec![0xa7],
+        I::Hlt => vec![0xf4],
+        I::Insb => vec![0x6c],
+        I::Insw => vec![0x6d],
+        I::Int3 => vec![0xcc],
+        I::Into => vec![0xce],
+        I::Iret => vec![0xcf],
+        I::Lahf => vec![0x9f],
+        I::Leave => vec![0xc9],
+        I::Lock => vec![0xf0],
+        I::Lodsb => vec![0xac],
+        I::Lodsw => vec![0xad],
+        I::Movsb => vec![0xa4],
+        I::Movsw => vec![0xa5],
+        I::Nop => vec![0x90],
+        I::Outsb => vec![0x6e],
+        I::Outsw => vec![0x6f],
+        I::Popa => vec![0x61],
+        I::Popf => vec![0x9d],
+        I::Pusha => vec![0x60],
+        I::Pushf => vec![0x9c],
+        I::Rep => vec![0xf3],
+        I::Repne => vec![0xf2],
+        I::Sahf => vec![0x9e],
+        I::Scasb => vec![0xae],
+        I::Scasw => vec![0xaf],
+        I::Stc => vec![0xf9],
+        I::Std => vec![0xfd],
+        I::Sti => vec![0xfb],
+        I::Stosb => vec![0xaa],
+        I::Stosw => vec![0xab],
+        I::Wait => vec![0x9b],
+        I::Xlat => vec![0xd7],
+        _ => return None,
+    };
+
+    Some(bytes)
+}
+
+/// add/or/adc/sbb/and/sub/xor/cmp, `index` in the order of their encoding
+fn arithmetic(encoding: &mut Encoding, index: u8, operands: &[Operand]) -> Result<(), String> {
+    let [destination, source] = operands else {
+        return Err(invalid(ARITHMETIC[index as usize]));
+    };
+    let wide = width(destination, source)?;
+
+    match (destination, source) {
+        (_, Operand::Register(register)) => {
+            let (reg, _) = general_purpose(*register)?;
+            encoding.byte(index << 3 | wide as u8);
+            encoding.modrm(reg, destination)?;
+        }
+        (Operand::Register(register), Operand::Memory(_)) => {
+            let (reg, _) = general_purpose(*register)?;
+            encoding.byte(index << 3 | 0b10 | wide as u8);
+            encoding.modrm(reg, source)?;
+        }
+        (_, Operand::Immediate(value)) => {
+            let short = sign_extended(value).filter(|_| wide);
+            match short {
+                Some(byte) => {
+                    encoding.byte(0x83);
+                    encoding.modrm(index, destination)?;
+                    encoding.byte(byte);
+                }
+                None if is_accumulator(destination)
+                    && matches!(destination, Operand::Register(_)) =>
+                {
+                    encoding.byte(index << 3 | 0b100 | wide as u8);
+                    encoding.value(value, wide)?;
+                }
+                None => {
+                    encoding.byte(0x80 | wide as u8);
+                    encoding.modrm(index, destination)?;
+                    encoding.value(value, wide)?;
+                }
+            }
+        }
+        _ => return Err(invalid(ARITHMETIC[index as usize])),
+    }
+
+    Ok(())
+}
+
+fn mov(encoding: &mut Encoding, destination: &Operand, source: &Operand) -> Result<(), String> {
+    match (destination, source) {
+        (Operand::Register(RegisterType::Segment(segment)), rm) => {
+            if *segment == SegmentRegister::Cs {
+                return Err("Cannot move into cs.".to_string());
+            }
+            if matches!(rm, Operand::Register(RegisterType::Segment(_)))
+                || !size(rm).unwrap_or(true)
+            {
+                return Err("Expected 16-bit register or memory operand.".to_string());
+            }
+            encoding.byte(0x8e);
+            encoding.modrm(segment_code(*segment), rm)?;
+        }
+        (rm, Operand::Register(RegisterType::Segment(segment))) => {
+            if !size(rm).unwrap_or(true) {
+                return Err("Expected 16-bit register or memory operand.".to_string());
+            }
+            encoding.byte(0x8c);
+            encoding.modrm(segment_code(*segment), rm)?;
+        }
+        // accumulator from or to a direct address
+        (Operand::Register(register), Operand::Memory(memory))
+        | (Operand::Memory(memory), Operand::Register(register))
+            if memory.base.is_none()
+                && memory.index.is_none()
+                && is_accumulator(&Operand::Register(*register)) =>
+        {
+            let wide = width(destination, source)?;
+            let store = matches!(destination, Operand::Memory(_)) as u8;
+            encoding.byte(0xa0 | store << 1 | wide as u8);
+            encoding.value(&memory.displacement, true)?;
+        }
+        (_, Operand::Register(register)) => {
+            let wide = width(destination, source)?;
+            let (reg, _) = general_purpose(*register)?;
+            encoding.byte(0x88 | wide as u8);
+            encoding.modrm(reg, destination)?;
+        }
+        (Operand::Register(register), Operand::Memory(_)) => {
+            let wide = width(destination, source)?;
+            let (reg, _) = general_purpose(*register)?;
+            encoding.byte(0x8a | wide as u8);
+            encoding.modrm(reg, source)?;
+        }
+        (Operand::Register(register), Operand::Immediate(value)) => {
+            let (code, wide) = general_purpose(*register)?;
+            encoding.byte(0xb0 | (wide as u8) << 3 | code);
+            encoding.value(value, wide)?;
+        }
+        (Operand::Memory(_), Operand::Immediate(value)) => {
+            let wide = size(destination)?;
+            encoding.byte(0xc6 | wide as u8);
+            encoding.modrm(0, destination)?;
+            encoding.value(value, wide)?;
+        }
+        _ => return Err(invalid(InstructionType::Mov)),
+    }
+
+    Ok(())
+}
+
+/// Encodes 8087 instructions as ESC D8h-DFh opcodes. The forms mirror the decoder:
+/// `fop st, st(i)` through D8h, `fop st(i), st` through DCh and the popping forms through
+/// DEh; memory operands select the opcode by their size.
+fn floating_point(
+    encoding: &mut Encoding,
+    r#type: InstructionType,
+    operands: &[Operand],
+) -> Result<(), String> {
+    use InstructionType as I;
+    use OperandSize as S;
+
+    let st = |operand: &Operand| match operand {
+        Operand::Register(RegisterType::FloatingPoint(register)) => Some(register.index()),
+        _ => None,
+    };
+
+    // waiting forms are wait followed by the no-wait form
+    let no_wait = match r#type {
+        I::Fclex => Some(I::Fnclex),
+        I::Fdisi => Some(I::Fndisi),
+        I::Feni => Some(I::Fneni),
+        I::Finit => Some(I::Fninit),
+        I::Fsave => Some(I::Fnsave),
+        I::Fstcw => Some(I::Fnstcw),
+        I::Fstenv => Some(I::Fnstenv),
+        I::Fstsw => Some(I::Fnstsw),
+        _ => None,
+    };
+    if let Some(no_wait) = no_wait {
+        encoding.byte(0x9b);
+        return floating_point(encoding, no_wait, operands);
+    }
+
+    let constant = match r#type {
+        I::Fnop => Some([0xd9, 0xd0]),
+        I::Fchs => Some([0xd9, 0xe0]),
+        I::Fabs => Some([0xd9, 0xe1]),
+        I::Ftst => Some([0xd9, 0xe4]),
+        I::Fxam => Some([0xd9, 0xe5]),
+        I::Fld1 => Some([0xd9, 0xe8]),
+        I::Fldl2t => Some([0xd9, 0xe9]),
+        I::Fldl2e => Some([0xd9, 0xea]),
+        I::Fldpi => Some([0xd9, 0xeb]),
+        I::Fldlg2 => Some([0xd9, 0xec]),
+        I::Fldln2 => Some([0xd9, 0xed]),
+        I::Fldz => Some([0xd9, 0xee]),
+        I::F2xm1 => Some([0xd9, 0xf0]),
+        I::Fyl2x => Some([0xd9, 0xf1]),
+        I::Fptan => Some([0xd9, 0xf2]),
+        I::Fpatan => Some([0xd9, 0xf3]),
+        I::Fxtract => Some([0xd9, 0xf4]),
+        I::Fdecstp => Some([0xd9, 0xf6]),
+        I::Fincstp => Some([0xd9, 0xf7]),
+        I::Fprem => Some([0xd9, 0xf8]),
+        I::Fyl2xp1 => Some([0xd9, 0xf9]),
+        I::Fsqrt => Some([0xd9, 0xfa]),
+        I::Frndint => Some([0xd9, 0xfc]),
+        I::Fscale => Some([0xd9, 0xfd]),
+        I::Fneni => Some([0xdb, 0xe0]),
+        I::Fndisi => Some([0xdb, 0xe1]),
+        I::Fnclex => Some([0xdb, 0xe2]),
+        I::Fninit => Some([0xdb, 0xe3]),
+        I::Fcompp => Some([0xde, 0xd9]),
+        _ => None,
+    };
+    if let Some(bytes) = constant {
+        expect_operands(operands, 0)?;
+        encoding.bytes.extend(bytes);
+        return Ok(());
+    }
+
+    let memory_size = |operand: &Operand| match operand {
+        Operand::Memory(memory) => Some(memory.size),
+        _ => None,
+    };
+
+    // fop st, st(i) / fop st(i), st / fop size [memory]
+    if let Some(index) = FLOATING_POINT_ARITHMETIC
+        .iter()
+        .position(|other| *other == r#type)
+    {
+        let index = index as u8;
+        let compare = matches!(r#type, I::Fcom | I::Fcomp);
+
+        match operands {
+            [] if compare => encoding.bytes.extend([0xd8, 0xc1 | index << 3]),
+            [register] if compare && st(register).is_some() => encoding
+                .bytes
+                .extend([0xd8, 0xc0 | index << 3 | st(register).unwrap()]),
+            [first, second] if st(first) == Some(0) && st(second).is_some() => encoding
+                .bytes
+                .extend([0xd8, 0xc0 | index << 3 | st(second).unwrap()]),
+            [first, second] if !compare && st(first).is_some() && st(second) == Some(0) => {
+                // DCh swaps the reversed and normal forms of sub and div
+                let reg = if index >= 4 { index ^ 1 } else { index };
+                encoding
+                    .bytes
+                    .extend([0xdc, 0xc0 | reg << 3 | st(first).unwrap()])
+            }
+            [operand] => match memory_size(operand) {
+                Some(Some(S::Dword)) => {
+                    encoding.byte(0xd8);
+                    encoding.modrm(index, operand)?;
+                }
+                Some(Some(S::Qword)) => {
+                    encoding.byte(0xdc);
+                    encoding.modrm(index, operand)?;
+                }
+                _ => {
+                    return Err(format!(
+                        "Expected dword or qword memory after '{}'.",
+                        r#type
+                    ))
+                }
+            },
+            _ => return Err(invalid(r#type)),
+        }
+        return Ok(());
+    }
+
+    if let Some(index) = INTEGER_ARITHMETIC.iter().position(|other| *other == r#type) {
+        let [operand] = operands else {
+            return Err(invalid(r#type));
+        };
+        let opcode = match memory_size(operand) {
+            Some(Some(S::Dword)) => 0xda,
+            Some(Some(S::Word)) => 0xde,
+            _ => return Err(format!("Expected word or dword memory after '{}'.", r#type)),
+        };
+        encoding.byte(opcode);
+        return encoding.modrm(index as u8, operand);
+    }
+
+    let popping = [I::Faddp, I::Fmulp, I::Fsubrp, I::Fsubp, I::Fdivrp, I::Fdivp];
+    if let Some(index) = popping.iter().position(|other| *other == r#type) {
+        let reg = [0, 1, 4, 5, 6, 7][index];
+        let register = match operands {
+            [] => 1,
+            [first, second] if st(first).is_some() && st(second) == Some(0) => st(first).unwrap(),
+            _ => return Err(format!("Expected 'st(i), st' after '{}'.", r#type)),
+        };
+        encoding.bytes.extend([0xde, 0xc0 | reg << 3 | register]);
+        return Ok(());
+    }
+
+    // register forms
+    let register = match (r#type, operands) {
+        (I::Fxch, []) => Some(1),
+        (I::Fxch, [first, second]) if st(first) == Some(0) => st(second),
+        (_, [operand]) => st(operand),
+        _ => None,
+    };
+    if let Some(register) = register {
+        let (opcode, modrm) = match r#type {
+            I::Fld => (0xd9, 0xc0),
+            I::Fxch => (0xd9, 0xc8),
+            I::Ffree => (0xdd, 0xc0),
+            I::Fst => (0xdd, 0xd0),
+            I::Fstp => (0xdd, 0xd8),
+            _ => return Err(invalid(r#type)),
+        };
+        encoding.bytes.extend([opcode, modrm | register]);
+        return Ok(());
+    }
+
+    // memory forms
+    let [operand @ Operand::Memory(_)] = operands else {
+        return Err(invalid(r#type));
+    };
+    let size = memory_size(operand).unwrap();
+
+    let (opcode, reg) = match (r#type, size) {
+        (I::Fld, Some(S::Dword)) => (0xd9, 0),
+        (I::Fld, Some(S::Qword)) => (0xdd, 0),
+        (I::Fld, Some(S::Tword)) => (0xdb, 5),
+        (I::Fst, Some(S::Dword)) => (0xd9, 2),
+        (I::Fst, Some(S::Qword)) => (0xdd, 2),
+        (I::Fstp, Some(S::Dword)) => (0xd9, 3),
+        (I::Fstp, Some(S::Qword)) => (0xdd, 3),
+        (I::Fstp, Some(S::Tword)) => (0xdb, 7),
+        (I::Fild, Some(S::Word)) => (0xdf, 0),
+        (I::Fild, Some(S::Dword)) => (0xdb, 0),
+        (I::Fild, Some(S::Qword)) => (0xdf, 5),
+        (I::Fist, Some(S::Word)) => (0xdf, 2),
+        (I::Fist, Some(S::Dword)) => (0xdb, 2),
+        (I::Fistp, Some(S::Word)) => (0xdf, 3),
+        (I::Fistp, Some(S::Dword)) => (0xdb, 3),
+        (I::Fistp, Some(S::Qword)) => (0xdf, 7),
+        (I::Fbld, None | Some(S::Tword)) => (0xdf, 4),
+        (I::Fbstp, None | Some(S::Tword)) => (0xdf, 6),
+        (I::Fldenv, None) => (0xd9, 4),
+        (I::Fldcw, None | Some(S::Word)) => (0xd9, 5),
+        (I::Fnstenv, None) => (0xd9, 6),
+        (I::Fnstcw, None | Some(S::Word)) => (0xd9, 7),
+        (I::Frstor, None) => (0xdd, 4),
+        (I::Fnsave, None) => (0xdd, 6),
+        (I::Fnstsw, None | Some(S::Word)) => (0xdd, 7),
+        (_, None) => return Err(format!("Operand size required for '{}'.", r#type)),
+        (_, Some(size)) => return Err(format!("'{}' cannot take a {} operand.", r#type, size)),
+    };
+
+    encoding.byte(opcode);
+    encoding.modrm(reg, operand)
+}
+
+fn prefix_byte(prefix: InstructionType) -> Option<u8> {
+    match prefix {
+        InstructionType::Lock => Some(0xf0),
+        InstructionType::Repne => Some(0xf2),
+        InstructionType::Rep => Some(0xf3),
+        _ => None,
+    }
+}
+
+fn require(cpu: Cpu, required: Cpu, form: &str) -> Result<(), String> {
+    if required > cpu {
+        return Err(format!(
+            "The form {} requires an {} or later, but target CPU is {}.",
+            form, required, cpu
+        ));
+    }
+    Ok(())
+}
+
+fn expect_operands(operands: &[Operand], count: usize) -> Result<(), String> {
+    if operands.len() != count {
+        return Err(format!(
+            "Expected {} operands, found {}.",
+            count,
+            operands.len()
+        ));
+    }
+    Ok(())
+}
+
+fn invalid(r#type: InstructionType) -> String {
+    format!("Invalid operands for '{}'.", r#type)
+}
+
+/// Register number and width (16-bit) of a general purpose, index or pointer register
+fn general_purpose(register: RegisterType) -> Result<(u8, bool), String> {
+    use GeneralPurposeRegister as G;
+    use SpecialPurposeRegister as S;
+
+    match register {
+        RegisterType::GeneralPurpose(register) => Ok(match register {
+            G::Al => (0, false),
+            G::Cl => (1, false),
+            G::Dl => (2, false),
+            G::Bl => (3, false),
+            G::Ah => (4, false),
+            G::Ch => (5, false),
+            G::Dh => (6, false),
+            G::Bh => (7, false),
+            G::Ax => (0, true),
+            G::Cx => (1, true),
+            G::Dx => (2, true),
+            G::Bx => (3, true),
+        }),
+        RegisterType::SpecialPurpose(S::Sp) => Ok((4, true)),
+        RegisterType::SpecialPurpose(S::Bp) => Ok((5, true)),
+        RegisterType::SpecialPurpose(S::Si) => Ok((6, true)),
+        RegisterType::SpecialPurpose(S::Di) => Ok((7, true)),
+        _ => Err(format!("Register '{}' cannot be used here.", register)),
+    }
+}
+
+fn segment_code(segment: SegmentRegister) -> u8 {
+    match segment {
+        SegmentRegister::Es => 0,
+        SegmentRegister::Cs => 1,
+        SegmentRegister::Ss => 2,
+        SegmentRegister::Ds => 3,
+    }
+}
+
+fn condition_code(condition: Condition) -> u8 {
+    Condition::ALL
+        .iter()
+        .position(|other| *other == condition)
+        .unwrap() as u8
+}
+
+fn is_accumulator(operand: &Operand) -> bool {
+    matches!(
+        operand,
+        Operand::Register(RegisterType::GeneralPurpose(
+            GeneralPurposeRegister::Al | GeneralPurposeRegister::Ax
+        ))
+    )
+}
+
+/// Whether an operand is 16 bits wide. Memory operands need an explicit size.
+fn size(operand: &Operand) -> Result<bool, String> {
+    match operand {
+        Operand::Register(RegisterType::Segment(_)) => Ok(true),
+        Operand::Register(register) => Ok(general_purpose(*register)?.1),
+        Operand::Memory(Memory {
+            size: Some(OperandSize::Byte),
+            ..
+        }) => Ok(false),
+        Operand::Memory(Memory {
+            size: Some(OperandSize::Word),
+            ..
+        }) => Ok(true),
+        Operand::Memory(Memory { size: None, .. }) => {
+            Err("Operand size required, e.g. 'byte [bx]' or 'word [bx]'.".to_string())
+        }
+        Operand::Memory(Memory {
+            size: Some(size), ..
+        }) => Err(format!("Expected byte or word operand, found {}.", size)),
+        _ => Err("Expected register or memory operand.".to_string()),
+    }
+}
+
+/// Common width of two operands, taken from whichever has a size
+fn width(first: &Operand, second: &Operand) -> Result<bool, String> {
+    let sized = |operand: &Operand| {
+        !matches!(
+            operand,
+            Operand::Immediate(_) | Operand::Memory(Memory { size: None, .. })
+        )
+    };
+
+    match (sized(first), sized(second)) {
+        (true, true) => {
+            let wide = size(first)?;
+            if wide != size(second)? {
+                return Err("Operand sizes do not match.".to_string());
+            }
+            Ok(wide)
+        }
+        (true, false) => size(first),
+        (false, true) => size(second),
+        (false, false) => size(first),
+    }
+}
+
+/// Byte of an immediate that fits into a sign extended byte (83h, 6Ah, 6Bh)
+fn sign_extended(value: &Value) -> Option<u8> {
+    if value.symbol.is_some() {
+        return None;
+    }
+    let value = word(value.constant).ok()? as i16;
+    i8::try_from(value).ok().map(|value| value as u8)
+}
+
+/// Byte of a constant; negative values are stored in two's complement.
+fn byte(constant: i32) -> Result<u8, String> {
+    match constant {
+        -0x80..=0xff => Ok(constant as u8),
+        // negative constants arrive as 16-bit two's complement
+        0xff80..=0xffff => Ok(constant as u8),
+        _ => Err(format!(
+            "Constant {:#x} does not fit into a byte.",
+            constant
+        )),
+    }
+}
+
+/// Word of a constant; negative values are stored in two's complement.
+fn word(constant: i32) -> Result<u16, String> {
+    match constant {
+        -0x8000..=0xffff => Ok(constant as u16),
+        _ => Err(format!(
+            "Constant {:#x} does not fit into a word.",
+            constant
+        )),
+    }
+}
@@ -0,0 +1,149 @@
+#[cfg(test)]
+fn instruction(
+    r#type: asmrs_parser::lexer::token::InstructionType,
+    operands: Vec<crate::encoder::Operand>,
+) -> crate::encoder::Instruction {
+    crate::encoder::Instruction {
+        prefix: None,
+        segment: None,
+        r#type,
+        distance: None,
+        operands,
+    }
+}
+
+#[test]
+fn encode_instructions() {
+    use crate::encoder::{encode, test::instruction, Memory, Operand, Value};
+    use asmrs_parser::lexer::token::{
+        Cpu, GeneralPurposeRegister, InstructionType, OperandSize, OperatorType, RegisterType,
+        SegmentRegister, SpecialPurposeRegister,
+    };
+
+    let ax = Operand::Register(RegisterType::GeneralPurpose(GeneralPurposeRegister::Ax));
+    let bx = Operand::Register(RegisterType::GeneralPurpose(GeneralPurposeRegister::Bx));
+    let bx_si = Operand::Memory(Memory {
+        size: Some(OperandSize::Word),
+        segment: Some(SegmentRegister::Es),
+        base: Some(RegisterType::GeneralPurpose(GeneralPurposeRegister::Bx)),
+        index: Some(RegisterType::SpecialPurpose(SpecialPurposeRegister::Si)),
+        displacement: Value::constant(-2),
+    });
+
+    let cases = [
+        (
+            InstructionType::Xchg,
+            vec![ax.clone(), bx.clone()],
+            vec![0x93],
+        ),
+        (
+            InstructionType::Mov,
+            vec![bx.clone(), ax.clone()],
+            vec![0x89, 0xc3],
+        ),
+        (
+            InstructionType::Add,
+            vec![bx_si.clone(), Operand::Immediate(Value::constant(0x7f))],
+            vec![0x26, 0x83, 0x40, 0xfe, 0x7f],
+        ),
+        (
+            InstructionType::Sub,
+            vec![ax.clone(), Operand::Immediate(Value::constant(0x1234))],
+            vec![0x2d, 0x34, 0x12],
+        ),
+        (
+            InstructionType::Jmp,
+            vec![Operand::Far {
+                segment: 0xf000,
+                offset: 0xfff0,
+            }],
+            vec![0xea, 0xf0, 0xff, 0x00, 0xf0],
+        ),
+        (
+            InstructionType::Shl,
+            vec![bx.clone(), Operand::Immediate(Value::constant(1))],
+            vec![0xd1, 0xe3],
+        ),
+    ];
+
+    for (r#type, operands, bytes) in cases {
+        let encoding = encode(&instruction(r#type, operands), Cpu::I8086, false).unwrap();
+        assert_eq!(encoding.bytes, bytes, "{}", r#type);
+    }
+
+    // call far [bx]
+    let mut call = instruction(
+        InstructionType::Call,
+        vec![Operand::Memory(Memory {
+            base: Some(RegisterType::GeneralPurpose(GeneralPurposeRegister::Bx)),
+            ..Memory::default()
+        })],
+    );
+    call.distance = Some(OperatorType::Far);
+    assert_eq!(
+        encode(&call, Cpu::I8086, false).unwrap().bytes,
+        [0xff, 0x1f]
+    );
+
+    // byte and word operands do not mix
+    let al = Operand::Register(RegisterType::GeneralPurpose(GeneralPurposeRegister::Al));
+    assert!(encode(
+        &instruction(InstructionType::Mov, vec![al, bx]),
+        Cpu::I8086,
+        false
+    )
+    .is_err());
+}
+
+#[test]
+fn encode_fixups() {
+    use crate::{
+        encoder::{encode, test::instruction, Fixup, Operand, Value},
+        object::RelocationKind,
+    };
+    use asmrs_parser::lexer::token::{Cpu, GeneralPurposeRegister, InstructionType, RegisterType};
+
+    let message = Value {
+        symbol: Some("message".to_string()),
+        constant: 2,
+        segment: false,
+    };
+    let mov = instruction(
+        InstructionType::Mov,
+        vec![
+            Operand::Register(RegisterType::GeneralPurpose(GeneralPurposeRegister::Dx)),
+            Operand::Immediate(message.clone()),
+        ],
+    );
+    let encoding = encode(&mov, Cpu::I8086, false).unwrap();
+    assert_eq!(encoding.bytes, [0xba, 0x00, 0x00]);
+    assert_eq!(
+        encoding.fixups,
+        [Fixup {
+            offset: 1,
+            kind: RelocationKind::Absolute,
+            symbol: Some("message".to_string()),
+            addend: 2,
+        }]
+    );
+
+    // jumps are short unless asked to be near
+    let jmp = instruction(InstructionType::Jmp, vec![Operand::Immediate(message)]);
+    let short = encode(&jmp, Cpu::I8086, false).unwrap();
+    assert_eq!(short.bytes, [0xeb, 0x00]);
+    assert_eq!(short.fixups[0].kind, RelocationKind::Relative8);
+    let near = encode(&jmp, Cpu::I8086, true).unwrap();
+    assert_eq!(near.bytes, [0xe9, 0x00, 0x00]);
+    assert_eq!(near.fixups[0].kind, RelocationKind::Relative16);
+
+    // push imm is an 80186 form
+    let push = instruction(
+        InstructionType::Push,
+        vec![Operand::Immediate(Value::constant(0x10))],
+    );
+    assert!(encode(&push, Cpu::I8086, false).is_err());
+    assert_eq!(
+        encode(&push, Cpu::I80186, false).unwrap().bytes,
+        [0x6a, 0x10]
+    );
+}
@@ -0,0 +1,366 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+mod test;
+
+/// Size of the DOS program segment prefix in front of a .COM image
+pub const PSP_SIZE: usize = 0x100;
+
+/// Bytes kept free at the end of a .COM segment for the stack
+pub const COM_STACK_SIZE: usize = 0x100;
+
+/// Address the BIOS loads the boot sector to (0000:7C00)
+pub const BOOT_SECTOR_ORIGIN: u16 = 0x7c00;
+
+/// Size of a disk sector
+pub const SECTOR_SIZE: usize = 512;
+
+/// Signature the BIOS expects in the last two bytes of the boot sector
+pub const BOOT_SIGNATURE: [u8; 2] = [0x55, 0xaa];
+
+/// Output file formats
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Flat binary
+    Bin,
+    /// MS-DOS .COM program
+    Com,
+    /// Boot sector, padded and signed
+    BootSector,
+    /// Intel HEX with extended segment address records
+    IntelHex,
+    /// Motorola S-records
+    SRecord,
+    /// MS-DOS MZ executable, built by `mz` from a segmented layout
+    Exe,
+    /// Relocatable object for the linker, written with `Object::to_bytes`
+    Object,
+}
+
+impl Format {
+    /// Origin implied by the format (`org`)
+    pub fn origin(&self) -> u16 {
+        match self {
+            Format::Bin => 0,
+            Format::Com => PSP_SIZE as u16,
+            Format::BootSector => BOOT_SECTOR_ORIGIN,
+            Format::IntelHex | Format::SRecord | Format::Exe | Format::Object => 0,
+        }
+    }
+
+    /// Wraps the assembled image, located at `segment:offset`, into the output format.
+    /// Only the hex formats record the address. An .exe needs relocations, entry point and
+    /// stack as well and is written with `mz` instead, an object with `Object::to_bytes`.
+    pub fn write(&self, image: &[u8], segment: u16, offset: u16) -> Result<Vec<u8>, FormatError> {
+        match self {
+            Format::Bin => Ok(image.to_vec()),
+            Format::Com => com(image),
+            Format::BootSector => boot_sector(image),
+            Format::IntelHex => intel_hex(image, segment, offset).map(String::into_bytes),
+            Format::SRecord => {
+                s_record(image, ((segment as u32) << 4) + offset as u32).map(String::into_bytes)
+            }
+            Format::Exe => Err(FormatError::new(
+                "An .exe is written from an Executable with mz.".to_string(),
+            )),
+            Format::Object => Err(FormatError::new(
+                "An object is written from an Object with to_bytes.".to_string(),
+            )),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bin" => Ok(Format::Bin),
+            "com" => Ok(Format::Com),
+            "bootsector" => Ok(Format::BootSector),
+            "ihex" => Ok(Format::IntelHex),
+            "srec" => Ok(Format::SRecord),
+            "exe" => Ok(Format::Exe),
+            "obj" => Ok(Format::Object),
+            _ => Err(format!(
+                "Unknown format: '{}'. Expected bin, com, bootsector, ihex, srec, exe or obj.",
+                s
+            )),
+        }
+    }
+}
+
+/// Builds a .COM file. The image is loaded behind the PSP and has to leave room for the
+/// stack within the single 64 KiB segment.
+pub fn com(image: &[u8]) -> Result<Vec<u8>, FormatError> {
+    let limit = 0x10000 - PSP_SIZE - COM_STACK_SIZE;
+
+    if image.len() > limit {
+        return Err(FormatError::new(format!(
+            "Image of {} bytes exceeds the .COM limit of {} bytes.",
+            image.len(),
+            limit
+        )));
+    }
+
+    Ok(image.to_vec())
+}
+
+/// Builds a boot sector: the image padded with zeros to 510 bytes, followed by 55h AAh.
+pub fn boot_sector(image: &[u8]) -> Result<Vec<u8>, FormatError> {
+    let limit = SECTOR_SIZE - BOOT_SIGNATURE.len();
+
+    if image.len() > limit {
+        return Err(FormatError::new(format!(
+            "Image of {} bytes exceeds the boot sector limit of {} bytes.",
+            image.len(),
+            limit
+        )));
+    }
+
+    let mut output = image.to_vec();
+    output.resize(limit, 0);
+    output.extend(BOOT_SIGNATURE);
+
+    Ok(output)
+}
+
+/// Data bytes per hex record
+const HEX_RECORD_LENGTH: usize = 16;
+
+/// Builds Intel HEX records for an image at `segment:offset`. Addresses are kept within
+/// 64 KiB by extended segment address records (type 02), emitted at the start and
+/// whenever the offset wraps.
+pub fn intel_hex(image: &[u8], segment: u16, offset: u16) -> Result<String, FormatError> {
+    if ((segment as usize) << 4) + offset as usize + image.len() > 0x100000 {
+        return Err(FormatError::new(
+            "Image exceeds the 1 MiB address space.".to_string(),
+        ));
+    }
+
+    let mut output = String::new();
+    let mut segment = segment;
+    let mut offset = offset as usize;
+    let mut remaining = image;
+
+    output.push_str(&hex_record(0x02, 0, &segment.to_be_bytes()));
+
+    while !remaining.is_empty() {
+        if offset == 0x10000 {
+            segment += 0x1000;
+            offset = 0;
+            output.push_str(&hex_record(0x02, 0, &segment.to_be_bytes()));
+        }
+
+        let length = remaining.len().min(HEX_RECORD_LENGTH).min(0x10000 - offset);
+        output.push_str(&hex_record(0x00, offset as u16, &remaining[..length]));
+
+        remaining = &remaining[length..];
+        offset += length;
+    }
+
+    output.push_str(&hex_record(0x01, 0, &[]));
+    Ok(output)
+}
+
+fn hex_record(r#type: u8, address: u16, data: &[u8]) -> String {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend(address.to_be_bytes());
+    bytes.push(r#type);
+    bytes.extend(data);
+
+    let checksum = bytes
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+        .wrapping_neg();
+    bytes.push(checksum);
+
+    format!(":{}\n", hex(&bytes))
+}
+
+/// Builds Motorola S-records for an image at linear `address`: an S0 header, S1 data
+/// records with S9 termination when the image ends below 64 KiB, otherwise S2 (24-bit)
+/// records with S8 termination.
+pub fn s_record(image: &[u8], address: u32) -> Result<String, FormatError> {
+    let end = address as usize + image.len();
+    if end > 0x100000 {
+        return Err(FormatError::new(
+            "Image exceeds the 1 MiB address space.".to_string(),
+        ));
+    }
+
+    let (data, termination, address_length) = if end <= 0x10000 {
+        ('1', '9', 2)
+    } else {
+        ('2', '8', 3)
+    };
+
+    let mut output = s_record_line('0', 0, 2, b"asmrs");
+
+    for (index, chunk) in image.chunks(HEX_RECORD_LENGTH).enumerate() {
+        let address = address + (index * HEX_RECORD_LENGTH) as u32;
+        output.push_str(&s_record_line(data, address, address_length, chunk));
+    }
+
+    output.push_str(&s_record_line(termination, address, address_length, &[]));
+    Ok(output)
+}
+
+fn s_record_line(r#type: char, address: u32, address_length: usize, data: &[u8]) -> String {
+    let mut bytes = vec![(address_length + data.len() + 1) as u8];
+    bytes.extend(&address.to_be_bytes()[4 - address_length..]);
+    bytes.extend(data);
+
+    let checksum = !bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    bytes.push(checksum);
+
+    format!("S{}{}\n", r#type, hex(&bytes))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+/// Stack reserved behind the image of an .exe without a `.stack` section
+pub const DEFAULT_STACK_SIZE: u32 = 0x400;
+
+/// Size of an MZ file page
+pub const MZ_PAGE_SIZE: usize = 512;
+
+/// Size of the fixed part of the MZ header
+const MZ_HEADER_SIZE: usize = 0x1c;
+
+/// Program laid out in segments, as needed for an MZ executable.
+/// Segments are paragraph offsets relative to the start of the image.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Executable {
+    pub image: Vec<u8>,
+    /// Locations (segment:offset) of words holding a segment value (`mov ax, seg data`)
+    pub relocations: Vec<(u16, u16)>,
+    /// Initial CS:IP, from the `entry` directive
+    pub entry: (u16, u16),
+    /// Initial SS:SP of the stack segment
+    pub stack: (u16, u16),
+    /// Paragraphs needed beyond the image (uninitialized data and stack)
+    pub min_alloc: u16,
+    /// Paragraphs requested beyond the image
+    pub max_alloc: u16,
+}
+
+impl Executable {
+    /// Lays out an executable that takes `size` bytes in memory, of which `image` is the
+    /// initialized part. The stack is `stack` (segment and size) if the program has a
+    /// `.stack` section, otherwise `DEFAULT_STACK_SIZE` bytes behind the image.
+    pub fn new(
+        image: Vec<u8>,
+        size: u32,
+        relocations: Vec<(u16, u16)>,
+        entry: (u16, u16),
+        stack: Option<(u16, u32)>,
+    ) -> Self {
+        let (stack, end) = match stack {
+            Some((segment, stack_size)) => ((segment, stack_size as u16), size),
+            None => {
+                let segment = size.div_ceil(16);
+                (
+                    (segment as u16, DEFAULT_STACK_SIZE as u16),
+                    segment * 16 + DEFAULT_STACK_SIZE,
+                )
+            }
+        };
+
+        let image_paragraphs = (image.len() as u32).div_ceil(16);
+
+        Self {
+            image,
+            relocations,
+            entry,
+            stack,
+            min_alloc: (end.div_ceil(16) - image_paragraphs) as u16,
+            max_alloc: 0xffff,
+        }
+    }
+}
+
+/// Builds an MZ executable: header, relocation table padded to a paragraph, image.
+pub fn mz(executable: &Executable) -> Result<Vec<u8>, FormatError> {
+    let relocations = executable.relocations.len();
+    if relocations > u16::MAX as usize {
+        return Err(FormatError::new(format!(
+            "{} relocations exceed the MZ limit of {}.",
+            relocations,
+            u16::MAX
+        )));
+    }
+
+    let header_paragraphs = (MZ_HEADER_SIZE + relocations * 4).div_ceil(16);
+    let header_size = header_paragraphs * 16;
+    let file_size = header_size + executable.image.len();
+    let pages = file_size.div_ceil(MZ_PAGE_SIZE);
+
+    if pages > u16::MAX as usize {
+        return Err(FormatError::new(format!(
+            "Image of {} bytes exceeds the MZ size limit.",
+            executable.image.len()
+        )));
+    }
+
+    if executable.min_alloc > executable.max_alloc {
+        return Err(FormatError::new(
+            "Minimum allocation exceeds maximum allocation.".to_string(),
+        ));
+    }
+
+    let fields = [
+        0x5a4d,
+        (file_size % MZ_PAGE_SIZE) as u16,
+        pages as u16,
+        relocations as u16,
+        header_paragraphs as u16,
+        executable.min_alloc,
+        executable.max_alloc,
+        executable.stack.0,
+        executable.stack.1,
+        // checksum (unused by DOS)
+        0,
+        executable.entry.1,
+        executable.entry.0,
+        MZ_HEADER_SIZE as u16,
+        // overlay number
+        0,
+    ];
+
+    let mut output = fields
+        .iter()
+        .flat_map(|field| field.to_le_bytes())
+        .collect::<Vec<_>>();
+
+    for (segment, offset) in &executable.relocations {
+        output.extend(offset.to_le_bytes());
+        output.extend(segment.to_le_bytes());
+    }
+
+    output.resize(header_size, 0);
+    output.extend(&executable.image);
+
+    Ok(output)
+}
+
+#[derive(Clone, Debug)]
+pub struct FormatError {
+    message: String,
+}
+
+impl FormatError {
+    /// Creates a new Format Error with the given message.
+    pub fn new(message: String) -> FormatError {
+        Self { message }
+    }
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Format Error: {}", self.message)
+    }
+}
+
+impl Error for FormatError {}
@@ -0,0 +1,119 @@
+#[test]
+fn format_com() {
+    use crate::format::{com, Format};
+
+    assert_eq!(Format::Com.origin(), 0x100);
+    assert_eq!("COM".parse(), Ok(Format::Com));
+
+    let output = Format::Com.write(&[0xcd, 0x20], 0, 0x100);
+    assert!(output.is_ok());
+    assert_eq!(output.unwrap(), vec![0xcd, 0x20]);
+
+    assert!(com(&vec![0x90; 0xfe00]).is_ok());
+    assert!(com(&vec![0x90; 0xfe01]).is_err());
+}
+
+#[test]
+fn format_mz() {
+    use crate::format::{mz, Executable, Format};
+
+    assert_eq!("exe".parse(), Ok(Format::Exe));
+    assert!(Format::Exe.write(&[0x90], 0, 0).is_err());
+
+    let executable = Executable {
+        image: vec![0x90; 600],
+        relocations: vec![(0x0000, 0x0001), (0x0010, 0x0004)],
+        entry: (0x0000, 0x0000),
+        stack: (0x0030, 0x0100),
+        min_alloc: 0x10,
+        max_alloc: 0xffff,
+    };
+
+    let output = mz(&executable);
+    assert!(output.is_ok());
+    let output = output.unwrap();
+
+    let word = |offset: usize| u16::from_le_bytes([output[offset], output[offset + 1]]);
+    assert_eq!(&output[..2], b"MZ");
+    // header: 1Ch + 2 * 4 bytes, padded to 3 paragraphs
+    assert_eq!(word(0x08), 3);
+    assert_eq!(output.len(), 48 + 600);
+    assert_eq!(word(0x02), (648 % 512) as u16);
+    assert_eq!(word(0x04), 2);
+    assert_eq!(word(0x06), 2);
+    assert_eq!(word(0x0e), 0x0030);
+    assert_eq!(word(0x10), 0x0100);
+    assert_eq!(word(0x18), 0x1c);
+    // second relocation entry: offset, segment
+    assert_eq!(word(0x20), 0x0004);
+    assert_eq!(word(0x22), 0x0010);
+    assert_eq!(output[48], 0x90);
+
+    // 20h bytes of data, 10h of them reserved: the default stack follows in paragraph 2
+    let executable = Executable::new(vec![0x90; 0x10], 0x20, Vec::new(), (0, 0), None);
+    assert_eq!(executable.stack, (0x0002, 0x0400));
+    assert_eq!(executable.min_alloc, 0x41);
+    let executable = Executable::new(
+        vec![0x90; 0x10],
+        0x120,
+        Vec::new(),
+        (0, 0),
+        Some((2, 0x100)),
+    );
+    assert_eq!(executable.stack, (0x0002, 0x0100));
+    assert_eq!(executable.min_alloc, 0x11);
+}
+
+#[test]
+fn format_boot_sector() {
+    use crate::format::{boot_sector, Format};
+
+    assert_eq!(Format::BootSector.origin(), 0x7c00);
+
+    let output = Format::BootSector.write(&[0xeb, 0xfe], 0, 0x7c00);
+    assert!(output.is_ok());
+    let output = output.unwrap();
+    assert_eq!(output.len(), 512);
+    assert_eq!(&output[..3], &[0xeb, 0xfe, 0x00]);
+    assert_eq!(&output[510..], &[0x55, 0xaa]);
+
+    assert!(boot_sector(&[0x90; 510]).is_ok());
+    assert!(boot_sector(&[0x90; 511]).is_err());
+}
+
+#[test]
+fn format_intel_hex() {
+    use crate::format::{intel_hex, Format};
+
+    let output = Format::IntelHex.write(&[0xb8, 0x34, 0x12], 0xf000, 0x0100);
+    assert!(output.is_ok());
+    assert_eq!(
+        String::from_utf8(output.unwrap()).unwrap(),
+        ":02000002F0000C\n:03010000B83412FE\n:00000001FF\n"
+    );
+
+    // offset wraps into the next 64 KiB segment
+    let output = intel_hex(&[0x11, 0x22, 0x33], 0x1000, 0xfffe).unwrap();
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines[1], ":02FFFE001122CE");
+    assert_eq!(lines[2], ":020000022000DC");
+    assert_eq!(lines[3], ":0100000033CC");
+
+    assert!(intel_hex(&[0; 16], 0xffff, 0x0010).is_err());
+}
+
+#[test]
+fn format_s_record() {
+    use crate::format::s_record;
+
+    let output = s_record(&[0xb8, 0x34, 0x12], 0x0100).unwrap();
+    assert_eq!(
+        output,
+        "S008000061736D7273D1\nS1060100B83412FA\nS9030100FB\n"
+    );
+
+    let output = s_record(&[0xea], 0xffff0).unwrap();
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines[1], "S2050FFFF0EA12");
+    assert_eq!(lines[2], "S8040FFFF0FD");
+}
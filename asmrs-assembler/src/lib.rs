@@ -0,0 +1,8 @@
+pub mod archive;
+pub mod assembler;
+pub mod debug;
+pub mod encoder;
+pub mod format;
+pub mod listing;
+pub mod map;
+pub mod object;
@@ -0,0 +1,175 @@
+use crate::assembler::Assembly;
+use std::fmt::Display;
+
+mod test;
+
+/// Number of bytes shown per listing row; longer lines continue on the next rows
+const BYTES_PER_ROW: usize = 6;
+
+/// Source line of the listing with the bytes emitted for it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    /// Line number within its file (0-based, like `Token`)
+    pub line_index: usize,
+    /// Segment of the first emitted byte
+    pub segment: u16,
+    /// Offset of the first emitted byte
+    pub offset: u16,
+    pub bytes: Vec<u8>,
+    /// Original source text
+    pub text: String,
+}
+
+/// Entry of the symbol table at the end of the listing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub value: u16,
+    pub section: String,
+    /// Line numbers (0-based) referencing the symbol
+    pub references: Vec<usize>,
+}
+
+/// Listing file (.lst): addresses, bytes and source of every line, followed by the symbol table.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Listing {
+    lines: Vec<Line>,
+    symbols: Vec<Symbol>,
+}
+
+impl Listing {
+    pub fn new() -> Listing {
+        Self::default()
+    }
+
+    pub fn push_line(&mut self, line: Line) {
+        self.lines.push(line);
+    }
+
+    pub fn push_symbol(&mut self, symbol: Symbol) {
+        self.symbols.push(symbol);
+    }
+
+    /// Collects the listing of `assembly`, assembled from `source`. Lines without
+    /// statements are shown at the address the previous line ended at.
+    pub fn from_assembly(assembly: &Assembly, source: &str) -> Listing {
+        let mut listing = Listing::new();
+        let mut placements = assembly.placements.iter().peekable();
+        let mut location = assembly
+            .sections
+            .first()
+            .map_or((0, assembly.origin), |section| {
+                (section.segment, section.address)
+            });
+
+        for (line_index, text) in source.lines().enumerate() {
+            let mut start = None;
+            let mut bytes = Vec::new();
+
+            while let Some(placement) =
+                placements.next_if(|placement| placement.token.line_index() == line_index)
+            {
+                let section = &assembly.sections[placement.section];
+                let offset = section.address.wrapping_add(placement.offset as u16);
+                start.get_or_insert((section.segment, offset));
+                location = (
+                    section.segment,
+                    offset.wrapping_add(placement.bytes.len() as u16),
+                );
+                bytes.extend(&placement.bytes);
+            }
+
+            let (segment, offset) = start.unwrap_or(location);
+            listing.push_line(Line {
+                line_index,
+                segment,
+                offset,
+                bytes,
+                text: text.to_string(),
+            });
+        }
+
+        for label in &assembly.labels {
+            let references = assembly
+                .placements
+                .iter()
+                .filter(|placement| placement.references.contains(&label.name))
+                .map(|placement| placement.token.line_index())
+                .collect();
+
+            listing.push_symbol(Symbol {
+                name: label.name.clone(),
+                value: assembly.address(label),
+                section: assembly.sections[label.section].name.clone(),
+                references,
+            });
+        }
+
+        listing
+    }
+}
+
+impl Display for Listing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            let mut rows = line.bytes.chunks(BYTES_PER_ROW);
+            let first = rows.next().unwrap_or_default();
+
+            writeln!(
+                f,
+                "{:>6} {:04X}:{:04X} {:<width$} {}",
+                line.line_index + 1,
+                line.segment,
+                line.offset,
+                hex(first),
+                line.text,
+                width = BYTES_PER_ROW * 2
+            )?;
+
+            for (index, row) in rows.enumerate() {
+                let offset = line
+                    .offset
+                    .wrapping_add(((index + 1) * BYTES_PER_ROW) as u16);
+                writeln!(
+                    f,
+                    "{:>6} {:04X}:{:04X} {}",
+                    line.line_index + 1,
+                    line.segment,
+                    offset,
+                    hex(row)
+                )?;
+            }
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Symbols:")?;
+        writeln!(
+            f,
+            "{:<24} {:<5} {:<12} References",
+            "Name", "Value", "Section"
+        )?;
+
+        let mut symbols = self.symbols.iter().collect::<Vec<_>>();
+        symbols.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for symbol in symbols {
+            let references = symbol
+                .references
+                .iter()
+                .map(|line_index| (line_index + 1).to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let row = format!(
+                "{:<24} {:04X}  {:<12} {}",
+                symbol.name, symbol.value, symbol.section, references
+            );
+            writeln!(f, "{}", row.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
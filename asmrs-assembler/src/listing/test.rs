@@ -0,0 +1,87 @@
+#[test]
+fn listing_lines() {
+    use crate::listing::{Line, Listing};
+
+    let mut listing = Listing::new();
+    listing.push_line(Line {
+        line_index: 0,
+        segment: 0,
+        offset: 0x100,
+        bytes: vec![0xb8, 0x34, 0x12],
+        text: "mov ax, 1234h".to_string(),
+    });
+    listing.push_line(Line {
+        line_index: 4,
+        segment: 0,
+        offset: 0x103,
+        bytes: (0..8).collect(),
+        text: "db 0, 1, 2, 3, 4, 5, 6, 7".to_string(),
+    });
+
+    let output = listing.to_string();
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "     1 0000:0100 B83412       mov ax, 1234h");
+    assert_eq!(
+        lines[1],
+        "     5 0000:0103 000102030405 db 0, 1, 2, 3, 4, 5, 6, 7"
+    );
+    assert_eq!(lines[2], "     5 0000:0109 0607");
+}
+
+#[test]
+fn listing_symbols() {
+    use crate::listing::{Listing, Symbol};
+
+    let mut listing = Listing::new();
+    listing.push_symbol(Symbol {
+        name: "start".to_string(),
+        value: 0x100,
+        section: ".text".to_string(),
+        references: vec![6, 9],
+    });
+    listing.push_symbol(Symbol {
+        name: "message".to_string(),
+        value: 0x120,
+        section: ".data".to_string(),
+        references: vec![],
+    });
+
+    let output = listing.to_string();
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "");
+    assert_eq!(lines[1], "Symbols:");
+    assert_eq!(lines[3], "message                  0120  .data");
+    assert_eq!(
+        lines[4],
+        "start                    0100  .text        7, 10"
+    );
+}
+
+#[test]
+fn listing_assembly() {
+    use crate::{
+        assembler::{assemble, Options, Target},
+        listing::Listing,
+    };
+    use asmrs_parser::lexer::{token::Cpu, tokenize};
+
+    let source = "org 0x100\nstart: mov ax, message\n\njmp start\nmessage: db \"Hi\"";
+    let options = Options {
+        cpu: Cpu::I8086,
+        target: Target::Flat { origin: 0 },
+    };
+    let assembly = assemble(&tokenize(source.to_string()).unwrap(), &options).unwrap();
+
+    let output = Listing::from_assembly(&assembly, source).to_string();
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "     1 0000:0100              org 0x100");
+    assert_eq!(
+        lines[1],
+        "     2 0000:0100 B80501       start: mov ax, message"
+    );
+    assert_eq!(lines[2].trim_end(), "     3 0000:0103");
+    assert_eq!(lines[3], "     4 0000:0103 EBFB         jmp start");
+    assert_eq!(lines[4], "     5 0000:0105 4869         message: db \"Hi\"");
+    assert_eq!(lines[8], "message                  0105  .text        2");
+    assert_eq!(lines[9], "start                    0100  .text        4");
+}
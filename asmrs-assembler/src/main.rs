@@ -1,3 +1,149 @@
-fn main() {
-    println!("Hello, world!");
+use asmrs_assembler::{
+    assembler::{self, assemble, Assembly, Target},
+    debug::DebugInfo,
+    format::{self, Format},
+    listing::Listing,
+    map::Map,
+};
+use asmrs_parser::lexer::{check_cpu, tokenize};
+use options::{Options, USAGE};
+use std::{env, fs, process::ExitCode};
+
+mod archiver;
+mod options;
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1).peekable();
+
+    if args.peek().is_some_and(|arg| arg == "archive") {
+        return match archiver::run(args.skip(1)) {
+            Ok(output) => {
+                print!("{}", output);
+                ExitCode::SUCCESS
+            }
+            Err(message) => {
+                eprintln!("{}\n{}", message, archiver::USAGE);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let input = match fs::read_to_string(&options.input) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("Could not read '{}': {}", options.input.display(), error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let tokens = match tokenize(input.clone()).and_then(|tokens| {
+        check_cpu(&tokens, options.cpu)?;
+        Ok(tokens)
+    }) {
+        Ok(tokens) => tokens,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let target = match options.format {
+        Format::Exe => Target::Segmented,
+        Format::Object => Target::Object,
+        format => Target::Flat {
+            origin: format.origin(),
+        },
+    };
+    let assembly = match assemble(
+        &tokens,
+        &assembler::Options {
+            cpu: options.cpu,
+            target,
+        },
+    ) {
+        Ok(assembly) => assembly,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let name = options
+        .input
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let bytes = match write(&assembly, options.format, &name) {
+        Ok(bytes) => bytes,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(error) = fs::write(&options.output, bytes) {
+        eprintln!("Could not write '{}': {}", options.output.display(), error);
+        return ExitCode::FAILURE;
+    }
+
+    if let Some(path) = &options.listing {
+        let listing = Listing::from_assembly(&assembly, &input);
+        if let Err(error) = fs::write(path, listing.to_string()) {
+            eprintln!("Could not write '{}': {}", path.display(), error);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(path) = &options.map {
+        let map = Map::from_assembly(&assembly, &name);
+        if let Err(error) = fs::write(path, map.to_string()) {
+            eprintln!("Could not write '{}': {}", path.display(), error);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(path) = &options.debug {
+        let debug = DebugInfo::from_assembly(&assembly, &name);
+        if let Err(error) = fs::write(path, debug.to_string()) {
+            eprintln!("Could not write '{}': {}", path.display(), error);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Writes the image in `format`; objects record the source file `name`. Programs loaded
+/// at a fixed address (.COM, boot sector) must not move their origin with `org`.
+fn write(assembly: &Assembly, format: Format, name: &str) -> Result<Vec<u8>, String> {
+    match format {
+        Format::Exe => {
+            return assembly
+                .executable()
+                .and_then(|executable| format::mz(&executable))
+                .map_err(|error| error.to_string())
+        }
+        Format::Object => return Ok(assembly.object(name).to_bytes()),
+        _ => {}
+    }
+
+    if matches!(format, Format::Com | Format::BootSector) && assembly.origin != format.origin() {
+        return Err(format!(
+            "Origin {:#x} does not match the {:#x} the format is loaded at.",
+            assembly.origin,
+            format.origin()
+        ));
+    }
+
+    format
+        .write(&assembly.image(), 0, assembly.origin)
+        .map_err(|error| error.to_string())
 }
@@ -0,0 +1,148 @@
+use crate::assembler::Assembly;
+use std::fmt::Display;
+
+mod test;
+
+/// Output section with its addresses
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MapSection {
+    pub name: String,
+    /// Segment:offset the section runs at
+    pub segment: u16,
+    pub offset: u16,
+    /// Segment:offset the initial data is stored at, if it is copied at startup
+    pub load: Option<(u16, u16)>,
+    pub size: u32,
+}
+
+/// Symbol with its location and defining file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MapSymbol {
+    pub name: String,
+    pub segment: u16,
+    pub offset: u16,
+    pub section: String,
+    pub file: String,
+    pub global: bool,
+}
+
+/// Map file: every output section with its addresses, every symbol with its location and
+/// defining file, and how much each input file contributes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Map {
+    sections: Vec<MapSection>,
+    symbols: Vec<MapSymbol>,
+    /// Input file and the bytes it contributes to every section, in input order
+    files: Vec<(String, Vec<(String, u32)>)>,
+}
+
+impl Map {
+    pub fn new() -> Map {
+        Self::default()
+    }
+
+    pub fn push_section(&mut self, section: MapSection) {
+        self.sections.push(section);
+    }
+
+    pub fn push_symbol(&mut self, symbol: MapSymbol) {
+        self.symbols.push(symbol);
+    }
+
+    /// Records the bytes `file` contributes to every section, as (section, size) pairs.
+    pub fn push_file(&mut self, file: &str, sizes: Vec<(String, u32)>) {
+        self.files.push((file.to_string(), sizes));
+    }
+
+    /// Collects the map of `assembly`, assembled from the source file `file`.
+    pub fn from_assembly(assembly: &Assembly, file: &str) -> Map {
+        let mut map = Map::new();
+
+        for section in &assembly.sections {
+            map.push_section(MapSection {
+                name: section.name.clone(),
+                segment: section.segment,
+                offset: section.address,
+                load: None,
+                size: section.size,
+            });
+        }
+
+        for label in &assembly.labels {
+            let section = &assembly.sections[label.section];
+            map.push_symbol(MapSymbol {
+                name: label.name.clone(),
+                segment: section.segment,
+                offset: assembly.address(label),
+                section: section.name.clone(),
+                file: file.to_string(),
+                global: label.global,
+            });
+        }
+
+        let sizes = assembly
+            .sections
+            .iter()
+            .map(|section| (section.name.clone(), section.size))
+            .collect();
+        map.push_file(file, sizes);
+
+        map
+    }
+}
+
+impl Display for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Sections:")?;
+        writeln!(f, "{:<16} {:<9}  {:<9}  Size", "Name", "Start", "Load")?;
+
+        for section in &self.sections {
+            // sections that are not copied are loaded where they run
+            let (segment, offset) = section.load.unwrap_or((section.segment, section.offset));
+            writeln!(
+                f,
+                "{:<16} {:04X}:{:04X}  {:04X}:{:04X}  {:05X}",
+                section.name, section.segment, section.offset, segment, offset, section.size
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Symbols:")?;
+        writeln!(f, "{:<9}  {:<24} {:<12} File", "Address", "Name", "Section")?;
+
+        let mut symbols = self.symbols.iter().collect::<Vec<_>>();
+        symbols.sort_by_key(|symbol| (linear(symbol.segment, symbol.offset), &symbol.name));
+
+        for symbol in symbols {
+            let name = if symbol.global {
+                symbol.name.clone()
+            } else {
+                format!("{} (local)", symbol.name)
+            };
+            writeln!(
+                f,
+                "{:04X}:{:04X}  {:<24} {:<12} {}",
+                symbol.segment, symbol.offset, name, symbol.section, symbol.file
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Files:")?;
+
+        for (name, sizes) in &self.files {
+            let total = sizes.iter().map(|(_, size)| size).sum::<u32>();
+            let sizes = sizes
+                .iter()
+                .map(|(section, size)| format!("{} {}", section, size))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(f, "{:<24} {:>7} bytes  {}", name, total, sizes)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn linear(segment: u16, offset: u16) -> u32 {
+    ((segment as u32) << 4) + offset as u32
+}
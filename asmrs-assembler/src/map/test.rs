@@ -0,0 +1,46 @@
+#[test]
+fn map_assembly() {
+    use crate::{
+        assembler::{assemble, Options, Target},
+        map::Map,
+    };
+    use asmrs_parser::lexer::{token::Cpu, tokenize};
+
+    let source = "\
+entry start
+start: mov ax, seg message
+.loop: jmp .loop
+section .data
+message: db \"Hi\"
+section .bss
+buffer: resb 0x20
+";
+    let options = Options {
+        cpu: Cpu::I8086,
+        target: Target::Segmented,
+    };
+    let assembly = assemble(&tokenize(source.to_string()).unwrap(), &options).unwrap();
+
+    let map = Map::from_assembly(&assembly, "main.asmrs").to_string();
+    let lines = map.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "Sections:",
+            "Name             Start      Load       Size",
+            ".text            0000:0000  0000:0000  00005",
+            ".data            0001:0000  0001:0000  00002",
+            ".bss             0002:0000  0002:0000  00020",
+            "",
+            "Symbols:",
+            "Address    Name                     Section      File",
+            "0000:0000  start                    .text        main.asmrs",
+            "0000:0003  start.loop (local)       .text        main.asmrs",
+            "0001:0000  message                  .data        main.asmrs",
+            "0002:0000  buffer                   .bss         main.asmrs",
+            "",
+            "Files:",
+            "main.asmrs                    39 bytes  .text 5, .data 2, .bss 32",
+        ]
+    );
+}
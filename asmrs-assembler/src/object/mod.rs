@@ -0,0 +1,314 @@
+use std::{error::Error, fmt::Display};
+
+mod test;
+
+/// Magic number at the start of every object file
+pub const OBJECT_MAGIC: &[u8; 4] = b"AOBJ";
+
+/// Version of the object file layout
+pub const OBJECT_VERSION: u16 = 1;
+
+/// Relocatable object file: sections, symbols and the fixups the linker has to apply.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Object {
+    /// Source file the object was assembled from
+    pub name: String,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+    pub relocations: Vec<Relocation>,
+}
+
+/// Named chunk of code or data. Sections with the same name are combined by the linker.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    /// Required alignment of the start in bytes (power of two)
+    pub alignment: u16,
+    /// Initialized contents
+    pub data: Vec<u8>,
+    /// Total size; bytes beyond `data` are uninitialized (zero filled)
+    pub size: u32,
+}
+
+/// Visibility of a symbol
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Binding {
+    /// Only visible inside the object
+    Local,
+    /// Defined here and exported (`global`)
+    Global,
+    /// Defined in another object (`extern`)
+    Extern,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub binding: Binding,
+    /// Index of the defining section, `None` for extern symbols
+    pub section: Option<usize>,
+    /// Offset within the defining section
+    pub value: u32,
+}
+
+/// How a relocated field is computed from its symbol
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RelocationKind {
+    /// 16-bit offset of the symbol (`mov ax, message`)
+    Absolute,
+    /// 8-bit displacement from the end of the field (`jmp short`, `jcc`, `loop`)
+    Relative8,
+    /// 16-bit displacement from the end of the field (`call`, `jmp near`)
+    Relative16,
+    /// 16-bit segment (paragraph) of the symbol (`mov ax, seg data`)
+    Segment,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Relocation {
+    /// Index of the section containing the field
+    pub section: usize,
+    /// Offset of the field within the section
+    pub offset: u32,
+    /// Index of the referenced symbol
+    pub symbol: usize,
+    pub kind: RelocationKind,
+    /// Constant added to the symbol value
+    pub addend: i32,
+}
+
+impl Object {
+    /// Serializes the object: magic, version, name, then the section, symbol and relocation
+    /// tables, each prefixed with its length. All integers are little endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes.extend(OBJECT_MAGIC);
+        writer.u16(OBJECT_VERSION);
+        writer.string(&self.name);
+
+        writer.u32(self.sections.len() as u32);
+        for section in &self.sections {
+            writer.string(&section.name);
+            writer.u16(section.alignment);
+            writer.u32(section.size);
+            writer.u32(section.data.len() as u32);
+            writer.bytes.extend(&section.data);
+        }
+
+        writer.u32(self.symbols.len() as u32);
+        for symbol in &self.symbols {
+            writer.string(&symbol.name);
+            writer.bytes.push(match symbol.binding {
+                Binding::Local => 0,
+                Binding::Global => 1,
+                Binding::Extern => 2,
+            });
+            writer.u32(symbol.section.map_or(u32::MAX, |section| section as u32));
+            writer.u32(symbol.value);
+        }
+
+        writer.u32(self.relocations.len() as u32);
+        for relocation in &self.relocations {
+            writer.u32(relocation.section as u32);
+            writer.u32(relocation.offset);
+            writer.u32(relocation.symbol as u32);
+            writer.bytes.push(match relocation.kind {
+                RelocationKind::Absolute => 0,
+                RelocationKind::Relative8 => 1,
+                RelocationKind::Relative16 => 2,
+                RelocationKind::Segment => 3,
+            });
+            writer.u32(relocation.addend as u32);
+        }
+
+        writer.bytes
+    }
+
+    /// Parses and validates an object produced by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Object, ObjectError> {
+        let mut reader = Reader { bytes, position: 0 };
+
+        if reader.take(4)? != OBJECT_MAGIC {
+            return Err(ObjectError::new("Not an asmrs object file.".to_string()));
+        }
+
+        let version = reader.u16()?;
+        if version != OBJECT_VERSION {
+            return Err(ObjectError::new(format!(
+                "Unsupported object version {}.",
+                version
+            )));
+        }
+
+        let name = reader.string()?;
+
+        let mut sections = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = reader.string()?;
+            let alignment = reader.u16()?;
+            let size = reader.u32()?;
+            let length = reader.u32()? as usize;
+            let data = reader.take(length)?.to_vec();
+
+            if !alignment.is_power_of_two() || data.len() as u64 > size as u64 {
+                return Err(ObjectError::new(format!("Invalid section '{}'.", name)));
+            }
+
+            sections.push(Section {
+                name,
+                alignment,
+                data,
+                size,
+            });
+        }
+
+        let mut symbols = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = reader.string()?;
+            let binding = match reader.take(1)?[0] {
+                0 => Binding::Local,
+                1 => Binding::Global,
+                2 => Binding::Extern,
+                _ => return Err(ObjectError::new(format!("Invalid binding of '{}'.", name))),
+            };
+            let section = match reader.u32()? {
+                u32::MAX => None,
+                section => Some(section as usize),
+            };
+            let value = reader.u32()?;
+
+            let defined = section.is_some_and(|section| section < sections.len());
+            if defined == (binding == Binding::Extern) {
+                return Err(ObjectError::new(format!("Invalid symbol '{}'.", name)));
+            }
+
+            symbols.push(Symbol {
+                name,
+                binding,
+                section,
+                value,
+            });
+        }
+
+        let mut relocations = Vec::new();
+        for _ in 0..reader.u32()? {
+            let section = reader.u32()? as usize;
+            let offset = reader.u32()?;
+            let symbol = reader.u32()? as usize;
+            let kind = match reader.take(1)?[0] {
+                0 => RelocationKind::Absolute,
+                1 => RelocationKind::Relative8,
+                2 => RelocationKind::Relative16,
+                3 => RelocationKind::Segment,
+                _ => return Err(ObjectError::new("Invalid relocation kind.".to_string())),
+            };
+            let addend = reader.u32()? as i32;
+
+            let width = if kind == RelocationKind::Relative8 {
+                1
+            } else {
+                2
+            };
+            let in_bounds = sections
+                .get(section)
+                .is_some_and(|section| offset as u64 + width <= section.data.len() as u64);
+            if !in_bounds || symbol >= symbols.len() {
+                return Err(ObjectError::new(format!(
+                    "Relocation at {:#x} is out of bounds.",
+                    offset
+                )));
+            }
+
+            relocations.push(Relocation {
+                section,
+                offset,
+                symbol,
+                kind,
+                addend,
+            });
+        }
+
+        if reader.position != bytes.len() {
+            return Err(ObjectError::new("Trailing bytes after object.".to_string()));
+        }
+
+        Ok(Object {
+            name,
+            sections,
+            symbols,
+            relocations,
+        })
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Writer {
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl Writer {
+    pub(crate) fn u16(&mut self, value: u16) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    pub(crate) fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend(value.as_bytes());
+    }
+}
+
+pub(crate) struct Reader<'a> {
+    pub(crate) bytes: &'a [u8],
+    pub(crate) position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn take(&mut self, length: usize) -> Result<&'a [u8], ObjectError> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position.saturating_add(length))
+            .ok_or_else(|| ObjectError::new("Unexpected end of object file.".to_string()))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, ObjectError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, ObjectError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub(crate) fn string(&mut self) -> Result<String, ObjectError> {
+        let length = self.u32()? as usize;
+        String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| ObjectError::new("Invalid UTF-8 in name.".to_string()))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ObjectError {
+    message: String,
+}
+
+impl ObjectError {
+    /// Creates a new Object Error with the given message.
+    pub fn new(message: String) -> ObjectError {
+        Self { message }
+    }
+}
+
+impl Display for ObjectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Object Error: {}", self.message)
+    }
+}
+
+impl Error for ObjectError {}
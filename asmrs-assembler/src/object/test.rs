@@ -0,0 +1,80 @@
+#[test]
+fn object_round_trip() {
+    use crate::object::{Binding, Object, Relocation, RelocationKind, Section, Symbol};
+
+    let object = Object {
+        name: "hello.asmrs".to_string(),
+        sections: vec![
+            Section {
+                name: ".text".to_string(),
+                alignment: 1,
+                data: vec![0xe8, 0x00, 0x00, 0xc3],
+                size: 4,
+            },
+            Section {
+                name: ".bss".to_string(),
+                alignment: 16,
+                data: vec![],
+                size: 0x100,
+            },
+        ],
+        symbols: vec![
+            Symbol {
+                name: "start".to_string(),
+                binding: Binding::Global,
+                section: Some(0),
+                value: 0,
+            },
+            Symbol {
+                name: "print".to_string(),
+                binding: Binding::Extern,
+                section: None,
+                value: 0,
+            },
+        ],
+        relocations: vec![Relocation {
+            section: 0,
+            offset: 1,
+            symbol: 1,
+            kind: RelocationKind::Relative16,
+            addend: -2,
+        }],
+    };
+
+    let bytes = object.to_bytes();
+    assert_eq!(&bytes[..4], b"AOBJ");
+
+    let output = Object::from_bytes(&bytes);
+    assert!(output.is_ok());
+    assert_eq!(output.unwrap(), object);
+}
+
+#[test]
+fn object_invalid() {
+    use crate::object::{Object, Relocation, RelocationKind, Section};
+
+    assert!(Object::from_bytes(b"ELF").is_err());
+
+    let mut object = Object {
+        name: String::new(),
+        sections: vec![Section {
+            name: ".text".to_string(),
+            alignment: 1,
+            data: vec![0x90],
+            size: 1,
+        }],
+        ..Object::default()
+    };
+    let bytes = object.to_bytes();
+    assert!(Object::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+    // relocation past the end of the section and without symbol
+    object.relocations.push(Relocation {
+        section: 0,
+        offset: 0,
+        symbol: 0,
+        kind: RelocationKind::Absolute,
+        addend: 0,
+    });
+    assert!(Object::from_bytes(&object.to_bytes()).is_err());
+}
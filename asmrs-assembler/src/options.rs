@@ -0,0 +1,83 @@
+use asmrs_assembler::format::Format;
+use asmrs_parser::lexer::token::Cpu;
+use std::path::PathBuf;
+
+/// Command line options of the assembler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// Source file to assemble
+    pub input: PathBuf,
+    /// File to write, the input with the extension of the format by default
+    pub output: PathBuf,
+    /// Processor the program has to run on
+    pub cpu: Cpu,
+    pub format: Format,
+    /// Listing file (.lst) to write
+    pub listing: Option<PathBuf>,
+    /// Map file to write
+    pub map: Option<PathBuf>,
+    /// Debug file (.dbg) to write
+    pub debug: Option<PathBuf>,
+}
+
+impl Options {
+    /// Parses options from command line arguments (without the program name).
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut input = None;
+        let mut output = None;
+        let mut cpu = Cpu::I8086;
+        let mut format = Format::Bin;
+        let mut listing = None;
+        let mut map = None;
+        let mut debug = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("Expected value after '{}'.", arg))
+            };
+
+            match arg.as_str() {
+                "--cpu" => cpu = value()?.parse()?,
+                "--format" => format = value()?.parse()?,
+                "--listing" => listing = Some(PathBuf::from(value()?)),
+                "--map" => map = Some(PathBuf::from(value()?)),
+                "--debug" => debug = Some(PathBuf::from(value()?)),
+                "-o" => output = Some(PathBuf::from(value()?)),
+                _ if arg.starts_with('-') => return Err(format!("Unknown option: '{}'", arg)),
+                _ if input.is_none() => input = Some(PathBuf::from(arg)),
+                _ => return Err(format!("Unexpected argument: '{}'", arg)),
+            }
+        }
+
+        let input = input.ok_or_else(|| "Expected input file.".to_string())?;
+        let output = output.unwrap_or_else(|| input.with_extension(extension(format)));
+
+        Ok(Options {
+            input,
+            output,
+            cpu,
+            format,
+            listing,
+            map,
+            debug,
+        })
+    }
+}
+
+/// File extension written for `format`
+fn extension(format: Format) -> &'static str {
+    match format {
+        Format::Bin => "bin",
+        Format::Com => "com",
+        Format::BootSector => "img",
+        Format::IntelHex => "hex",
+        Format::SRecord => "srec",
+        Format::Exe => "exe",
+        Format::Object => "o",
+    }
+}
+
+/// Usage text printed on invalid arguments.
+pub const USAGE: &str = "Usage: asmrs-assembler [--cpu 8086|186|286] \
+[--format bin|com|bootsector|ihex|srec|exe|obj] [--listing FILE] [--map FILE] [--debug FILE] [-o <output>] <input>";
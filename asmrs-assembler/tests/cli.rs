@@ -0,0 +1,273 @@
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+/// Writes `source` into a fresh directory for `name` and runs the assembler on it.
+fn assemble(name: &str, source: &str, args: &[&str]) -> (PathBuf, Output) {
+    let directory = std::env::temp_dir().join(format!("asmrs-cli-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let input = directory.join("input.asm");
+    fs::write(&input, source).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asmrs-assembler"))
+        .args(args)
+        .arg(&input)
+        .current_dir(&directory)
+        .output()
+        .unwrap();
+
+    (directory, output)
+}
+
+#[test]
+fn cli_format_bin() {
+    let (directory, output) = assemble("bin", "start: mov ax, 1\njmp start\n", &[]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let bytes = fs::read(directory.join("input.bin")).unwrap();
+    assert_eq!(bytes, [0xb8, 0x01, 0x00, 0xeb, 0xfb]);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn cli_format_com() {
+    let source = "mov dx, message\nmov ah, 9\nint 0x21\nret\nmessage: db \"Hi$\"\n";
+    let (directory, output) = assemble("com", source, &["--format", "com", "-o", "hi.com"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    // the image is addressed from 100h
+    let bytes = fs::read(directory.join("hi.com")).unwrap();
+    assert_eq!(
+        bytes,
+        [0xba, 0x08, 0x01, 0xb4, 0x09, 0xcd, 0x21, 0xc3, b'H', b'i', b'$']
+    );
+
+    fs::remove_dir_all(directory).unwrap();
+
+    let (directory, output) = assemble("com-origin", "org 0\nret\n", &["--format", "com"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Origin 0x0"));
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn cli_cpu() {
+    // data on the line after an 8086 shift or imul is not taken as their operands
+    let source = "shl ax, cl\ndb 5\nimul bx\ndb 1, 2\n";
+    let (directory, output) = assemble("cpu", source, &["--cpu", "8086"]);
+    assert!(output.status.success(), "{:?}", output);
+    let bytes = fs::read(directory.join("input.bin")).unwrap();
+    assert_eq!(bytes, [0xd3, 0xe0, 0x05, 0xf7, 0xeb, 0x01, 0x02]);
+    fs::remove_dir_all(directory).unwrap();
+
+    let (directory, output) = assemble("cpu-186", "shl ax, 4\n", &["--cpu", "8086"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("requires an 80186"));
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn cli_errors() {
+    let (directory, output) = assemble("unknown-format", "nop\n", &["--format", "elf"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Unknown format: 'elf'"));
+    fs::remove_dir_all(directory).unwrap();
+
+    let (directory, output) = assemble("undefined", "jmp nowhere\n", &[]);
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Assemble Error: at line: 0, column: 0: Undefined symbol: 'nowhere'\n"
+    );
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn cli_format_exe() {
+    let source = "\
+entry start
+start: mov ax, seg message
+    mov ds, ax
+    mov ax, 0x4c00
+    int 0x21
+section .data
+message: db \"Hi$\"
+";
+    let (directory, output) = assemble("exe", source, &["--format", "exe"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let bytes = fs::read(directory.join("input.exe")).unwrap();
+    let word = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+    assert_eq!(&bytes[..2], b"MZ");
+    // one relocation, for the segment of 'message'
+    assert_eq!(word(0x06), 1);
+    assert_eq!((word(0x1e), word(0x1c)), (0, 1));
+    // entry point CS:IP
+    assert_eq!((word(0x16), word(0x14)), (0, 0));
+    let image = &bytes[word(0x08) as usize * 16..];
+    assert_eq!(&image[..3], [0xb8, 0x01, 0x00]);
+    assert_eq!(&image[0x10..], b"Hi$");
+    fs::remove_dir_all(directory).unwrap();
+
+    let (directory, output) = assemble("exe-entry", "ret\n", &["--format", "exe"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Format Error: No entry point; set one with 'entry'.\n"
+    );
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn cli_format_obj() {
+    use asmrs_assembler::object::{Binding, Object};
+
+    let source = "global start\nextern print\nstart: call print\nret\n";
+    let (directory, output) = assemble("obj", source, &["--format", "obj"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let object = Object::from_bytes(&fs::read(directory.join("input.o")).unwrap()).unwrap();
+    assert_eq!(object.name, "input.asm");
+    assert_eq!(object.sections[0].data, [0xe8, 0x00, 0x00, 0xc3]);
+    assert_eq!(object.symbols[0].binding, Binding::Global);
+    assert_eq!(object.symbols[1].binding, Binding::Extern);
+    assert_eq!(object.relocations.len(), 1);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn cli_archive() {
+    let source = "global print
+extern putc
+print: call putc
+ret
+";
+    let (directory, output) = assemble("archive", source, &["--format", "obj"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let archive = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asmrs-assembler"))
+            .arg("archive")
+            .args(args)
+            .current_dir(&directory)
+            .output()
+            .unwrap()
+    };
+    let output = archive(&["create", "libprint.a", "input.o"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    // members are listed by the name they were added with, not their source file
+    let output = archive(&["list", "libprint.a"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "input.o:\n    T print\n    U putc\n"
+    );
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn cli_listing() {
+    let source = "start: mov ax, 1\njmp start\n";
+    let (directory, output) = assemble("listing", source, &["--listing", "input.lst"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let listing = fs::read_to_string(directory.join("input.lst")).unwrap();
+    let lines = listing.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "     1 0000:0000 B80100       start: mov ax, 1");
+    assert_eq!(lines[1], "     2 0000:0003 EBFB         jmp start");
+    assert!(lines.contains(&"start                    0000  .text        2"));
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn cli_format_bootsector() {
+    let (directory, output) = assemble(
+        "bootsector",
+        "start: jmp start\n",
+        &["--format", "bootsector"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+
+    let bytes = fs::read(directory.join("input.img")).unwrap();
+    assert_eq!(bytes.len(), 512);
+    assert_eq!(&bytes[..2], [0xeb, 0xfe]);
+    assert_eq!(&bytes[510..], [0x55, 0xaa]);
+    fs::remove_dir_all(directory).unwrap();
+
+    let (directory, output) = assemble(
+        "bootsector-size",
+        "resb 510\nnop\n",
+        &["--format", "bootsector"],
+    );
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Format Error: Image of 511 bytes exceeds the boot sector limit of 510 bytes.\n"
+    );
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn cli_format_hex() {
+    let source = "org 0x100\nmov ax, 1\n";
+    let (directory, output) = assemble("ihex", source, &["--format", "ihex"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let hex = fs::read_to_string(directory.join("input.hex")).unwrap();
+    assert_eq!(hex, ":020000020000FC\n:03010000B8010043\n:00000001FF\n");
+    fs::remove_dir_all(directory).unwrap();
+
+    let (directory, output) = assemble("srec", source, &["--format", "srec"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let srec = fs::read_to_string(directory.join("input.srec")).unwrap();
+    assert_eq!(srec, "S008000061736D7273D1\nS1060100B801003F\nS9030100FB\n");
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn cli_map() {
+    let source = "start: mov ax, 1\nsection .data\nmessage: db \"Hi\"\n";
+    let (directory, output) = assemble("map", source, &["--format", "com", "--map", "hi.map"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let map = fs::read_to_string(directory.join("hi.map")).unwrap();
+    let lines = map.lines().collect::<Vec<_>>();
+    assert_eq!(lines[2], ".text            0000:0100  0000:0100  00003");
+    assert_eq!(lines[3], ".data            0000:0103  0000:0103  00002");
+    assert_eq!(
+        lines[7],
+        "0000:0100  start                    .text        input.asm"
+    );
+    assert_eq!(
+        lines[8],
+        "0000:0103  message                  .data        input.asm"
+    );
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn cli_debug() {
+    let source = "start:\n    mov cx, 10\n.loop:\n    loop .loop\n";
+    let (directory, output) = assemble("debug", source, &["--format", "com", "--debug", "hi.dbg"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let debug = fs::read_to_string(directory.join("hi.dbg")).unwrap();
+    assert_eq!(
+        debug,
+        "\
+asmrs-debug 1
+file input.asm
+line 0000:0100 0003 0 1 4
+line 0000:0103 0002 0 3 4
+scope start 0000:0100 0105 -
+scope .loop 0000:0103 0105 0
+symbol start 0000:0100 -
+symbol .loop 0000:0103 0
+"
+    );
+    fs::remove_dir_all(directory).unwrap();
+}
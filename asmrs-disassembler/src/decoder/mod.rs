@@ -0,0 +1,854 @@
+use asmrs_parser::lexer::token::{
+    Condition, Cpu, FloatingPointRegister, GeneralPurposeRegister, InstructionType, OperandSize,
+    RegisterType, SegmentRegister, SpecialPurposeRegister,
+};
+use std::{error::Error, fmt::Display};
+
+mod test;
+
+/// Decoded machine instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    /// Offset of the first byte (prefixes included)
+    pub address: u16,
+    /// Raw bytes (prefixes included)
+    pub bytes: Vec<u8>,
+    /// Repeat or lock prefix
+    pub prefix: Option<InstructionType>,
+    /// Segment override of the implicit memory operand of string instructions and xlat
+    pub segment: Option<SegmentRegister>,
+    pub r#type: InstructionType,
+    pub operands: Vec<Operand>,
+    /// Indirect far call or jump through a memory operand
+    pub far: bool,
+}
+
+/// Operand of a decoded instruction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Register(RegisterType),
+    Immediate(u16),
+    Memory(Memory),
+    /// Absolute offset of a relative jump, call or loop
+    Target(u16),
+    /// Direct far pointer segment:offset
+    Far {
+        segment: u16,
+        offset: u16,
+    },
+}
+
+/// Memory operand, addressed through ModRM or a direct offset
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Memory {
+    /// Explicit size, set when it cannot be inferred from another operand
+    pub size: Option<OperandSize>,
+    /// Segment override prefix
+    pub segment: Option<SegmentRegister>,
+    pub base: Option<RegisterType>,
+    pub index: Option<RegisterType>,
+    /// Signed displacement, or the offset itself without base and index
+    pub displacement: u16,
+}
+
+impl Display for Memory {
+    /// Writes the operand in NASM syntax, e.g. `word [es:bp+si-0x2]`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(size) = self.size {
+            write!(f, "{} ", size)?;
+        }
+
+        write!(f, "[")?;
+
+        if let Some(segment) = self.segment {
+            write!(f, "{}:", RegisterType::Segment(segment))?;
+        }
+
+        let registers = [self.base, self.index]
+            .into_iter()
+            .flatten()
+            .map(|register| register.to_string())
+            .collect::<Vec<_>>()
+            .join("+");
+
+        if registers.is_empty() {
+            write!(f, "{:#x}", self.displacement)?;
+        } else {
+            write!(f, "{}", registers)?;
+
+            let displacement = self.displacement as i16;
+            if displacement < 0 {
+                write!(f, "-{:#x}", displacement.unsigned_abs())?;
+            } else if displacement > 0 {
+                write!(f, "+{:#x}", displacement)?;
+            }
+        }
+
+        write!(f, "]")
+    }
+}
+
+/// Decodes the instruction at the start of `bytes`, located at offset `address`.
+/// Opcodes introduced after `cpu` are rejected like undefined opcodes.
+pub fn decode(bytes: &[u8], address: u16, cpu: Cpu) -> Result<Instruction, DecodeError> {
+    let mut decoder = Decoder {
+        bytes,
+        position: 0,
+        address,
+        segment: None,
+    };
+
+    let mut prefix = None;
+
+    let opcode = loop {
+        let byte = decoder.byte()?;
+
+        let (repeat_or_lock, segment) = match byte {
+            0x26 => (None, Some(SegmentRegister::Es)),
+            0x2e => (None, Some(SegmentRegister::Cs)),
+            0x36 => (None, Some(SegmentRegister::Ss)),
+            0x3e => (None, Some(SegmentRegister::Ds)),
+            0xf0 => (Some(InstructionType::Lock), None),
+            0xf2 => (Some(InstructionType::Repne), None),
+            0xf3 => (Some(InstructionType::Rep), None),
+            _ => break byte,
+        };
+
+        if repeat_or_lock.is_some() && prefix.is_some()
+            || segment.is_some() && decoder.segment.is_some()
+        {
+            return Err(decoder.error("Repeated prefix."));
+        }
+
+        prefix = prefix.or(repeat_or_lock);
+        decoder.segment = decoder.segment.or(segment);
+    };
+
+    let (r#type, operands, far) = decoder.instruction(opcode, cpu)?;
+
+    let has_memory = operands
+        .iter()
+        .any(|operand| matches!(operand, Operand::Memory(_)));
+    let segment = match decoder.segment {
+        Some(_) if has_memory => None,
+        Some(_) if !r#type.has_implicit_memory() => {
+            return Err(decoder.error("Segment override without memory operand."))
+        }
+        segment => segment,
+    };
+
+    Ok(Instruction {
+        address,
+        bytes: bytes[..decoder.position].to_vec(),
+        prefix,
+        segment,
+        r#type,
+        operands,
+        far,
+    })
+}
+
+/// Arithmetic/logic operations in the order of their encoding
+const ARITHMETIC: [InstructionType; 8] = [
+    InstructionType::Add,
+    InstructionType::Or,
+    InstructionType::Adc,
+    InstructionType::Sbb,
+    InstructionType::And,
+    InstructionType::Sub,
+    InstructionType::Xor,
+    InstructionType::Cmp,
+];
+
+/// Shift/rotate operations in the order of their encoding (`/6` is undefined)
+const SHIFTS: [Option<InstructionType>; 8] = [
+    Some(InstructionType::Rol),
+    Some(InstructionType::Ror),
+    Some(InstructionType::Rcl),
+    Some(InstructionType::Rcr),
+    Some(InstructionType::Shl),
+    Some(InstructionType::Shr),
+    None,
+    Some(InstructionType::Sar),
+];
+
+type Decoded = (InstructionType, Vec<Operand>, bool);
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    address: u16,
+    segment: Option<SegmentRegister>,
+}
+
+impl Decoder<'_> {
+    fn instruction(&mut self, opcode: u8, cpu: Cpu) -> Result<Decoded, DecodeError> {
+        use InstructionType as I;
+
+        let wide = opcode & 1 == 1;
+        let simple = |r#type| Ok((r#type, Vec::new(), false));
+
+        if cpu < Cpu::I80186 && matches!(opcode, 0x60..=0x6f | 0xc0 | 0xc1 | 0xc8 | 0xc9) {
+            return Err(self.error("Opcode requires an 80186 or later."));
+        }
+
+        match opcode {
+            // add/or/adc/sbb/and/sub/xor/cmp in all six forms
+            0x00..=0x3f if opcode & 0b111 < 6 => {
+                let r#type = ARITHMETIC[(opcode >> 3) as usize];
+                let operands = match opcode & 0b111 {
+                    0..=3 => self.modrm_operands(opcode & 0b10 != 0, wide)?,
+                    _ => vec![Operand::Register(register(0, wide)), self.immediate(wide)?],
+                };
+                Ok((r#type, operands, false))
+            }
+            0x06 | 0x0e | 0x16 | 0x1e => Ok((
+                I::Push,
+                vec![Operand::Register(RegisterType::Segment(segment(
+                    opcode >> 3,
+                )))],
+                false,
+            )),
+            0x07 | 0x17 | 0x1f => Ok((
+                I::Pop,
+                vec![Operand::Register(RegisterType::Segment(segment(
+                    opcode >> 3,
+                )))],
+                false,
+            )),
+            0x27 => simple(I::Daa),
+            0x2f => simple(I::Das),
+            0x37 => simple(I::Aaa),
+            0x3f => simple(I::Aas),
+            0x40..=0x5f => {
+                let r#type = [I::Inc, I::Dec, I::Push, I::Pop][((opcode - 0x40) >> 3) as usize];
+                Ok((
+                    r#type,
+                    vec![Operand::Register(register(opcode & 0b111, true))],
+                    false,
+                ))
+            }
+            0x60 => simple(I::Pusha),
+            0x61 => simple(I::Popa),
+            0x62 => {
+                let (reg, rm) = self.modrm(true)?;
+                if !matches!(rm, Operand::Memory(_)) {
+                    return Err(self.error("Expected memory operand."));
+                }
+                Ok((
+                    I::Bound,
+                    vec![Operand::Register(register(reg, true)), rm],
+                    false,
+                ))
+            }
+            0x68 => Ok((I::Push, vec![self.immediate(true)?], false)),
+            0x6a => Ok((I::Push, vec![self.sign_extended()?], false)),
+            0x69 | 0x6b => {
+                let mut operands = self.modrm_operands(true, true)?;
+                operands.push(if opcode == 0x69 {
+                    self.immediate(true)?
+                } else {
+                    self.sign_extended()?
+                });
+                Ok((I::Imul, operands, false))
+            }
+            0x6c => simple(I::Insb),
+            0x6d => simple(I::Insw),
+            0x6e => simple(I::Outsb),
+            0x6f => simple(I::Outsw),
+            0x70..=0x7f => Ok((
+                I::Jcc(Condition::ALL[(opcode & 0x0f) as usize]),
+                vec![self.relative(false)?],
+                false,
+            )),
+            0x80 | 0x81 | 0x83 => {
+                let (reg, rm) = self.modrm(wide)?;
+                let immediate = if opcode == 0x83 {
+                    self.sign_extended()?
+                } else {
+                    self.immediate(wide)?
+                };
+                Ok((
+                    ARITHMETIC[reg as usize],
+                    vec![sized(rm, wide), immediate],
+                    false,
+                ))
+            }
+            0x84 | 0x85 => Ok((I::Test, self.modrm_operands(false, wide)?, false)),
+            0x86 | 0x87 => Ok((I::Xchg, self.modrm_operands(false, wide)?, false)),
+            0x88..=0x8b => Ok((
+                I::Mov,
+                self.modrm_operands(opcode & 0b10 != 0, wide)?,
+                false,
+            )),
+            0x8c | 0x8e => {
+                let (reg, rm) = self.modrm(true)?;
+                if reg > 3 {
+                    return Err(self.error("Invalid segment register."));
+                }
+                let segment = Operand::Register(RegisterType::Segment(segment(reg)));
+                let operands = if opcode == 0x8c {
+                    vec![rm, segment]
+                } else {
+                    vec![segment, rm]
+                };
+                Ok((I::Mov, operands, false))
+            }
+            0x8d | 0xc4 | 0xc5 => {
+                let (reg, rm) = self.modrm(true)?;
+                if !matches!(rm, Operand::Memory(_)) {
+                    return Err(self.error("Expected memory operand."));
+                }
+                let r#type = match opcode {
+                    0x8d => I::Lea,
+                    0xc4 => I::Les,
+                    _ => I::Lds,
+                };
+                Ok((
+                    r#type,
+                    vec![Operand::Register(register(reg, true)), rm],
+                    false,
+                ))
+            }
+            0x8f => {
+                let (reg, rm) = self.modrm(true)?;
+                if reg != 0 {
+                    return Err(self.error("Undefined opcode extension."));
+                }
+                Ok((I::Pop, vec![sized(rm, true)], false))
+            }
+            0x90 => simple(I::Nop),
+            0x91..=0x97 => Ok((
+                I::Xchg,
+                vec![
+                    Operand::Register(register(0, true)),
+                    Operand::Register(register(opcode & 0b111, true)),
+                ],
+                false,
+            )),
+            0x98 => simple(I::Cbw),
+            0x99 => simple(I::Cwd),
+            0x9a | 0xea => {
+                let offset = self.word()?;
+                let segment = self.word()?;
+                let r#type = if opcode == 0x9a { I::Call } else { I::Jmp };
+                Ok((r#type, vec![Operand::Far { segment, offset }], false))
+            }
+            0x9b => simple(I::Wait),
+            0x9c => simple(I::Pushf),
+            0x9d => simple(I::Popf),
+            0x9e => simple(I::Sahf),
+            0x9f => simple(I::Lahf),
+            0xa0..=0xa3 => {
+                let memory = Operand::Memory(Memory {
+                    size: None,
+                    segment: self.segment,
+                    base: None,
+                    index: None,
+                    displacement: self.word()?,
+                });
+                let accumulator = Operand::Register(register(0, wide));
+                let operands = if opcode < 0xa2 {
+                    vec![accumulator, memory]
+                } else {
+                    vec![memory, accumulator]
+                };
+                Ok((I::Mov, operands, false))
+            }
+            0xa4 => simple(I::Movsb),
+            0xa5 => simple(I::Movsw),
+            0xa6 => simple(I::Cmpsb),
+            0xa7 => simple(I::Cmpsw),
+            0xa8 | 0xa9 => Ok((
+                I::Test,
+                vec![Operand::Register(register(0, wide)), self.immediate(wide)?],
+                false,
+            )),
+            0xaa => simple(I::Stosb),
+            0xab => simple(I::Stosw),
+            0xac => simple(I::Lodsb),
+            0xad => simple(I::Lodsw),
+            0xae => simple(I::Scasb),
+            0xaf => simple(I::Scasw),
+            0xb0..=0xbf => {
+                let wide = opcode >= 0xb8;
+                Ok((
+                    I::Mov,
+                    vec![
+                        Operand::Register(register(opcode & 0b111, wide)),
+                        self.immediate(wide)?,
+                    ],
+                    false,
+                ))
+            }
+            0xc0 | 0xc1 | 0xd0..=0xd3 => {
+                let (reg, rm) = self.modrm(wide)?;
+                let r#type = SHIFTS[reg as usize]
+                    .ok_or_else(|| self.error("Undefined opcode extension."))?;
+                let count = match opcode {
+                    0xc0 | 0xc1 => Operand::Immediate(self.byte()? as u16),
+                    0xd0 | 0xd1 => Operand::Immediate(1),
+                    _ => {
+                        Operand::Register(RegisterType::GeneralPurpose(GeneralPurposeRegister::Cl))
+                    }
+                };
+                Ok((r#type, vec![sized(rm, wide), count], false))
+            }
+            0xc2 => Ok((I::Ret, vec![self.immediate(true)?], false)),
+            0xc3 => simple(I::Ret),
+            0xc6 | 0xc7 => {
+                let (reg, rm) = self.modrm(wide)?;
+                if reg != 0 {
+                    return Err(self.error("Undefined opcode extension."));
+                }
+                Ok((I::Mov, vec![sized(rm, wide), self.immediate(wide)?], false))
+            }
+            0xc8 => {
+                let size = self.immediate(true)?;
+                let level = Operand::Immediate(self.byte()? as u16);
+                Ok((I::Enter, vec![size, level], false))
+            }
+            0xc9 => simple(I::Leave),
+            0xca => Ok((I::Retf, vec![self.immediate(true)?], false)),
+            0xcb => simple(I::Retf),
+            0xcc => simple(I::Int3),
+            0xcd => Ok((I::Int, vec![self.immediate(false)?], false)),
+            0xce => simple(I::Into),
+            0xcf => simple(I::Iret),
+            0xd4 | 0xd5 => {
+                let r#type = if opcode == 0xd4 { I::Aam } else { I::Aad };
+                match self.byte()? {
+                    10 => simple(r#type),
+                    base => Ok((r#type, vec![Operand::Immediate(base as u16)], false)),
+                }
+            }
+            0xd7 => simple(I::Xlat),
+            0xd8..=0xdf => self.floating_point(opcode),
+            0xe0..=0xe3 => {
+                let r#type = [I::Loopne, I::Loope, I::Loop, I::Jcxz][(opcode & 0b11) as usize];
+                Ok((r#type, vec![self.relative(false)?], false))
+            }
+            0xe4 | 0xe5 => Ok((
+                I::In,
+                vec![Operand::Register(register(0, wide)), self.immediate(false)?],
+                false,
+            )),
+            0xe6 | 0xe7 => Ok((
+                I::Out,
+                vec![self.immediate(false)?, Operand::Register(register(0, wide))],
+                false,
+            )),
+            0xe8 => Ok((I::Call, vec![self.relative(true)?], false)),
+            0xe9 => Ok((I::Jmp, vec![self.relative(true)?], false)),
+            0xeb => Ok((I::Jmp, vec![self.relative(false)?], false)),
+            0xec | 0xed => Ok((
+                I::In,
+                vec![Operand::Register(register(0, wide)), dx()],
+                false,
+            )),
+            0xee | 0xef => Ok((
+                I::Out,
+                vec![dx(), Operand::Register(register(0, wide))],
+                false,
+            )),
+            0xf4 => simple(I::Hlt),
+            0xf5 => simple(I::Cmc),
+            0xf6 | 0xf7 => {
+                let (reg, rm) = self.modrm(wide)?;
+                let rm = sized(rm, wide);
+                match reg {
+                    0 => Ok((I::Test, vec![rm, self.immediate(wide)?], false)),
+                    2..=7 => {
+                        let r#type =
+                            [I::Not, I::Neg, I::Mul, I::Imul, I::Div, I::Idiv][reg as usize - 2];
+                        Ok((r#type, vec![rm], false))
+                    }
+                    _ => Err(self.error("Undefined opcode extension.")),
+                }
+            }
+            0xf8 => simple(I::Clc),
+            0xf9 => simple(I::Stc),
+            0xfa => simple(I::Cli),
+            0xfb => simple(I::Sti),
+            0xfc => simple(I::Cld),
+            0xfd => simple(I::Std),
+            0xfe => {
+                let (reg, rm) = self.modrm(false)?;
+                match reg {
+                    0 => Ok((I::Inc, vec![sized(rm, false)], false)),
+                    1 => Ok((I::Dec, vec![sized(rm, false)], false)),
+                    _ => Err(self.error("Undefined opcode extension.")),
+                }
+            }
+            0xff => {
+                let (reg, rm) = self.modrm(true)?;
+                let far = matches!(reg, 3 | 5);
+                if far && !matches!(rm, Operand::Memory(_)) {
+                    return Err(self.error("Expected memory operand."));
+                }
+                let r#type = match reg {
+                    0 => I::Inc,
+                    1 => I::Dec,
+                    2 | 3 => I::Call,
+                    4 | 5 => I::Jmp,
+                    6 => I::Push,
+                    _ => return Err(self.error("Undefined opcode extension.")),
+                };
+                let rm = if matches!(reg, 0 | 1 | 6) {
+                    sized(rm, true)
+                } else {
+                    rm
+                };
+                Ok((r#type, vec![rm], far))
+            }
+            _ => Err(self.error("Undefined opcode.")),
+        }
+    }
+
+    /// Decodes ESC opcodes D8h-DFh as 8087 instructions.
+    fn floating_point(&mut self, opcode: u8) -> Result<Decoded, DecodeError> {
+        use InstructionType as I;
+        use OperandSize as S;
+
+        let escape = opcode & 0b111;
+        let modrm = *self
+            .bytes
+            .get(self.position)
+            .ok_or_else(|| self.error("Unexpected end of input."))?;
+        let reg = (modrm >> 3) & 0b111;
+
+        if modrm >> 6 != 0b11 {
+            let (_, rm) = self.modrm(true)?;
+            let Operand::Memory(mut memory) = rm else {
+                unreachable!()
+            };
+
+            let arithmetic = |integer: bool| {
+                [
+                    [I::Fadd, I::Fiadd],
+                    [I::Fmul, I::Fimul],
+                    [I::Fcom, I::Ficom],
+                    [I::Fcomp, I::Ficomp],
+                    [I::Fsub, I::Fisub],
+                    [I::Fsubr, I::Fisubr],
+                    [I::Fdiv, I::Fidiv],
+                    [I::Fdivr, I::Fidivr],
+                ][reg as usize][integer as usize]
+            };
+
+            let (r#type, size) = match (escape, reg) {
+                (0, _) => (arithmetic(false), Some(S::Dword)),
+                (1, 0) => (I::Fld, Some(S::Dword)),
+                (1, 2) => (I::Fst, Some(S::Dword)),
+                (1, 3) => (I::Fstp, Some(S::Dword)),
+                (1, 4) => (I::Fldenv, None),
+                (1, 5) => (I::Fldcw, None),
+                (1, 6) => (I::Fnstenv, None),
+                (1, 7) => (I::Fnstcw, None),
+                (2, _) => (arithmetic(true), Some(S::Dword)),
+                (3, 0) => (I::Fild, Some(S::Dword)),
+                (3, 2) => (I::Fist, Some(S::Dword)),
+                (3, 3) => (I::Fistp, Some(S::Dword)),
+                (3, 5) => (I::Fld, Some(S::Tword)),
+                (3, 7) => (I::Fstp, Some(S::Tword)),
+                (4, _) => (arithmetic(false), Some(S::Qword)),
+                (5, 0) => (I::Fld, Some(S::Qword)),
+                (5, 2) => (I::Fst, Some(S::Qword)),
+                (5, 3) => (I::Fstp, Some(S::Qword)),
+                (5, 4) => (I::Frstor, None),
+                (5, 6) => (I::Fnsave, None),
+                (5, 7) => (I::Fnstsw, None),
+                (6, _) => (arithmetic(true), Some(S::Word)),
+                (7, 0) => (I::Fild, Some(S::Word)),
+                (7, 2) => (I::Fist, Some(S::Word)),
+                (7, 3) => (I::Fistp, Some(S::Word)),
+                (7, 4) => (I::Fbld, Some(S::Tword)),
+                (7, 5) => (I::Fild, Some(S::Qword)),
+                (7, 6) => (I::Fbstp, Some(S::Tword)),
+                (7, 7) => (I::Fistp, Some(S::Qword)),
+                _ => return Err(self.error("Undefined coprocessor opcode.")),
+            };
+
+            memory.size = size;
+            return Ok((r#type, vec![Operand::Memory(memory)], false));
+        }
+
+        self.position += 1;
+        let st = |index: u8| {
+            Operand::Register(RegisterType::FloatingPoint(
+                FloatingPointRegister::from_index(index).unwrap(),
+            ))
+        };
+        let i = modrm & 0b111;
+
+        let constant = |r#type| Ok((r#type, Vec::new(), false));
+
+        match (escape, reg) {
+            // fop st, st(i)
+            (0, _) => {
+                let r#type = [
+                    I::Fadd,
+                    I::Fmul,
+                    I::Fcom,
+                    I::Fcomp,
+                    I::Fsub,
+                    I::Fsubr,
+                    I::Fdiv,
+                    I::Fdivr,
+                ][reg as usize];
+                let operands = if matches!(r#type, I::Fcom | I::Fcomp) {
+                    vec![st(i)]
+                } else {
+                    vec![st(0), st(i)]
+                };
+                Ok((r#type, operands, false))
+            }
+            (1, 0) => Ok((I::Fld, vec![st(i)], false)),
+            (1, 1) => Ok((I::Fxch, vec![st(i)], false)),
+            (1, _) => match modrm {
+                0xd0 => constant(I::Fnop),
+                0xe0 => constant(I::Fchs),
+                0xe1 => constant(I::Fabs),
+                0xe4 => constant(I::Ftst),
+                0xe5 => constant(I::Fxam),
+                0xe8 => constant(I::Fld1),
+                0xe9 => constant(I::Fldl2t),
+                0xea => constant(I::Fldl2e),
+                0xeb => constant(I::Fldpi),
+                0xec => constant(I::Fldlg2),
+                0xed => constant(I::Fldln2),
+                0xee => constant(I::Fldz),
+                0xf0 => constant(I::F2xm1),
+                0xf1 => constant(I::Fyl2x),
+                0xf2 => constant(I::Fptan),
+                0xf3 => constant(I::Fpatan),
+                0xf4 => constant(I::Fxtract),
+                0xf6 => constant(I::Fdecstp),
+                0xf7 => constant(I::Fincstp),
+                0xf8 => constant(I::Fprem),
+                0xf9 => constant(I::Fyl2xp1),
+                0xfa => constant(I::Fsqrt),
+                0xfc => constant(I::Frndint),
+                0xfd => constant(I::Fscale),
+                _ => Err(self.error("Undefined coprocessor opcode.")),
+            },
+            (3, 4) => match modrm {
+                0xe0 => constant(I::Fneni),
+                0xe1 => constant(I::Fndisi),
+                0xe2 => constant(I::Fnclex),
+                0xe3 => constant(I::Fninit),
+                _ => Err(self.error("Undefined coprocessor opcode.")),
+            },
+            // fop st(i), st
+            (4, 0 | 1 | 4..=7) => {
+                let r#type = [
+                    I::Fadd,
+                    I::Fmul,
+                    I::Fcom,
+                    I::Fcomp,
+                    I::Fsubr,
+                    I::Fsub,
+                    I::Fdivr,
+                    I::Fdiv,
+                ][reg as usize];
+                Ok((r#type, vec![st(i), st(0)], false))
+            }
+            (5, 0) => Ok((I::Ffree, vec![st(i)], false)),
+            (5, 2) => Ok((I::Fst, vec![st(i)], false)),
+            (5, 3) => Ok((I::Fstp, vec![st(i)], false)),
+            (6, 3) if i == 1 => constant(I::Fcompp),
+            // fopp st(i), st
+            (6, 0 | 1 | 4..=7) => {
+                let r#type = [
+                    I::Faddp,
+                    I::Fmulp,
+                    I::Fcom,
+                    I::Fcomp,
+                    I::Fsubrp,
+                    I::Fsubp,
+                    I::Fdivrp,
+                    I::Fdivp,
+                ][reg as usize];
+                Ok((r#type, vec![st(i), st(0)], false))
+            }
+            _ => Err(self.error("Undefined coprocessor opcode.")),
+        }
+    }
+
+    /// Decodes a ModRM byte into a `reg, r/m` or `r/m, reg` operand pair.
+    fn modrm_operands(&mut self, reg_first: bool, wide: bool) -> Result<Vec<Operand>, DecodeError> {
+        let (reg, rm) = self.modrm(wide)?;
+        let reg = Operand::Register(register(reg, wide));
+
+        Ok(if reg_first {
+            vec![reg, rm]
+        } else {
+            vec![rm, reg]
+        })
+    }
+
+    /// Decodes a ModRM byte (and its displacement) into the reg field and the r/m operand.
+    fn modrm(&mut self, wide: bool) -> Result<(u8, Operand), DecodeError> {
+        let modrm = self.byte()?;
+        let (mode, reg, rm) = (modrm >> 6, (modrm >> 3) & 0b111, modrm & 0b111);
+
+        if mode == 0b11 {
+            return Ok((reg, Operand::Register(register(rm, wide))));
+        }
+
+        let bx = Some(RegisterType::GeneralPurpose(GeneralPurposeRegister::Bx));
+        let bp = Some(RegisterType::SpecialPurpose(SpecialPurposeRegister::Bp));
+        let si = Some(RegisterType::SpecialPurpose(SpecialPurposeRegister::Si));
+        let di = Some(RegisterType::SpecialPurpose(SpecialPurposeRegister::Di));
+
+        let (base, index) = match rm {
+            0 => (bx, si),
+            1 => (bx, di),
+            2 => (bp, si),
+            3 => (bp, di),
+            4 => (si, None),
+            5 => (di, None),
+            6 if mode == 0 => (None, None),
+            6 => (bp, None),
+            _ => (bx, None),
+        };
+
+        let displacement = match mode {
+            0 if rm == 6 => self.word()?,
+            0 => 0,
+            1 => self.byte()? as i8 as u16,
+            _ => self.word()?,
+        };
+
+        Ok((
+            reg,
+            Operand::Memory(Memory {
+                size: None,
+                segment: self.segment,
+                base,
+                index,
+                displacement,
+            }),
+        ))
+    }
+
+    fn immediate(&mut self, wide: bool) -> Result<Operand, DecodeError> {
+        let value = if wide {
+            self.word()?
+        } else {
+            self.byte()? as u16
+        };
+        Ok(Operand::Immediate(value))
+    }
+
+    fn sign_extended(&mut self) -> Result<Operand, DecodeError> {
+        Ok(Operand::Immediate(self.byte()? as i8 as u16))
+    }
+
+    /// Reads a relative displacement and resolves it against the end of the instruction.
+    fn relative(&mut self, wide: bool) -> Result<Operand, DecodeError> {
+        let displacement = if wide {
+            self.word()?
+        } else {
+            self.byte()? as i8 as u16
+        };
+        let next = self.address.wrapping_add(self.position as u16);
+        Ok(Operand::Target(next.wrapping_add(displacement)))
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self
+            .bytes
+            .get(self.position)
+            .ok_or_else(|| self.error("Unexpected end of input."))?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn word(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes([self.byte()?, self.byte()?]))
+    }
+
+    fn error(&self, message: &str) -> DecodeError {
+        DecodeError::new(message.to_string(), self.address)
+    }
+}
+
+/// Register encoded as `index` in a reg or r/m field
+fn register(index: u8, wide: bool) -> RegisterType {
+    use GeneralPurposeRegister as G;
+    use SpecialPurposeRegister as S;
+
+    match (wide, index) {
+        (false, 0) => RegisterType::GeneralPurpose(G::Al),
+        (false, 1) => RegisterType::GeneralPurpose(G::Cl),
+        (false, 2) => RegisterType::GeneralPurpose(G::Dl),
+        (false, 3) => RegisterType::GeneralPurpose(G::Bl),
+        (false, 4) => RegisterType::GeneralPurpose(G::Ah),
+        (false, 5) => RegisterType::GeneralPurpose(G::Ch),
+        (false, 6) => RegisterType::GeneralPurpose(G::Dh),
+        (false, _) => RegisterType::GeneralPurpose(G::Bh),
+        (true, 0) => RegisterType::GeneralPurpose(G::Ax),
+        (true, 1) => RegisterType::GeneralPurpose(G::Cx),
+        (true, 2) => RegisterType::GeneralPurpose(G::Dx),
+        (true, 3) => RegisterType::GeneralPurpose(G::Bx),
+        (true, 4) => RegisterType::SpecialPurpose(S::Sp),
+        (true, 5) => RegisterType::SpecialPurpose(S::Bp),
+        (true, 6) => RegisterType::SpecialPurpose(S::Si),
+        (true, _) => RegisterType::SpecialPurpose(S::Di),
+    }
+}
+
+/// Segment register encoded as `index` (only the low two bits are used)
+fn segment(index: u8) -> SegmentRegister {
+    match index & 0b11 {
+        0 => SegmentRegister::Es,
+        1 => SegmentRegister::Cs,
+        2 => SegmentRegister::Ss,
+        _ => SegmentRegister::Ds,
+    }
+}
+
+fn dx() -> Operand {
+    Operand::Register(RegisterType::GeneralPurpose(GeneralPurposeRegister::Dx))
+}
+
+/// Adds an explicit size to memory operands whose size is not implied by a register.
+fn sized(operand: Operand, wide: bool) -> Operand {
+    match operand {
+        Operand::Memory(memory) => Operand::Memory(Memory {
+            size: Some(if wide {
+                OperandSize::Word
+            } else {
+                OperandSize::Byte
+            }),
+            ..memory
+        }),
+        operand => operand,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DecodeError {
+    message: String,
+    address: u16,
+}
+
+impl DecodeError {
+    /// Creates a new Decode Error with the given message and instruction address.
+    pub fn new(message: String, address: u16) -> DecodeError {
+        Self { message, address }
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Decode Error: at address: {:04x}: {}",
+            self.address, self.message
+        )
+    }
+}
+
+impl Error for DecodeError {}
@@ -0,0 +1,118 @@
+#[test]
+fn decode_modrm() {
+    use crate::decoder::{decode, Memory, Operand};
+    use asmrs_parser::lexer::token::{
+        Cpu, GeneralPurposeRegister, InstructionType, RegisterType, SegmentRegister,
+        SpecialPurposeRegister,
+    };
+
+    // mov [es:bp-0x2], al
+    let output = decode(&[0x26, 0x88, 0x46, 0xfe], 0x100, Cpu::I8086);
+    assert!(output.is_ok());
+    let output = output.unwrap();
+
+    assert_eq!(output.address, 0x100);
+    assert_eq!(output.bytes, vec![0x26, 0x88, 0x46, 0xfe]);
+    assert_eq!(output.r#type, InstructionType::Mov);
+    assert_eq!(
+        output.operands,
+        vec![
+            Operand::Memory(Memory {
+                size: None,
+                segment: Some(SegmentRegister::Es),
+                base: Some(RegisterType::SpecialPurpose(SpecialPurposeRegister::Bp)),
+                index: None,
+                displacement: 0xfffe,
+            }),
+            Operand::Register(RegisterType::GeneralPurpose(GeneralPurposeRegister::Al)),
+        ]
+    );
+    let Operand::Memory(memory) = &output.operands[0] else {
+        panic!("expected memory operand");
+    };
+    assert_eq!(memory.to_string(), "[es:bp-0x2]");
+}
+
+#[test]
+fn decode_relative() {
+    use crate::decoder::{decode, Operand};
+    use asmrs_parser::lexer::token::{Condition, Cpu, InstructionType};
+
+    // je $+7
+    let output = decode(&[0x74, 0x05], 0x12, Cpu::I8086).unwrap();
+    assert_eq!(output.r#type, InstructionType::Jcc(Condition::E));
+    assert_eq!(output.operands, vec![Operand::Target(0x19)]);
+
+    // call $-2
+    let output = decode(&[0xe8, 0xfb, 0xff], 0x14, Cpu::I8086).unwrap();
+    assert_eq!(output.r#type, InstructionType::Call);
+    assert_eq!(output.operands, vec![Operand::Target(0x12)]);
+}
+
+#[test]
+fn decode_floating_point() {
+    use crate::decoder::{decode, Operand};
+    use asmrs_parser::lexer::token::{
+        Cpu, FloatingPointRegister, InstructionType, OperandSize, RegisterType,
+    };
+
+    // fistp qword [0x1000]
+    let output = decode(&[0xdf, 0x3e, 0x00, 0x10], 0, Cpu::I8086).unwrap();
+    assert_eq!(output.r#type, InstructionType::Fistp);
+    let Operand::Memory(memory) = &output.operands[0] else {
+        panic!("expected memory operand");
+    };
+    assert_eq!(memory.size, Some(OperandSize::Qword));
+    assert_eq!(memory.displacement, 0x1000);
+
+    // fsubp st(1), st(0)
+    let output = decode(&[0xde, 0xe9], 0, Cpu::I8086).unwrap();
+    assert_eq!(output.r#type, InstructionType::Fsubp);
+    assert_eq!(
+        output.operands[0],
+        Operand::Register(RegisterType::FloatingPoint(FloatingPointRegister::St1))
+    );
+}
+
+#[test]
+fn decode_invalid() {
+    use crate::decoder::decode;
+    use asmrs_parser::lexer::token::{Cpu, InstructionType};
+
+    // truncated immediate
+    assert!(decode(&[0xb8, 0x34], 0, Cpu::I8086).is_err());
+    // pop cs and undefined group extension
+    assert!(decode(&[0x0f], 0, Cpu::I8086).is_err());
+    assert!(decode(&[0xfe, 0xd0], 0, Cpu::I8086).is_err());
+    // segment override without memory operand
+    assert!(decode(&[0x2e, 0x90], 0, Cpu::I8086).is_err());
+
+    // pusha only exists on the 80186
+    assert!(decode(&[0x60], 0, Cpu::I8086).is_err());
+    let output = decode(&[0x60], 0, Cpu::I80186).unwrap();
+    assert_eq!(output.r#type, InstructionType::Pusha);
+}
+
+#[test]
+fn decode_segment_override() {
+    use crate::decoder::decode;
+    use asmrs_parser::lexer::token::{Cpu, InstructionType, SegmentRegister};
+
+    // cs lodsb
+    let output = decode(&[0x2e, 0xac], 0, Cpu::I8086).unwrap();
+    assert_eq!(output.r#type, InstructionType::Lodsb);
+    assert_eq!(output.segment, Some(SegmentRegister::Cs));
+    assert!(output.operands.is_empty());
+
+    // repne ss outsb
+    let output = decode(&[0xf2, 0x36, 0x6e], 0, Cpu::I80186).unwrap();
+    assert_eq!(output.prefix, Some(InstructionType::Repne));
+    assert_eq!(output.segment, Some(SegmentRegister::Ss));
+
+    // the override of a memory operand stays on the operand
+    let output = decode(&[0x26, 0x8b, 0x07], 0, Cpu::I8086).unwrap();
+    assert_eq!(output.segment, None);
+
+    // es inc ax
+    assert!(decode(&[0x26, 0x40], 0, Cpu::I8086).is_err());
+}
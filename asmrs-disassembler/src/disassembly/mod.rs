@@ -0,0 +1,253 @@
+use crate::decoder::{decode, Instruction, Operand};
+use asmrs_assembler::assembler::{assemble, Options as AssemblerOptions, Target};
+use asmrs_parser::lexer::{
+    token::{Cpu, RegisterType},
+    tokenize,
+};
+use std::{collections::BTreeSet, ops::Range};
+
+mod test;
+
+/// Number of bytes per `db` line
+const DATA_LINE_LENGTH: usize = 8;
+
+/// Column of the hex dump comment
+const HEX_COLUMN: usize = 32;
+
+/// How the bytes of a region are interpreted
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RegionKind {
+    Code,
+    Data,
+}
+
+/// Hint for a range of addresses (origin included, end exclusive)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub range: Range<u32>,
+    pub kind: RegionKind,
+}
+
+/// Disassembly settings
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// Address of the first byte, as set by `org`
+    pub origin: u16,
+    /// Processor whose instruction set is decoded
+    pub cpu: Cpu,
+    /// Region hints. Later hints take precedence, bytes without a hint are code.
+    pub regions: Vec<Region>,
+    /// Append the address and raw bytes of every line as a comment
+    pub hex: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            origin: 0,
+            cpu: Cpu::I8086,
+            regions: Vec::new(),
+            hex: false,
+        }
+    }
+}
+
+/// Decoded line of the image
+enum Line {
+    Instruction(Instruction),
+    Data { address: u16, bytes: Vec<u8> },
+}
+
+/// Disassembles a flat binary into asmrs source. Targets of jumps, calls and loops
+/// that start an instruction get a label; undefined opcodes are emitted as `db`, and so
+/// are instructions the assembler encodes differently, followed by the instruction as
+/// a comment.
+pub fn disassemble(image: &[u8], options: &Options) -> String {
+    let lines = decode_lines(image, options);
+
+    let starts = lines
+        .iter()
+        .filter_map(|line| match line {
+            Line::Instruction(instruction) => Some(instruction.address),
+            Line::Data { .. } => None,
+        })
+        .collect::<BTreeSet<_>>();
+
+    let labels = lines
+        .iter()
+        .filter_map(|line| match line {
+            Line::Instruction(instruction) => Some(instruction),
+            Line::Data { .. } => None,
+        })
+        .flat_map(|instruction| instruction.operands.iter())
+        .filter_map(|operand| match operand {
+            Operand::Target(target) if starts.contains(target) => Some(*target),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+
+    let mut output = String::new();
+
+    if options.origin != 0 {
+        output.push_str(&format!("    org {:#x}\n", options.origin));
+    }
+
+    for line in lines {
+        let (address, bytes, text) = match line {
+            Line::Instruction(instruction) => {
+                if labels.contains(&instruction.address) {
+                    output.push_str(&format!("{}:\n", label(instruction.address)));
+                }
+                let mut text = format_instruction(&instruction, &labels);
+                if !reassembles(&instruction, options.cpu) {
+                    text = format!("{} // {}", data(&instruction.bytes), text);
+                }
+                (instruction.address, instruction.bytes, text)
+            }
+            Line::Data { address, bytes } => {
+                let text = data(&bytes);
+                (address, bytes, text)
+            }
+        };
+
+        if options.hex {
+            let hex = bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(" ");
+            output.push_str(&format!(
+                "    {:<width$} // {:04x}: {}\n",
+                text,
+                address,
+                hex,
+                width = HEX_COLUMN - 1
+            ));
+        } else {
+            output.push_str(&format!("    {}\n", text));
+        }
+    }
+
+    output
+}
+
+/// Formats a decoded instruction, replacing jump targets by their label.
+pub fn format_instruction(instruction: &Instruction, labels: &BTreeSet<u16>) -> String {
+    let mut text = String::new();
+
+    if let Some(prefix) = instruction.prefix {
+        text.push_str(&format!("{} ", prefix));
+    }
+
+    if let Some(segment) = instruction.segment {
+        text.push_str(&format!("{} ", RegisterType::Segment(segment)));
+    }
+
+    text.push_str(&instruction.r#type.to_string());
+
+    if instruction.far {
+        text.push_str(" far");
+    }
+
+    let operands = instruction
+        .operands
+        .iter()
+        .map(|operand| match operand {
+            Operand::Register(register) => register.to_string(),
+            Operand::Immediate(value) => format!("{:#x}", value),
+            Operand::Memory(memory) => memory.to_string(),
+            Operand::Target(target) if labels.contains(target) => label(*target),
+            Operand::Target(target) => format!("{:#x}", target),
+            Operand::Far { segment, offset } => format!("{:#x}:{:#x}", segment, offset),
+        })
+        .collect::<Vec<_>>();
+
+    if !operands.is_empty() {
+        text.push(' ');
+        text.push_str(&operands.join(", "));
+    }
+
+    text
+}
+
+/// Whether the assembler encodes the instruction to the same bytes at the same address.
+/// Other encodings, e.g. `mov al, 5` as C6h or register moves with the direction bit set,
+/// would not survive reassembly.
+fn reassembles(instruction: &Instruction, cpu: Cpu) -> bool {
+    let text = format_instruction(instruction, &BTreeSet::new());
+    let Ok(tokens) = tokenize(text) else {
+        return false;
+    };
+    let options = AssemblerOptions {
+        cpu,
+        target: Target::Flat {
+            origin: instruction.address,
+        },
+    };
+
+    assemble(&tokens, &options).is_ok_and(|assembly| assembly.image() == instruction.bytes)
+}
+
+/// `db` line of `bytes`
+fn data(bytes: &[u8]) -> String {
+    let bytes = bytes
+        .iter()
+        .map(|byte| format!("{:#04x}", byte))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("db {}", bytes)
+}
+
+/// Name of the automatically generated label at `address`
+fn label(address: u16) -> String {
+    format!("L{:04x}", address)
+}
+
+/// Splits the image into instructions and data according to the region hints.
+fn decode_lines(image: &[u8], options: &Options) -> Vec<Line> {
+    let origin = options.origin as u32;
+    let end = origin + image.len() as u32;
+    let mut lines = Vec::new();
+    let mut address = origin;
+
+    while address < end {
+        let kind = options
+            .regions
+            .iter()
+            .rev()
+            .find(|region| region.range.contains(&address))
+            .map_or(RegionKind::Code, |region| region.kind);
+
+        // the interpretation may change at the next region boundary
+        let boundary = options
+            .regions
+            .iter()
+            .flat_map(|region| [region.range.start, region.range.end])
+            .filter(|boundary| *boundary > address)
+            .fold(end, u32::min);
+
+        let bytes = &image[(address - origin) as usize..(boundary - origin) as usize];
+
+        let line = match kind {
+            RegionKind::Code => match decode(bytes, address as u16, options.cpu) {
+                Ok(instruction) => Line::Instruction(instruction),
+                Err(_) => Line::Data {
+                    address: address as u16,
+                    bytes: bytes[..1].to_vec(),
+                },
+            },
+            RegionKind::Data => Line::Data {
+                address: address as u16,
+                bytes: bytes[..bytes.len().min(DATA_LINE_LENGTH)].to_vec(),
+            },
+        };
+
+        address += match &line {
+            Line::Instruction(instruction) => instruction.bytes.len(),
+            Line::Data { bytes, .. } => bytes.len(),
+        } as u32;
+        lines.push(line);
+    }
+
+    lines
+}
@@ -0,0 +1,271 @@
+#[test]
+fn disassemble_labels() {
+    use crate::disassembly::{disassemble, Options};
+
+    let image = [0xb9, 0x03, 0x00, 0x40, 0xe2, 0xfd, 0xeb, 0xfe];
+    let options = Options {
+        origin: 0x100,
+        ..Options::default()
+    };
+
+    let output = disassemble(&image, &options);
+    assert_eq!(
+        output,
+        "    org 0x100
+    mov cx, 0x3
+L0103:
+    inc ax
+    loop L0103
+L0106:
+    jmp L0106
+"
+    );
+}
+
+#[test]
+fn disassemble_data_and_hex() {
+    use crate::disassembly::{disassemble, Options, Region, RegionKind};
+
+    let image = [0xeb, 0x02, 0x48, 0x69, 0x0f, 0xc3];
+    let options = Options {
+        regions: vec![Region {
+            range: 2..4,
+            kind: RegionKind::Data,
+        }],
+        hex: true,
+        ..Options::default()
+    };
+
+    let output = disassemble(&image, &options);
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[0],
+        "    jmp 0x4                         // 0000: eb 02"
+    );
+    assert_eq!(
+        lines[1],
+        "    db 0x48, 0x69                   // 0002: 48 69"
+    );
+    // undefined opcode
+    assert_eq!(lines[2], "    db 0x0f                         // 0004: 0f");
+    assert_eq!(lines[3], "    ret                             // 0005: c3");
+}
+
+#[test]
+fn disassemble_segment_override() {
+    use crate::disassembly::{disassemble, Options};
+    use asmrs_parser::lexer::token::Cpu;
+
+    let image = [0x2e, 0xac, 0xf2, 0x36, 0x6e, 0x26, 0xd7];
+    let options = Options {
+        cpu: Cpu::I80186,
+        ..Options::default()
+    };
+
+    let output = disassemble(&image, &options);
+    assert_eq!(output, "    cs lodsb\n    repne ss outsb\n    es xlat\n");
+}
+
+#[test]
+fn disassemble_round_trip() {
+    use crate::disassembly::{disassemble, Options};
+    use asmrs_assembler::assembler::{assemble, Options as AssemblerOptions, Target};
+    use asmrs_parser::lexer::{token::Cpu, tokenize};
+
+    let assemble = |source: &str| {
+        let tokens = tokenize(source.to_string()).unwrap();
+        let options = AssemblerOptions {
+            cpu: Cpu::I80186,
+            target: Target::Flat { origin: 0 },
+        };
+        assemble(&tokens, &options).unwrap()
+    };
+
+    let source = "\
+org 0x100
+start:
+    mov ax, 0x1234
+    mov bl, [bx+si+0x4]
+    mov [es:bx], al
+    mov word [bp-0x2], 0x10
+    mov ds, ax
+    mov [0x200], ax
+    add ax, -2
+    add word [di], 0x1000
+    adc cl, bh
+    sub si, [bx]
+    cmp byte [bp], 1
+    test al, 0x80
+    xchg ax, bx
+    xchg cl, [bx]
+    inc word [si]
+    dec dx
+    push ds
+    pop es
+    push word [bx+di]
+    pop word [0x300]
+    push 0x1234
+    imul dx, [bx], 3
+    not ax
+    neg byte [bx]
+    mul cx
+    idiv word [bp+si]
+    shl bx, 1
+    sar byte [di], cl
+    rol ax, 4
+    lea dx, [bx+si+0x10]
+    les di, [0x400]
+    lds si, [bp]
+    rep movsb
+    repne scasw
+    cs lodsb
+    rep es movsb
+    ss xlat
+    lock xchg [bx], ax
+    in al, 0x60
+    out dx, ax
+    int 0x21
+    aam
+    aad 0x8
+    xlat
+    cbw
+    lahf
+    enter 0x10, 0
+.loop:
+    loop .loop
+    jz .loop
+    jcxz done
+    call far [bx]
+    jmp far [si]
+    call 0xf000:0xfff0
+    jmp 0x0:0x7c00
+    call done
+    jmp bx
+    ret 4
+    retf
+done:
+    fld qword [bx]
+    fadd st, st(3)
+    fsubp st(2), st
+    fcom st(1)
+    fxch st(1)
+    fistp dword [bp+0x8]
+    fninit
+    hlt
+";
+    let image = assemble(source).image();
+
+    let options = Options {
+        origin: 0x100,
+        cpu: Cpu::I80186,
+        ..Options::default()
+    };
+    let output = disassemble(&image, &options);
+    assert!(output.contains("mov bl, [bx+si+0x4]"), "{}", output);
+    assert!(output.contains("mov [es:bx], al"), "{}", output);
+
+    let reassembled = assemble(&output);
+    assert_eq!(reassembled.origin, 0x100);
+    assert_eq!(reassembled.image(), image, "{}", output);
+
+    // the hex dump is a comment
+    let output = disassemble(
+        &image,
+        &Options {
+            hex: true,
+            ..options
+        },
+    );
+    assert_eq!(assemble(&output).image(), image, "{}", output);
+}
+
+#[test]
+fn disassemble_non_canonical() {
+    use crate::disassembly::{disassemble, Options};
+    use asmrs_assembler::assembler::{assemble, Options as AssemblerOptions, Target};
+    use asmrs_parser::lexer::{token::Cpu, tokenize};
+
+    let reassemble = |output: &str| {
+        let tokens = tokenize(output.to_string()).unwrap();
+        let options = AssemblerOptions {
+            cpu: Cpu::I80186,
+            target: Target::Flat { origin: 0 },
+        };
+        assemble(&tokens, &options).unwrap().image()
+    };
+    let options = Options {
+        cpu: Cpu::I80186,
+        ..Options::default()
+    };
+
+    let mut images = vec![
+        vec![0xc6, 0xc0, 0x05],       // mov al, 0x5
+        vec![0xc7, 0xc0, 0x34, 0x12], // mov ax, 0x1234
+        vec![0xff, 0xc0],             // inc ax
+        vec![0xff, 0xf0],             // push ax
+        vec![0x84, 0xc3],             // test bl, al
+        vec![0x87, 0xc3],             // xchg bx, ax
+        vec![0xf0, 0xff, 0xc0],       // lock inc ax
+        vec![0x8e, 0xc8],             // mov cs, ax
+        vec![0x90, 0xeb, 0x80],       // jmp 0xff83
+    ];
+    // register to register with the direction bit set
+    for opcode in [
+        0x02, 0x03, 0x0a, 0x0b, 0x12, 0x13, 0x1a, 0x1b, 0x22, 0x23, 0x2a, 0x2b, 0x32, 0x33, 0x3a,
+        0x3b, 0x8a, 0x8b,
+    ] {
+        images.push(vec![opcode, 0xc3]);
+    }
+
+    for image in images {
+        let output = disassemble(&image, &options);
+        assert_eq!(reassemble(&output), image, "{}", output);
+    }
+
+    assert_eq!(
+        disassemble(&[0xc6, 0xc0, 0x05], &options),
+        "    db 0xc6, 0xc0, 0x05 // mov al, 0x5\n"
+    );
+    assert_eq!(
+        disassemble(&[0x90, 0xeb, 0x80], &options),
+        "    nop\n    jmp 0xff83\n"
+    );
+}
+
+#[test]
+fn disassemble_random() {
+    use crate::disassembly::{disassemble, Options};
+    use asmrs_assembler::assembler::{assemble, Options as AssemblerOptions, Target};
+    use asmrs_parser::lexer::{token::Cpu, tokenize};
+
+    // xorshift, for reproducible input
+    let mut state = 0x2545_f491u32;
+    let image = (0..0x2000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect::<Vec<_>>();
+
+    for cpu in [Cpu::I8086, Cpu::I80186] {
+        for hex in [false, true] {
+            let options = Options {
+                origin: 0x100,
+                cpu,
+                hex,
+                ..Options::default()
+            };
+            let output = disassemble(&image, &options);
+
+            let tokens = tokenize(output.clone()).unwrap();
+            let options = AssemblerOptions {
+                cpu,
+                target: Target::Flat { origin: 0 },
+            };
+            let assembly = assemble(&tokens, &options).unwrap_or_else(|error| panic!("{}", error));
+            assert!(assembly.image() == image, "{}", output);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use asmrs_disassembler::disassembly::{disassemble, Options, Region, RegionKind};
+use asmrs_parser::lexer::parse_number_argument;
+use std::{env, fs, process::ExitCode};
+
+const USAGE: &str = "Usage: asmrs-disassembler [--origin ADDRESS] [--cpu 8086|186|286] \
+[--code START-END] [--data START-END] [--hex] <input>";
+
+fn main() -> ExitCode {
+    let (input, options) = match parse_arguments(env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let image = match fs::read(&input) {
+        Ok(image) => image,
+        Err(error) => {
+            eprintln!("Could not read '{}': {}", input, error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if options.origin as usize + image.len() > 0x10000 {
+        eprintln!("Image does not fit into a 64 KiB segment at the given origin.");
+        return ExitCode::FAILURE;
+    }
+
+    print!("{}", disassemble(&image, &options));
+    ExitCode::SUCCESS
+}
+
+fn parse_arguments(mut args: impl Iterator<Item = String>) -> Result<(String, Options), String> {
+    let mut input = None;
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Expected value after '{}'.", arg))
+        };
+
+        match arg.as_str() {
+            "--origin" => {
+                let origin = parse_number_argument(&value()?)?;
+                options.origin =
+                    u16::try_from(origin).map_err(|_| "Origin exceeds 16 bits.".to_string())?;
+            }
+            "--cpu" => options.cpu = value()?.parse()?,
+            "--code" | "--data" => {
+                let kind = if arg == "--code" {
+                    RegionKind::Code
+                } else {
+                    RegionKind::Data
+                };
+                let range = value()?;
+                let (start, end) = range
+                    .split_once('-')
+                    .ok_or_else(|| format!("Expected START-END, found '{}'.", range))?;
+                options.regions.push(Region {
+                    range: parse_number_argument(start)?..parse_number_argument(end)?,
+                    kind,
+                });
+            }
+            "--hex" => options.hex = true,
+            _ if arg.starts_with("--") => return Err(format!("Unknown option: '{}'", arg)),
+            _ if input.is_none() => input = Some(arg),
+            _ => return Err(format!("Unexpected argument: '{}'", arg)),
+        }
+    }
+
+    Ok((
+        input.ok_or_else(|| "Expected input file.".to_string())?,
+        options,
+    ))
+}
@@ -0,0 +1,3 @@
+pub mod linker;
+pub mod map;
+pub mod script;
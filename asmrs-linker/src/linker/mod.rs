@@ -0,0 +1,650 @@
+use crate::script::{Region, Script};
+use asmrs_assembler::{
+    archive::Archive,
+    object::{Binding, Object, RelocationKind},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+};
+
+mod test;
+
+/// Size of the real mode address space, no layout can reach beyond it
+const ADDRESS_SPACE: u32 = 0x100000;
+
+/// How the output is addressed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// Single segment image starting at `origin` (.bin, .com)
+    Flat { origin: u16 },
+    /// One paragraph aligned segment per output section (.exe)
+    Segmented,
+    /// Sections placed into memory regions by a linker script (ROMs)
+    Script(Script),
+}
+
+/// Linker settings
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    pub target: Target,
+    /// Global symbol execution starts at
+    pub entry: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            target: Target::Flat { origin: 0 },
+            entry: "start".to_string(),
+        }
+    }
+}
+
+/// Output section: all input sections of the same name, in object order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlacedSection {
+    pub name: String,
+    /// Linear address the section runs at; relative to the image start unless placed by
+    /// a script
+    pub address: u32,
+    pub size: u32,
+    /// Segment the section is addressed through (relative to the image start for .exe)
+    pub segment: u16,
+    /// Offset of the section start within `segment`
+    pub offset: u16,
+    /// Segment:offset the initial data is stored at, if it is copied at startup
+    pub load: Option<(u16, u16)>,
+}
+
+/// Defined symbol with its final address
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlacedSymbol {
+    pub name: String,
+    /// Name of the defining object, `LINKER` for symbols defined by a script
+    pub object: String,
+    pub section: String,
+    pub segment: u16,
+    pub offset: u16,
+    pub global: bool,
+}
+
+/// Name given as defining object of symbols created by the linker
+pub const LINKER: &str = "<linker>";
+
+/// Result of linking
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Output {
+    /// Initialized part of the image; uninitialized data at the end is not included
+    pub image: Vec<u8>,
+    /// Total size including uninitialized data
+    pub size: u32,
+    pub sections: Vec<PlacedSection>,
+    pub symbols: Vec<PlacedSymbol>,
+    /// Locations (segment:offset) of segment fixups, for the MZ relocation table
+    pub segment_fixups: Vec<(u16, u16)>,
+    /// Segment:offset of the entry symbol; always set for segmented output
+    pub entry: Option<(u16, u16)>,
+}
+
+/// Where every input section ends up
+struct Layout {
+    /// Segment:offset every input section runs at, indexed by [object][section]
+    run: Vec<Vec<(u16, u16)>>,
+    /// Position of the data of every input section in the image, indexed like `run`
+    load: Vec<Vec<usize>>,
+    sections: Vec<PlacedSection>,
+    /// Symbols defined by the layout (`__data_start`, ...)
+    symbols: Vec<PlacedSymbol>,
+    /// Image filled with padding, without the section data
+    image: Vec<u8>,
+    /// Length of the image up to the last initialized byte
+    initialized: usize,
+}
+
+impl Layout {
+    /// Layout without image, for links that failed
+    fn empty() -> Self {
+        Self {
+            run: Vec::new(),
+            load: Vec::new(),
+            sections: Vec::new(),
+            symbols: Vec::new(),
+            image: Vec::new(),
+            initialized: 0,
+        }
+    }
+}
+
+/// Combines objects into one image. Sections with the same name are concatenated in
+/// object order, symbols are resolved across objects and all relocations are applied.
+/// Every duplicate and unresolved symbol is reported.
+pub fn link(objects: &[Object], options: &Options) -> Result<Output, LinkError> {
+    let mut errors = Vec::new();
+
+    let layout = match &options.target {
+        Target::Script(script) => script_layout(objects, script, &mut errors),
+        target => layout(objects, target, &mut errors),
+    };
+
+    // the image can not hold sections that do not fit
+    if !errors.is_empty() {
+        return Err(LinkError::new(errors));
+    }
+
+    let size = layout.image.len() as u32;
+    let mut image = layout.image;
+
+    for (object, input) in objects.iter().enumerate() {
+        for (section, input) in input.sections.iter().enumerate() {
+            let start = layout.load[object][section];
+            image[start..start + input.data.len()].copy_from_slice(&input.data);
+        }
+    }
+
+    // symbols
+    let mut symbols = Vec::new();
+    let mut globals: HashMap<&str, (&str, (u16, u16))> = HashMap::new();
+
+    for symbol in &layout.symbols {
+        globals.insert(&symbol.name, (LINKER, (symbol.segment, symbol.offset)));
+    }
+
+    for (object_index, object) in objects.iter().enumerate() {
+        for symbol in &object.symbols {
+            let Some(section) = symbol.section else {
+                continue;
+            };
+
+            let (segment, offset) = layout.run[object_index][section];
+            let offset = offset.wrapping_add(symbol.value as u16);
+
+            if symbol.binding == Binding::Global {
+                if let Some((other, _)) = globals.get(symbol.name.as_str()) {
+                    errors.push(format!(
+                        "Duplicate symbol '{}' in '{}' and '{}'.",
+                        symbol.name, other, object.name
+                    ));
+                    continue;
+                }
+                globals.insert(&symbol.name, (&object.name, (segment, offset)));
+            }
+
+            symbols.push(PlacedSymbol {
+                name: symbol.name.clone(),
+                object: object.name.clone(),
+                section: object.sections[section].name.clone(),
+                segment,
+                offset,
+                global: symbol.binding == Binding::Global,
+            });
+        }
+    }
+
+    // relocations; an unresolved symbol is reported once per object
+    let mut segment_fixups = Vec::new();
+    let mut unresolved = HashSet::new();
+
+    for (object_index, object) in objects.iter().enumerate() {
+        for relocation in &object.relocations {
+            let symbol = &object.symbols[relocation.symbol];
+
+            let (target_segment, target_offset) = match symbol.section {
+                Some(section) => {
+                    let (segment, offset) = layout.run[object_index][section];
+                    (segment, offset.wrapping_add(symbol.value as u16))
+                }
+                None => match globals.get(symbol.name.as_str()) {
+                    Some((_, location)) => *location,
+                    None => {
+                        if unresolved.insert((object_index, relocation.symbol)) {
+                            errors.push(format!(
+                                "Unresolved symbol '{}' referenced in '{}'.",
+                                symbol.name, object.name
+                            ));
+                        }
+                        continue;
+                    }
+                },
+            };
+            let target_offset = target_offset.wrapping_add(relocation.addend as u16);
+
+            let (field_segment, field_offset) = layout.run[object_index][relocation.section];
+            let field_offset = field_offset.wrapping_add(relocation.offset as u16);
+
+            let width = if relocation.kind == RelocationKind::Relative8 {
+                1
+            } else {
+                2
+            };
+            let next = field_offset.wrapping_add(width);
+
+            let value = match relocation.kind {
+                RelocationKind::Absolute => target_offset,
+                RelocationKind::Relative8 | RelocationKind::Relative16
+                    if target_segment != field_segment =>
+                {
+                    errors.push(format!(
+                        "Relative reference to '{}' in '{}' crosses segments.",
+                        symbol.name, object.name
+                    ));
+                    continue;
+                }
+                RelocationKind::Relative8 => {
+                    let displacement = target_offset.wrapping_sub(next) as i16;
+                    if i8::try_from(displacement).is_err() {
+                        errors.push(format!(
+                            "Short reference to '{}' in '{}' is out of range.",
+                            symbol.name, object.name
+                        ));
+                        continue;
+                    }
+                    displacement as u16
+                }
+                RelocationKind::Relative16 => target_offset.wrapping_sub(next),
+                RelocationKind::Segment => match options.target {
+                    Target::Flat { .. } => {
+                        errors.push(format!(
+                            "Segment reference to '{}' in '{}' needs a segmented (.exe) output.",
+                            symbol.name, object.name
+                        ));
+                        continue;
+                    }
+                    Target::Segmented => {
+                        segment_fixups.push((field_segment, field_offset));
+                        target_segment
+                    }
+                    // scripts place everything at absolute addresses
+                    Target::Script(_) => target_segment,
+                },
+            };
+
+            let field = layout.load[object_index][relocation.section] + relocation.offset as usize;
+            if width == 1 {
+                image[field] = value as u8;
+            } else {
+                image[field..field + 2].copy_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+
+    // an .exe has to start somewhere, flat images start at their first byte
+    let entry = globals
+        .get(options.entry.as_str())
+        .map(|(_, location)| *location);
+    if entry.is_none() && matches!(options.target, Target::Segmented) {
+        errors.push(format!(
+            "Entry symbol '{}' is not a defined global symbol.",
+            options.entry
+        ));
+    }
+
+    if !errors.is_empty() {
+        return Err(LinkError::new(errors));
+    }
+
+    image.truncate(layout.initialized);
+
+    let mut placed = layout.symbols;
+    placed.extend(symbols);
+
+    Ok(Output {
+        image,
+        size,
+        sections: layout.sections,
+        symbols: placed,
+        segment_fixups,
+        entry,
+    })
+}
+
+/// Input sections grouped by output section name, in order of first appearance.
+/// Contributions are (object, section) indices.
+fn output_sections(objects: &[Object]) -> Vec<(&str, Vec<(usize, usize)>)> {
+    let mut groups: Vec<(&str, Vec<(usize, usize)>)> = Vec::new();
+
+    for (object, input) in objects.iter().enumerate() {
+        for (section, input) in input.sections.iter().enumerate() {
+            match groups.iter_mut().find(|(name, _)| *name == input.name) {
+                Some((_, contributions)) => contributions.push((object, section)),
+                None => groups.push((&input.name, vec![(object, section)])),
+            }
+        }
+    }
+
+    groups
+}
+
+/// Lays sections out one after another: in one segment for flat output, in one paragraph
+/// aligned segment each for .exe output.
+fn layout(objects: &[Object], target: &Target, errors: &mut Vec<String>) -> Layout {
+    let mut run = objects
+        .iter()
+        .map(|object| vec![(0, 0); object.sections.len()])
+        .collect::<Vec<_>>();
+    let mut load = objects
+        .iter()
+        .map(|object| vec![0; object.sections.len()])
+        .collect::<Vec<_>>();
+    let mut sections = Vec::new();
+    let mut cursor = 0u32;
+    let mut initialized = 0u32;
+
+    for (name, contributions) in output_sections(objects) {
+        let alignment = match target {
+            Target::Segmented => 16,
+            _ => contributions
+                .iter()
+                .map(|(object, section)| objects[*object].sections[*section].alignment as u32)
+                .max()
+                .unwrap_or(1),
+        };
+        let start = cursor.next_multiple_of(alignment);
+        cursor = start;
+
+        let (segment, offset) = match target {
+            Target::Flat { origin } => (0, (*origin as u32 + start) as u16),
+            _ => ((start / 16) as u16, 0),
+        };
+
+        for (object, section) in contributions {
+            let input = &objects[object].sections[section];
+            cursor = cursor.next_multiple_of(input.alignment as u32);
+            run[object][section] = (segment, offset.wrapping_add((cursor - start) as u16));
+            load[object][section] = cursor as usize;
+            if !input.data.is_empty() {
+                initialized = cursor + input.data.len() as u32;
+            }
+            cursor = match advance(cursor, input.size) {
+                Some(cursor) => cursor,
+                None => {
+                    errors.push(format!(
+                        "Section '{}' of '{}' does not fit into the 1 MiB address space.",
+                        name, objects[object].name
+                    ));
+                    return Layout::empty();
+                }
+            };
+        }
+
+        if offset as u32 + (cursor - start) > 0x10000 {
+            errors.push(format!("Section '{}' exceeds 64 KiB.", name));
+        }
+
+        sections.push(PlacedSection {
+            name: name.to_string(),
+            address: start,
+            size: cursor - start,
+            segment,
+            offset,
+            load: None,
+        });
+    }
+
+    if let Target::Flat { origin } = target {
+        if *origin as u32 + cursor > 0x10000 {
+            errors.push(format!(
+                "Image of {} bytes does not fit into a segment at origin {:#x}.",
+                cursor, origin
+            ));
+        }
+    }
+
+    if !errors.is_empty() {
+        return Layout::empty();
+    }
+
+    Layout {
+        run,
+        load,
+        sections,
+        symbols: Vec::new(),
+        image: vec![0; cursor as usize],
+        initialized: initialized as usize,
+    }
+}
+
+/// Moves `cursor` past `size` bytes, `None` if that leaves the address space.
+fn advance(cursor: u32, size: u32) -> Option<u32> {
+    cursor
+        .checked_add(size)
+        .filter(|cursor| *cursor <= ADDRESS_SPACE)
+}
+
+/// Places sections into the regions of a linker script. Sections with a load region are
+/// stored there and copied to their run region at startup. The image covers every region
+/// holding data, filled with the region's fill byte, and for every section `.name` the
+/// symbols `__name_start`, `__name_size` and, with a load region, `__name_load` are
+/// defined.
+fn script_layout(objects: &[Object], script: &Script, errors: &mut Vec<String>) -> Layout {
+    let groups = output_sections(objects);
+    for (name, _) in &groups {
+        if !script.sections.iter().any(|rule| rule.name == *name) {
+            errors.push(format!("Section '{}' is not placed by the script.", name));
+        }
+    }
+
+    let mut run = objects
+        .iter()
+        .map(|object| vec![(0, 0); object.sections.len()])
+        .collect::<Vec<_>>();
+    // linear load addresses until the image start is known
+    let mut linear = objects
+        .iter()
+        .map(|object| vec![0u32; object.sections.len()])
+        .collect::<Vec<_>>();
+    let mut used = vec![0u32; script.regions.len()];
+    let mut stored = vec![false; script.regions.len()];
+    let mut sections = Vec::new();
+    let mut symbols = Vec::new();
+
+    for rule in &script.sections {
+        let contributions = groups
+            .iter()
+            .find(|(name, _)| *name == rule.name)
+            .map_or(&[][..], |(_, contributions)| contributions);
+        let region = &script.regions[rule.region];
+
+        let alignment = contributions
+            .iter()
+            .map(|(object, section)| objects[*object].sections[*section].alignment)
+            .fold(rule.align, u16::max) as u32;
+        let start = used[rule.region].next_multiple_of(alignment);
+        let mut cursor = start;
+        let mut data = false;
+
+        for (object, section) in contributions {
+            let input = &objects[*object].sections[*section];
+            cursor = cursor.next_multiple_of(input.alignment as u32);
+            run[*object][*section] = (region.segment, region.offset.wrapping_add(cursor as u16));
+            linear[*object][*section] = cursor - start;
+            data |= !input.data.is_empty();
+            cursor = match advance(cursor, input.size) {
+                Some(cursor) => cursor,
+                None => {
+                    errors.push(format!(
+                        "Section '{}' of '{}' does not fit into the 1 MiB address space.",
+                        rule.name, objects[*object].name
+                    ));
+                    return Layout::empty();
+                }
+            };
+        }
+
+        used[rule.region] = cursor;
+        let size = cursor - start;
+        let offset = region.offset.wrapping_add(start as u16);
+
+        let load = match rule.load {
+            Some(index) => {
+                let load_start = used[index].next_multiple_of(alignment);
+                used[index] = advance(load_start, size).unwrap_or(ADDRESS_SPACE);
+                stored[index] |= data;
+                Some((
+                    script.regions[index].segment,
+                    script.regions[index].offset.wrapping_add(load_start as u16),
+                    script.regions[index].start() + load_start,
+                ))
+            }
+            None => {
+                stored[rule.region] |= data;
+                None
+            }
+        };
+
+        let data_start = load.map_or(region.start() + start, |(_, _, linear)| linear);
+        for (object, section) in contributions {
+            linear[*object][*section] += data_start;
+        }
+
+        let stem = rule.name.trim_start_matches('.');
+        let mut define = |suffix: &str, segment: u16, offset: u16| {
+            symbols.push(PlacedSymbol {
+                name: format!("__{}_{}", stem, suffix),
+                object: LINKER.to_string(),
+                section: rule.name.clone(),
+                segment,
+                offset,
+                global: true,
+            })
+        };
+        define("start", region.segment, offset);
+        define("size", 0, size as u16);
+        if let Some((segment, offset, _)) = load {
+            define("load", segment, offset);
+        }
+
+        sections.push(PlacedSection {
+            name: rule.name.clone(),
+            address: region.start() + start,
+            size,
+            segment: region.segment,
+            offset,
+            load: load.map(|(segment, offset, _)| (segment, offset)),
+        });
+    }
+
+    for (region, used) in script.regions.iter().zip(&used) {
+        if *used > region.length {
+            errors.push(format!(
+                "Region '{}' overflows by {} bytes.",
+                region.name,
+                used - region.length
+            ));
+        }
+    }
+
+    if !errors.is_empty() {
+        return Layout::empty();
+    }
+
+    let stored_regions = script
+        .regions
+        .iter()
+        .zip(&stored)
+        .filter(|(_, stored)| **stored)
+        .map(|(region, _)| region);
+    let base = stored_regions.clone().map(Region::start).min().unwrap_or(0);
+    let end = stored_regions
+        .clone()
+        .map(|region| region.start() + region.length)
+        .max()
+        .unwrap_or(0);
+
+    let mut image = vec![0; (end - base) as usize];
+    for region in stored_regions {
+        let start = (region.start() - base) as usize;
+        image[start..start + region.length as usize].fill(region.fill);
+    }
+
+    // sections outside of the image (uninitialized) have no data to copy
+    let load = linear
+        .iter()
+        .map(|sections| {
+            sections
+                .iter()
+                .map(|linear| linear.saturating_sub(base).min(image.len() as u32) as usize)
+                .collect()
+        })
+        .collect();
+
+    let initialized = image.len();
+    Layout {
+        run,
+        load,
+        sections,
+        symbols,
+        image,
+        initialized,
+    }
+}
+
+/// Adds the archive members needed to resolve the undefined symbols of `objects`, including
+/// those of members pulled in along the way. Archives are searched in order; symbols no
+/// archive defines are left for `link` to report.
+pub fn extract(objects: &[Object], archives: &[Archive]) -> Vec<Object> {
+    let mut linked = objects.to_vec();
+    let mut pulled = Vec::new();
+
+    loop {
+        let defined = linked
+            .iter()
+            .flat_map(|object| &object.symbols)
+            .filter(|symbol| symbol.binding == Binding::Global && symbol.section.is_some())
+            .map(|symbol| symbol.name.as_str())
+            .collect::<HashSet<_>>();
+
+        let next = linked
+            .iter()
+            .flat_map(|object| &object.symbols)
+            .filter(|symbol| symbol.binding == Binding::Extern)
+            .filter(|symbol| !defined.contains(symbol.name.as_str()))
+            .find_map(|symbol| {
+                archives
+                    .iter()
+                    .enumerate()
+                    .find_map(|(archive, input)| {
+                        input.find(&symbol.name).map(|member| (archive, member))
+                    })
+                    .filter(|member| !pulled.contains(member))
+            });
+
+        match next {
+            Some((archive, member)) => {
+                pulled.push((archive, member));
+                linked.push(archives[archive].members[member].object.clone());
+            }
+            None => return linked,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LinkError {
+    messages: Vec<String>,
+}
+
+impl LinkError {
+    /// Creates a new Link Error from all problems found while linking.
+    pub fn new(messages: Vec<String>) -> LinkError {
+        Self { messages }
+    }
+
+    pub fn messages(&self) -> &[String] {
+        &self.messages
+    }
+}
+
+impl Display for LinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let messages = self
+            .messages
+            .iter()
+            .map(|message| format!("Link Error: {}", message))
+            .collect::<Vec<_>>();
+        write!(f, "{}", messages.join("\n"))
+    }
+}
+
+impl Error for LinkError {}
@@ -0,0 +1,376 @@
+#[cfg(test)]
+fn objects() -> Vec<asmrs_assembler::object::Object> {
+    use asmrs_assembler::object::{Binding, Object, Relocation, RelocationKind, Section, Symbol};
+
+    // main.asmrs: start: call print / mov ax, message / ret
+    let main = Object {
+        name: "main.asmrs".to_string(),
+        sections: vec![Section {
+            name: ".text".to_string(),
+            alignment: 1,
+            data: vec![0xe8, 0x00, 0x00, 0xb8, 0x00, 0x00, 0xc3],
+            size: 7,
+        }],
+        symbols: vec![
+            Symbol {
+                name: "start".to_string(),
+                binding: Binding::Global,
+                section: Some(0),
+                value: 0,
+            },
+            Symbol {
+                name: "print".to_string(),
+                binding: Binding::Extern,
+                section: None,
+                value: 0,
+            },
+            Symbol {
+                name: "message".to_string(),
+                binding: Binding::Extern,
+                section: None,
+                value: 0,
+            },
+        ],
+        relocations: vec![
+            Relocation {
+                section: 0,
+                offset: 1,
+                symbol: 1,
+                kind: RelocationKind::Relative16,
+                addend: 0,
+            },
+            Relocation {
+                section: 0,
+                offset: 4,
+                symbol: 2,
+                kind: RelocationKind::Absolute,
+                addend: 0,
+            },
+        ],
+    };
+
+    // print.asmrs: print: ret / message: db "hi", plus a buffer in .bss
+    let print = Object {
+        name: "print.asmrs".to_string(),
+        sections: vec![
+            Section {
+                name: ".text".to_string(),
+                alignment: 1,
+                data: vec![0xc3],
+                size: 1,
+            },
+            Section {
+                name: ".data".to_string(),
+                alignment: 2,
+                data: b"hi".to_vec(),
+                size: 2,
+            },
+            Section {
+                name: ".bss".to_string(),
+                alignment: 2,
+                data: vec![],
+                size: 0x20,
+            },
+        ],
+        symbols: vec![
+            Symbol {
+                name: "print".to_string(),
+                binding: Binding::Global,
+                section: Some(0),
+                value: 0,
+            },
+            Symbol {
+                name: "message".to_string(),
+                binding: Binding::Global,
+                section: Some(1),
+                value: 0,
+            },
+        ],
+        relocations: vec![],
+    };
+
+    vec![main, print]
+}
+
+#[test]
+fn link_flat() {
+    use crate::linker::{link, Options, Target};
+
+    let options = Options {
+        target: Target::Flat { origin: 0x100 },
+        ..Options::default()
+    };
+    let output = link(&objects(), &options).unwrap();
+
+    // .text: main at 0100, print at 0107; .data at 0108
+    assert_eq!(
+        output.image,
+        vec![0xe8, 0x04, 0x00, 0xb8, 0x08, 0x01, 0xc3, 0xc3, b'h', b'i']
+    );
+    assert_eq!(output.size, 0x2a);
+    assert_eq!(output.entry, Some((0, 0x100)));
+    assert_eq!(output.sections[2].name, ".bss");
+    assert_eq!(output.sections[2].offset, 0x10a);
+    assert!(output.segment_fixups.is_empty());
+}
+
+#[test]
+fn link_segmented() {
+    use crate::linker::{link, Options, Target};
+    use asmrs_assembler::object::{Relocation, RelocationKind};
+
+    let mut objects = objects();
+    objects[0].relocations.push(Relocation {
+        section: 0,
+        offset: 4,
+        symbol: 2,
+        kind: RelocationKind::Segment,
+        addend: 0,
+    });
+    objects[0].relocations.remove(1);
+
+    let options = Options {
+        target: Target::Segmented,
+        ..Options::default()
+    };
+    let output = link(&objects, &options).unwrap();
+
+    // .text in paragraph 0, .data in paragraph 1
+    assert_eq!(
+        &output.image[..8],
+        &[0xe8, 0x04, 0x00, 0xb8, 0x01, 0x00, 0xc3, 0xc3]
+    );
+    assert_eq!(&output.image[0x10..], b"hi");
+    assert_eq!(output.segment_fixups, vec![(0, 4)]);
+    assert_eq!(output.sections[1].segment, 1);
+    assert_eq!(output.entry, Some((0, 0)));
+
+    let options = Options {
+        entry: "main".to_string(),
+        ..options
+    };
+    assert_eq!(
+        link(&objects, &options).unwrap_err().to_string(),
+        "Link Error: Entry symbol 'main' is not a defined global symbol."
+    );
+}
+
+#[test]
+fn link_errors() {
+    use crate::linker::{link, Options, Target};
+    use asmrs_assembler::object::{Binding, Relocation, RelocationKind, Symbol};
+
+    let mut objects = objects();
+    objects[1].symbols[0].name = "start".to_string();
+    objects[0].symbols.push(Symbol {
+        name: "data".to_string(),
+        binding: Binding::Extern,
+        section: None,
+        value: 0,
+    });
+    objects[0].relocations.push(Relocation {
+        section: 0,
+        offset: 4,
+        symbol: 3,
+        kind: RelocationKind::Segment,
+        addend: 0,
+    });
+
+    // a symbol referenced twice is reported once
+    objects[0].relocations.push(Relocation {
+        section: 0,
+        offset: 4,
+        symbol: 1,
+        kind: RelocationKind::Absolute,
+        addend: 0,
+    });
+
+    let error = link(&objects, &Options::default()).unwrap_err();
+    assert_eq!(
+        error.messages(),
+        [
+            "Duplicate symbol 'start' in 'main.asmrs' and 'print.asmrs'.",
+            "Unresolved symbol 'print' referenced in 'main.asmrs'.",
+            "Unresolved symbol 'data' referenced in 'main.asmrs'.",
+        ]
+    );
+
+    let options = Options {
+        target: Target::Flat { origin: 0 },
+        ..Options::default()
+    };
+    let mut objects = self::objects();
+    objects[0].relocations[1].kind = RelocationKind::Segment;
+    let error = link(&objects, &options).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Link Error: Segment reference to 'message' in 'main.asmrs' needs a segmented (.exe) output."
+    );
+
+    // sizes are checked before the image is allocated
+    let mut objects = self::objects();
+    objects[1].sections[2].size = u32::MAX;
+    assert_eq!(
+        link(&objects, &Options::default()).unwrap_err().messages(),
+        ["Section '.bss' of 'print.asmrs' does not fit into the 1 MiB address space."]
+    );
+    let options = Options {
+        target: Target::Segmented,
+        ..Options::default()
+    };
+    assert_eq!(
+        link(&objects, &options).unwrap_err().messages(),
+        ["Section '.bss' of 'print.asmrs' does not fit into the 1 MiB address space."]
+    );
+}
+
+#[test]
+fn link_archive() {
+    use crate::linker::{extract, link, Options};
+    use asmrs_assembler::{
+        archive::{Archive, Member},
+        object::{Binding, Object, Section, Symbol},
+    };
+
+    let mut objects = objects();
+    let print = objects.pop().unwrap();
+    let unused = Object {
+        name: "unused.asmrs".to_string(),
+        sections: vec![Section {
+            name: ".text".to_string(),
+            alignment: 1,
+            data: vec![0x90],
+            size: 1,
+        }],
+        symbols: vec![Symbol {
+            name: "unused".to_string(),
+            binding: Binding::Global,
+            section: Some(0),
+            value: 0,
+        }],
+        relocations: vec![],
+    };
+    let archive = Archive::new(
+        [("unused.o", unused), ("print.o", print)]
+            .into_iter()
+            .map(|(name, object)| Member {
+                name: name.to_string(),
+                object,
+            })
+            .collect(),
+    );
+
+    let linked = extract(&objects, &[archive]);
+    assert_eq!(linked.len(), 2);
+    assert_eq!(linked[1].name, "print.asmrs");
+    assert!(link(&linked, &Options::default()).is_ok());
+
+    assert_eq!(extract(&objects, &[]).len(), 1);
+}
+
+#[test]
+fn link_script() {
+    use crate::{
+        linker::{link, Options, Target},
+        script::Script,
+    };
+
+    let script = Script::parse(
+        "[regions.rom]
+origin = \"F000:0000\"
+length = 0x100
+fill = 0xff
+
+[regions.ram]
+origin = \"0040:0000\"
+length = 0x100
+
+[[sections]]
+name = \".text\"
+region = \"rom\"
+
+[[sections]]
+name = \".data\"
+region = \"ram\"
+load = \"rom\"
+
+[[sections]]
+name = \".bss\"
+region = \"ram\"",
+    )
+    .unwrap();
+
+    let options = Options {
+        target: Target::Script(script.clone()),
+        ..Options::default()
+    };
+    let output = link(&objects(), &options).unwrap();
+
+    // .text runs from ROM, the initial .data is stored behind it and copied to RAM
+    assert_eq!(output.image.len(), 0x100);
+    assert_eq!(
+        &output.image[..11],
+        &[0xe8, 0x04, 0x00, 0xb8, 0x00, 0x00, 0xc3, 0xc3, b'h', b'i', 0xff]
+    );
+    assert_eq!(output.entry, Some((0xf000, 0)));
+    assert_eq!(output.sections[1].load, Some((0xf000, 8)));
+
+    let symbol = |name: &str| {
+        output
+            .symbols
+            .iter()
+            .find(|symbol| symbol.name == name)
+            .map(|symbol| (symbol.segment, symbol.offset))
+    };
+    assert_eq!(symbol("__data_load"), Some((0xf000, 8)));
+    assert_eq!(symbol("__data_start"), Some((0x40, 0)));
+    assert_eq!(symbol("__data_size"), Some((0, 2)));
+    assert_eq!(symbol("__bss_start"), Some((0x40, 2)));
+    assert_eq!(symbol("__bss_size"), Some((0, 0x20)));
+
+    let mut small = script;
+    small.regions[1].length = 0x10;
+    let options = Options {
+        target: Target::Script(small),
+        ..Options::default()
+    };
+    assert_eq!(
+        link(&objects(), &options).unwrap_err().messages(),
+        ["Region 'ram' overflows by 18 bytes."]
+    );
+}
+
+#[test]
+fn link_assembled() {
+    use crate::linker::{link, Options};
+    use asmrs_assembler::assembler::{self, assemble, Target};
+    use asmrs_parser::lexer::{token::Cpu, tokenize};
+
+    let object = |name: &str, source: &str| {
+        let options = assembler::Options {
+            cpu: Cpu::I8086,
+            target: Target::Object,
+        };
+        assemble(&tokenize(source.to_string()).unwrap(), &options)
+            .unwrap()
+            .object(name)
+    };
+
+    let objects = [
+        object(
+            "main.asmrs",
+            "global start\nextern print, message\nstart: call print\nmov ax, message\nret",
+        ),
+        object(
+            "print.asmrs",
+            "global print, message\nprint: ret\nsection .data\nmessage: db \"hi\"",
+        ),
+    ];
+
+    let output = link(&objects, &Options::default()).unwrap();
+    assert_eq!(
+        output.image,
+        [0xe8, 0x04, 0x00, 0xb8, 0x08, 0x00, 0xc3, 0xc3, b'h', b'i']
+    );
+    assert_eq!(output.entry, Some((0, 0)));
+}
@@ -0,0 +1,208 @@
+use asmrs_assembler::{
+    archive::{is_archive, Archive},
+    format::{self, Executable, Format, FormatError},
+    object::Object,
+};
+use asmrs_linker::{
+    linker::{extract, link, Options, Output, Target},
+    map::map,
+    script::Script,
+};
+use asmrs_parser::lexer::parse_number_argument;
+use std::{env, fs, process::ExitCode};
+
+const USAGE: &str = "Usage: asmrs-linker [--format bin|com|exe] [--origin ADDRESS] \
+[--script FILE] [--entry SYMBOL] [--map FILE] -o <output> <input>...";
+
+struct Arguments {
+    inputs: Vec<String>,
+    output: String,
+    format: Format,
+    /// Linker script replacing the target implied by the format
+    script: Option<String>,
+    /// Map file to write
+    map: Option<String>,
+    options: Options,
+}
+
+fn main() -> ExitCode {
+    let arguments = match parse_arguments(env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut options = arguments.options;
+
+    if let Some(path) = &arguments.script {
+        let script = fs::read_to_string(path)
+            .map_err(|error| format!("Could not read '{}': {}", path, error))
+            .and_then(|text| Script::parse(&text).map_err(|error| error.to_string()));
+
+        match script {
+            Ok(script) => options.target = Target::Script(script),
+            Err(message) => {
+                eprintln!("{}", message);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let mut objects = Vec::new();
+    let mut archives = Vec::new();
+    for input in &arguments.inputs {
+        let bytes = match fs::read(input) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                eprintln!("Could not read '{}': {}", input, error);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let result = if is_archive(&bytes) {
+            Archive::from_bytes(&bytes).map(|archive| archives.push(archive))
+        } else {
+            Object::from_bytes(&bytes).map(|object| objects.push(object))
+        };
+
+        if let Err(error) = result {
+            eprintln!("{}: {}", input, error);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let objects = extract(&objects, &archives);
+
+    let output = match link(&objects, &options) {
+        Ok(output) => output,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let bytes = match arguments.format {
+        Format::Exe => executable(&output).and_then(|executable| format::mz(&executable)),
+        format => format.write(&output.image, 0, format.origin()),
+    };
+
+    let bytes = match bytes {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(path) = &arguments.map {
+        if let Err(error) = fs::write(path, map(&output, &objects).to_string()) {
+            eprintln!("Could not write '{}': {}", path, error);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Err(error) = fs::write(&arguments.output, bytes) {
+        eprintln!("Could not write '{}': {}", arguments.output, error);
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Lays a segmented link out as MZ executable. The stack is the `.stack` section if one
+/// was linked, otherwise a default sized stack behind the image.
+fn executable(output: &Output) -> Result<Executable, FormatError> {
+    let entry = output
+        .entry
+        .ok_or_else(|| FormatError::new("The executable has no entry point.".to_string()))?;
+    let stack = output
+        .sections
+        .iter()
+        .find(|section| section.name == ".stack")
+        .map(|section| (section.segment, section.size));
+
+    Ok(Executable::new(
+        output.image.clone(),
+        output.size,
+        output.segment_fixups.clone(),
+        entry,
+        stack,
+    ))
+}
+
+fn parse_arguments(mut args: impl Iterator<Item = String>) -> Result<Arguments, String> {
+    let mut inputs = Vec::new();
+    let mut output = None;
+    let mut format = Format::Bin;
+    let mut origin = None;
+    let mut script = None;
+    let mut map = None;
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Expected value after '{}'.", arg))
+        };
+
+        match arg.as_str() {
+            "--format" => {
+                format = match value()?.as_str() {
+                    "exe" => Format::Exe,
+                    "bin" => Format::Bin,
+                    "com" => Format::Com,
+                    other => {
+                        return Err(format!(
+                            "Unknown format: '{}'. Expected bin, com or exe.",
+                            other
+                        ))
+                    }
+                }
+            }
+            "--origin" => {
+                let address = parse_number_argument(&value()?)?;
+                origin = Some(
+                    u16::try_from(address).map_err(|_| "Origin exceeds 16 bits.".to_string())?,
+                );
+            }
+            "--script" => script = Some(value()?),
+            "--map" => map = Some(value()?),
+            "--entry" => options.entry = value()?,
+            "-o" => output = Some(value()?),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option: '{}'", arg)),
+            _ => inputs.push(arg),
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err("Expected input files.".to_string());
+    }
+
+    if script.is_some() && (origin.is_some() || format != Format::Bin) {
+        return Err(
+            "A script places sections itself; use it with the bin format only.".to_string(),
+        );
+    }
+
+    options.target = match format {
+        Format::Exe if origin.is_some() => return Err("An .exe has no origin.".to_string()),
+        Format::Exe => Target::Segmented,
+        Format::Com if origin.is_some_and(|origin| origin != 0x100) => {
+            return Err("A .com is always linked at origin 100h.".to_string())
+        }
+        format => Target::Flat {
+            origin: origin.unwrap_or(format.origin()),
+        },
+    };
+
+    Ok(Arguments {
+        inputs,
+        output: output.ok_or_else(|| "Expected output file (-o).".to_string())?,
+        format,
+        script,
+        map,
+        options,
+    })
+}
@@ -0,0 +1,46 @@
+use crate::linker::Output;
+use asmrs_assembler::{
+    map::{Map, MapSection, MapSymbol},
+    object::Object,
+};
+
+mod test;
+
+/// Collects the map of a link of `objects`.
+pub fn map(output: &Output, objects: &[Object]) -> Map {
+    let mut map = Map::new();
+
+    for section in &output.sections {
+        map.push_section(MapSection {
+            name: section.name.clone(),
+            segment: section.segment,
+            offset: section.offset,
+            load: section.load,
+            size: section.size,
+        });
+    }
+
+    for symbol in &output.symbols {
+        map.push_symbol(MapSymbol {
+            name: symbol.name.clone(),
+            segment: symbol.segment,
+            offset: symbol.offset,
+            section: symbol.section.clone(),
+            file: symbol.object.clone(),
+            global: symbol.global,
+        });
+    }
+
+    for object in objects {
+        let mut sizes: Vec<(String, u32)> = Vec::new();
+        for section in &object.sections {
+            match sizes.iter_mut().find(|(name, _)| *name == section.name) {
+                Some((_, size)) => *size += section.size,
+                None => sizes.push((section.name.clone(), section.size)),
+            }
+        }
+        map.push_file(&object.name, sizes);
+    }
+
+    map
+}
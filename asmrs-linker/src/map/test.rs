@@ -0,0 +1,64 @@
+#[test]
+fn map_file() {
+    use crate::{
+        linker::{link, Options, Target},
+        map::map,
+    };
+    use asmrs_assembler::object::{Binding, Object, Section, Symbol};
+
+    let object = |name: &str, text: u32, data: u32, symbol: &str, value: u32| Object {
+        name: name.to_string(),
+        sections: vec![
+            Section {
+                name: ".text".to_string(),
+                alignment: 1,
+                data: vec![0x90; text as usize],
+                size: text,
+            },
+            Section {
+                name: ".data".to_string(),
+                alignment: 2,
+                data: vec![0; data as usize],
+                size: data,
+            },
+        ],
+        symbols: vec![Symbol {
+            name: symbol.to_string(),
+            binding: Binding::Global,
+            section: Some(0),
+            value,
+        }],
+        relocations: vec![],
+    };
+
+    let objects = vec![
+        object("main.asmrs", 0x10, 3, "start", 0),
+        object("print.asmrs", 5, 0, "print", 2),
+    ];
+    let options = Options {
+        target: Target::Flat { origin: 0x100 },
+        ..Options::default()
+    };
+    let output = link(&objects, &options).unwrap();
+
+    let map = map(&output, &objects).to_string();
+    let lines = map.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "Sections:",
+            "Name             Start      Load       Size",
+            ".text            0000:0100  0000:0100  00015",
+            ".data            0000:0116  0000:0116  00004",
+            "",
+            "Symbols:",
+            "Address    Name                     Section      File",
+            "0000:0100  start                    .text        main.asmrs",
+            "0000:0112  print                    .text        print.asmrs",
+            "",
+            "Files:",
+            "main.asmrs                    19 bytes  .text 16, .data 3",
+            "print.asmrs                    5 bytes  .text 5, .data 0",
+        ]
+    );
+}
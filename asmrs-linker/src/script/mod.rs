@@ -0,0 +1,288 @@
+use std::{error::Error, fmt::Display};
+
+mod test;
+
+/// Memory map for targets without a loader (ROMs), in a TOML subset:
+///
+/// ```toml
+/// [regions.rom]
+/// origin = "F000:0000"
+/// length = 0x10000
+/// fill = 0xff
+///
+/// [regions.ram]
+/// origin = "0040:0000"
+/// length = 0x1000
+///
+/// [[sections]]
+/// name = ".text"
+/// region = "rom"
+///
+/// [[sections]]
+/// name = ".data"
+/// region = "ram"
+/// load = "rom"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Script {
+    pub regions: Vec<Region>,
+    /// Placement rules in layout order
+    pub sections: Vec<Rule>,
+}
+
+/// Contiguous memory area within one segment
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub name: String,
+    pub segment: u16,
+    pub offset: u16,
+    pub length: u32,
+    /// Byte unused space of the region is filled with in the image
+    pub fill: u8,
+}
+
+impl Region {
+    /// Linear address of the first byte
+    pub fn start(&self) -> u32 {
+        ((self.segment as u32) << 4) + self.offset as u32
+    }
+}
+
+/// Placement of an output section
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub name: String,
+    /// Index of the region the section runs in
+    pub region: usize,
+    /// Index of the region the initial data is stored in, if it differs (copied at startup)
+    pub load: Option<usize>,
+    /// Minimum alignment of the section start
+    pub align: u16,
+}
+
+/// Table currently being filled while parsing
+enum Table {
+    None,
+    Region(usize),
+    Section(usize),
+}
+
+/// Section rule before region names are resolved
+struct PendingRule {
+    line_index: usize,
+    name: Option<String>,
+    region: Option<String>,
+    load: Option<String>,
+    align: u16,
+}
+
+impl Script {
+    /// Parses a memory map. Only tables, arrays of tables, integers and strings are
+    /// supported; every error names the line it occurs on.
+    pub fn parse(text: &str) -> Result<Script, ScriptError> {
+        let mut regions: Vec<Region> = Vec::new();
+        let mut region_lines = Vec::new();
+        let mut rules: Vec<PendingRule> = Vec::new();
+        let mut table = Table::None;
+
+        for (line_index, line) in text.lines().enumerate() {
+            let error = |message: String| ScriptError::new(message, line_index);
+            let line = strip_comment(line).trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if line == "[[sections]]" {
+                rules.push(PendingRule {
+                    line_index,
+                    name: None,
+                    region: None,
+                    load: None,
+                    align: 1,
+                });
+                table = Table::Section(rules.len() - 1);
+                continue;
+            }
+
+            if let Some(name) = line
+                .strip_prefix("[regions.")
+                .and_then(|rest| rest.strip_suffix(']'))
+            {
+                if regions.iter().any(|region| region.name == name) {
+                    return Err(error(format!("Duplicate region '{}'.", name)));
+                }
+
+                regions.push(Region {
+                    name: name.to_string(),
+                    segment: 0,
+                    offset: 0,
+                    length: 0,
+                    fill: 0,
+                });
+                region_lines.push(line_index);
+                table = Table::Region(regions.len() - 1);
+                continue;
+            }
+
+            if line.starts_with('[') {
+                return Err(error(format!("Unknown table '{}'.", line)));
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("Expected 'key = value', found '{}'.", line)))?;
+            let (key, value) = (key.trim(), parse_value(value.trim()).map_err(&error)?);
+
+            match (&table, key, value) {
+                (Table::Region(index), "origin", Value::String(origin)) => {
+                    let (segment, offset) = parse_address(&origin).ok_or_else(|| {
+                        error(format!("Expected SEGMENT:OFFSET, found '{}'.", origin))
+                    })?;
+                    regions[*index].segment = segment;
+                    regions[*index].offset = offset;
+                }
+                (Table::Region(index), "length", Value::Integer(length)) => {
+                    regions[*index].length = length
+                }
+                (Table::Region(index), "fill", Value::Integer(fill)) => {
+                    regions[*index].fill =
+                        u8::try_from(fill).map_err(|_| error("Fill exceeds a byte.".to_string()))?
+                }
+                (Table::Section(index), "name", Value::String(name)) => {
+                    rules[*index].name = Some(name)
+                }
+                (Table::Section(index), "region", Value::String(region)) => {
+                    rules[*index].region = Some(region)
+                }
+                (Table::Section(index), "load", Value::String(load)) => {
+                    rules[*index].load = Some(load)
+                }
+                (Table::Section(index), "align", Value::Integer(align)) => {
+                    rules[*index].align = u16::try_from(align)
+                        .ok()
+                        .filter(|align| align.is_power_of_two())
+                        .ok_or_else(|| error("Alignment must be a power of two.".to_string()))?
+                }
+                (Table::None, _, _) => {
+                    return Err(error(format!("Key '{}' outside of a table.", key)))
+                }
+                _ => return Err(error(format!("Invalid key or value for '{}'.", key))),
+            }
+        }
+
+        for (region, line_index) in regions.iter().zip(region_lines) {
+            if region.offset as u32 + region.length > 0x10000 {
+                return Err(ScriptError::new(
+                    format!("Region '{}' exceeds its segment.", region.name),
+                    line_index,
+                ));
+            }
+        }
+
+        let mut sections: Vec<Rule> = Vec::new();
+        for rule in rules {
+            let error = |message: String| ScriptError::new(message, rule.line_index);
+            let find = |name: &str| {
+                regions
+                    .iter()
+                    .position(|region| region.name == name)
+                    .ok_or_else(|| error(format!("Unknown region '{}'.", name)))
+            };
+
+            let name = rule
+                .name
+                .ok_or_else(|| error("Section without a name.".to_string()))?;
+            if sections.iter().any(|section| section.name == name) {
+                return Err(error(format!("Section '{}' is placed twice.", name)));
+            }
+
+            let region = rule
+                .region
+                .ok_or_else(|| error(format!("Section '{}' without a region.", name)))?;
+
+            sections.push(Rule {
+                region: find(&region)?,
+                load: rule.load.as_deref().map(find).transpose()?,
+                align: rule.align,
+                name,
+            });
+        }
+
+        Ok(Script { regions, sections })
+    }
+}
+
+enum Value {
+    Integer(u32),
+    String(String),
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    if let Some(string) = text
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        return Ok(Value::String(string.to_string()));
+    }
+
+    let digits = text.replace('_', "");
+    let number = match digits.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => digits.parse(),
+    };
+
+    number
+        .map(Value::Integer)
+        .map_err(|_| format!("Invalid value '{}'.", text))
+}
+
+/// Parses `SEGMENT:OFFSET` in hexadecimal.
+fn parse_address(text: &str) -> Option<(u16, u16)> {
+    let (segment, offset) = text.split_once(':')?;
+    Some((
+        u16::from_str_radix(segment, 16).ok()?,
+        u16::from_str_radix(offset, 16).ok()?,
+    ))
+}
+
+#[derive(Clone, Debug)]
+pub struct ScriptError {
+    message: String,
+    line_index: usize,
+}
+
+impl ScriptError {
+    /// Creates a new Script Error with the given message and line index.
+    pub fn new(message: String, line_index: usize) -> ScriptError {
+        Self {
+            message,
+            line_index,
+        }
+    }
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Script Error: at line: {}: {}",
+            self.line_index + 1,
+            self.message
+        )
+    }
+}
+
+impl Error for ScriptError {}
@@ -0,0 +1,85 @@
+#[test]
+fn script_parse() {
+    use crate::script::{Region, Rule, Script};
+
+    let script = Script::parse(
+        "# ROM with copy-initialized data
+[regions.rom]
+origin = \"F000:0000\"
+length = 0x1_0000
+fill = 0xff
+
+[regions.ram]
+origin = \"0040:0000\"
+length = 4096
+
+[[sections]]
+name = \".text\"
+region = \"rom\"
+align = 16
+
+[[sections]]
+name = \".data\" # runs in RAM
+region = \"ram\"
+load = \"rom\"
+",
+    )
+    .unwrap();
+
+    assert_eq!(
+        script.regions[0],
+        Region {
+            name: "rom".to_string(),
+            segment: 0xf000,
+            offset: 0,
+            length: 0x10000,
+            fill: 0xff,
+        }
+    );
+    assert_eq!(script.regions[1].start(), 0x400);
+    assert_eq!(
+        script.sections,
+        vec![
+            Rule {
+                name: ".text".to_string(),
+                region: 0,
+                load: None,
+                align: 16,
+            },
+            Rule {
+                name: ".data".to_string(),
+                region: 1,
+                load: Some(0),
+                align: 1,
+            },
+        ]
+    );
+}
+
+#[test]
+fn script_errors() {
+    use crate::script::Script;
+
+    let error = |text: &str| Script::parse(text).unwrap_err().to_string();
+
+    assert_eq!(
+        error("length = 1"),
+        "Script Error: at line: 1: Key 'length' outside of a table."
+    );
+    assert_eq!(
+        error("[regions.rom]\norigin = \"F000\""),
+        "Script Error: at line: 2: Expected SEGMENT:OFFSET, found 'F000'."
+    );
+    assert_eq!(
+        error("[regions.rom]\norigin = \"0000:8000\"\nlength = 0x9000"),
+        "Script Error: at line: 1: Region 'rom' exceeds its segment."
+    );
+    assert_eq!(
+        error("[[sections]]\nname = \".text\"\nregion = \"rom\""),
+        "Script Error: at line: 1: Unknown region 'rom'."
+    );
+    assert_eq!(
+        error("[regions.rom]\n[[sections]]\nname = \".text\"\nregion = \"rom\"\nalign = 3"),
+        "Script Error: at line: 5: Alignment must be a power of two."
+    );
+}
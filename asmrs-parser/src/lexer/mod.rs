@@ -1,6 +1,7 @@
 use token::{
-    GeneralPurposeRegister, InstructionType, RegisterType, SegmentRegister, SpecialPurposeRegister,
-    TokenType,
+    Condition, Cpu, DirectiveType, FloatingPointRegister, GeneralPurposeRegister, InstructionType,
+    MemoryOperand, OperandSize, OperatorType, RegisterType, SegmentRegister,
+    SpecialPurposeRegister, TokenType,
 };
 
 use crate::lexer::token::Token;
@@ -22,7 +23,7 @@ pub fn tokenize(input: String) -> Result<Vec<Token>, SyntaxError> {
                 line_index += 1;
                 char_index = 0;
             }
-            ' ' => char_index += 1,
+            ' ' | '\t' => char_index += 1,
             '/' => {
                 if let Some(next_character) = input.next() {
                     if next_character == '/' {
@@ -42,14 +43,14 @@ pub fn tokenize(input: String) -> Result<Vec<Token>, SyntaxError> {
                     char_index,
                 ));
             }
-            'a'..='z' | 'A'..='Z' => {
+            'a'..='z' | 'A'..='Z' | '.' | '_' => {
                 let start_index = char_index;
 
                 let mut buffer = String::from(current_character);
                 char_index += 1;
 
                 while let Some(next_character) = input.peek() {
-                    if !next_character.is_alphabetic() {
+                    if !is_identifier_character(*next_character) {
                         break;
                     }
 
@@ -58,8 +59,35 @@ pub fn tokenize(input: String) -> Result<Vec<Token>, SyntaxError> {
                     char_index += 1;
                 }
 
+                // parse floating point stack registers: st(i)
+                if buffer.eq_ignore_ascii_case("st") && input.peek() == Some(&'(') {
+                    let index = input
+                        .next()
+                        .and_then(|_| input.next())
+                        .and_then(|digit| digit.to_digit(10))
+                        .and_then(|digit| FloatingPointRegister::from_index(digit as u8))
+                        .filter(|_| input.next() == Some(')'))
+                        .ok_or_else(|| {
+                            SyntaxError::new(
+                                "Expected st(0) to st(7). Invalid register syntax.".to_string(),
+                                line_index,
+                                start_index,
+                            )
+                        })?;
+                    char_index += 3;
+
+                    tokens.push(Token::new(
+                        TokenType::Register(RegisterType::FloatingPoint(index)),
+                        line_index,
+                        start_index,
+                        char_index - start_index,
+                    ));
+                    continue;
+                }
+
                 // parse instruction & registers
-                if let Some(token_type) = parse_token(buffer.as_str()) {
+                let token_type = parse_token(buffer.as_str());
+                if let Some(token_type) = token_type.clone() {
                     tokens.push(Token::new(
                         token_type,
                         line_index,
@@ -69,18 +97,25 @@ pub fn tokenize(input: String) -> Result<Vec<Token>, SyntaxError> {
                 }
 
                 // parse labels
-                if let Some(next_character) = input.peek() {
-                    if *next_character == ':' {
-                        buffer.push(input.next().unwrap());
-                        char_index += 1;
-
-                        tokens.push(Token::new(
-                            TokenType::Label(buffer),
-                            line_index,
-                            start_index,
-                            char_index - start_index,
-                        ));
-                    }
+                if input.peek() == Some(&':') {
+                    buffer.push(input.next().unwrap());
+                    char_index += 1;
+
+                    tokens.push(Token::new(
+                        TokenType::Label(buffer),
+                        line_index,
+                        start_index,
+                        char_index - start_index,
+                    ));
+                }
+                // parse references to symbols
+                else if token_type.is_none() {
+                    tokens.push(Token::new(
+                        TokenType::Identifier(buffer),
+                        line_index,
+                        start_index,
+                        char_index - start_index,
+                    ));
                 }
             }
             // parse comma
@@ -88,7 +123,7 @@ pub fn tokenize(input: String) -> Result<Vec<Token>, SyntaxError> {
                 tokens.push(Token::new(TokenType::Comma, line_index, char_index, 1));
                 char_index += 1;
             }
-            // parse memory location
+            // parse memory operands: [0xbeef], [es:bx+si-0x2], [message+2], ...
             '[' => {
                 let start_index = char_index;
 
@@ -96,7 +131,7 @@ pub fn tokenize(input: String) -> Result<Vec<Token>, SyntaxError> {
                 char_index += 1;
 
                 while let Some(next_character) = input.peek() {
-                    if !next_character.is_ascii_alphanumeric() {
+                    if matches!(next_character, ']' | '\n' | '\r') {
                         break;
                     }
 
@@ -106,45 +141,137 @@ pub fn tokenize(input: String) -> Result<Vec<Token>, SyntaxError> {
                     char_index += 1;
                 }
 
-                if let Some(next_character) = input.next() {
-                    if next_character == ']' {
-                        char_index += 1;
-
-                        // hex
-                        let (buffer, radix) = if let Some(stripped) = buffer.strip_prefix("0x") {
-                            (stripped, 16)
-                        } else if let Some(stripped) = buffer.strip_suffix("h") {
-                            (stripped, 16)
-                        }
-                        // dec
-                        else {
-                            (buffer.as_str(), 10)
-                        };
+                if input.peek() != Some(&']') {
+                    return Err(SyntaxError::new(
+                        "Expected ']'. Invalid memory location syntax.".to_string(),
+                        line_index,
+                        char_index,
+                    ));
+                }
+                input.next();
+                char_index += 1;
 
-                        let value = u16::from_str_radix(buffer, radix).map_err(|_| {
-                            SyntaxError::new(
-                                "Expected 16-bit memory address. Invalid memory location syntax."
-                                    .to_string(),
+                let memory = parse_memory(&buffer)
+                    .map_err(|message| SyntaxError::new(message, line_index, start_index))?;
+
+                // plain addresses keep their own token
+                let token_type = match memory {
+                    MemoryOperand {
+                        segment: None,
+                        base: None,
+                        index: None,
+                        symbol: None,
+                        displacement,
+                    } => TokenType::MemoryLocation(displacement as u16),
+                    memory => TokenType::Memory(memory),
+                };
+
+                tokens.push(Token::new(
+                    token_type,
+                    line_index,
+                    start_index,
+                    char_index - start_index,
+                ));
+            }
+            // parse strings
+            '"' | '\'' => {
+                let start_index = char_index;
+
+                let mut buffer = String::default();
+                char_index += 1;
+
+                loop {
+                    match input.next() {
+                        Some(next_character) if next_character == current_character => break,
+                        Some(next_character) if !matches!(next_character, '\n' | '\r') => {
+                            buffer.push(next_character);
+                            char_index += 1;
+                        }
+                        _ => {
+                            return Err(SyntaxError::new(
+                                format!("Expected {}. Unterminated string.", current_character),
                                 line_index,
                                 start_index,
-                            )
-                        })?;
+                            ))
+                        }
+                    }
+                }
+                char_index += 1;
+
+                tokens.push(Token::new(
+                    TokenType::String(buffer),
+                    line_index,
+                    start_index,
+                    char_index - start_index,
+                ));
+            }
+            // parse the colon of far pointers
+            ':' => {
+                tokens.push(Token::new(TokenType::Colon, line_index, char_index, 1));
+                char_index += 1;
+            }
+            // parse negative constants
+            '-' if input.peek().is_some_and(char::is_ascii_digit) => {
+                let start_index = char_index;
 
-                        tokens.push(Token::new(
-                            TokenType::MemoryLocation(value),
-                            line_index,
-                            start_index,
-                            char_index - start_index,
-                        ));
+                let mut buffer = String::default();
+                char_index += 1;
 
-                        continue;
+                while let Some(next_character) = input.peek() {
+                    if !next_character.is_ascii_alphanumeric() {
+                        break;
                     }
+
+                    buffer.push(*next_character);
+                    input.next();
+                    char_index += 1;
                 }
 
-                return Err(SyntaxError::new(
-                    "Expected ']'. Invalid memory location syntax.".to_string(),
+                let value = parse_word(buffer.as_str()).ok_or_else(|| {
+                    SyntaxError::new(
+                        "Expected 16-bit constant. Invalid constant syntax.".to_string(),
+                        line_index,
+                        start_index,
+                    )
+                })?;
+
+                tokens.push(Token::new(
+                    TokenType::Constant(value.wrapping_neg()),
                     line_index,
-                    char_index,
+                    start_index,
+                    char_index - start_index,
+                ));
+            }
+            // parse constant
+            '0'..='9' => {
+                let start_index = char_index;
+
+                let mut buffer = String::from(current_character);
+                char_index += 1;
+
+                while let Some(next_character) = input.peek() {
+                    if !next_character.is_ascii_alphanumeric() {
+                        break;
+                    }
+
+                    buffer.push(*next_character);
+                    input.next();
+                    char_index += 1;
+                }
+
+                let value = parse_word(buffer.as_str()).ok_or_else(|| {
+                    SyntaxError::new(
+                        "Expected 16-bit constant. Invalid constant syntax.".to_string(),
+                        line_index,
+                        start_index,
+                    )
+                })?;
+
+                tokens.push(Token::new(
+                    TokenType::Constant(value),
+                    line_index,
+                    start_index,
+                    char_index - start_index,
                 ));
             }
             _ => Err(SyntaxError::new(
@@ -158,6 +285,197 @@ pub fn tokenize(input: String) -> Result<Vec<Token>, SyntaxError> {
     Ok(tokens)
 }
 
+/// Checks that every instruction in `tokens` is available on `cpu`.
+/// Besides 80186+ mnemonics, this catches the 80186 forms of existing instructions:
+/// `push imm`, `imul r, r/m, imm` and shifts/rotates by an immediate other than 1.
+pub fn check_cpu(tokens: &[Token], cpu: Cpu) -> Result<(), SyntaxError> {
+    for (index, token) in tokens.iter().enumerate() {
+        let TokenType::Instruction(instruction) = *token.r#type() else {
+            continue;
+        };
+
+        // operands reach until the end of the line or the next instruction or label
+        let operands = tokens[index + 1..]
+            .iter()
+            .take_while(|next| {
+                next.line_index() == token.line_index()
+                    && !matches!(
+                        next.r#type(),
+                        TokenType::Instruction(_) | TokenType::Label(_)
+                    )
+            })
+            .map(Token::r#type)
+            .collect::<Vec<_>>();
+
+        let required = match instruction {
+            InstructionType::Push if matches!(operands[..], [TokenType::Constant(_)]) => {
+                Cpu::I80186
+            }
+            InstructionType::Imul if operands.contains(&&TokenType::Comma) => Cpu::I80186,
+            InstructionType::Rcl
+            | InstructionType::Rcr
+            | InstructionType::Rol
+            | InstructionType::Ror
+            | InstructionType::Sal
+            | InstructionType::Sar
+            | InstructionType::Shl
+            | InstructionType::Shr
+                if operands.last().is_some_and(
+                    |operand| matches!(operand, TokenType::Constant(count) if *count != 1),
+                ) =>
+            {
+                Cpu::I80186
+            }
+            _ => instruction.cpu(),
+        };
+
+        if required > cpu {
+            return Err(SyntaxError::new(
+                format!(
+                    "Instruction requires an {} or later, but target CPU is {}.",
+                    required, cpu
+                ),
+                token.line_index(),
+                token.char_index(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Characters allowed after the first one of identifiers and labels.
+fn is_identifier_character(character: char) -> bool {
+    character.is_alphanumeric() || matches!(character, '_' | '.')
+}
+
+/// Parses the contents of a memory operand: `[segment:]term{(+|-)term}`, where a term is
+/// a base or index register, a number or a symbol. Registers and symbols can only be added.
+fn parse_memory(buffer: &str) -> Result<MemoryOperand, String> {
+    let mut memory = MemoryOperand::default();
+
+    let terms = match buffer.split_once(':') {
+        Some((segment, terms)) => {
+            memory.segment = match parse_token(segment.trim()) {
+                Some(TokenType::Register(RegisterType::Segment(segment))) => Some(segment),
+                _ => {
+                    return Err(format!(
+                        "Expected segment register, found '{}'.",
+                        segment.trim()
+                    ))
+                }
+            };
+            terms
+        }
+        None => buffer,
+    };
+
+    // split before every sign, keeping the sign with its term
+    let mut signed = Vec::new();
+    let mut start = 0;
+    for (position, character) in terms.char_indices() {
+        if matches!(character, '+' | '-') {
+            signed.push(&terms[start..position]);
+            start = position;
+        }
+    }
+    signed.push(&terms[start..]);
+    let signed_count = signed.len();
+
+    for (index, term) in signed.into_iter().enumerate() {
+        let (negative, term) = match term.trim().strip_prefix('-') {
+            Some(term) => (true, term.trim()),
+            None => (false, term.trim().trim_start_matches('+').trim()),
+        };
+
+        // a leading sign leaves an empty first term
+        if term.is_empty() {
+            if index == 0 && signed_count > 1 {
+                continue;
+            }
+            return Err("Expected register, number or symbol.".to_string());
+        }
+
+        if let Some(value) = parse_word(term) {
+            let value = value as i32;
+            memory.displacement += if negative { -value } else { value };
+            continue;
+        }
+
+        let register = match parse_token(term) {
+            Some(TokenType::Register(register)) => Some(register),
+            Some(_) => return Err(format!("Unexpected '{}' in memory operand.", term)),
+            None => None,
+        };
+
+        if negative {
+            return Err(format!("Cannot subtract '{}' in memory operand.", term));
+        }
+
+        match register {
+            Some(
+                register @ RegisterType::GeneralPurpose(GeneralPurposeRegister::Bx)
+                | register @ RegisterType::SpecialPurpose(SpecialPurposeRegister::Bp),
+            ) if memory.base.is_none() => memory.base = Some(register),
+            Some(
+                register @ RegisterType::SpecialPurpose(
+                    SpecialPurposeRegister::Si | SpecialPurposeRegister::Di,
+                ),
+            ) if memory.index.is_none() => memory.index = Some(register),
+            Some(register) => {
+                return Err(format!(
+                "Register '{}' cannot be used here. Expected one of bx or bp and one of si or di.",
+                register
+            ))
+            }
+            None if memory.symbol.is_some() => {
+                return Err(format!(
+                    "Unexpected second symbol '{}' in memory operand.",
+                    term
+                ))
+            }
+            None if term.starts_with(|character: char| character.is_ascii_digit())
+                || !term.chars().all(is_identifier_character) =>
+            {
+                return Err(format!(
+                    "Invalid number or symbol '{}' in memory operand.",
+                    term
+                ))
+            }
+            None => memory.symbol = Some(term.to_string()),
+        }
+    }
+
+    Ok(memory)
+}
+
+/// Parses a number in the syntax of the source, as the tools take them on their command
+/// lines and debugger prompts.
+pub fn parse_number_argument(text: &str) -> Result<u32, String> {
+    parse_number(text).ok_or_else(|| format!("Invalid number: '{}'", text))
+}
+
+/// Parses a hexadecimal (`0x` prefix or `h` suffix) or decimal number.
+fn parse_number(buffer: &str) -> Option<u32> {
+    // hex
+    let (buffer, radix) = if let Some(stripped) = buffer.strip_prefix("0x") {
+        (stripped, 16)
+    } else if let Some(stripped) = buffer.strip_suffix("h") {
+        (stripped, 16)
+    }
+    // dec
+    else {
+        (buffer, 10)
+    };
+
+    u32::from_str_radix(buffer, radix).ok()
+}
+
+/// Parses a number that fits into 16 bits.
+fn parse_word(buffer: &str) -> Option<u16> {
+    parse_number(buffer).and_then(|value| u16::try_from(value).ok())
+}
+
 fn parse_token(buffer: &str) -> Option<TokenType> {
     match buffer.to_lowercase().as_str() {
         // Instruction types
@@ -168,11 +486,13 @@ fn parse_token(buffer: &str) -> Option<TokenType> {
         "adc" => Some(TokenType::Instruction(InstructionType::Adc)),
         "add" => Some(TokenType::Instruction(InstructionType::Add)),
         "and" => Some(TokenType::Instruction(InstructionType::And)),
+        "bound" => Some(TokenType::Instruction(InstructionType::Bound)),
         "call" => Some(TokenType::Instruction(InstructionType::Call)),
         "cbw" => Some(TokenType::Instruction(InstructionType::Cbw)),
         "clc" => Some(TokenType::Instruction(InstructionType::Clc)),
         "cld" => Some(TokenType::Instruction(InstructionType::Cld)),
         "cli" => Some(TokenType::Instruction(InstructionType::Cli)),
+        "clts" => Some(TokenType::Instruction(InstructionType::Clts)),
         "cmc" => Some(TokenType::Instruction(InstructionType::Cmc)),
         "cmp" => Some(TokenType::Instruction(InstructionType::Cmp)),
         "cmpsb" => Some(TokenType::Instruction(InstructionType::Cmpsb)),
@@ -182,26 +502,142 @@ fn parse_token(buffer: &str) -> Option<TokenType> {
         "das" => Some(TokenType::Instruction(InstructionType::Das)),
         "dec" => Some(TokenType::Instruction(InstructionType::Dec)),
         "div" => Some(TokenType::Instruction(InstructionType::Div)),
+        "enter" => Some(TokenType::Instruction(InstructionType::Enter)),
         "esc" => Some(TokenType::Instruction(InstructionType::Esc)),
+        "f2xm1" => Some(TokenType::Instruction(InstructionType::F2xm1)),
+        "fabs" => Some(TokenType::Instruction(InstructionType::Fabs)),
+        "fadd" => Some(TokenType::Instruction(InstructionType::Fadd)),
+        "faddp" => Some(TokenType::Instruction(InstructionType::Faddp)),
+        "fbld" => Some(TokenType::Instruction(InstructionType::Fbld)),
+        "fbstp" => Some(TokenType::Instruction(InstructionType::Fbstp)),
+        "fchs" => Some(TokenType::Instruction(InstructionType::Fchs)),
+        "fclex" => Some(TokenType::Instruction(InstructionType::Fclex)),
+        "fcom" => Some(TokenType::Instruction(InstructionType::Fcom)),
+        "fcomp" => Some(TokenType::Instruction(InstructionType::Fcomp)),
+        "fcompp" => Some(TokenType::Instruction(InstructionType::Fcompp)),
+        "fdecstp" => Some(TokenType::Instruction(InstructionType::Fdecstp)),
+        "fdisi" => Some(TokenType::Instruction(InstructionType::Fdisi)),
+        "fdiv" => Some(TokenType::Instruction(InstructionType::Fdiv)),
+        "fdivp" => Some(TokenType::Instruction(InstructionType::Fdivp)),
+        "fdivr" => Some(TokenType::Instruction(InstructionType::Fdivr)),
+        "fdivrp" => Some(TokenType::Instruction(InstructionType::Fdivrp)),
+        "feni" => Some(TokenType::Instruction(InstructionType::Feni)),
+        "ffree" => Some(TokenType::Instruction(InstructionType::Ffree)),
+        "fiadd" => Some(TokenType::Instruction(InstructionType::Fiadd)),
+        "ficom" => Some(TokenType::Instruction(InstructionType::Ficom)),
+        "ficomp" => Some(TokenType::Instruction(InstructionType::Ficomp)),
+        "fidiv" => Some(TokenType::Instruction(InstructionType::Fidiv)),
+        "fidivr" => Some(TokenType::Instruction(InstructionType::Fidivr)),
+        "fild" => Some(TokenType::Instruction(InstructionType::Fild)),
+        "fimul" => Some(TokenType::Instruction(InstructionType::Fimul)),
+        "fincstp" => Some(TokenType::Instruction(InstructionType::Fincstp)),
+        "finit" => Some(TokenType::Instruction(InstructionType::Finit)),
+        "fist" => Some(TokenType::Instruction(InstructionType::Fist)),
+        "fistp" => Some(TokenType::Instruction(InstructionType::Fistp)),
+        "fisub" => Some(TokenType::Instruction(InstructionType::Fisub)),
+        "fisubr" => Some(TokenType::Instruction(InstructionType::Fisubr)),
+        "fld" => Some(TokenType::Instruction(InstructionType::Fld)),
+        "fld1" => Some(TokenType::Instruction(InstructionType::Fld1)),
+        "fldcw" => Some(TokenType::Instruction(InstructionType::Fldcw)),
+        "fldenv" => Some(TokenType::Instruction(InstructionType::Fldenv)),
+        "fldl2e" => Some(TokenType::Instruction(InstructionType::Fldl2e)),
+        "fldl2t" => Some(TokenType::Instruction(InstructionType::Fldl2t)),
+        "fldlg2" => Some(TokenType::Instruction(InstructionType::Fldlg2)),
+        "fldln2" => Some(TokenType::Instruction(InstructionType::Fldln2)),
+        "fldpi" => Some(TokenType::Instruction(InstructionType::Fldpi)),
+        "fldz" => Some(TokenType::Instruction(InstructionType::Fldz)),
+        "fmul" => Some(TokenType::Instruction(InstructionType::Fmul)),
+        "fmulp" => Some(TokenType::Instruction(InstructionType::Fmulp)),
+        "fnclex" => Some(TokenType::Instruction(InstructionType::Fnclex)),
+        "fndisi" => Some(TokenType::Instruction(InstructionType::Fndisi)),
+        "fneni" => Some(TokenType::Instruction(InstructionType::Fneni)),
+        "fninit" => Some(TokenType::Instruction(InstructionType::Fninit)),
+        "fnop" => Some(TokenType::Instruction(InstructionType::Fnop)),
+        "fnsave" => Some(TokenType::Instruction(InstructionType::Fnsave)),
+        "fnstcw" => Some(TokenType::Instruction(InstructionType::Fnstcw)),
+        "fnstenv" => Some(TokenType::Instruction(InstructionType::Fnstenv)),
+        "fnstsw" => Some(TokenType::Instruction(InstructionType::Fnstsw)),
+        "fpatan" => Some(TokenType::Instruction(InstructionType::Fpatan)),
+        "fprem" => Some(TokenType::Instruction(InstructionType::Fprem)),
+        "fptan" => Some(TokenType::Instruction(InstructionType::Fptan)),
+        "frndint" => Some(TokenType::Instruction(InstructionType::Frndint)),
+        "frstor" => Some(TokenType::Instruction(InstructionType::Frstor)),
+        "fsave" => Some(TokenType::Instruction(InstructionType::Fsave)),
+        "fscale" => Some(TokenType::Instruction(InstructionType::Fscale)),
+        "fsqrt" => Some(TokenType::Instruction(InstructionType::Fsqrt)),
+        "fst" => Some(TokenType::Instruction(InstructionType::Fst)),
+        "fstcw" => Some(TokenType::Instruction(InstructionType::Fstcw)),
+        "fstenv" => Some(TokenType::Instruction(InstructionType::Fstenv)),
+        "fstp" => Some(TokenType::Instruction(InstructionType::Fstp)),
+        "fstsw" => Some(TokenType::Instruction(InstructionType::Fstsw)),
+        "fsub" => Some(TokenType::Instruction(InstructionType::Fsub)),
+        "fsubp" => Some(TokenType::Instruction(InstructionType::Fsubp)),
+        "fsubr" => Some(TokenType::Instruction(InstructionType::Fsubr)),
+        "fsubrp" => Some(TokenType::Instruction(InstructionType::Fsubrp)),
+        "ftst" => Some(TokenType::Instruction(InstructionType::Ftst)),
+        "fwait" => Some(TokenType::Instruction(InstructionType::Wait)),
+        "fxam" => Some(TokenType::Instruction(InstructionType::Fxam)),
+        "fxch" => Some(TokenType::Instruction(InstructionType::Fxch)),
+        "fxtract" => Some(TokenType::Instruction(InstructionType::Fxtract)),
+        "fyl2x" => Some(TokenType::Instruction(InstructionType::Fyl2x)),
+        "fyl2xp1" => Some(TokenType::Instruction(InstructionType::Fyl2xp1)),
         "hlt" => Some(TokenType::Instruction(InstructionType::Hlt)),
         "idiv" => Some(TokenType::Instruction(InstructionType::Idiv)),
         "imul" => Some(TokenType::Instruction(InstructionType::Imul)),
         "in" => Some(TokenType::Instruction(InstructionType::In)),
         "inc" => Some(TokenType::Instruction(InstructionType::Inc)),
+        "insb" => Some(TokenType::Instruction(InstructionType::Insb)),
+        "insw" => Some(TokenType::Instruction(InstructionType::Insw)),
         "int" => Some(TokenType::Instruction(InstructionType::Int)),
+        "int3" => Some(TokenType::Instruction(InstructionType::Int3)),
         "into" => Some(TokenType::Instruction(InstructionType::Into)),
         "iret" => Some(TokenType::Instruction(InstructionType::Iret)),
-        "jcc" => Some(TokenType::Instruction(InstructionType::Jcc)),
+        "jo" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::O))),
+        "jno" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::No))),
+        "jb" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::B))),
+        "jc" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::B))),
+        "jnae" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::B))),
+        "jae" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Ae))),
+        "jnb" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Ae))),
+        "jnc" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Ae))),
+        "je" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::E))),
+        "jz" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::E))),
+        "jne" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Ne))),
+        "jnz" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Ne))),
+        "jbe" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Be))),
+        "jna" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Be))),
+        "ja" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::A))),
+        "jnbe" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::A))),
+        "js" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::S))),
+        "jns" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Ns))),
+        "jp" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::P))),
+        "jpe" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::P))),
+        "jnp" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Np))),
+        "jpo" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Np))),
+        "jl" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::L))),
+        "jnge" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::L))),
+        "jge" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Ge))),
+        "jnl" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Ge))),
+        "jle" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Le))),
+        "jng" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::Le))),
+        "jg" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::G))),
+        "jnle" => Some(TokenType::Instruction(InstructionType::Jcc(Condition::G))),
         "jcxz" => Some(TokenType::Instruction(InstructionType::Jcxz)),
         "jmp" => Some(TokenType::Instruction(InstructionType::Jmp)),
         "lahf" => Some(TokenType::Instruction(InstructionType::Lahf)),
         "lds" => Some(TokenType::Instruction(InstructionType::Lds)),
         "lea" => Some(TokenType::Instruction(InstructionType::Lea)),
+        "leave" => Some(TokenType::Instruction(InstructionType::Leave)),
         "les" => Some(TokenType::Instruction(InstructionType::Les)),
+        "lgdt" => Some(TokenType::Instruction(InstructionType::Lgdt)),
+        "lidt" => Some(TokenType::Instruction(InstructionType::Lidt)),
+        "lmsw" => Some(TokenType::Instruction(InstructionType::Lmsw)),
         "lock" => Some(TokenType::Instruction(InstructionType::Lock)),
         "lodsb" => Some(TokenType::Instruction(InstructionType::Lodsb)),
         "lodsw" => Some(TokenType::Instruction(InstructionType::Lodsw)),
         "loop" => Some(TokenType::Instruction(InstructionType::Loop)),
+        "loope" | "loopz" => Some(TokenType::Instruction(InstructionType::Loope)),
+        "loopne" | "loopnz" => Some(TokenType::Instruction(InstructionType::Loopne)),
         "mov" => Some(TokenType::Instruction(InstructionType::Mov)),
         "movsb" => Some(TokenType::Instruction(InstructionType::Movsb)),
         "movsw" => Some(TokenType::Instruction(InstructionType::Movsw)),
@@ -211,14 +647,20 @@ fn parse_token(buffer: &str) -> Option<TokenType> {
         "not" => Some(TokenType::Instruction(InstructionType::Not)),
         "or" => Some(TokenType::Instruction(InstructionType::Or)),
         "out" => Some(TokenType::Instruction(InstructionType::Out)),
+        "outsb" => Some(TokenType::Instruction(InstructionType::Outsb)),
+        "outsw" => Some(TokenType::Instruction(InstructionType::Outsw)),
         "pop" => Some(TokenType::Instruction(InstructionType::Pop)),
+        "popa" => Some(TokenType::Instruction(InstructionType::Popa)),
         "popf" => Some(TokenType::Instruction(InstructionType::Popf)),
         "push" => Some(TokenType::Instruction(InstructionType::Push)),
+        "pusha" => Some(TokenType::Instruction(InstructionType::Pusha)),
         "pushf" => Some(TokenType::Instruction(InstructionType::Pushf)),
         "rcl" => Some(TokenType::Instruction(InstructionType::Rcl)),
         "rcr" => Some(TokenType::Instruction(InstructionType::Rcr)),
-        "rep" => Some(TokenType::Instruction(InstructionType::Rep)),
+        "rep" | "repe" | "repz" => Some(TokenType::Instruction(InstructionType::Rep)),
+        "repne" | "repnz" => Some(TokenType::Instruction(InstructionType::Repne)),
         "ret" => Some(TokenType::Instruction(InstructionType::Ret)),
+        "retf" => Some(TokenType::Instruction(InstructionType::Retf)),
         "rol" => Some(TokenType::Instruction(InstructionType::Rol)),
         "ror" => Some(TokenType::Instruction(InstructionType::Ror)),
         "sahf" => Some(TokenType::Instruction(InstructionType::Sahf)),
@@ -227,8 +669,11 @@ fn parse_token(buffer: &str) -> Option<TokenType> {
         "sbb" => Some(TokenType::Instruction(InstructionType::Sbb)),
         "scasb" => Some(TokenType::Instruction(InstructionType::Scasb)),
         "scasw" => Some(TokenType::Instruction(InstructionType::Scasw)),
+        "sgdt" => Some(TokenType::Instruction(InstructionType::Sgdt)),
         "shl" => Some(TokenType::Instruction(InstructionType::Shl)),
         "shr" => Some(TokenType::Instruction(InstructionType::Shr)),
+        "sidt" => Some(TokenType::Instruction(InstructionType::Sidt)),
+        "smsw" => Some(TokenType::Instruction(InstructionType::Smsw)),
         "stc" => Some(TokenType::Instruction(InstructionType::Stc)),
         "std" => Some(TokenType::Instruction(InstructionType::Std)),
         "sti" => Some(TokenType::Instruction(InstructionType::Sti)),
@@ -279,6 +724,35 @@ fn parse_token(buffer: &str) -> Option<TokenType> {
             GeneralPurposeRegister::Dx,
         ))),
 
+        // Directives
+        "global" => Some(TokenType::Directive(DirectiveType::Global)),
+        "extern" => Some(TokenType::Directive(DirectiveType::Extern)),
+        "org" => Some(TokenType::Directive(DirectiveType::Org)),
+        "section" => Some(TokenType::Directive(DirectiveType::Section)),
+        "entry" => Some(TokenType::Directive(DirectiveType::Entry)),
+        "db" => Some(TokenType::Directive(DirectiveType::Db)),
+        "dw" => Some(TokenType::Directive(DirectiveType::Dw)),
+        "resb" => Some(TokenType::Directive(DirectiveType::Resb)),
+        "resw" => Some(TokenType::Directive(DirectiveType::Resw)),
+
+        // Operators
+        "seg" => Some(TokenType::Operator(OperatorType::Seg)),
+        "far" => Some(TokenType::Operator(OperatorType::Far)),
+        "near" => Some(TokenType::Operator(OperatorType::Near)),
+        "short" => Some(TokenType::Operator(OperatorType::Short)),
+
+        // Floating Point Registers (st is shorthand for st(0))
+        "st" => Some(TokenType::Register(RegisterType::FloatingPoint(
+            FloatingPointRegister::St0,
+        ))),
+
+        // Operand sizes
+        "byte" => Some(TokenType::Size(OperandSize::Byte)),
+        "word" => Some(TokenType::Size(OperandSize::Word)),
+        "dword" => Some(TokenType::Size(OperandSize::Dword)),
+        "qword" => Some(TokenType::Size(OperandSize::Qword)),
+        "tword" => Some(TokenType::Size(OperandSize::Tword)),
+
         // Segment Registers
         "cs" => Some(TokenType::Register(RegisterType::Segment(
             SegmentRegister::Cs,
@@ -183,3 +183,269 @@ fn tokenize_all() {
         )
     );
 }
+
+#[test]
+fn tokenize_constant() {
+    use crate::lexer::{tokenize, Token, TokenType};
+
+    let input = "42, 0x2a, 02ah".to_string();
+    let output = tokenize(input);
+    assert!(output.is_ok());
+    let output = output.unwrap();
+    assert_eq!(output[0], Token::new(TokenType::Constant(42), 0, 0, 2));
+    assert_eq!(output[2], Token::new(TokenType::Constant(42), 0, 4, 4));
+    assert_eq!(output[4], Token::new(TokenType::Constant(42), 0, 10, 4));
+}
+
+#[test]
+fn parse_number_argument() {
+    use crate::lexer::{parse_number_argument, tokenize};
+
+    assert_eq!(parse_number_argument("0x10100"), Ok(0x10100));
+    assert_eq!(parse_number_argument("0ffffh"), Ok(0xffff));
+    assert_eq!(parse_number_argument("65536"), Ok(65536));
+    assert_eq!(
+        parse_number_argument("12g"),
+        Err("Invalid number: '12g'".to_string())
+    );
+
+    // constants have to fit into 16 bits
+    assert!(tokenize("65536".to_string()).is_err());
+}
+
+#[test]
+fn check_cpu_mnemonics() {
+    use crate::lexer::{check_cpu, token::Cpu, tokenize};
+
+    let tokens = tokenize("mov ax, bx\npusha\nsmsw ax".to_string()).unwrap();
+
+    let output = check_cpu(&tokens, Cpu::I8086);
+    assert!(output.is_err());
+    let output = output.unwrap_err();
+    assert_eq!(output.line_index, 1);
+    assert_eq!(output.char_index, 0);
+
+    let output = check_cpu(&tokens, Cpu::I80186);
+    assert!(output.is_err());
+    assert_eq!(output.unwrap_err().line_index, 2);
+
+    assert!(check_cpu(&tokens, Cpu::I80286).is_ok());
+}
+
+#[test]
+fn check_cpu_operand_forms() {
+    use crate::lexer::{check_cpu, token::Cpu, tokenize};
+
+    let legacy = tokenize("push ax\nshl bx, 1\nimul cx".to_string()).unwrap();
+    assert!(check_cpu(&legacy, Cpu::I8086).is_ok());
+
+    // data on the next line is not an operand
+    for input in ["shl ax, cl\ndb 5", "imul bx\ndb 1, 2"] {
+        let tokens = tokenize(input.to_string()).unwrap();
+        assert!(check_cpu(&tokens, Cpu::I8086).is_ok());
+    }
+
+    for input in ["push 10h", "shl bx, 4", "imul ax, bx, 3"] {
+        let tokens = tokenize(input.to_string()).unwrap();
+        assert!(check_cpu(&tokens, Cpu::I8086).is_err());
+        assert!(check_cpu(&tokens, Cpu::I80186).is_ok());
+    }
+}
+
+#[test]
+fn tokenize_floating_point() {
+    use crate::lexer::{
+        token::{FloatingPointRegister, OperandSize},
+        tokenize, InstructionType, RegisterType, Token, TokenType,
+    };
+
+    let input = "fld qword [10h]\nfadd st, st(3)\nfld1".to_string();
+    let output = tokenize(input);
+    assert!(output.is_ok());
+    let output = output.unwrap();
+
+    assert_eq!(
+        output[1],
+        Token::new(TokenType::Size(OperandSize::Qword), 0, 4, 5)
+    );
+    assert_eq!(
+        output[4],
+        Token::new(
+            TokenType::Register(RegisterType::FloatingPoint(FloatingPointRegister::St0)),
+            1,
+            5,
+            2
+        )
+    );
+    assert_eq!(
+        output[6],
+        Token::new(
+            TokenType::Register(RegisterType::FloatingPoint(FloatingPointRegister::St3)),
+            1,
+            9,
+            5
+        )
+    );
+    assert_eq!(
+        output[7],
+        Token::new(TokenType::Instruction(InstructionType::Fld1), 2, 0, 4)
+    );
+
+    assert!(tokenize("fxch st(8)".to_string()).is_err());
+}
+
+#[test]
+fn tokenize_conditional_jump() {
+    use crate::lexer::{token::Condition, tokenize, InstructionType, TokenType};
+
+    let output = tokenize("jz done\njnae done\nrepnz".to_string()).unwrap();
+    assert_eq!(
+        output[0].r#type(),
+        &TokenType::Instruction(InstructionType::Jcc(Condition::E))
+    );
+    assert_eq!(
+        output[2].r#type(),
+        &TokenType::Instruction(InstructionType::Jcc(Condition::B))
+    );
+    assert_eq!(
+        output[4].r#type(),
+        &TokenType::Instruction(InstructionType::Repne)
+    );
+    assert_eq!(InstructionType::Jcc(Condition::B).to_string(), "jb");
+}
+
+#[test]
+fn tokenize_directives() {
+    use crate::lexer::{token::DirectiveType, tokenize, InstructionType, Token, TokenType};
+
+    let input = "global start\nextern print\nstart:\n    call print".to_string();
+    let output = tokenize(input);
+    assert!(output.is_ok());
+    let output = output.unwrap();
+
+    assert_eq!(
+        output[0],
+        Token::new(TokenType::Directive(DirectiveType::Global), 0, 0, 6)
+    );
+    assert_eq!(
+        output[1],
+        Token::new(TokenType::Identifier("start".to_string()), 0, 7, 5)
+    );
+    assert_eq!(
+        output[2],
+        Token::new(TokenType::Directive(DirectiveType::Extern), 1, 0, 6)
+    );
+    assert_eq!(
+        output[4],
+        Token::new(TokenType::Label("start:".to_string()), 2, 0, 6)
+    );
+    assert_eq!(
+        output[5],
+        Token::new(TokenType::Instruction(InstructionType::Call), 3, 4, 4)
+    );
+    assert_eq!(
+        output[6],
+        Token::new(TokenType::Identifier("print".to_string()), 3, 9, 5)
+    );
+}
+
+#[test]
+fn tokenize_memory_operand() {
+    use crate::lexer::{
+        token::{MemoryOperand, SegmentRegister},
+        tokenize, GeneralPurposeRegister, RegisterType, SpecialPurposeRegister, Token, TokenType,
+    };
+
+    let bx = Some(RegisterType::GeneralPurpose(GeneralPurposeRegister::Bx));
+    let bp = Some(RegisterType::SpecialPurpose(SpecialPurposeRegister::Bp));
+    let si = Some(RegisterType::SpecialPurpose(SpecialPurposeRegister::Si));
+
+    let output = tokenize("mov ax, [bx+si+0x4]\nmov [es:bx], al".to_string()).unwrap();
+    assert_eq!(
+        output[3],
+        Token::new(
+            TokenType::Memory(MemoryOperand {
+                base: bx,
+                index: si,
+                displacement: 4,
+                ..MemoryOperand::default()
+            }),
+            0,
+            8,
+            11
+        )
+    );
+    assert_eq!(
+        output[5].r#type(),
+        &TokenType::Memory(MemoryOperand {
+            segment: Some(SegmentRegister::Es),
+            base: bx,
+            ..MemoryOperand::default()
+        })
+    );
+
+    // registers in any order, signed displacements and symbols
+    let output = tokenize("[si + bp - 2h] [message+2] [ds:0x10] [-2]".to_string()).unwrap();
+    assert_eq!(
+        output[0].r#type(),
+        &TokenType::Memory(MemoryOperand {
+            base: bp,
+            index: si,
+            displacement: -2,
+            ..MemoryOperand::default()
+        })
+    );
+    assert_eq!(
+        output[1].r#type(),
+        &TokenType::Memory(MemoryOperand {
+            symbol: Some("message".to_string()),
+            displacement: 2,
+            ..MemoryOperand::default()
+        })
+    );
+    assert_eq!(
+        output[2].r#type(),
+        &TokenType::Memory(MemoryOperand {
+            segment: Some(SegmentRegister::Ds),
+            displacement: 0x10,
+            ..MemoryOperand::default()
+        })
+    );
+    assert_eq!(output[3].r#type(), &TokenType::MemoryLocation(0xfffe));
+
+    for input in [
+        "[bx+bp]", "[ax]", "[si-bx]", "[fs:bx]", "[]", "[bx+]", "[a+b]", "[bx",
+    ] {
+        assert!(tokenize(input.to_string()).is_err(), "{}", input);
+    }
+}
+
+#[test]
+fn tokenize_data() {
+    use crate::lexer::{
+        token::{DirectiveType, OperatorType},
+        tokenize, Token, TokenType,
+    };
+
+    let input =
+        "    org 0x100\n.loop_1: db 'Hi', -1\njmp 0xf000:0xfff0\nmov ax, seg data".to_string();
+    let output = tokenize(input).unwrap();
+
+    assert_eq!(
+        output[0],
+        Token::new(TokenType::Directive(DirectiveType::Org), 0, 4, 3)
+    );
+    assert_eq!(
+        output[2],
+        Token::new(TokenType::Label(".loop_1:".to_string()), 1, 0, 8)
+    );
+    assert_eq!(
+        output[4],
+        Token::new(TokenType::String("Hi".to_string()), 1, 12, 4)
+    );
+    assert_eq!(output[6], Token::new(TokenType::Constant(0xffff), 1, 18, 2));
+    assert_eq!(output[9], Token::new(TokenType::Colon, 2, 10, 1));
+    assert_eq!(output[14].r#type(), &TokenType::Operator(OperatorType::Seg));
+
+    assert!(tokenize("db \"open".to_string()).is_err());
+}
@@ -1,3 +1,5 @@
+use std::{fmt::Display, str::FromStr};
+
 /// Token representation of assembly code.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Token {
@@ -20,6 +22,26 @@ impl Token {
             char_len,
         }
     }
+
+    /// Type of token
+    pub fn r#type(&self) -> &TokenType {
+        &self.r#type
+    }
+
+    /// Line of occurrence
+    pub fn line_index(&self) -> usize {
+        self.line_index
+    }
+
+    /// Character index of occurrence
+    pub fn char_index(&self) -> usize {
+        self.char_index
+    }
+
+    /// Length of token in chars
+    pub fn char_len(&self) -> usize {
+        self.char_len
+    }
 }
 
 /// Types of tokens
@@ -27,10 +49,90 @@ impl Token {
 pub enum TokenType {
     Instruction(InstructionType), // mov, add, xor, ...
     Register(RegisterType),       // ax, bx, si, di, ...
-    Constant(u16),                // 1234h, ...
+    Constant(u16),                // 1234h, -1, ...
     MemoryLocation(u16),          // [0xbeef], [0xcafe], ...
-    Label(String),                // hello:, MSG:, ...
+    Memory(MemoryOperand),        // [bx+si+0x4], [es:di], [message], ...
+    Label(String),                // hello:, MSG:, .loop:, ...
+    Size(OperandSize),            // byte, word, qword, ...
+    Directive(DirectiveType),     // global, extern, org, db, ...
+    Operator(OperatorType),       // seg, far, near, short
+    Identifier(String),           // message, print, ...
+    String(String),               // "Hello, World!$", 'A', ...
     Comma,
+    Colon, // 0xf000:0xfff0
+}
+
+/// Assembler directives
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DirectiveType {
+    /// Export symbols to other objects
+    Global,
+    /// Import symbols defined in other objects
+    Extern,
+    /// Address the flat image is loaded at
+    Org,
+    /// Switch to the named section
+    Section,
+    /// Symbol execution starts at (.exe)
+    Entry,
+    /// Define bytes
+    Db,
+    /// Define words
+    Dw,
+    /// Reserve uninitialized bytes
+    Resb,
+    /// Reserve uninitialized words
+    Resw,
+}
+
+/// Operators and jump distances
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OperatorType {
+    /// Segment (paragraph) of a symbol, fixed up by the loader
+    Seg,
+    /// Far jump or call (through a memory operand)
+    Far,
+    /// Near jump, even if the target is in short range
+    Near,
+    /// Short jump with an 8-bit displacement
+    Short,
+}
+
+/// Memory operand in brackets: an optional segment override, base (bx, bp) and index
+/// (si, di) registers and a displacement relative to an optional symbol.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MemoryOperand {
+    /// Segment override (`[es:...]`)
+    pub segment: Option<SegmentRegister>,
+    /// bx or bp
+    pub base: Option<RegisterType>,
+    /// si or di
+    pub index: Option<RegisterType>,
+    /// Symbol the displacement is added to
+    pub symbol: Option<String>,
+    /// Constant part of the displacement
+    pub displacement: i32,
+}
+
+/// Explicit operand sizes
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OperandSize {
+    /// 8 bits
+    Byte,
+    /// 16 bits
+    Word,
+    /// 32 bits (short real or long integer)
+    Dword,
+    /// 64 bits (long real or 64-bit integer)
+    Qword,
+    /// 80 bits (temporary real or packed BCD)
+    Tword,
+}
+
+impl Display for OperandSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -49,6 +151,8 @@ pub enum InstructionType {
     Add,
     /// Logical And
     And,
+    /// Check array index against bounds (80186)
+    Bound,
     /// Call procedure
     Call,
     /// Convert byte to word
@@ -59,6 +163,8 @@ pub enum InstructionType {
     Cld,
     /// Clear interrupt flag
     Cli,
+    /// Clear task-switched flag in MSW (80286)
+    Clts,
     /// Complement carry flag
     Cmc,
     /// Compare operands
@@ -77,8 +183,162 @@ pub enum InstructionType {
     Dec,
     /// Unsigned divide
     Div,
+    /// Make stack frame for procedure parameters (80186)
+    Enter,
     /// Used with floating point unit
     Esc,
+    /// Compute 2^x - 1 (8087)
+    F2xm1,
+    /// Absolute value (8087)
+    Fabs,
+    /// Add real (8087)
+    Fadd,
+    /// Add real and pop (8087)
+    Faddp,
+    /// Load packed BCD (8087)
+    Fbld,
+    /// Store packed BCD and pop (8087)
+    Fbstp,
+    /// Change sign (8087)
+    Fchs,
+    /// Clear exceptions (8087)
+    Fclex,
+    /// Compare real (8087)
+    Fcom,
+    /// Compare real and pop (8087)
+    Fcomp,
+    /// Compare real and pop twice (8087)
+    Fcompp,
+    /// Decrement stack top pointer (8087)
+    Fdecstp,
+    /// Disable interrupts (8087)
+    Fdisi,
+    /// Divide real (8087)
+    Fdiv,
+    /// Divide real and pop (8087)
+    Fdivp,
+    /// Divide real reversed (8087)
+    Fdivr,
+    /// Divide real reversed and pop (8087)
+    Fdivrp,
+    /// Enable interrupts (8087)
+    Feni,
+    /// Free register (8087)
+    Ffree,
+    /// Add integer (8087)
+    Fiadd,
+    /// Compare integer (8087)
+    Ficom,
+    /// Compare integer and pop (8087)
+    Ficomp,
+    /// Divide integer (8087)
+    Fidiv,
+    /// Divide integer reversed (8087)
+    Fidivr,
+    /// Load integer (8087)
+    Fild,
+    /// Multiply integer (8087)
+    Fimul,
+    /// Increment stack top pointer (8087)
+    Fincstp,
+    /// Initialize coprocessor (8087)
+    Finit,
+    /// Store integer (8087)
+    Fist,
+    /// Store integer and pop (8087)
+    Fistp,
+    /// Subtract integer (8087)
+    Fisub,
+    /// Subtract integer reversed (8087)
+    Fisubr,
+    /// Load real (8087)
+    Fld,
+    /// Load +1.0 (8087)
+    Fld1,
+    /// Load control word (8087)
+    Fldcw,
+    /// Load environment (8087)
+    Fldenv,
+    /// Load log2(e) (8087)
+    Fldl2e,
+    /// Load log2(10) (8087)
+    Fldl2t,
+    /// Load log10(2) (8087)
+    Fldlg2,
+    /// Load ln(2) (8087)
+    Fldln2,
+    /// Load pi (8087)
+    Fldpi,
+    /// Load +0.0 (8087)
+    Fldz,
+    /// Multiply real (8087)
+    Fmul,
+    /// Multiply real and pop (8087)
+    Fmulp,
+    /// Clear exceptions without waiting (8087)
+    Fnclex,
+    /// Disable interrupts without waiting (8087)
+    Fndisi,
+    /// Enable interrupts without waiting (8087)
+    Fneni,
+    /// Initialize coprocessor without waiting (8087)
+    Fninit,
+    /// No operation (8087)
+    Fnop,
+    /// Save state without waiting (8087)
+    Fnsave,
+    /// Store control word without waiting (8087)
+    Fnstcw,
+    /// Store environment without waiting (8087)
+    Fnstenv,
+    /// Store status word without waiting (8087)
+    Fnstsw,
+    /// Partial arctangent (8087)
+    Fpatan,
+    /// Partial remainder (8087)
+    Fprem,
+    /// Partial tangent (8087)
+    Fptan,
+    /// Round to integer (8087)
+    Frndint,
+    /// Restore state (8087)
+    Frstor,
+    /// Save state (8087)
+    Fsave,
+    /// Scale by power of 2 (8087)
+    Fscale,
+    /// Square root (8087)
+    Fsqrt,
+    /// Store real (8087)
+    Fst,
+    /// Store control word (8087)
+    Fstcw,
+    /// Store environment (8087)
+    Fstenv,
+    /// Store real and pop (8087)
+    Fstp,
+    /// Store status word (8087)
+    Fstsw,
+    /// Subtract real (8087)
+    Fsub,
+    /// Subtract real and pop (8087)
+    Fsubp,
+    /// Subtract real reversed (8087)
+    Fsubr,
+    /// Subtract real reversed and pop (8087)
+    Fsubrp,
+    /// Compare with +0.0 (8087)
+    Ftst,
+    /// Examine (8087)
+    Fxam,
+    /// Exchange registers (8087)
+    Fxch,
+    /// Extract exponent and significand (8087)
+    Fxtract,
+    /// Compute y * log2(x) (8087)
+    Fyl2x,
+    /// Compute y * log2(x + 1) (8087)
+    Fyl2xp1,
     /// Enter hlt state
     Hlt,
     /// Signed divide
@@ -89,14 +349,20 @@ pub enum InstructionType {
     In,
     /// Increment by 1
     Inc,
+    /// Input byte string from port (80186)
+    Insb,
+    /// Input word string from port (80186)
+    Insw,
     /// Call to interrupt
     Int,
+    /// Call to breakpoint interrupt (single byte form of int 3)
+    Int3,
     /// Call to interrupt if overflow
     Into,
     /// Return from interrupt
     Iret,
     /// Jump if condition
-    Jcc,
+    Jcc(Condition),
     /// Jump if CX is zero
     Jcxz,
     /// Jump
@@ -107,8 +373,16 @@ pub enum InstructionType {
     Lds,
     /// Load effective address
     Lea,
+    /// High level procedure exit (80186)
+    Leave,
     /// Load ES:r with far pointer
     Les,
+    /// Load global descriptor table register (80286)
+    Lgdt,
+    /// Load interrupt descriptor table register (80286)
+    Lidt,
+    /// Load machine status word (80286)
+    Lmsw,
     /// Assert BUS LOCK# signal
     Lock,
     /// Load string byte
@@ -117,6 +391,10 @@ pub enum InstructionType {
     Lodsw,
     /// Loop control
     Loop,
+    /// Loop while equal (zero flag set)
+    Loope,
+    /// Loop while not equal (zero flag clear)
+    Loopne,
     /// Move data
     Mov,
     /// Move byte from string to string
@@ -135,22 +413,34 @@ pub enum InstructionType {
     Or,
     /// Output to port
     Out,
+    /// Output byte string to port (80186)
+    Outsb,
+    /// Output word string to port (80186)
+    Outsw,
     /// Pop data from stack
     Pop,
+    /// Pop all general purpose registers (80186)
+    Popa,
     /// Pop FLAGS register from stack
     Popf,
     /// Push data onto stack
     Push,
+    /// Push all general purpose registers (80186)
+    Pusha,
     /// Push FLAGS onto stack
     Pushf,
     /// Rotate left (with carry)
     Rcl,
     /// Rotate right (with carry)
     Rcr,
-    /// Repeat instructions (REPxx)
+    /// Repeat string instruction (REP, REPE, REPZ)
     Rep,
+    /// Repeat string instruction while not equal (REPNE, REPNZ)
+    Repne,
     /// Return from procedure
     Ret,
+    /// Return from far procedure
+    Retf,
     /// Rotate left
     Rol,
     /// Rotate right
@@ -167,10 +457,16 @@ pub enum InstructionType {
     Scasb,
     /// Compare word string
     Scasw,
+    /// Store global descriptor table register (80286)
+    Sgdt,
     /// Shift left
     Shl,
     /// Shift right
     Shr,
+    /// Store interrupt descriptor table register (80286)
+    Sidt,
+    /// Store machine status word (80286)
+    Smsw,
     /// Set carry flag
     Stc,
     /// Set direction flag
@@ -195,11 +491,184 @@ pub enum InstructionType {
     Xor,
 }
 
+impl InstructionType {
+    /// Oldest processor that implements the instruction in any of its forms
+    pub fn cpu(&self) -> Cpu {
+        match self {
+            InstructionType::Bound
+            | InstructionType::Enter
+            | InstructionType::Insb
+            | InstructionType::Insw
+            | InstructionType::Leave
+            | InstructionType::Outsb
+            | InstructionType::Outsw
+            | InstructionType::Popa
+            | InstructionType::Pusha => Cpu::I80186,
+            InstructionType::Clts
+            | InstructionType::Lgdt
+            | InstructionType::Lidt
+            | InstructionType::Lmsw
+            | InstructionType::Sgdt
+            | InstructionType::Sidt
+            | InstructionType::Smsw => Cpu::I80286,
+            _ => Cpu::I8086,
+        }
+    }
+
+    /// Whether the 8087 coprocessor executes the instruction (ESC opcodes D8h-DFh)
+    pub fn is_floating_point(&self) -> bool {
+        // every coprocessor mnemonic and no other starts with 'f'
+        format!("{:?}", self).starts_with('F')
+    }
+
+    /// Whether the instruction addresses memory without an operand (string instructions
+    /// and xlat), so that a segment override prefix can be written before it (`es lodsb`)
+    pub fn has_implicit_memory(&self) -> bool {
+        matches!(
+            self,
+            InstructionType::Cmpsb
+                | InstructionType::Cmpsw
+                | InstructionType::Insb
+                | InstructionType::Insw
+                | InstructionType::Lodsb
+                | InstructionType::Lodsw
+                | InstructionType::Movsb
+                | InstructionType::Movsw
+                | InstructionType::Outsb
+                | InstructionType::Outsw
+                | InstructionType::Scasb
+                | InstructionType::Scasw
+                | InstructionType::Stosb
+                | InstructionType::Stosw
+                | InstructionType::Xlat
+        )
+    }
+}
+
+impl Display for InstructionType {
+    /// Writes the mnemonic as accepted by the lexer.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstructionType::Jcc(condition) => {
+                write!(f, "j{}", format!("{:?}", condition).to_lowercase())
+            }
+            _ => write!(f, "{}", format!("{:?}", self).to_lowercase()),
+        }
+    }
+}
+
+/// Conditions of conditional jumps, in the order of their encoding
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Condition {
+    /// Overflow
+    O,
+    /// Not overflow
+    No,
+    /// Below (carry)
+    B,
+    /// Above or equal (not carry)
+    Ae,
+    /// Equal (zero)
+    E,
+    /// Not equal (not zero)
+    Ne,
+    /// Below or equal
+    Be,
+    /// Above
+    A,
+    /// Sign
+    S,
+    /// Not sign
+    Ns,
+    /// Parity (even)
+    P,
+    /// Not parity (odd)
+    Np,
+    /// Less
+    L,
+    /// Greater or equal
+    Ge,
+    /// Less or equal
+    Le,
+    /// Greater
+    G,
+}
+
+impl Condition {
+    /// All conditions, indexed by their encoding (low nibble of opcodes 70h-7Fh)
+    pub const ALL: [Condition; 16] = [
+        Condition::O,
+        Condition::No,
+        Condition::B,
+        Condition::Ae,
+        Condition::E,
+        Condition::Ne,
+        Condition::Be,
+        Condition::A,
+        Condition::S,
+        Condition::Ns,
+        Condition::P,
+        Condition::Np,
+        Condition::L,
+        Condition::Ge,
+        Condition::Le,
+        Condition::G,
+    ];
+}
+
+/// Processor generations that can be targeted. Later generations compare greater.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Cpu {
+    /// Intel 8086/8088
+    I8086,
+    /// Intel 80186/80188
+    I80186,
+    /// Intel 80286 (real mode)
+    I80286,
+}
+
+impl FromStr for Cpu {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "8086" | "8088" => Ok(Cpu::I8086),
+            "186" | "188" | "80186" | "80188" => Ok(Cpu::I80186),
+            "286" | "80286" => Ok(Cpu::I80286),
+            _ => Err(format!("Unknown CPU: '{}'. Expected 8086, 186 or 286.", s)),
+        }
+    }
+}
+
+impl Display for Cpu {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cpu::I8086 => write!(f, "8086"),
+            Cpu::I80186 => write!(f, "80186"),
+            Cpu::I80286 => write!(f, "80286"),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum RegisterType {
     GeneralPurpose(GeneralPurposeRegister),
     Segment(SegmentRegister),
     SpecialPurpose(SpecialPurposeRegister),
+    FloatingPoint(FloatingPointRegister),
+}
+
+impl Display for RegisterType {
+    /// Writes the register name as accepted by the lexer.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            RegisterType::GeneralPurpose(register) => format!("{:?}", register),
+            RegisterType::Segment(register) => format!("{:?}", register),
+            RegisterType::SpecialPurpose(register) => format!("{:?}", register),
+            RegisterType::FloatingPoint(register) => format!("st({})", register.index()),
+        };
+        write!(f, "{}", name.to_lowercase())
+    }
 }
 
 /// Versitile
@@ -261,3 +730,46 @@ pub enum SpecialPurposeRegister {
     /// Instruaction Pointer (address of next instruction to be exectured)
     Ip,
 }
+
+/// 8087 register stack, relative to the current stack top
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FloatingPointRegister {
+    /// Stack top, st(0)
+    St0,
+    /// st(1)
+    St1,
+    /// st(2)
+    St2,
+    /// st(3)
+    St3,
+    /// st(4)
+    St4,
+    /// st(5)
+    St5,
+    /// st(6)
+    St6,
+    /// st(7)
+    St7,
+}
+
+impl FloatingPointRegister {
+    /// Returns the register for stack index `index` (0-7).
+    pub fn from_index(index: u8) -> Option<FloatingPointRegister> {
+        match index {
+            0 => Some(FloatingPointRegister::St0),
+            1 => Some(FloatingPointRegister::St1),
+            2 => Some(FloatingPointRegister::St2),
+            3 => Some(FloatingPointRegister::St3),
+            4 => Some(FloatingPointRegister::St4),
+            5 => Some(FloatingPointRegister::St5),
+            6 => Some(FloatingPointRegister::St6),
+            7 => Some(FloatingPointRegister::St7),
+            _ => None,
+        }
+    }
+
+    /// Stack index of the register (0-7)
+    pub fn index(&self) -> u8 {
+        *self as u8
+    }
+}
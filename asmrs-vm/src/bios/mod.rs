@@ -0,0 +1,802 @@
+use crate::{
+    graphics::{
+        self, default_palette, CGA_COLOR_PORT, DAC_DATA_PORT, DAC_READ_INDEX_PORT,
+        DAC_WRITE_INDEX_PORT,
+    },
+    interrupt::InterruptHandler,
+    keyboard::{Controller, KEYBOARD_DATA_PORT, KEYBOARD_IRQ, KEYBOARD_IRQ_VECTOR},
+    machine::Machine,
+    memory::address,
+    registers::{FLAG_CARRY, FLAG_INTERRUPT, FLAG_ZERO},
+    screen::{self, DEFAULT_ATTRIBUTE},
+};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io::{self, Read, Write},
+    rc::Rc,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+mod test;
+
+/// Video services (`int 10h`)
+pub const VIDEO_INTERRUPT: u8 = 0x10;
+/// Disk services (`int 13h`)
+pub const DISK_INTERRUPT: u8 = 0x13;
+/// Keyboard services (`int 16h`)
+pub const KEYBOARD_INTERRUPT: u8 = 0x16;
+/// Clock services (`int 1Ah`)
+pub const CLOCK_INTERRUPT: u8 = 0x1a;
+
+/// Machine steps per timer tick: the PC timer ticks 18.2 times a second, and a 4.77 MHz
+/// 8088 executes very roughly 260000 instructions in that time.
+pub const INSTRUCTIONS_PER_TICK: u64 = 14_300;
+
+/// Timer ticks per day; the counter wraps at midnight
+pub const TICKS_PER_DAY: u32 = 0x1800b0;
+
+/// BIOS data area byte holding the current video mode
+pub const VIDEO_MODE: usize = 0x449;
+/// BIOS data area byte holding the last value written to the CGA color select register
+pub const CGA_PALETTE: usize = 0x466;
+
+/// Installs the default BIOS services: video output to stdout, keyboard input from stdin,
+/// `disks` for int 13h and the tick counter, and clears the text buffer. Single handlers
+/// can be replaced afterwards through `machine.interrupts`.
+pub fn install(machine: &mut Machine, disks: Vec<Disk>) {
+    screen::clear(machine);
+    machine.write_byte(VIDEO_MODE, 3);
+    machine
+        .interrupts
+        .set(VIDEO_INTERRUPT, Video::new(Box::new(io::stdout())));
+    machine
+        .interrupts
+        .set(KEYBOARD_INTERRUPT, Keyboard::stdin());
+    machine.interrupts.set(DISK_INTERRUPT, Disks::new(disks));
+    machine.interrupts.set(CLOCK_INTERRUPT, Clock::new());
+}
+
+fn ah(machine: &Machine) -> u8 {
+    (machine.registers.ax >> 8) as u8
+}
+
+fn al(machine: &Machine) -> u8 {
+    machine.registers.ax as u8
+}
+
+pub(crate) fn unsupported(vector: u8, machine: &Machine) -> String {
+    format!(
+        "int {:02X}h function AH={:02X}h is not supported.",
+        vector,
+        ah(machine)
+    )
+}
+
+/// Text mode video services writing teletype output to a host stream. In the 80x25 color
+/// text modes characters also go to the text buffer at B800:0000 like on a real PC, so
+/// output placed with any function can be seen through `screen`.
+pub struct Video {
+    output: Box<dyn Write>,
+    pub mode: u8,
+    /// Cursor row and column
+    pub cursor: (u8, u8),
+}
+
+impl Video {
+    pub const COLUMNS: u8 = 80;
+    pub const ROWS: u8 = 25;
+
+    pub fn new(output: Box<dyn Write>) -> Video {
+        Self {
+            output,
+            mode: 3,
+            cursor: (0, 0),
+        }
+    }
+
+    /// Whether the mode keeps 80x25 characters in the text buffer
+    pub fn text_mode(&self) -> bool {
+        matches!(self.mode, 2 | 3)
+    }
+
+    /// Writes a character like the teletype function, handling BEL, BS, LF and CR and
+    /// scrolling the text buffer at the bottom of the screen.
+    pub fn teletype(&mut self, machine: &mut Machine, character: u8) -> io::Result<()> {
+        let (row, column) = self.cursor;
+        let text = self.text_mode();
+
+        let (row, column) = match character {
+            0x07 => (row, column),
+            0x08 => (row, column.saturating_sub(1)),
+            b'\n' => (row + 1, column),
+            b'\r' => (row, 0),
+            _ => {
+                if text {
+                    machine.write_byte(screen::cell_address(row, column), character);
+                }
+                if column + 1 == Self::COLUMNS {
+                    (row + 1, 0)
+                } else {
+                    (row, column + 1)
+                }
+            }
+        };
+
+        self.cursor = if row == Self::ROWS {
+            if text {
+                self.scroll(machine, 1, DEFAULT_ATTRIBUTE);
+            }
+            (Self::ROWS - 1, column)
+        } else {
+            (row, column)
+        };
+
+        if character != 0x07 {
+            self.output.write_all(&[character])?;
+            self.output.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Scrolls the whole screen up by `lines`.
+    fn scroll(&self, machine: &mut Machine, lines: i8, attribute: u8) {
+        screen::scroll(
+            machine,
+            (0, 0),
+            (Self::ROWS - 1, Self::COLUMNS - 1),
+            lines,
+            attribute,
+        );
+    }
+}
+
+impl InterruptHandler for Video {
+    fn interrupt(&mut self, machine: &mut Machine, vector: u8) -> Result<(), String> {
+        let [al, ah] = machine.registers.ax.to_le_bytes();
+        let [bl, bh] = machine.registers.bx.to_le_bytes();
+        let cell = screen::cell_address(self.cursor.0, self.cursor.1);
+
+        match ah {
+            // set video mode (clears the screen unless AL bit 7 is set)
+            0x00 => {
+                self.mode = al & 0x7f;
+                self.cursor = (0, 0);
+                machine.write_byte(VIDEO_MODE, self.mode);
+                if al & 0x80 == 0 {
+                    if self.text_mode() {
+                        screen::clear(machine);
+                    }
+                    graphics::clear(machine, self.mode);
+                }
+                match self.mode {
+                    0x04..=0x06 => {
+                        let color = if self.mode == 0x06 { 0x3f } else { 0x30 };
+                        set_cga_palette(machine, color)?;
+                    }
+                    0x13 => {
+                        let colors = default_palette().concat();
+                        set_dac_registers(machine, 0, &colors)?;
+                    }
+                    _ => {}
+                }
+            }
+            // set cursor shape
+            0x01 => {}
+            // set cursor position of page BH to DH:DL
+            0x02 => {
+                let [column, row] = machine.registers.dx.to_le_bytes();
+                self.cursor = (row.min(Self::ROWS - 1), column.min(Self::COLUMNS - 1));
+            }
+            // get cursor position and shape
+            0x03 => {
+                machine.registers.dx = u16::from_le_bytes([self.cursor.1, self.cursor.0]);
+                machine.registers.cx = 0x0607;
+            }
+            // scroll the window CH:CL to DH:DL up or down by AL lines, blanking with BH
+            0x06 | 0x07 if self.text_mode() => {
+                let [left, top] = machine.registers.cx.to_le_bytes();
+                let [right, bottom] = machine.registers.dx.to_le_bytes();
+                let lines = al.min(Self::ROWS) as i8;
+                let lines = if ah == 0x06 { lines } else { -lines };
+                screen::scroll(machine, (top, left), (bottom, right), lines, bh);
+            }
+            // read the character and attribute at the cursor
+            0x08 if self.text_mode() => {
+                machine.registers.ax = machine.memory.read_word(cell);
+            }
+            // write AL CX times at the cursor, with attribute BL (09h) or keeping it (0Ah)
+            0x09 | 0x0a if self.text_mode() => {
+                let cells = Self::ROWS as usize * Self::COLUMNS as usize;
+                let first =
+                    self.cursor.0 as usize * Self::COLUMNS as usize + self.cursor.1 as usize;
+                let count = (machine.registers.cx as usize).min(cells - first);
+                for address in (cell..cell + count * 2).step_by(2) {
+                    machine.write_byte(address, al);
+                    if ah == 0x09 {
+                        machine.write_byte(address + 1, bl);
+                    }
+                }
+            }
+            // nothing to show outside the text modes
+            0x06..=0x0a => {}
+            // CGA palette: background (BH=0) or palette (BH=1) from BL
+            0x0b => {
+                let color = machine.memory.read_byte(CGA_PALETTE);
+                let color = match bh {
+                    0 => color & 0x20 | bl & 0x1f,
+                    _ => color & 0x1f | (bl & 1) << 5,
+                };
+                set_cga_palette(machine, color)?;
+            }
+            // teletype output of AL
+            0x0e => self
+                .teletype(machine, al)
+                .map_err(|error| format!("Could not write video output: {}", error))?,
+            // get video mode: AL mode, AH columns, BH page
+            0x0f => {
+                machine.registers.ax = u16::from_le_bytes([self.mode, Self::COLUMNS]);
+                machine.registers.bx &= 0x00ff;
+            }
+            // DAC color BX from DH, CH and CL
+            0x10 if al == 0x10 => {
+                let [blue, green] = machine.registers.cx.to_le_bytes();
+                let red = (machine.registers.dx >> 8) as u8;
+                set_dac_registers(machine, bl, &[red, green, blue])?;
+            }
+            // CX DAC colors from BX on, from the table at ES:DX
+            0x10 if al == 0x12 => {
+                let registers = machine.registers.clone();
+                let colors = (0..registers.cx.wrapping_mul(3))
+                    .map(|index| {
+                        machine.read_byte(address(registers.es, registers.dx.wrapping_add(index)))
+                    })
+                    .collect::<Vec<_>>();
+                set_dac_registers(machine, bl, &colors)?;
+            }
+            // DAC color BX into DH, CH and CL
+            0x10 if al == 0x15 => {
+                machine.write_port(DAC_READ_INDEX_PORT, 1, bl as u16)?;
+                let red = machine.read_port(DAC_DATA_PORT, 1)?;
+                let green = machine.read_port(DAC_DATA_PORT, 1)?;
+                let blue = machine.read_port(DAC_DATA_PORT, 1)?;
+                machine.registers.dx = machine.registers.dx & 0x00ff | red << 8;
+                machine.registers.cx = green << 8 | blue;
+            }
+            _ => return Err(unsupported(vector, machine)),
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the CGA color select register and records it in the BIOS data area.
+fn set_cga_palette(machine: &mut Machine, color: u8) -> Result<(), String> {
+    machine.write_byte(CGA_PALETTE, color);
+    machine.write_port(CGA_COLOR_PORT, 1, color as u16)
+}
+
+/// Loads DAC colors from `first` on with red, green and blue components in turn.
+fn set_dac_registers(machine: &mut Machine, first: u8, components: &[u8]) -> Result<(), String> {
+    machine.write_port(DAC_WRITE_INDEX_PORT, 1, first as u16)?;
+    for component in components {
+        machine.write_port(DAC_DATA_PORT, 1, *component as u16)?;
+    }
+    Ok(())
+}
+
+/// Where keystrokes come from
+enum Source {
+    /// Only queued keys
+    Queue,
+    /// Bytes of the host stdin, read on a separate thread once a key is wanted
+    Stdin(Option<Receiver<u8>>),
+    /// Scan codes of a keyboard controller, taken on IRQ 1
+    Controller(Rc<RefCell<Controller>>),
+}
+
+/// Keyboard services with a queue of keystrokes (scan code in the high byte, ASCII in the
+/// low byte), filled from the host, by `push` or from the scan codes of a keyboard
+/// controller like the BIOS IRQ 1 handler does.
+pub struct Keyboard {
+    source: Source,
+    pub keys: VecDeque<u16>,
+    /// Whether the source has no more input
+    closed: bool,
+    /// Shift flags: right and left shift, ctrl, alt (bits 0 to 3) and caps lock (bit 6)
+    pub shift: u8,
+    /// The last scan code was the E0h prefix of an extended key
+    extended: bool,
+}
+
+impl Keyboard {
+    /// Keyboard reading the host stdin. The reader thread starts with the first request so
+    /// stdin stays available to others until then.
+    pub fn stdin() -> Keyboard {
+        Self {
+            source: Source::Stdin(None),
+            keys: VecDeque::new(),
+            closed: false,
+            shift: 0,
+            extended: false,
+        }
+    }
+
+    /// Keyboard taking scan codes from `controller` when its IRQ 1 handler (vector 09h)
+    /// runs. Waiting for a key lets the machine run until one arrives.
+    pub fn controller(controller: Rc<RefCell<Controller>>) -> Keyboard {
+        Self {
+            source: Source::Controller(controller),
+            keys: VecDeque::new(),
+            closed: false,
+            shift: 0,
+            extended: false,
+        }
+    }
+
+    /// Keyboard typing `text`, then reporting the end of input.
+    pub fn from_text(text: &str) -> Keyboard {
+        let mut keyboard = Self {
+            source: Source::Queue,
+            keys: VecDeque::new(),
+            closed: true,
+            shift: 0,
+            extended: false,
+        };
+        text.bytes().for_each(|byte| keyboard.push(byte));
+        keyboard
+    }
+
+    /// Queues the keystroke producing the ASCII character (LF becomes Enter).
+    pub fn push(&mut self, character: u8) {
+        self.keys.push_back(key(character));
+    }
+
+    /// Queues the key of a scan code byte and tracks the shift keys; break codes and
+    /// prefixes only change the state.
+    pub fn scan(&mut self, code: u8) {
+        if code == 0xe0 {
+            self.extended = true;
+            return;
+        }
+        let extended = std::mem::take(&mut self.extended);
+        let (make, released) = (code & 0x7f, code & 0x80 != 0);
+
+        let flag = match make {
+            // extended keys send shifts of their own, which are not real ones
+            0x2a | 0x36 if extended => return,
+            0x36 => 0x01,
+            0x2a => 0x02,
+            0x1d => 0x04,
+            0x38 => 0x08,
+            _ => 0,
+        };
+        if released {
+            self.shift &= !flag;
+            return;
+        }
+        self.shift |= flag;
+        if make == 0x3a {
+            self.shift ^= 0x40;
+        }
+        if flag != 0 || make == 0x3a || make == 0 {
+            return;
+        }
+
+        let letter = character(make, false).is_ascii_lowercase();
+        let shifted = (self.shift & 0x03 != 0) != (letter && self.shift & 0x40 != 0);
+        let ascii = match character(make, shifted) {
+            _ if self.shift & 0x08 != 0 => 0,
+            ascii if self.shift & 0x04 != 0 && letter => ascii & 0x1f,
+            _ if self.shift & 0x04 != 0 => 0,
+            ascii => ascii,
+        };
+        self.keys.push_back((make as u16) << 8 | ascii as u16);
+    }
+
+    /// Takes a scan code waiting in the controller when interrupts are disabled, so that
+    /// IRQ 1 cannot deliver it.
+    fn receive(&mut self, machine: &mut Machine) -> Result<(), String> {
+        let Source::Controller(controller) = &self.source else {
+            return Ok(());
+        };
+        let waiting = controller.borrow().output.is_some();
+        if waiting && !machine.flag(FLAG_INTERRUPT) {
+            machine.pic.withdraw(KEYBOARD_IRQ);
+            let code = machine.read_port(KEYBOARD_DATA_PORT, 1)?;
+            self.scan(code as u8);
+        }
+        Ok(())
+    }
+
+    /// Moves available input into the queue, waiting for a key if `wait` is set.
+    fn poll(&mut self, wait: bool) {
+        let receiver = match &mut self.source {
+            Source::Stdin(receiver) => receiver,
+            Source::Controller(controller) => {
+                self.closed = controller.borrow().finished();
+                return;
+            }
+            Source::Queue => return,
+        };
+
+        let receiver = receiver.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                for byte in io::stdin().lock().bytes() {
+                    let Ok(byte) = byte else { break };
+                    if sender.send(byte).is_err() {
+                        break;
+                    }
+                }
+            });
+            receiver
+        });
+
+        if wait && self.keys.is_empty() && !self.closed {
+            match receiver.recv() {
+                Ok(byte) => self.keys.push_back(key(byte)),
+                Err(_) => self.closed = true,
+            }
+        }
+
+        loop {
+            match receiver.try_recv() {
+                Ok(byte) => self.keys.push_back(key(byte)),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl InterruptHandler for Keyboard {
+    fn interrupt(&mut self, machine: &mut Machine, vector: u8) -> Result<(), String> {
+        // IRQ 1: a scan code from the controller
+        if vector == KEYBOARD_IRQ_VECTOR {
+            let code = machine.read_port(KEYBOARD_DATA_PORT, 1)?;
+            self.scan(code as u8);
+            machine.pic.end_of_interrupt();
+            return Ok(());
+        }
+
+        self.receive(machine)?;
+        match ah(machine) {
+            // wait for a key and remove it
+            0x00 | 0x10 => {
+                self.poll(true);
+                if self.keys.is_empty() && !self.closed {
+                    machine.retry_interrupt();
+                    return Ok(());
+                }
+                machine.registers.ax = self
+                    .keys
+                    .pop_front()
+                    .ok_or_else(|| "Keyboard input ended.".to_string())?;
+            }
+            // check for a key: ZF clear and the key in AX if there is one
+            0x01 | 0x11 => {
+                self.poll(false);
+                match self.keys.front() {
+                    Some(key) => {
+                        machine.registers.ax = *key;
+                        machine.set_flag(FLAG_ZERO, false);
+                    }
+                    None => machine.set_flag(FLAG_ZERO, true),
+                }
+            }
+            // shift flags
+            0x02 | 0x12 => machine.registers.ax = machine.registers.ax & 0xff00 | self.shift as u16,
+            _ => return Err(unsupported(vector, machine)),
+        }
+
+        Ok(())
+    }
+}
+
+/// Keystroke for an ASCII character: scan code of the US layout key and the character
+pub fn key(character: u8) -> u16 {
+    let character = if character == b'\n' { b'\r' } else { character };
+    ((scan_code(character) as u16) << 8) | character as u16
+}
+
+/// Rows of character keys on a US keyboard: unshifted and shifted characters and the scan
+/// code of the first key
+const KEY_ROWS: [(&[u8], &[u8], u8); 4] = [
+    (b"1234567890-=", b"!@#$%^&*()_+", 0x02),
+    (b"qwertyuiop[]", b"QWERTYUIOP{}", 0x10),
+    (b"asdfghjkl;'`", b"ASDFGHJKL:\"~", 0x1e),
+    (b"\\zxcvbnm,./", b"|ZXCVBNM<>?", 0x2b),
+];
+
+/// Whether typing `character` on a US keyboard takes shift
+pub fn shifted(character: u8) -> bool {
+    KEY_ROWS
+        .iter()
+        .any(|(_, shifted, _)| shifted.contains(&character))
+}
+
+/// Character of the key with scan code `code` on a US keyboard, 0 if it has none
+pub fn character(code: u8, shifted: bool) -> u8 {
+    match code {
+        0x01 => return 0x1b,
+        0x0e => return 0x08,
+        0x0f => return b'\t',
+        0x1c => return b'\r',
+        0x39 => return b' ',
+        _ => {}
+    }
+
+    KEY_ROWS
+        .iter()
+        .find_map(|(plain, shifted_row, first)| {
+            let row = if shifted { shifted_row } else { plain };
+            row.get(code.checked_sub(*first)? as usize).copied()
+        })
+        .unwrap_or(0)
+}
+
+/// Scan code of the key producing `character` on a US keyboard, 0 if there is none
+pub fn scan_code(character: u8) -> u8 {
+    match character {
+        0x1b => return 0x01,
+        0x08 => return 0x0e,
+        b'\t' => return 0x0f,
+        b'\r' => return 0x1c,
+        b' ' => return 0x39,
+        _ => {}
+    }
+
+    for (plain, shifted, first) in KEY_ROWS {
+        if let Some(index) = plain
+            .iter()
+            .position(|c| *c == character)
+            .or_else(|| shifted.iter().position(|c| *c == character))
+        {
+            return first + index as u8;
+        }
+    }
+
+    // control characters share the key of their letter
+    if (1..=26).contains(&character) {
+        return scan_code(character + b'a' - 1);
+    }
+
+    0
+}
+
+/// Disk image with the geometry the BIOS reports for it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Disk {
+    /// BIOS drive number: 00h for the first floppy, 80h for the first hard disk
+    pub drive: u8,
+    pub data: Vec<u8>,
+    pub cylinders: u16,
+    pub heads: u8,
+    pub sectors: u8,
+}
+
+impl Disk {
+    pub const SECTOR_SIZE: usize = 512;
+
+    /// Wraps an image, picking the geometry of the standard floppy of that size or a
+    /// hard disk with 16 heads and 63 sectors per track.
+    pub fn new(drive: u8, mut data: Vec<u8>) -> Disk {
+        let (cylinders, heads, sectors) = match (drive < 0x80, data.len() / 1024) {
+            (true, 360) => (40, 2, 9),
+            (true, 720) => (80, 2, 9),
+            (true, 1200) => (80, 2, 15),
+            (true, 1440) => (80, 2, 18),
+            (true, 2880) => (80, 2, 36),
+            (true, _) => (80, 2, 18),
+            (false, _) => {
+                let cylinder = 16 * 63 * Self::SECTOR_SIZE;
+                (
+                    (data.len().div_ceil(cylinder)).clamp(1, 1024) as u16,
+                    16,
+                    63,
+                )
+            }
+        };
+
+        // partial tracks read as zeros
+        let size = cylinders as usize * heads as usize * sectors as usize * Self::SECTOR_SIZE;
+        if data.len() < size {
+            data.resize(size, 0);
+        }
+
+        Self {
+            drive,
+            data,
+            cylinders,
+            heads,
+            sectors,
+        }
+    }
+
+    /// Byte offset of a CHS address (sectors count from 1), if it is on the disk
+    pub fn offset(&self, cylinder: u16, head: u8, sector: u8) -> Option<usize> {
+        if cylinder >= self.cylinders || head >= self.heads || sector == 0 || sector > self.sectors
+        {
+            return None;
+        }
+
+        let lba = (cylinder as usize * self.heads as usize + head as usize) * self.sectors as usize
+            + sector as usize
+            - 1;
+        Some(lba * Self::SECTOR_SIZE)
+    }
+}
+
+/// Status codes returned in AH
+const DISK_OK: u8 = 0x00;
+const DISK_BAD_COMMAND: u8 = 0x01;
+const DISK_SECTOR_NOT_FOUND: u8 = 0x04;
+const DISK_TIMEOUT: u8 = 0x80;
+
+/// Disk services on in-memory images. Writes change the images only.
+pub struct Disks {
+    pub disks: Vec<Disk>,
+    /// Status of the last operation (function 01h)
+    status: u8,
+}
+
+impl Disks {
+    pub fn new(disks: Vec<Disk>) -> Disks {
+        Self {
+            disks,
+            status: DISK_OK,
+        }
+    }
+
+    /// Reads or writes AL sectors from CHS (CH, CL, DH) to or from ES:BX. Returns the
+    /// status and the number of sectors transferred.
+    fn transfer(&mut self, machine: &mut Machine, write: bool) -> (u8, u8) {
+        let registers = machine.registers.clone();
+        let [count, _] = registers.ax.to_le_bytes();
+        let [sector, cylinder] = registers.cx.to_le_bytes();
+        let [drive, head] = registers.dx.to_le_bytes();
+        let cylinder = cylinder as u16 | ((sector as u16 & 0xc0) << 2);
+        let sector = sector & 0x3f;
+
+        let Some(disk) = self.disks.iter_mut().find(|disk| disk.drive == drive) else {
+            return (DISK_TIMEOUT, 0);
+        };
+
+        for index in 0..count {
+            // transfers continue on the following sectors, heads and cylinders
+            let linear = (sector - 1) as u16 + index as u16;
+            let track = linear / disk.sectors as u16;
+            let head = head as u16 + track;
+            let cylinder = cylinder + head / disk.heads as u16;
+
+            let offset = disk.offset(
+                cylinder,
+                (head % disk.heads as u16) as u8,
+                (linear % disk.sectors as u16) as u8 + 1,
+            );
+            let Some(offset) = offset else {
+                return (DISK_SECTOR_NOT_FOUND, index);
+            };
+
+            let buffer = registers
+                .bx
+                .wrapping_add(index as u16 * Disk::SECTOR_SIZE as u16);
+            let sector = offset..offset + Disk::SECTOR_SIZE;
+            if write {
+                for (byte, position) in disk.data[sector].iter_mut().zip(0u16..) {
+                    *byte = machine
+                        .memory
+                        .read_byte(address(registers.es, buffer.wrapping_add(position)));
+                }
+            } else {
+                for (byte, position) in disk.data[sector].iter().zip(0u16..) {
+                    machine
+                        .memory
+                        .write_byte(address(registers.es, buffer.wrapping_add(position)), *byte);
+                }
+            }
+        }
+
+        (DISK_OK, count)
+    }
+}
+
+impl InterruptHandler for Disks {
+    fn interrupt(&mut self, machine: &mut Machine, _vector: u8) -> Result<(), String> {
+        let drive = machine.registers.dx as u8;
+
+        let (status, al) = match ah(machine) {
+            // reset
+            0x00 => (DISK_OK, al(machine)),
+            // status of the last operation
+            0x01 => (self.status, self.status),
+            0x02 => self.transfer(machine, false),
+            0x03 => self.transfer(machine, true),
+            // verify
+            0x04 => (DISK_OK, al(machine)),
+            // drive parameters: maximum cylinder, sector and head, number of drives
+            0x08 => match self.disks.iter().find(|disk| disk.drive == drive) {
+                Some(disk) => {
+                    let cylinder = disk.cylinders - 1;
+                    machine.registers.cx =
+                        ((cylinder & 0xff) << 8) | ((cylinder >> 2) & 0xc0) | disk.sectors as u16;
+                    let drives = self
+                        .disks
+                        .iter()
+                        .filter(|other| (other.drive < 0x80) == (drive < 0x80))
+                        .count();
+                    machine.registers.dx = ((disk.heads as u16 - 1) << 8) | drives as u16;
+                    if drive < 0x80 {
+                        // 1.44 MB drive type
+                        machine.registers.bx = (machine.registers.bx & 0xff00) | 0x04;
+                    }
+                    (DISK_OK, 0)
+                }
+                None => (DISK_TIMEOUT, 0),
+            },
+            _ => (DISK_BAD_COMMAND, 0),
+        };
+
+        self.status = status;
+        machine.registers.ax = u16::from_le_bytes([al, status]);
+        machine.set_flag(FLAG_CARRY, status != DISK_OK);
+        Ok(())
+    }
+}
+
+/// Tick counter (`int 1Ah`) advancing with the machine's clock, so runs are
+/// reproducible. The midnight flag is never set.
+pub struct Clock {
+    /// Ticks at clock 0
+    base: u32,
+}
+
+impl Clock {
+    pub fn new() -> Clock {
+        Self { base: 0 }
+    }
+
+    /// Ticks since midnight
+    pub fn ticks(&self, machine: &Machine) -> u32 {
+        (self.base + elapsed(machine)) % TICKS_PER_DAY
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new()
+    }
+}
+
+/// Ticks since the start of the machine, modulo a day
+fn elapsed(machine: &Machine) -> u32 {
+    ((machine.clock / INSTRUCTIONS_PER_TICK) % TICKS_PER_DAY as u64) as u32
+}
+
+impl InterruptHandler for Clock {
+    fn interrupt(&mut self, machine: &mut Machine, vector: u8) -> Result<(), String> {
+        match ah(machine) {
+            // tick count in CX:DX, AL midnight flag
+            0x00 => {
+                let ticks = self.ticks(machine);
+                machine.registers.cx = (ticks >> 16) as u16;
+                machine.registers.dx = ticks as u16;
+                machine.registers.ax &= 0xff00;
+            }
+            // set tick count from CX:DX
+            0x01 => {
+                let ticks = ((machine.registers.cx as u32) << 16) | machine.registers.dx as u32;
+                self.base =
+                    (ticks % TICKS_PER_DAY + TICKS_PER_DAY - elapsed(machine)) % TICKS_PER_DAY;
+            }
+            _ => return Err(unsupported(vector, machine)),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+/// Output shared between a video handler and the test
+#[cfg(test)]
+#[derive(Clone, Default)]
+struct Output(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+#[cfg(test)]
+impl std::io::Write for Output {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buffer);
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn bios_video() {
+    use crate::{
+        bios::{Video, VIDEO_INTERRUPT},
+        machine::test_machine,
+        screen,
+    };
+
+    // mov ax, 0e48h / int 10h / mov al, 69h / int 10h / mov ah, 3 / int 10h / hlt
+    let mut machine = test_machine(&[
+        0xb8, 0x48, 0x0e, 0xcd, 0x10, 0xb0, 0x69, 0xcd, 0x10, 0xb4, 0x03, 0xcd, 0x10, 0xf4,
+    ]);
+    let output = Output::default();
+    machine
+        .interrupts
+        .set(VIDEO_INTERRUPT, Video::new(Box::new(output.clone())));
+
+    machine.run(100).unwrap();
+    assert_eq!(*output.0.borrow(), b"Hi");
+    assert_eq!(machine.registers.dx, 0x0002);
+
+    let mut video = Video::new(Box::new(Output::default()));
+    for character in b"abc\r\n\x08x" {
+        video.teletype(&mut machine, *character).unwrap();
+    }
+    assert_eq!(video.cursor, (1, 1));
+
+    // text buffer writes are recorded like the program's own
+    let access = machine.accesses().last().unwrap();
+    assert_eq!(
+        (access.address, access.value),
+        (screen::cell_address(1, 0), 0x78)
+    );
+}
+
+#[test]
+fn bios_video_journal() {
+    use crate::{
+        bios::{Video, VIDEO_INTERRUPT},
+        journal::Journal,
+        machine::test_machine,
+        screen::{cell_address, TextScreen},
+    };
+
+    // mov ax, 0600h / mov bh, 1fh / int 10h / hlt: blank the top left cell
+    let mut machine = test_machine(&[0xb8, 0x00, 0x06, 0xb7, 0x1f, 0xcd, 0x10, 0xf4]);
+    machine
+        .interrupts
+        .set(VIDEO_INTERRUPT, Video::new(Box::new(std::io::sink())));
+    machine.memory.load(cell_address(0, 0), &[b'A', 0x07]);
+
+    // scrolling writes through the machine, so the journal can undo it
+    let mut journal = Journal::default();
+    while !machine.halted {
+        journal.step(&mut machine).unwrap();
+    }
+    assert_eq!(
+        TextScreen::capture(&machine.memory).cell(0, 0),
+        (b' ', 0x1f)
+    );
+    while journal.undo(&mut machine).is_some() {}
+    assert_eq!(
+        TextScreen::capture(&machine.memory).cell(0, 0),
+        (b'A', 0x07)
+    );
+}
+
+#[test]
+fn bios_keyboard() {
+    use crate::{
+        bios::{key, scan_code, Keyboard, KEYBOARD_INTERRUPT},
+        machine::test_machine,
+        registers::FLAG_ZERO,
+    };
+
+    assert_eq!(key(b'a'), 0x1e61);
+    assert_eq!(key(b'\n'), 0x1c0d);
+    assert_eq!(scan_code(b'?'), 0x35);
+    assert_eq!(scan_code(0x03), 0x2e);
+
+    // mov ah, 1 / int 16h / mov ah, 0 / int 16h / hlt
+    let code = [0xb4, 0x01, 0xcd, 0x16, 0xb4, 0x00, 0xcd, 0x16, 0xf4];
+    let mut machine = test_machine(&code);
+    machine
+        .interrupts
+        .set(KEYBOARD_INTERRUPT, Keyboard::from_text("x"));
+
+    machine.step().unwrap();
+    machine.step().unwrap();
+    assert!(!machine.flag(FLAG_ZERO));
+    assert_eq!(machine.registers.ax, 0x2d78);
+    machine.run(10).unwrap();
+    assert_eq!(machine.registers.ax, 0x2d78);
+
+    // the key was taken, and there is no more input
+    machine.registers.ip = 0x100;
+    machine.halted = false;
+    machine.step().unwrap();
+    machine.step().unwrap();
+    assert!(machine.flag(FLAG_ZERO));
+    let error = machine.run(10).unwrap_err();
+    assert!(error.to_string().ends_with("Keyboard input ended."));
+}
+
+#[test]
+fn bios_disk() {
+    use crate::{
+        bios::{Disk, Disks, DISK_INTERRUPT},
+        machine::test_machine,
+        registers::FLAG_CARRY,
+    };
+
+    let mut image = vec![0u8; 1440 * 1024];
+    // cylinder 1, head 1, sector 18 is the last sector of the second cylinder
+    let last = ((2 * 2) * 18 - 1) * 512;
+    image[last] = 0xaa;
+    image[last + 512] = 0xbb;
+    let disk = Disk::new(0, image);
+    assert_eq!((disk.cylinders, disk.heads, disk.sectors), (80, 2, 18));
+    assert_eq!(disk.offset(1, 1, 18), Some(last));
+    assert_eq!(disk.offset(1, 1, 19), None);
+
+    // mov ax, 0202h / mov cx, 0112h / mov dx, 0100h / mov bx, 1000h / int 13h / hlt
+    let mut machine = test_machine(&[
+        0xb8, 0x02, 0x02, 0xb9, 0x12, 0x01, 0xba, 0x00, 0x01, 0xbb, 0x00, 0x10, 0xcd, 0x13, 0xf4,
+    ]);
+    machine
+        .interrupts
+        .set(DISK_INTERRUPT, Disks::new(vec![disk]));
+
+    machine.run(10).unwrap();
+    assert!(!machine.flag(FLAG_CARRY));
+    assert_eq!(machine.registers.ax, 0x0002);
+    // the second sector continues on the next cylinder
+    assert_eq!(machine.memory.read_byte(0x1000), 0xaa);
+    assert_eq!(machine.memory.read_byte(0x1200), 0xbb);
+
+    // drive parameters, then a drive that does not exist
+    machine.registers = crate::registers::Registers {
+        ax: 0x0800,
+        ip: 0x10c,
+        ..Default::default()
+    };
+    machine.halted = false;
+    machine.run(10).unwrap();
+    assert_eq!(machine.registers.cx, 0x4f12);
+    assert_eq!(machine.registers.dx, 0x0101);
+
+    machine.registers = crate::registers::Registers {
+        ax: 0x0201,
+        cx: 0x0001,
+        dx: 0x0080,
+        ip: 0x10c,
+        ..Default::default()
+    };
+    machine.halted = false;
+    machine.run(10).unwrap();
+    assert!(machine.flag(FLAG_CARRY));
+    assert_eq!(machine.registers.ax >> 8, 0x80);
+}
+
+#[test]
+fn bios_clock() {
+    use crate::{
+        bios::{Clock, CLOCK_INTERRUPT, INSTRUCTIONS_PER_TICK},
+        machine::test_machine,
+    };
+
+    // xor ax, ax / int 1Ah / hlt
+    let mut machine = test_machine(&[0x31, 0xc0, 0xcd, 0x1a, 0xf4]);
+    machine.interrupts.set(CLOCK_INTERRUPT, Clock::new());
+    machine.clock = 3 * INSTRUCTIONS_PER_TICK;
+
+    machine.run(10).unwrap();
+    assert_eq!((machine.registers.cx, machine.registers.dx), (0, 3));
+
+    // set the count to 10000h, then read it back
+    machine.registers.ax = 0x0100;
+    machine.registers.cx = 0x0001;
+    machine.registers.dx = 0;
+    machine.interrupt(CLOCK_INTERRUPT).unwrap();
+    machine.registers.ax = 0;
+    machine.interrupt(CLOCK_INTERRUPT).unwrap();
+    assert_eq!((machine.registers.cx, machine.registers.dx), (1, 0));
+}
@@ -0,0 +1,543 @@
+use crate::{
+    journal::Journal,
+    machine::{Access, AccessKind, Machine},
+    memory::address,
+    registers::FLAG_NAMES,
+};
+use asmrs_assembler::debug::DebugInfo;
+use asmrs_disassembler::{decoder::Instruction, disassembly::format_instruction};
+use asmrs_parser::lexer::{parse_number_argument, token::InstructionType};
+use std::{collections::BTreeSet, ops::Range};
+
+mod test;
+
+/// Help text of the `help` command.
+pub const HELP: &str = "\
+step [N]             execute N instructions (s)
+next                 execute one instruction, running calls and interrupts to completion (n)
+continue             run until a breakpoint, watchpoint or hlt (c)
+finish               run until the current procedure returns
+reverse-step [N]     undo N instructions (rs)
+reverse-continue     undo until a breakpoint or a write to a watchpoint (rc)
+last-write LOCATION  show the last recorded instruction writing LOCATION
+break LOCATION       stop before executing LOCATION (b)
+watch LOCATION [N]   stop after N bytes at LOCATION are written
+awatch LOCATION [N]  stop after N bytes at LOCATION are read or written
+delete NUMBER        remove a breakpoint or watchpoint
+info                 list breakpoints, watchpoints and the recorded history
+regs                 show registers and flags (r)
+dump LOCATION [N]    show N bytes as hex and ASCII (x)
+disasm [N]           disassemble N instructions around CS:IP (d)
+quit                 leave the debugger (q)
+LOCATION is SEGMENT:OFFSET, OFFSET (in CS), a label or FILE:LINE.";
+
+/// Condition stopping execution
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Stop {
+    /// Before the instruction at segment:offset executes
+    Breakpoint(u16, u16),
+    /// After an access to the linear address range; reads only count if `reads` is set
+    Watchpoint { range: Range<usize>, reads: bool },
+}
+
+/// Interactive debugger driving a machine, one command line at a time.
+pub struct Debugger {
+    pub machine: Machine,
+    /// Source mapping, used for label and line locations
+    pub debug: Option<DebugInfo>,
+    /// History of executed instructions for reverse execution
+    pub journal: Journal,
+    /// Breakpoints and watchpoints by number (1-based); deleted entries stay `None`
+    stops: Vec<Option<Stop>>,
+    /// Command repeated by an empty line
+    last: String,
+}
+
+impl Debugger {
+    pub fn new(machine: Machine, debug: Option<DebugInfo>) -> Debugger {
+        Self {
+            machine,
+            debug,
+            journal: Journal::default(),
+            stops: Vec::new(),
+            last: String::new(),
+        }
+    }
+
+    /// Breakpoints and watchpoints with their numbers
+    pub fn stops(&self) -> impl Iterator<Item = (usize, &Stop)> {
+        self.stops
+            .iter()
+            .enumerate()
+            .filter_map(|(index, stop)| stop.as_ref().map(|stop| (index + 1, stop)))
+    }
+
+    /// Adds a breakpoint or watchpoint and returns its number.
+    pub fn add_stop(&mut self, stop: Stop) -> usize {
+        self.stops.push(Some(stop));
+        self.stops.len()
+    }
+
+    /// Executes a command line and returns its output. An empty line repeats the previous
+    /// command.
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let line = if line.trim().is_empty() {
+            self.last.clone()
+        } else {
+            line.trim().to_string()
+        };
+        self.last = line.clone();
+
+        let arguments = line.split_whitespace().collect::<Vec<_>>();
+        let Some((command, arguments)) = arguments.split_first() else {
+            return Ok(String::new());
+        };
+
+        match (*command, arguments) {
+            ("step" | "s", []) => Ok(self.step(1)),
+            ("step" | "s", [count]) => Ok(self.step(parse_number_argument(count)? as u64)),
+            ("next" | "n", []) => Ok(self.next()),
+            ("continue" | "c", []) => Ok(self.resume(|_, _| false)),
+            ("finish", []) => Ok(self.finish()),
+            ("reverse-step" | "rs", []) => Ok(self.reverse_step(1)),
+            ("reverse-step" | "rs", [count]) => {
+                Ok(self.reverse_step(parse_number_argument(count)? as u64))
+            }
+            ("reverse-continue" | "rc", []) => Ok(self.reverse(|_| false)),
+            ("last-write", [location]) => {
+                let (segment, offset) = self.location(location)?;
+                let linear = address(segment, offset);
+                Ok(match self.journal.last_write(linear) {
+                    Some(entry) => {
+                        let write = entry.wrote(linear, linear + 1).unwrap();
+                        format!(
+                            "{:05X} last written by #{}: {:#x} -> {:#x}\n{}",
+                            linear,
+                            entry.instruction,
+                            write.previous,
+                            write.value,
+                            self.instruction_line(entry.registers.cs, entry.registers.ip)
+                        )
+                    }
+                    None => format!("No recorded write to {:05X}.", linear),
+                })
+            }
+            ("break" | "b", [location]) => {
+                let (segment, offset) = self.location(location)?;
+                let number = self.add_stop(Stop::Breakpoint(segment, offset));
+                Ok(format!(
+                    "Breakpoint {} at {:04X}:{:04X}",
+                    number, segment, offset
+                ))
+            }
+            ("watch" | "awatch", [location, length @ ..]) => {
+                let (segment, offset) = self.location(location)?;
+                let length = match length {
+                    [] => 1,
+                    [length] => parse_number_argument(length)? as usize,
+                    _ => return Err(format!("Unexpected arguments for '{}'.", command)),
+                };
+                let start = address(segment, offset);
+                let number = self.add_stop(Stop::Watchpoint {
+                    range: start..start + length.max(1),
+                    reads: *command == "awatch",
+                });
+                Ok(format!(
+                    "Watchpoint {} on {:05X}-{:05X}",
+                    number,
+                    start,
+                    start + length.max(1) - 1
+                ))
+            }
+            ("delete", [text]) => {
+                let number = parse_number_argument(text)? as usize;
+                match number
+                    .checked_sub(1)
+                    .and_then(|index| self.stops.get_mut(index))
+                {
+                    Some(stop @ Some(_)) => {
+                        *stop = None;
+                        Ok(format!("Deleted {}", number))
+                    }
+                    _ => Err(format!("No breakpoint or watchpoint {}.", number)),
+                }
+            }
+            ("info", []) => Ok(self
+                .stops()
+                .map(|(number, stop)| match stop {
+                    Stop::Breakpoint(segment, offset) => {
+                        format!("{:>3} breakpoint {:04X}:{:04X}", number, segment, offset)
+                    }
+                    Stop::Watchpoint { range, reads } => format!(
+                        "{:>3} {} {:05X}-{:05X}",
+                        number,
+                        if *reads { "awatch" } else { "watch" },
+                        range.start,
+                        range.end - 1
+                    ),
+                })
+                .chain(self.journal.checkpoint().map(|checkpoint| {
+                    format!(
+                        "History: {} instructions, back to #{}",
+                        self.journal.len(),
+                        checkpoint
+                    )
+                }))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            ("regs" | "r", []) => Ok(self.registers()),
+            ("dump" | "x", [location, length @ ..]) => {
+                let (segment, offset) = self.location(location)?;
+                let length = match length {
+                    [] => 0x80,
+                    [length] => parse_number_argument(length)? as u16,
+                    _ => return Err(format!("Unexpected arguments for '{}'.", command)),
+                };
+                Ok(self.dump(segment, offset, length))
+            }
+            ("disasm" | "d", []) => Ok(self.disassemble(8)),
+            ("disasm" | "d", [count]) => {
+                Ok(self.disassemble(parse_number_argument(count)? as usize))
+            }
+            ("help" | "h", []) => Ok(HELP.to_string()),
+            _ => Err(format!(
+                "Unknown command or arguments: '{}'. Type 'help' for a list.",
+                line
+            )),
+        }
+    }
+
+    fn step(&mut self, count: u64) -> String {
+        let mut executed = 0;
+        self.resume(|_, _| {
+            executed += 1;
+            executed >= count
+        })
+    }
+
+    /// Steps over calls and interrupts: they run until they return to the next
+    /// instruction.
+    fn next(&mut self) -> String {
+        let instruction = match self.machine.fetch() {
+            Ok(instruction) => instruction,
+            Err(error) => return error.to_string(),
+        };
+
+        if !matches!(
+            instruction.r#type,
+            InstructionType::Call | InstructionType::Int | InstructionType::Into
+        ) {
+            return self.step(1);
+        }
+
+        let (cs, sp) = (self.machine.registers.cs, self.machine.registers.sp);
+        let ip = self
+            .machine
+            .registers
+            .ip
+            .wrapping_add(instruction.bytes.len() as u16);
+
+        // a recursive call passes the same address with a deeper stack
+        self.resume(|_, machine| {
+            machine.registers.cs == cs && machine.registers.ip == ip && machine.registers.sp >= sp
+        })
+    }
+
+    /// Runs until a return leaves the current stack frame.
+    fn finish(&mut self) -> String {
+        let sp = self.machine.registers.sp;
+        self.resume(|instruction, machine| {
+            matches!(
+                instruction.r#type,
+                InstructionType::Ret | InstructionType::Retf | InstructionType::Iret
+            ) && machine.registers.sp > sp
+        })
+    }
+
+    /// Executes instructions until `done` (called after every instruction with the
+    /// instruction just executed) holds, a breakpoint is reached, a watchpoint triggers,
+    /// the machine halts or fails.
+    fn resume(&mut self, mut done: impl FnMut(&Instruction, &Machine) -> bool) -> String {
+        let mut first = true;
+
+        loop {
+            let (cs, ip) = (self.machine.registers.cs, self.machine.registers.ip);
+
+            // the breakpoint at the current instruction was reported already
+            if !first {
+                if let Some(number) = self.breakpoint(cs, ip) {
+                    return format!("Breakpoint {}\n{}", number, self.current());
+                }
+            }
+            first = false;
+
+            if self.machine.halted && !self.machine.waiting() {
+                return format!("Halted.\n{}", self.current());
+            }
+
+            let instruction = match self.machine.fetch() {
+                Ok(instruction) => instruction,
+                Err(error) => return error.to_string(),
+            };
+
+            if let Err(error) = self.journal.step(&mut self.machine) {
+                return format!("{}\n{}", error, self.current());
+            }
+
+            if let Some(message) = self.watchpoint(self.machine.accesses()) {
+                return format!("{}\n{}", message, self.current());
+            }
+
+            if done(&instruction, &self.machine) {
+                return self.current();
+            }
+        }
+    }
+
+    fn reverse_step(&mut self, count: u64) -> String {
+        let mut undone = 0;
+        self.reverse(|_| {
+            undone += 1;
+            undone >= count
+        })
+    }
+
+    /// Undoes instructions until `done` holds, a breakpoint is reached, an undone
+    /// instruction wrote to a watchpoint or the history is exhausted. Watchpoints stop
+    /// before the writing instruction.
+    fn reverse(&mut self, mut done: impl FnMut(&Machine) -> bool) -> String {
+        loop {
+            let Some(entry) = self.journal.undo(&mut self.machine) else {
+                return format!("No more history.\n{}", self.current());
+            };
+
+            if let Some(message) = self.watchpoint(&entry.writes) {
+                return format!("{}\n{}", message, self.current());
+            }
+
+            let (cs, ip) = (self.machine.registers.cs, self.machine.registers.ip);
+            if let Some(number) = self.breakpoint(cs, ip) {
+                return format!("Breakpoint {}\n{}", number, self.current());
+            }
+
+            if done(&self.machine) {
+                return self.current();
+            }
+        }
+    }
+
+    fn breakpoint(&self, segment: u16, offset: u16) -> Option<usize> {
+        self.stops().find_map(|(number, stop)| {
+            (*stop == Stop::Breakpoint(segment, offset)).then_some(number)
+        })
+    }
+
+    /// Describes the first of `accesses` hitting a watchpoint.
+    fn watchpoint(&self, accesses: &[Access]) -> Option<String> {
+        accesses.iter().find_map(|access| {
+            let accessed = access.address..access.address + access.size as usize;
+            self.stops().find_map(|(number, stop)| match stop {
+                Stop::Watchpoint { range, reads }
+                    if (access.kind == AccessKind::Write || *reads)
+                        && accessed.start < range.end
+                        && range.start < accessed.end =>
+                {
+                    Some(match access.kind {
+                        AccessKind::Write => format!(
+                            "Watchpoint {}: write {:05X}: {:#x} -> {:#x}",
+                            number, access.address, access.previous, access.value
+                        ),
+                        AccessKind::Read => format!(
+                            "Watchpoint {}: read {:05X}: {:#x}",
+                            number, access.address, access.value
+                        ),
+                    })
+                }
+                _ => None,
+            })
+        })
+    }
+
+    /// Resolves `SEGMENT:OFFSET`, `OFFSET` (in CS), `FILE:LINE` or a label.
+    pub fn location(&self, text: &str) -> Result<(u16, u16), String> {
+        if let Some((segment, offset)) = text.split_once(':') {
+            if let (Ok(segment), Ok(offset)) = (parse_hex(segment), parse_hex(offset)) {
+                return Ok((segment, offset));
+            }
+
+            let debug = self.debug_info()?;
+            let line = parse_number_argument(offset)? as usize;
+            return debug
+                .address_of(segment, line.saturating_sub(1))
+                .ok_or_else(|| format!("No code at '{}'.", text));
+        }
+
+        if let Ok(offset) = parse_number_argument(text) {
+            return Ok((self.machine.registers.cs, offset as u16));
+        }
+
+        let (cs, ip) = (self.machine.registers.cs, self.machine.registers.ip);
+        self.debug_info()?
+            .symbol(text, cs, ip)
+            .map(|symbol| (symbol.segment, symbol.offset))
+            .ok_or_else(|| format!("Unknown symbol '{}'.", text))
+    }
+
+    fn debug_info(&self) -> Result<&DebugInfo, String> {
+        self.debug
+            .as_ref()
+            .ok_or_else(|| "Labels and lines need debug info (--symbols).".to_string())
+    }
+
+    /// CS:IP, the instruction there and its source line if known
+    pub fn current(&self) -> String {
+        let (cs, ip) = (self.machine.registers.cs, self.machine.registers.ip);
+        let mut text = self.instruction_line(cs, ip);
+
+        let line = self
+            .debug
+            .as_ref()
+            .and_then(|debug| Some((debug, debug.line_at(cs, ip)?)));
+        if let Some((debug, line)) = line {
+            text.push_str(&format!(
+                "  ; {}:{}",
+                debug.files[line.file],
+                line.line_index + 1
+            ));
+        }
+
+        text
+    }
+
+    fn instruction_line(&self, segment: u16, offset: u16) -> String {
+        match self.machine.decode_at(segment, offset) {
+            Ok(instruction) => format!(
+                "{:04X}:{:04X}  {}",
+                segment,
+                offset,
+                format_instruction(&instruction, &BTreeSet::new())
+            ),
+            Err(_) => format!("{:04X}:{:04X}  (bad)", segment, offset),
+        }
+    }
+
+    fn registers(&self) -> String {
+        let registers = &self.machine.registers;
+        let flags = FLAG_NAMES
+            .iter()
+            .filter(|(flag, _)| registers.flags & flag != 0)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        format!(
+            "AX={:04X}  BX={:04X}  CX={:04X}  DX={:04X}  SP={:04X}  BP={:04X}  SI={:04X}  DI={:04X}\n\
+             CS={:04X}  DS={:04X}  SS={:04X}  ES={:04X}  IP={:04X}  FLAGS={:04X} [{}]",
+            registers.ax,
+            registers.bx,
+            registers.cx,
+            registers.dx,
+            registers.sp,
+            registers.bp,
+            registers.si,
+            registers.di,
+            registers.cs,
+            registers.ds,
+            registers.ss,
+            registers.es,
+            registers.ip,
+            registers.flags,
+            flags
+        )
+    }
+
+    /// Hex and ASCII dump, 16 bytes per row
+    fn dump(&self, segment: u16, offset: u16, length: u16) -> String {
+        let mut rows = Vec::new();
+
+        for row in (0..length).step_by(16) {
+            let start = offset.wrapping_add(row);
+            let bytes = (0..(length - row).min(16))
+                .map(|index| {
+                    self.machine
+                        .memory
+                        .read_byte(address(segment, start.wrapping_add(index)))
+                })
+                .collect::<Vec<_>>();
+
+            let hex = bytes
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii = bytes
+                .iter()
+                .map(|byte| {
+                    if byte.is_ascii_graphic() || *byte == b' ' {
+                        *byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+
+            rows.push(format!(
+                "{:04X}:{:04X}  {:<47}  |{}|",
+                segment, start, hex, ascii
+            ));
+        }
+
+        rows.join("\n")
+    }
+
+    /// Up to three instructions before CS:IP (found by decoding forwards from earlier
+    /// offsets that line up with CS:IP), the current one and the following ones.
+    fn disassemble(&self, count: usize) -> String {
+        let (cs, ip) = (self.machine.registers.cs, self.machine.registers.ip);
+        let mut before = Vec::new();
+
+        for distance in (1..=16u16).rev() {
+            let mut offsets = Vec::new();
+            let mut offset = ip.wrapping_sub(distance);
+
+            while offset != ip && offsets.len() < 16 {
+                let Ok(instruction) = self.machine.decode_at(cs, offset) else {
+                    break;
+                };
+                offsets.push(offset);
+                offset = offset.wrapping_add(instruction.bytes.len() as u16);
+                if ip.wrapping_sub(offset) > distance {
+                    break;
+                }
+            }
+
+            if offset == ip && offsets.len() > before.len() {
+                before = offsets;
+            }
+        }
+
+        let before = &before[before.len().saturating_sub(3)..];
+        let mut lines = before
+            .iter()
+            .map(|offset| format!("   {}", self.instruction_line(cs, *offset)))
+            .collect::<Vec<_>>();
+
+        let mut offset = ip;
+        for index in 0..count.max(1) {
+            let marker = if index == 0 { "=> " } else { "   " };
+            lines.push(format!("{}{}", marker, self.instruction_line(cs, offset)));
+            match self.machine.decode_at(cs, offset) {
+                Ok(instruction) => offset = offset.wrapping_add(instruction.bytes.len() as u16),
+                Err(_) => break,
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Parses one half of `SEGMENT:OFFSET`, always hexadecimal.
+fn parse_hex(text: &str) -> Result<u16, String> {
+    let text = text.strip_prefix("0x").unwrap_or(text);
+    u16::from_str_radix(text, 16).map_err(|_| format!("Invalid address: '{}'", text))
+}
@@ -0,0 +1,194 @@
+/// Debugger with `code` at 0000:0100 and the stack at 0000:FFFE
+#[cfg(test)]
+fn debugger(code: &[u8]) -> crate::debugger::Debugger {
+    use crate::{debugger::Debugger, machine::test_machine};
+
+    Debugger::new(test_machine(code), None)
+}
+
+/// mov sp, 200h / call 108h / hlt / nop / mov ax, 1234h / ret
+#[cfg(test)]
+const CALL: [u8; 12] = [
+    0xbc, 0x00, 0x02, 0xe8, 0x02, 0x00, 0xf4, 0x90, 0xb8, 0x34, 0x12, 0xc3,
+];
+
+#[test]
+fn debugger_step_next() {
+    let mut debugger = debugger(&CALL);
+
+    assert_eq!(debugger.execute("step").unwrap(), "0000:0103  call 0x108");
+    // an empty line repeats the last command
+    assert_eq!(debugger.execute("").unwrap(), "0000:0108  mov ax, 0x1234");
+    assert_eq!(debugger.execute("finish").unwrap(), "0000:0106  hlt");
+
+    let mut debugger = self::debugger(&CALL);
+    debugger.execute("s").unwrap();
+    assert_eq!(debugger.execute("next").unwrap(), "0000:0106  hlt");
+    assert_eq!(debugger.machine.registers.ax, 0x1234);
+    assert_eq!(debugger.machine.registers.sp, 0x200);
+
+    assert!(debugger.execute("c").unwrap().starts_with("Halted."));
+}
+
+#[test]
+fn debugger_breakpoints() {
+    let mut debugger = debugger(&CALL);
+
+    assert_eq!(
+        debugger.execute("break 0x10b").unwrap(),
+        "Breakpoint 1 at 0000:010B"
+    );
+    assert_eq!(
+        debugger.execute("continue").unwrap(),
+        "Breakpoint 1\n0000:010B  ret"
+    );
+    assert_eq!(debugger.machine.registers.ax, 0x1234);
+
+    debugger.execute("delete 1").unwrap();
+    assert!(debugger.execute("delete 1").is_err());
+    assert_eq!(
+        debugger.execute("info").unwrap(),
+        "History: 3 instructions, back to #0"
+    );
+    assert!(debugger.execute("c").unwrap().starts_with("Halted."));
+}
+
+#[test]
+fn debugger_watchpoints() {
+    // mov sp, 200h / call 108h / hlt / nop / mov ax, 1234h / ret
+    let mut debugger = debugger(&CALL);
+
+    assert_eq!(
+        debugger.execute("watch 0:1fe 2").unwrap(),
+        "Watchpoint 1 on 001FE-001FF"
+    );
+    assert_eq!(
+        debugger.execute("c").unwrap(),
+        "Watchpoint 1: write 001FE: 0xf000 -> 0x106\n0000:0108  mov ax, 0x1234"
+    );
+
+    // the return reads the address back
+    debugger.execute("delete 1").unwrap();
+    debugger.execute("awatch 0:1fe").unwrap();
+    assert_eq!(
+        debugger.execute("c").unwrap(),
+        "Watchpoint 2: read 001FE: 0x106\n0000:0106  hlt"
+    );
+}
+
+#[test]
+fn debugger_views() {
+    let mut debugger = debugger(&CALL);
+    debugger.execute("s 2").unwrap();
+
+    assert_eq!(
+        debugger.execute("regs").unwrap(),
+        "AX=0000  BX=0000  CX=0000  DX=0000  SP=01FE  BP=0000  SI=0000  DI=0000\n\
+         CS=0000  DS=0000  SS=0000  ES=0000  IP=0108  FLAGS=0002 []"
+    );
+
+    assert_eq!(
+        debugger.execute("x 0:100 18").unwrap(),
+        "0000:0100  BC 00 02 E8 02 00 F4 90 B8 34 12 C3 43 E0 00 F0  |.........4..C...|\n\
+         0000:0110  44 E0                                            |D.|"
+    );
+
+    assert_eq!(
+        debugger.execute("disasm 2").unwrap(),
+        "   0000:0103  call 0x108\n   \
+         0000:0106  hlt\n   \
+         0000:0107  nop\n\
+         => 0000:0108  mov ax, 0x1234\n   \
+         0000:010B  ret"
+    );
+
+    assert!(debugger.execute("bogus").is_err());
+    assert!(debugger.execute("break main").is_err());
+}
+
+#[test]
+fn debugger_symbols() {
+    use asmrs_assembler::debug::{DebugInfo, DebugSymbol, LineEntry};
+
+    let mut debugger = debugger(&CALL);
+    let mut debug = DebugInfo::new();
+    let file = debug.file("main.asmrs");
+    for (offset, length, line_index) in [(0x100, 3, 0), (0x103, 3, 1), (0x108, 3, 5)] {
+        debug.lines.push(LineEntry {
+            segment: 0,
+            offset,
+            length,
+            file,
+            line_index,
+            char_index: 4,
+        });
+    }
+    debug.push_symbol(DebugSymbol {
+        name: "answer".to_string(),
+        segment: 0,
+        offset: 0x108,
+        scope: None,
+    });
+    debugger.debug = Some(debug);
+
+    assert_eq!(
+        debugger.execute("b answer").unwrap(),
+        "Breakpoint 1 at 0000:0108"
+    );
+    assert_eq!(
+        debugger.execute("b main.asmrs:2").unwrap(),
+        "Breakpoint 2 at 0000:0103"
+    );
+    assert!(debugger.execute("b main.asmrs:3").is_err());
+
+    assert_eq!(
+        debugger.execute("c").unwrap(),
+        "Breakpoint 2\n0000:0103  call 0x108  ; main.asmrs:2"
+    );
+    assert_eq!(
+        debugger.execute("c").unwrap(),
+        "Breakpoint 1\n0000:0108  mov ax, 0x1234  ; main.asmrs:6"
+    );
+}
+
+#[test]
+fn debugger_reverse() {
+    let mut debugger = debugger(&CALL);
+
+    assert!(debugger.execute("c").unwrap().starts_with("Halted."));
+    assert_eq!(
+        debugger.execute("info").unwrap(),
+        "History: 5 instructions, back to #0"
+    );
+    assert_eq!(
+        debugger.execute("last-write 0:1fe").unwrap(),
+        "001FE last written by #1: 0xf000 -> 0x106\n0000:0103  call 0x108"
+    );
+    assert_eq!(
+        debugger.execute("last-write 0:200").unwrap(),
+        "No recorded write to 00200."
+    );
+
+    assert_eq!(debugger.execute("rs 2").unwrap(), "0000:010B  ret");
+    assert_eq!(debugger.machine.registers.sp, 0x1fe);
+    assert!(!debugger.machine.halted);
+
+    // reverse execution stops at the instruction writing a watchpoint
+    debugger.execute("watch 0:1fe 2").unwrap();
+    assert_eq!(
+        debugger.execute("rc").unwrap(),
+        "Watchpoint 1: write 001FE: 0xf000 -> 0x106\n0000:0103  call 0x108"
+    );
+    assert_eq!(debugger.machine.memory.read_word(0x1fe), 0xf000);
+
+    assert_eq!(
+        debugger.execute("rc").unwrap(),
+        "No more history.\n0000:0100  mov sp, 0x200"
+    );
+    assert_eq!(debugger.machine.registers.sp, 0xfffe);
+
+    // and forwards again
+    debugger.execute("delete 1").unwrap();
+    assert!(debugger.execute("c").unwrap().starts_with("Halted."));
+    assert_eq!(debugger.machine.registers.ax, 0x1234);
+}
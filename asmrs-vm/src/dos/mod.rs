@@ -0,0 +1,577 @@
+use crate::{
+    bios::{unsupported, KEYBOARD_INTERRUPT, VIDEO_INTERRUPT},
+    interrupt::InterruptHandler,
+    loader::MEMORY_END_SEGMENT,
+    machine::Machine,
+    memory::address,
+    registers::{FLAG_CARRY, FLAG_INTERRUPT},
+};
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+};
+
+mod test;
+
+/// Program termination (`int 20h`)
+pub const TERMINATE_INTERRUPT: u8 = 0x20;
+/// DOS services (`int 21h`)
+pub const DOS_INTERRUPT: u8 = 0x21;
+
+/// Handles of a process; 0 to 4 are stdin, stdout, stderr, stdaux and stdprn
+const HANDLES: usize = 20;
+const STANDARD_HANDLES: usize = 5;
+
+/// Error codes returned in AX with carry set
+const ERROR_INVALID_FUNCTION: u16 = 0x01;
+const ERROR_FILE_NOT_FOUND: u16 = 0x02;
+const ERROR_PATH_NOT_FOUND: u16 = 0x03;
+const ERROR_TOO_MANY_FILES: u16 = 0x04;
+const ERROR_ACCESS_DENIED: u16 = 0x05;
+const ERROR_INVALID_HANDLE: u16 = 0x06;
+const ERROR_INSUFFICIENT_MEMORY: u16 = 0x08;
+const ERROR_INVALID_BLOCK: u16 = 0x09;
+const ERROR_INVALID_ACCESS: u16 = 0x0c;
+
+/// Allocated memory block; its memory control block is the paragraph before `segment`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub segment: u16,
+    pub paragraphs: u16,
+}
+
+/// DOS personality for `int 20h` and `int 21h`. Console I/O goes through the BIOS video
+/// and keyboard services like the CON driver; files are confined to a host directory.
+pub struct Dos {
+    /// Host directory the program sees as the current directory of drive C:
+    sandbox: PathBuf,
+    files: Vec<Option<File>>,
+    /// Allocated blocks sorted by segment, the program's own block first
+    pub blocks: Vec<Block>,
+    /// Line read from the console, handed out by reads from handle 0
+    line: VecDeque<u8>,
+    /// Line being typed, kept while the keyboard waits for keys
+    editing: Vec<u8>,
+    /// Return code once the program terminated
+    pub exit_code: Option<u8>,
+}
+
+impl Dos {
+    /// Creates the services for a program whose PSP is at `psp_segment` and that owns all
+    /// memory up to the video memory.
+    pub fn new(sandbox: PathBuf, psp_segment: u16) -> Dos {
+        Self {
+            sandbox,
+            files: (0..HANDLES).map(|_| None).collect(),
+            blocks: vec![Block {
+                segment: psp_segment,
+                paragraphs: MEMORY_END_SEGMENT - psp_segment,
+            }],
+            line: VecDeque::new(),
+            editing: Vec::new(),
+            exit_code: None,
+        }
+    }
+
+    /// Stops the machine like `cli` / `hlt`, so no interrupt resumes the program.
+    fn terminate(&mut self, machine: &mut Machine, code: u8) {
+        self.exit_code = Some(code);
+        machine.set_flag(FLAG_INTERRUPT, false);
+        machine.halted = true;
+    }
+
+    /// Resolves a DOS path inside the sandbox. Drive letters are ignored, components are
+    /// matched case-insensitively and the path may not leave the sandbox.
+    fn resolve(&self, name: &str) -> Result<PathBuf, u16> {
+        let name = match name.as_bytes() {
+            [drive, b':', ..] if drive.is_ascii_alphabetic() => &name[2..],
+            _ => name,
+        };
+
+        let components = name
+            .split(['\\', '/'])
+            .filter(|component| !component.is_empty() && *component != ".")
+            .collect::<Vec<_>>();
+        let Some((file, directories)) = components.split_last() else {
+            return Err(ERROR_PATH_NOT_FOUND);
+        };
+
+        let mut path = self.sandbox.clone();
+        for directory in directories {
+            if *directory == ".." || Path::new(directory).components().count() != 1 {
+                return Err(ERROR_ACCESS_DENIED);
+            }
+            path = find(&path, directory)
+                .filter(|path| path.is_dir())
+                .ok_or(ERROR_PATH_NOT_FOUND)?;
+        }
+
+        if matches!(
+            Path::new(file).components().next(),
+            Some(Component::ParentDir | Component::RootDir | Component::Prefix(_))
+        ) {
+            return Err(ERROR_ACCESS_DENIED);
+        }
+
+        // new files are created in upper case like DOS names
+        Ok(find(&path, file).unwrap_or_else(|| path.join(file.to_uppercase())))
+    }
+
+    /// Stores `file` in the first free handle.
+    fn open_handle(&mut self, machine: &mut Machine, file: File) {
+        match (STANDARD_HANDLES..HANDLES).find(|handle| self.files[*handle].is_none()) {
+            Some(handle) => {
+                self.files[handle] = Some(file);
+                succeed(machine, handle as u16);
+            }
+            None => fail(machine, ERROR_TOO_MANY_FILES),
+        }
+    }
+
+    /// Reads a line from the keyboard with echo and backspace editing, at most `limit`
+    /// characters before the carriage return. Returns nothing while the keyboard waits for
+    /// keys; the machine then repeats the call, which goes on with the line typed so far.
+    fn read_line(
+        &mut self,
+        machine: &mut Machine,
+        limit: usize,
+    ) -> Result<Option<Vec<u8>>, String> {
+        loop {
+            let Some(character) = read_key(machine)? else {
+                return Ok(None);
+            };
+            match character {
+                b'\r' => {
+                    write_character(machine, b'\r')?;
+                    return Ok(Some(std::mem::take(&mut self.editing)));
+                }
+                0x08 if !self.editing.is_empty() => {
+                    self.editing.pop();
+                    for character in [0x08, b' ', 0x08] {
+                        write_character(machine, character)?;
+                    }
+                }
+                0x08 | 0 => {}
+                _ if self.editing.len() < limit => {
+                    self.editing.push(character);
+                    write_character(machine, character)?;
+                }
+                // full: beep
+                _ => write_character(machine, 0x07)?,
+            }
+        }
+    }
+
+    /// Reads CX bytes from handle BX into DS:DX.
+    fn read(&mut self, machine: &mut Machine) -> Result<(), String> {
+        let registers = machine.registers.clone();
+        let handle = registers.bx as usize;
+
+        let data = if handle == 0 {
+            if self.line.is_empty() {
+                let Some(mut line) = self.read_line(machine, 127)? else {
+                    return Ok(());
+                };
+                write_character(machine, b'\n')?;
+                line.extend(b"\r\n");
+                self.line.extend(line);
+            }
+            let count = self.line.len().min(registers.cx as usize);
+            self.line.drain(..count).collect::<Vec<_>>()
+        } else {
+            let Some(Some(file)) = self.files.get_mut(handle) else {
+                fail(machine, ERROR_INVALID_HANDLE);
+                return Ok(());
+            };
+
+            let mut data = vec![0; registers.cx as usize];
+            let mut count = 0;
+            while count < data.len() {
+                match file.read(&mut data[count..]) {
+                    Ok(0) => break,
+                    Ok(read) => count += read,
+                    Err(_) => {
+                        fail(machine, ERROR_ACCESS_DENIED);
+                        return Ok(());
+                    }
+                }
+            }
+            data.truncate(count);
+            data
+        };
+
+        for (offset, byte) in (0u16..).zip(&data) {
+            machine.write_byte(
+                address(registers.ds, registers.dx.wrapping_add(offset)),
+                *byte,
+            );
+        }
+        succeed(machine, data.len() as u16);
+        Ok(())
+    }
+
+    /// Writes CX bytes from DS:DX to handle BX. Standard output and error go to the
+    /// console.
+    fn write(&mut self, machine: &mut Machine) -> Result<(), String> {
+        let registers = machine.registers.clone();
+        let data = (0..registers.cx)
+            .map(|offset| {
+                machine.read_byte(address(registers.ds, registers.dx.wrapping_add(offset)))
+            })
+            .collect::<Vec<_>>();
+
+        match registers.bx as usize {
+            1 | 2 => {
+                for byte in &data {
+                    write_character(machine, *byte)?;
+                }
+            }
+            // no auxiliary device or printer attached
+            0 | 3 | 4 => {}
+            handle => {
+                let Some(Some(file)) = self.files.get_mut(handle) else {
+                    fail(machine, ERROR_INVALID_HANDLE);
+                    return Ok(());
+                };
+                if file.write_all(&data).is_err() {
+                    fail(machine, ERROR_ACCESS_DENIED);
+                    return Ok(());
+                }
+            }
+        }
+
+        succeed(machine, data.len() as u16);
+        Ok(())
+    }
+
+    /// Moves the file pointer of handle BX by CX:DX from the start (AL 0), the current
+    /// position (1) or the end (2); the new position is returned in DX:AX.
+    fn seek(&mut self, machine: &mut Machine) {
+        let registers = machine.registers.clone();
+        let distance = ((registers.cx as u32) << 16) | registers.dx as u32;
+        let position = match registers.ax as u8 {
+            0 => SeekFrom::Start(distance as u64),
+            1 => SeekFrom::Current(distance as i32 as i64),
+            2 => SeekFrom::End(distance as i32 as i64),
+            _ => return fail(machine, ERROR_INVALID_FUNCTION),
+        };
+
+        let Some(Some(file)) = self.files.get_mut(registers.bx as usize) else {
+            return fail(machine, ERROR_INVALID_HANDLE);
+        };
+
+        match file.seek(position) {
+            Ok(position) => {
+                machine.registers.dx = (position >> 16) as u16;
+                succeed(machine, position as u16);
+            }
+            Err(_) => fail(machine, ERROR_ACCESS_DENIED),
+        }
+    }
+
+    /// Creates or truncates the file named at DS:DX.
+    fn create(&mut self, machine: &mut Machine) {
+        let path = match self.resolve(&read_name(machine)) {
+            Ok(path) => path,
+            Err(error) => return fail(machine, error),
+        };
+
+        match File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+        {
+            Ok(file) => self.open_handle(machine, file),
+            Err(_) => fail(machine, ERROR_ACCESS_DENIED),
+        }
+    }
+
+    /// Opens the file named at DS:DX for reading (`mode` 0), writing (1) or both (2).
+    fn open(&mut self, machine: &mut Machine, mode: u8) {
+        let path = match self.resolve(&read_name(machine)) {
+            Ok(path) => path,
+            Err(error) => return fail(machine, error),
+        };
+
+        let mut options = OpenOptions::new();
+        match mode & 0x07 {
+            0 => options.read(true),
+            1 => options.write(true),
+            2 => options.read(true).write(true),
+            _ => return fail(machine, ERROR_INVALID_ACCESS),
+        };
+
+        match options.open(&path) {
+            Ok(file) if path.is_file() => self.open_handle(machine, file),
+            Ok(_) => fail(machine, ERROR_ACCESS_DENIED),
+            Err(_) if !path.exists() => fail(machine, ERROR_FILE_NOT_FOUND),
+            Err(_) => fail(machine, ERROR_ACCESS_DENIED),
+        }
+    }
+
+    /// Largest number of paragraphs that fits in one gap, and the first gap fitting
+    /// `paragraphs` (its block segment).
+    fn find_gap(&self, paragraphs: u16) -> (u16, Option<u16>) {
+        let mut largest = 0;
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            // the next memory control block follows the block
+            let start = block.segment as u32 + block.paragraphs as u32 + 1;
+            let end = self
+                .blocks
+                .get(index + 1)
+                .map_or(MEMORY_END_SEGMENT as u32, |next| next.segment as u32 - 1);
+            let size = end.saturating_sub(start);
+
+            if size >= paragraphs as u32 {
+                return (paragraphs, Some(start as u16));
+            }
+            largest = largest.max(size as u16);
+        }
+
+        (largest, None)
+    }
+
+    /// Allocates BX paragraphs; the segment is returned in AX, or the largest possible
+    /// size in BX.
+    fn allocate(&mut self, machine: &mut Machine) {
+        let paragraphs = machine.registers.bx;
+
+        match self.find_gap(paragraphs) {
+            (_, Some(segment)) => {
+                let index = self.blocks.partition_point(|block| block.segment < segment);
+                self.blocks.insert(
+                    index,
+                    Block {
+                        segment,
+                        paragraphs,
+                    },
+                );
+                succeed(machine, segment);
+            }
+            (largest, None) => {
+                machine.registers.bx = largest;
+                fail(machine, ERROR_INSUFFICIENT_MEMORY);
+            }
+        }
+    }
+
+    /// Frees the block at ES.
+    fn free(&mut self, machine: &mut Machine) {
+        let segment = machine.registers.es;
+        match self
+            .blocks
+            .iter()
+            .position(|block| block.segment == segment)
+        {
+            Some(index) => {
+                self.blocks.remove(index);
+                machine.set_flag(FLAG_CARRY, false);
+            }
+            None => fail(machine, ERROR_INVALID_BLOCK),
+        }
+    }
+
+    /// Resizes the block at ES to BX paragraphs; the largest possible size is returned in
+    /// BX when it cannot grow.
+    fn resize(&mut self, machine: &mut Machine) {
+        let (segment, paragraphs) = (machine.registers.es, machine.registers.bx);
+        let Some(index) = self
+            .blocks
+            .iter()
+            .position(|block| block.segment == segment)
+        else {
+            return fail(machine, ERROR_INVALID_BLOCK);
+        };
+
+        let end = self
+            .blocks
+            .get(index + 1)
+            .map_or(MEMORY_END_SEGMENT as u32, |next| next.segment as u32 - 1);
+        let largest = (end - segment as u32) as u16;
+
+        if paragraphs > largest {
+            machine.registers.bx = largest;
+            return fail(machine, ERROR_INSUFFICIENT_MEMORY);
+        }
+
+        self.blocks[index].paragraphs = paragraphs;
+        machine.set_flag(FLAG_CARRY, false);
+    }
+}
+
+impl InterruptHandler for Dos {
+    fn interrupt(&mut self, machine: &mut Machine, vector: u8) -> Result<(), String> {
+        if vector == TERMINATE_INTERRUPT {
+            self.terminate(machine, 0);
+            return Ok(());
+        }
+
+        let registers = machine.registers.clone();
+        let [al, ah] = registers.ax.to_le_bytes();
+
+        match ah {
+            0x00 => self.terminate(machine, 0),
+            // character output of DL
+            0x02 => {
+                write_character(machine, registers.dx as u8)?;
+                machine.registers.ax = (registers.ax & 0xff00) | (registers.dx & 0xff);
+            }
+            // string output from DS:DX up to '$'
+            0x09 => {
+                let mut offset = registers.dx;
+                loop {
+                    let character = machine.read_byte(address(registers.ds, offset));
+                    if character == b'$' {
+                        break;
+                    }
+                    write_character(machine, character)?;
+                    offset = offset.wrapping_add(1);
+                    if offset == registers.dx {
+                        return Err("String output is missing its '$' terminator.".to_string());
+                    }
+                }
+                machine.registers.ax = (registers.ax & 0xff00) | b'$' as u16;
+            }
+            // buffered input into DS:DX: maximum length, length read, characters and CR
+            0x0a => {
+                let buffer = address(registers.ds, registers.dx);
+                let size = machine.read_byte(buffer) as usize;
+                if size > 0 {
+                    let Some(line) = self.read_line(machine, size - 1)? else {
+                        return Ok(());
+                    };
+                    machine.write_byte(buffer + 1, line.len() as u8);
+                    for (offset, byte) in line.iter().chain(b"\r").enumerate() {
+                        machine.write_byte(buffer + 2 + offset, *byte);
+                    }
+                }
+            }
+            // current drive: C:
+            0x19 => machine.registers.ax = (registers.ax & 0xff00) | 2,
+            // set the vector AL to DS:DX
+            0x25 => {
+                let entry = al as usize * 4;
+                machine.write_word(entry, registers.dx);
+                machine.write_word(entry + 2, registers.ds);
+            }
+            // DOS version 5.0
+            0x30 => {
+                machine.registers.ax = 0x0005;
+                machine.registers.bx = 0;
+                machine.registers.cx = 0;
+            }
+            // get the vector AL into ES:BX
+            0x35 => {
+                let entry = al as usize * 4;
+                machine.registers.bx = machine.read_word(entry);
+                machine.registers.es = machine.read_word(entry + 2);
+            }
+            0x3c => self.create(machine),
+            0x3d => self.open(machine, al),
+            // close handle BX
+            0x3e => match self.files.get_mut(registers.bx as usize) {
+                Some(file) if file.is_some() => {
+                    *file = None;
+                    machine.set_flag(FLAG_CARRY, false);
+                }
+                Some(_) if (registers.bx as usize) < STANDARD_HANDLES => {
+                    machine.set_flag(FLAG_CARRY, false)
+                }
+                _ => fail(machine, ERROR_INVALID_HANDLE),
+            },
+            0x3f => self.read(machine)?,
+            0x40 => self.write(machine)?,
+            // delete the file named at DS:DX
+            0x41 => match self.resolve(&read_name(machine)) {
+                Ok(path) if path.is_file() => match fs::remove_file(path) {
+                    Ok(()) => machine.set_flag(FLAG_CARRY, false),
+                    Err(_) => fail(machine, ERROR_ACCESS_DENIED),
+                },
+                Ok(_) => fail(machine, ERROR_FILE_NOT_FOUND),
+                Err(error) => fail(machine, error),
+            },
+            0x42 => self.seek(machine),
+            0x48 => self.allocate(machine),
+            0x49 => self.free(machine),
+            0x4a => self.resize(machine),
+            // terminate with return code AL
+            0x4c => self.terminate(machine, al),
+            _ => return Err(unsupported(vector, machine)),
+        }
+
+        Ok(())
+    }
+}
+
+/// Directory entry of `directory` named `name`, ignoring case
+fn find(directory: &Path, name: &str) -> Option<PathBuf> {
+    let exact = directory.join(name);
+    if exact.exists() {
+        return Some(exact);
+    }
+
+    fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .eq_ignore_ascii_case(name)
+        })
+        .map(|entry| entry.path())
+}
+
+/// Zero terminated name at DS:DX
+fn read_name(machine: &mut Machine) -> String {
+    let (ds, dx) = (machine.registers.ds, machine.registers.dx);
+    let mut name = Vec::new();
+
+    for offset in 0..128u16 {
+        match machine.read_byte(address(ds, dx.wrapping_add(offset))) {
+            0 => break,
+            byte => name.push(byte),
+        }
+    }
+
+    String::from_utf8_lossy(&name).into_owned()
+}
+
+/// Clears carry and returns `ax`.
+fn succeed(machine: &mut Machine, ax: u16) {
+    machine.registers.ax = ax;
+    machine.set_flag(FLAG_CARRY, false);
+}
+
+/// Sets carry and returns the error code in AX.
+fn fail(machine: &mut Machine, error: u16) {
+    machine.registers.ax = error;
+    machine.set_flag(FLAG_CARRY, true);
+}
+
+/// Writes a character with the BIOS teletype function, preserving the registers.
+fn write_character(machine: &mut Machine, character: u8) -> Result<(), String> {
+    let registers = machine.registers.clone();
+    machine.registers.ax = 0x0e00 | character as u16;
+    machine.registers.bx = 0x0007;
+    let result = machine.interrupt(VIDEO_INTERRUPT);
+    machine.registers = registers;
+    result
+}
+
+/// Waits for a key with the BIOS keyboard service and returns its character, preserving
+/// the registers. Returns nothing when the service has to wait and asked for the
+/// interrupt to be taken again.
+fn read_key(machine: &mut Machine) -> Result<Option<u8>, String> {
+    let registers = machine.registers.clone();
+    machine.registers.ax = 0x0000;
+    let result = machine.interrupt(KEYBOARD_INTERRUPT);
+    let character = machine.registers.ax as u8;
+    machine.registers = registers;
+    result.map(|_| (!machine.retrying()).then_some(character))
+}
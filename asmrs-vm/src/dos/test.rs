@@ -0,0 +1,207 @@
+/// Output shared between a video handler and the test
+#[cfg(test)]
+#[derive(Clone, Default)]
+struct Output(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+#[cfg(test)]
+impl std::io::Write for Output {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buffer);
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Machine with `code` at 1000:0100 (the PSP at 1000:0000), DOS services sandboxed in
+/// `sandbox`, typed `input` and console output collected in the returned buffer
+#[cfg(test)]
+fn machine(
+    code: &[u8],
+    sandbox: &std::path::Path,
+    input: &str,
+) -> (
+    crate::machine::Machine,
+    std::rc::Rc<std::cell::RefCell<crate::dos::Dos>>,
+    Output,
+) {
+    use crate::{
+        bios::{Keyboard, Video, KEYBOARD_INTERRUPT, VIDEO_INTERRUPT},
+        dos::{Dos, DOS_INTERRUPT, TERMINATE_INTERRUPT},
+        machine::Machine,
+        memory::address,
+    };
+    use asmrs_parser::lexer::token::Cpu;
+    use std::{cell::RefCell, rc::Rc};
+
+    let mut machine = Machine::new(Cpu::I8086);
+    machine.memory.load(address(0x1000, 0x100), code);
+    machine.registers.cs = 0x1000;
+    machine.registers.ds = 0x1000;
+    machine.registers.es = 0x1000;
+    machine.registers.ss = 0x1000;
+    machine.registers.ip = 0x100;
+    machine.registers.sp = 0xfffe;
+
+    let output = Output::default();
+    machine
+        .interrupts
+        .set(VIDEO_INTERRUPT, Video::new(Box::new(output.clone())));
+    machine
+        .interrupts
+        .set(KEYBOARD_INTERRUPT, Keyboard::from_text(input));
+
+    let dos = Rc::new(RefCell::new(Dos::new(sandbox.to_path_buf(), 0x1000)));
+    machine.interrupts.set_shared(DOS_INTERRUPT, dos.clone());
+    machine
+        .interrupts
+        .set_shared(TERMINATE_INTERRUPT, dos.clone());
+
+    (machine, dos, output)
+}
+
+/// Empty directory for the files of one test
+#[cfg(test)]
+fn sandbox(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("asmrs-dos-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(&path).unwrap();
+    path
+}
+
+#[test]
+fn dos_console() {
+    use crate::memory::address;
+
+    // mov ah, 9 / mov dx, 120h / int 21h / mov ah, 2 / mov dl, '!' / int 21h
+    // mov ah, 0ah / mov dx, 130h / int 21h / mov ax, 4c03h / int 21h
+    let code = [
+        0xb4, 0x09, 0xba, 0x20, 0x01, 0xcd, 0x21, 0xb4, 0x02, 0xb2, 0x21, 0xcd, 0x21, 0xb4, 0x0a,
+        0xba, 0x30, 0x01, 0xcd, 0x21, 0xb8, 0x03, 0x4c, 0xcd, 0x21,
+    ];
+    let path = sandbox("console");
+    let (mut machine, dos, output) = machine(&code, &path, "abx\x08cd\r");
+    machine.memory.load(address(0x1000, 0x120), b"Hello$");
+    machine.memory.load(address(0x1000, 0x130), &[4]);
+
+    machine.run(100).unwrap();
+    assert!(machine.halted);
+    assert_eq!(dos.borrow().exit_code, Some(3));
+    assert_eq!(*output.0.borrow(), b"Hello!abx\x08 \x08c\r");
+    assert_eq!(
+        machine.memory.read(address(0x1000, 0x130), 6),
+        [4, 3, b'a', b'b', b'c', b'\r']
+    );
+}
+
+#[test]
+fn dos_files() {
+    use crate::{dos::Dos, interrupt::InterruptHandler, registers::FLAG_CARRY};
+
+    let path = sandbox("files");
+    std::fs::create_dir(path.join("Data")).unwrap();
+    std::fs::write(path.join("Data").join("input.txt"), b"0123456789").unwrap();
+
+    let (mut machine, _, _) = machine(&[], &path, "");
+    let mut dos = Dos::new(path.clone(), 0x1000);
+    let mut call = |machine: &mut crate::machine::Machine, ax: u16, bx: u16, cx: u16, dx: u16| {
+        machine.registers.ax = ax;
+        machine.registers.bx = bx;
+        machine.registers.cx = cx;
+        machine.registers.dx = dx;
+        dos.interrupt(machine, 0x21).unwrap();
+        (machine.flag(FLAG_CARRY), machine.registers.ax)
+    };
+
+    // open, seek and read with a DOS path in another case
+    machine.memory.load(0x10200, b"c:\\DATA\\INPUT.TXT\0");
+    assert_eq!(call(&mut machine, 0x3d00, 0, 0, 0x200), (false, 5));
+    assert_eq!(call(&mut machine, 0x4200, 5, 0, 4), (false, 4));
+    assert_eq!(call(&mut machine, 0x3f00, 5, 3, 0x300), (false, 3));
+    assert_eq!(machine.memory.read(0x10300, 3), b"456");
+    assert_eq!(call(&mut machine, 0x4202, 5, 0, 0), (false, 10));
+    assert_eq!(call(&mut machine, 0x3e00, 5, 0, 0), (false, 0x3e00));
+    assert_eq!(call(&mut machine, 0x3e00, 5, 0, 0), (true, 6));
+
+    // create and write a new file
+    machine.memory.load(0x10200, b"out.txt\0");
+    assert_eq!(call(&mut machine, 0x3c00, 0, 0, 0x200), (false, 5));
+    assert_eq!(call(&mut machine, 0x4000, 5, 3, 0x300), (false, 3));
+    assert_eq!(call(&mut machine, 0x3e00, 5, 0, 0), (false, 0x3e00));
+    assert_eq!(std::fs::read(path.join("OUT.TXT")).unwrap(), b"456");
+
+    // errors
+    machine.memory.load(0x10200, b"missing.txt\0");
+    assert_eq!(call(&mut machine, 0x3d00, 0, 0, 0x200), (true, 2));
+    machine.memory.load(0x10200, b"nowhere\\a.txt\0");
+    assert_eq!(call(&mut machine, 0x3d00, 0, 0, 0x200), (true, 3));
+    machine.memory.load(0x10200, b"..\\escape.txt\0");
+    assert_eq!(call(&mut machine, 0x3c00, 0, 0, 0x200), (true, 5));
+    assert_eq!(call(&mut machine, 0x3f00, 9, 1, 0x300), (true, 6));
+
+    // standard handles
+    assert_eq!(call(&mut machine, 0x4000, 3, 2, 0x300), (false, 2));
+
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn dos_memory() {
+    use crate::{dos::Dos, interrupt::InterruptHandler, registers::FLAG_CARRY};
+
+    let path = sandbox("memory");
+    let (mut machine, _, _) = machine(&[], &path, "");
+    let mut dos = Dos::new(path.clone(), 0x1000);
+    let mut call = |machine: &mut crate::machine::Machine, ax: u16, bx: u16, es: u16| {
+        machine.registers.ax = ax;
+        machine.registers.bx = bx;
+        machine.registers.es = es;
+        dos.interrupt(machine, 0x21).unwrap();
+        (
+            machine.flag(FLAG_CARRY),
+            machine.registers.ax,
+            machine.registers.bx,
+        )
+    };
+
+    // the program owns everything until it shrinks its block
+    assert_eq!(call(&mut machine, 0x4800, 0x100, 0), (true, 8, 0));
+    assert_eq!(
+        call(&mut machine, 0x4a00, 0x1000, 0x1000),
+        (false, 0x4a00, 0x1000)
+    );
+    assert_eq!(call(&mut machine, 0x4800, 0x100, 0), (false, 0x2001, 0x100));
+    assert_eq!(call(&mut machine, 0x4800, 0x100, 0), (false, 0x2102, 0x100));
+    assert_eq!(call(&mut machine, 0x4800, 0xffff, 0), (true, 8, 0x7dfd));
+
+    // growing into the next block fails
+    assert_eq!(call(&mut machine, 0x4a00, 0x2000, 0x2001), (true, 8, 0x100));
+    assert_eq!(call(&mut machine, 0x4900, 0, 0x2001), (false, 0x4900, 0));
+    assert_eq!(call(&mut machine, 0x4900, 0, 0x2001), (true, 9, 0));
+    assert_eq!(call(&mut machine, 0x4800, 0x80, 0), (false, 0x2001, 0x80));
+
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn dos_vectors() {
+    use crate::memory::address;
+
+    // mov ax, 2560h / mov dx, 1234h / int 21h / mov ax, 3560h / int 21h / int 20h
+    let code = [
+        0xb8, 0x60, 0x25, 0xba, 0x34, 0x12, 0xcd, 0x21, 0xb8, 0x60, 0x35, 0xcd, 0x21, 0xcd, 0x20,
+    ];
+    let path = sandbox("vectors");
+    let (mut machine, dos, _) = machine(&code, &path, "");
+
+    machine.run(100).unwrap();
+    assert_eq!(dos.borrow().exit_code, Some(0));
+    assert_eq!(machine.memory.read_word(address(0, 0x180)), 0x1234);
+    assert_eq!(machine.memory.read_word(address(0, 0x182)), 0x1000);
+    assert_eq!(machine.registers.bx, 0x1234);
+    assert_eq!(machine.registers.es, 0x1000);
+
+    std::fs::remove_dir_all(path).unwrap();
+}
@@ -0,0 +1,521 @@
+use asmrs_parser::lexer::token::FloatingPointRegister;
+
+mod test;
+
+/// Invalid operation (stack fault, NaN operand, integer overflow)
+pub const STATUS_INVALID: u16 = 1 << 0;
+/// Denormalized operand
+pub const STATUS_DENORMAL: u16 = 1 << 1;
+/// Division by zero
+pub const STATUS_ZERO_DIVIDE: u16 = 1 << 2;
+/// Result too large
+pub const STATUS_OVERFLOW: u16 = 1 << 3;
+/// Result too small
+pub const STATUS_UNDERFLOW: u16 = 1 << 4;
+/// Inexact result
+pub const STATUS_PRECISION: u16 = 1 << 5;
+/// Unmasked exception pending (interrupt request)
+pub const STATUS_INTERRUPT: u16 = 1 << 7;
+/// Condition code C0
+pub const STATUS_C0: u16 = 1 << 8;
+/// Condition code C1
+pub const STATUS_C1: u16 = 1 << 9;
+/// Condition code C2
+pub const STATUS_C2: u16 = 1 << 10;
+/// Condition code C3
+pub const STATUS_C3: u16 = 1 << 14;
+/// Busy
+pub const STATUS_BUSY: u16 = 1 << 15;
+
+/// Control word after `finit`: all exceptions masked, 64-bit precision, round to nearest
+pub const CONTROL_DEFAULT: u16 = 0x03ff;
+
+/// Tag of a register holding a valid number
+const TAG_VALID: u16 = 0b00;
+/// Tag of a register holding zero
+const TAG_ZERO: u16 = 0b01;
+/// Tag of a register holding NaN or infinity
+const TAG_SPECIAL: u16 = 0b10;
+/// Tag of an empty register
+const TAG_EMPTY: u16 = 0b11;
+
+/// Arithmetic operations sharing the `fadd`/`fsub`/`fmul`/`fdiv` encoding
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Arithmetic {
+    Add,
+    Sub,
+    /// Subtract reversed (source - destination)
+    SubReversed,
+    Mul,
+    Div,
+    /// Divide reversed (source / destination)
+    DivReversed,
+}
+
+/// 8087 coprocessor: eight register stack, status, control and tag word.
+/// Registers hold `f64`, so 80-bit extended precision is approximated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fpu {
+    /// Physical registers, addressed relative to TOP as st(i)
+    registers: [f64; 8],
+    status: u16,
+    control: u16,
+    tag: u16,
+}
+
+impl Default for Fpu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Fpu {
+    /// Creates a coprocessor in the state left by `finit`.
+    pub fn new() -> Fpu {
+        Self {
+            registers: [0.0; 8],
+            status: 0,
+            control: CONTROL_DEFAULT,
+            tag: 0xffff,
+        }
+    }
+
+    /// `finit`: resets control, status and tag word.
+    pub fn init(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Status word including the current stack top
+    pub fn status_word(&self) -> u16 {
+        self.status
+    }
+
+    pub fn control_word(&self) -> u16 {
+        self.control
+    }
+
+    /// `fldcw`
+    pub fn set_control_word(&mut self, control: u16) {
+        self.control = control;
+        self.update_interrupt();
+    }
+
+    pub fn tag_word(&self) -> u16 {
+        self.tag
+    }
+
+    /// `fldenv`/`frstor`: loads control, status and tag word.
+    pub fn set_environment(&mut self, control: u16, status: u16, tag: u16) {
+        self.control = control;
+        self.status = status;
+        self.tag = tag;
+        self.update_interrupt();
+    }
+
+    /// `fclex`: clears exception flags and the interrupt request.
+    pub fn clear_exceptions(&mut self) {
+        self.status &= !(0x00ff | STATUS_BUSY);
+    }
+
+    /// Whether an unmasked exception is waiting to interrupt the CPU
+    pub fn interrupt_pending(&self) -> bool {
+        self.status & STATUS_INTERRUPT != 0
+    }
+
+    /// Current stack top (physical register index of st(0))
+    pub fn top(&self) -> u8 {
+        ((self.status >> 11) & 0b111) as u8
+    }
+
+    /// Whether st(i) holds no value
+    pub fn is_empty(&self, register: FloatingPointRegister) -> bool {
+        self.physical_tag(self.physical(register)) == TAG_EMPTY
+    }
+
+    /// Reads st(i). Reading an empty register signals a stack underflow and yields NaN.
+    pub fn st(&mut self, register: FloatingPointRegister) -> f64 {
+        let physical = self.physical(register);
+
+        if self.physical_tag(physical) == TAG_EMPTY {
+            self.status &= !STATUS_C1;
+            self.raise(STATUS_INVALID);
+            return f64::NAN;
+        }
+
+        self.registers[physical]
+    }
+
+    /// Contents of st(i) without checking its tag (`fsave`)
+    pub fn value(&self, register: FloatingPointRegister) -> f64 {
+        self.registers[self.physical(register)]
+    }
+
+    /// Overwrites st(i) and marks it as in use.
+    pub fn set_st(&mut self, register: FloatingPointRegister, value: f64) {
+        let physical = self.physical(register);
+        self.write_physical(physical, value);
+    }
+
+    /// Pushes `value` (`fld`, `fild`, `fld1`, ...). Pushing onto a full stack signals
+    /// a stack overflow and loads NaN instead.
+    pub fn push(&mut self, value: f64) {
+        let top = (self.top() + 7) % 8;
+        self.set_top(top);
+
+        if self.physical_tag(top as usize) != TAG_EMPTY {
+            self.status |= STATUS_C1;
+            self.raise(STATUS_INVALID);
+            self.write_physical(top as usize, f64::NAN);
+            return;
+        }
+
+        self.write_physical(top as usize, value);
+    }
+
+    /// Pops st(0) and returns its value (`fstp`, `fistp`, ...).
+    pub fn pop(&mut self) -> f64 {
+        let value = self.st(FloatingPointRegister::St0);
+        self.free(FloatingPointRegister::St0);
+        self.set_top((self.top() + 1) % 8);
+        value
+    }
+
+    /// `fincstp`/`fdecstp`: moves the stack top without freeing or tagging registers.
+    pub fn rotate(&mut self, increment: bool) {
+        let top = if increment { 1 } else { 7 };
+        self.set_top((self.top() + top) % 8);
+    }
+
+    /// `ffree st(i)`
+    pub fn free(&mut self, register: FloatingPointRegister) {
+        let physical = self.physical(register);
+        self.set_physical_tag(physical, TAG_EMPTY);
+    }
+
+    /// `fxch st(i)`
+    pub fn exchange(&mut self, register: FloatingPointRegister) {
+        let top = self.st(FloatingPointRegister::St0);
+        let other = self.st(register);
+        self.set_st(FloatingPointRegister::St0, other);
+        self.set_st(register, top);
+    }
+
+    /// `fadd`/`fsub`/`fmul`/`fdiv` and their reversed forms: `destination op= source`.
+    /// With `pop`, the stack is popped afterwards (`faddp`, ...).
+    pub fn arithmetic(
+        &mut self,
+        operation: Arithmetic,
+        destination: FloatingPointRegister,
+        source: f64,
+        pop: bool,
+    ) {
+        let value = self.st(destination);
+
+        if matches!(operation, Arithmetic::Div) && source == 0.0
+            || matches!(operation, Arithmetic::DivReversed) && value == 0.0
+        {
+            self.raise(STATUS_ZERO_DIVIDE);
+        }
+
+        let result = match operation {
+            Arithmetic::Add => value + source,
+            Arithmetic::Sub => value - source,
+            Arithmetic::SubReversed => source - value,
+            Arithmetic::Mul => value * source,
+            Arithmetic::Div => value / source,
+            Arithmetic::DivReversed => source / value,
+        };
+
+        if result.is_nan() && !value.is_nan() && !source.is_nan() {
+            self.raise(STATUS_INVALID);
+        } else if result.is_infinite() && value.is_finite() && source.is_finite() {
+            self.raise(STATUS_OVERFLOW);
+        }
+
+        self.set_st(destination, result);
+
+        if pop {
+            self.pop();
+        }
+    }
+
+    /// `fcom`/`ftst`: compares st(0) with `source` and sets C3, C2 and C0.
+    /// Unordered operands (NaN) set all three.
+    pub fn compare(&mut self, source: f64) {
+        let value = self.st(FloatingPointRegister::St0);
+        self.status &= !(STATUS_C0 | STATUS_C2 | STATUS_C3);
+
+        self.status |= match value.partial_cmp(&source) {
+            Some(std::cmp::Ordering::Greater) => 0,
+            Some(std::cmp::Ordering::Less) => STATUS_C0,
+            Some(std::cmp::Ordering::Equal) => STATUS_C3,
+            None => {
+                self.raise(STATUS_INVALID);
+                STATUS_C0 | STATUS_C2 | STATUS_C3
+            }
+        };
+    }
+
+    /// Replaces st(0) by `operation` of it (`fchs`, `fsqrt`, ...). A NaN result from a
+    /// number signals an invalid operation.
+    pub fn apply(&mut self, operation: impl Fn(f64) -> f64) {
+        let value = self.st(FloatingPointRegister::St0);
+        let result = operation(value);
+
+        if result.is_nan() && !value.is_nan() {
+            self.raise(STATUS_INVALID);
+        }
+
+        self.set_st(FloatingPointRegister::St0, result);
+    }
+
+    /// `fxam`: classifies st(0) in C3, C2 and C0 and copies its sign to C1.
+    pub fn examine(&mut self) {
+        let empty = self.is_empty(FloatingPointRegister::St0);
+        let value = self.value(FloatingPointRegister::St0);
+
+        let class = if empty {
+            STATUS_C3 | STATUS_C0
+        } else if value.is_nan() {
+            STATUS_C0
+        } else if value.is_infinite() {
+            STATUS_C2 | STATUS_C0
+        } else if value == 0.0 {
+            STATUS_C3
+        } else if value.is_subnormal() {
+            STATUS_C3 | STATUS_C2
+        } else {
+            STATUS_C2
+        };
+        let sign = if value.is_sign_negative() {
+            STATUS_C1
+        } else {
+            0
+        };
+
+        self.status &= !(STATUS_C0 | STATUS_C1 | STATUS_C2 | STATUS_C3);
+        self.status |= class | sign;
+    }
+
+    /// `fprem`: st(0) = st(0) - st(1) * q with q truncated toward zero. The remainder is
+    /// always complete (C2 clear); the low three bits of q go to C0, C3 and C1.
+    pub fn partial_remainder(&mut self) {
+        let dividend = self.st(FloatingPointRegister::St0);
+        let divisor = self.st(FloatingPointRegister::St1);
+
+        if divisor == 0.0 || dividend.is_infinite() {
+            self.raise(STATUS_INVALID);
+            self.set_st(FloatingPointRegister::St0, f64::NAN);
+            return;
+        }
+
+        let quotient = (dividend / divisor).trunc().abs() as u64;
+        let condition = [
+            (quotient & 0b100 != 0, STATUS_C0),
+            (quotient & 0b010 != 0, STATUS_C3),
+            (quotient & 0b001 != 0, STATUS_C1),
+        ];
+
+        self.status &= !(STATUS_C0 | STATUS_C1 | STATUS_C2 | STATUS_C3);
+        for (set, flag) in condition {
+            if set {
+                self.status |= flag;
+            }
+        }
+
+        self.set_st(FloatingPointRegister::St0, dividend % divisor);
+    }
+
+    /// Rounds `value` according to the rounding control field (`frndint`, `fist`).
+    pub fn round(&self, value: f64) -> f64 {
+        match (self.control >> 10) & 0b11 {
+            0b00 => {
+                // round half to even
+                let rounded = value.round();
+                if (value - value.trunc()).abs() == 0.5 && rounded % 2.0 != 0.0 {
+                    rounded - value.signum()
+                } else {
+                    rounded
+                }
+            }
+            0b01 => value.floor(),
+            0b10 => value.ceil(),
+            _ => value.trunc(),
+        }
+    }
+
+    /// Converts st(0) to an integer of `bits` width (16, 32 or 64) for `fist`/`fistp`.
+    /// Values out of range signal an invalid operation and store the integer indefinite.
+    pub fn to_integer(&mut self, bits: u32) -> i64 {
+        let value = self.st(FloatingPointRegister::St0);
+        let rounded = self.round(value);
+        let min = -(2f64.powi(bits as i32 - 1));
+
+        if rounded.is_nan() || rounded < min || rounded >= -min {
+            self.raise(STATUS_INVALID);
+            return i64::MIN >> (64 - bits);
+        }
+
+        if rounded != value {
+            self.raise(STATUS_PRECISION);
+        }
+
+        rounded as i64
+    }
+
+    /// Converts st(0) to packed BCD for `fbstp`. Values beyond 18 digits signal an invalid
+    /// operation and store the BCD indefinite.
+    pub fn to_bcd(&mut self) -> [u8; 10] {
+        let value = self.st(FloatingPointRegister::St0);
+        let rounded = self.round(value);
+
+        if rounded.is_nan() || rounded.abs() >= 1e18 {
+            self.raise(STATUS_INVALID);
+            return [0, 0, 0, 0, 0, 0, 0, 0xc0, 0xff, 0xff];
+        }
+
+        let mut bytes = [0; 10];
+        let mut digits = rounded.abs() as u64;
+        for byte in bytes.iter_mut().take(9) {
+            *byte = (digits % 10) as u8 | ((digits / 10 % 10) as u8) << 4;
+            digits /= 100;
+        }
+        if rounded.is_sign_negative() {
+            bytes[9] = 0x80;
+        }
+
+        bytes
+    }
+
+    fn physical(&self, register: FloatingPointRegister) -> usize {
+        ((self.top() + register.index()) % 8) as usize
+    }
+
+    fn physical_tag(&self, physical: usize) -> u16 {
+        (self.tag >> (physical * 2)) & 0b11
+    }
+
+    fn set_physical_tag(&mut self, physical: usize, tag: u16) {
+        self.tag = (self.tag & !(0b11 << (physical * 2))) | (tag << (physical * 2));
+    }
+
+    fn write_physical(&mut self, physical: usize, value: f64) {
+        let tag = if value == 0.0 {
+            TAG_ZERO
+        } else if value.is_finite() {
+            TAG_VALID
+        } else {
+            TAG_SPECIAL
+        };
+
+        self.registers[physical] = value;
+        self.set_physical_tag(physical, tag);
+    }
+
+    fn set_top(&mut self, top: u8) {
+        self.status = (self.status & !(0b111 << 11)) | ((top as u16) << 11);
+    }
+
+    /// Records an exception; requests an interrupt if it is unmasked.
+    fn raise(&mut self, exception: u16) {
+        self.status |= exception;
+        self.update_interrupt();
+    }
+
+    fn update_interrupt(&mut self) {
+        let unmasked = self.status & !self.control & 0x3f;
+        // bit 7 of the control word is the 8087 interrupt enable mask
+        if unmasked != 0 && self.control & 0x80 == 0 {
+            self.status |= STATUS_INTERRUPT;
+        } else {
+            self.status &= !STATUS_INTERRUPT;
+        }
+    }
+}
+
+/// Reads a real in memory format: short (4 bytes), long (8 bytes) or temporary (10 bytes).
+pub fn real_from_bytes(bytes: &[u8]) -> f64 {
+    match bytes.len() {
+        4 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        8 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        10 => {
+            let mantissa = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+            let exponent = u16::from_le_bytes([bytes[8], bytes[9]]);
+            let sign = if exponent & 0x8000 != 0 { -1.0 } else { 1.0 };
+            let exponent = (exponent & 0x7fff) as i32;
+
+            if exponent == 0x7fff {
+                return if mantissa << 1 == 0 {
+                    sign * f64::INFINITY
+                } else {
+                    f64::NAN
+                };
+            }
+
+            sign * scale(mantissa as f64, exponent - 16383 - 63)
+        }
+        length => panic!("No real is {} bytes long.", length),
+    }
+}
+
+/// Writes a real in memory format of `length` bytes (4, 8 or 10).
+pub fn real_to_bytes(value: f64, length: usize) -> Vec<u8> {
+    match length {
+        4 => (value as f32).to_le_bytes().to_vec(),
+        8 => value.to_le_bytes().to_vec(),
+        10 => {
+            let bits = value.to_bits();
+            let sign = ((bits >> 63) as u16) << 15;
+            let exponent = ((bits >> 52) & 0x7ff) as i32;
+            let fraction = bits & ((1 << 52) - 1);
+
+            let (mantissa, exponent) = match exponent {
+                0 if fraction == 0 => (0, 0),
+                // denormals are normalized, the wider exponent has room for them
+                0 => {
+                    let shift = fraction.leading_zeros();
+                    (fraction << shift, 63 - 1074 - shift as i32 + 16383)
+                }
+                0x7ff if fraction == 0 => (1 << 63, 0x7fff),
+                0x7ff => (0xc000_0000_0000_0000, 0x7fff),
+                _ => (1 << 63 | fraction << 11, exponent - 1023 + 16383),
+            };
+
+            let mut bytes = mantissa.to_le_bytes().to_vec();
+            bytes.extend((sign | exponent as u16).to_le_bytes());
+            bytes
+        }
+        length => panic!("No real is {} bytes long.", length),
+    }
+}
+
+/// Reads 18 digit packed BCD (`fbld`): nine bytes of two digits each, low digits first,
+/// then the sign in bit 7 of the last byte.
+pub fn bcd_from_bytes(bytes: &[u8; 10]) -> f64 {
+    let digits = bytes[..9].iter().rev().fold(0u64, |value, byte| {
+        value * 100 + (byte >> 4) as u64 * 10 + (byte & 0x0f) as u64
+    });
+
+    if bytes[9] & 0x80 != 0 {
+        -(digits as f64)
+    } else {
+        digits as f64
+    }
+}
+
+/// `value * 2^exponent`, in steps that neither overflow nor underflow on the way
+fn scale(value: f64, exponent: i32) -> f64 {
+    let mut value = value;
+    let mut exponent = exponent;
+
+    while exponent > 1000 {
+        value *= 2f64.powi(1000);
+        exponent -= 1000;
+    }
+    while exponent < -1000 {
+        value *= 2f64.powi(-1000);
+        exponent += 1000;
+    }
+
+    value * 2f64.powi(exponent)
+}
@@ -0,0 +1,179 @@
+#[test]
+fn fpu_stack() {
+    use crate::fpu::Fpu;
+    use asmrs_parser::lexer::token::FloatingPointRegister;
+
+    let mut fpu = Fpu::new();
+    assert_eq!(fpu.tag_word(), 0xffff);
+
+    fpu.push(1.0);
+    fpu.push(2.0);
+    assert_eq!(fpu.top(), 6);
+    assert_eq!(fpu.st(FloatingPointRegister::St0), 2.0);
+    assert_eq!(fpu.st(FloatingPointRegister::St1), 1.0);
+
+    fpu.exchange(FloatingPointRegister::St1);
+    assert_eq!(fpu.pop(), 1.0);
+    assert_eq!(fpu.pop(), 2.0);
+    assert_eq!(fpu.top(), 0);
+    assert!(fpu.is_empty(FloatingPointRegister::St0));
+}
+
+#[test]
+fn fpu_stack_fault() {
+    use crate::fpu::{Fpu, STATUS_C1, STATUS_INVALID};
+
+    let mut fpu = Fpu::new();
+    assert!(fpu.pop().is_nan());
+    assert_ne!(fpu.status_word() & STATUS_INVALID, 0);
+    assert_eq!(fpu.status_word() & STATUS_C1, 0);
+    // masked by default
+    assert!(!fpu.interrupt_pending());
+
+    fpu.clear_exceptions();
+    for value in 0..9 {
+        fpu.push(value as f64);
+    }
+    assert_ne!(fpu.status_word() & STATUS_INVALID, 0);
+    assert_ne!(fpu.status_word() & STATUS_C1, 0);
+
+    fpu.set_control_word(0x037e);
+    assert!(fpu.interrupt_pending());
+}
+
+#[test]
+fn fpu_arithmetic_and_compare() {
+    use crate::fpu::{Arithmetic, Fpu, STATUS_C0, STATUS_C3, STATUS_ZERO_DIVIDE};
+    use asmrs_parser::lexer::token::FloatingPointRegister;
+
+    let mut fpu = Fpu::new();
+    fpu.push(3.0);
+    fpu.push(12.0);
+
+    // fdiv st(0), st(1)
+    let divisor = fpu.st(FloatingPointRegister::St1);
+    fpu.arithmetic(Arithmetic::Div, FloatingPointRegister::St0, divisor, false);
+    assert_eq!(fpu.st(FloatingPointRegister::St0), 4.0);
+
+    fpu.compare(5.0);
+    assert_eq!(fpu.status_word() & (STATUS_C0 | STATUS_C3), STATUS_C0);
+    fpu.compare(4.0);
+    assert_eq!(fpu.status_word() & (STATUS_C0 | STATUS_C3), STATUS_C3);
+
+    // fsubrp st(1), st(0): st(1) = st(0) - st(1), pop
+    let top = fpu.st(FloatingPointRegister::St0);
+    fpu.arithmetic(
+        Arithmetic::SubReversed,
+        FloatingPointRegister::St1,
+        top,
+        true,
+    );
+    assert_eq!(fpu.st(FloatingPointRegister::St0), 1.0);
+
+    fpu.arithmetic(Arithmetic::Div, FloatingPointRegister::St0, 0.0, false);
+    assert_ne!(fpu.status_word() & STATUS_ZERO_DIVIDE, 0);
+}
+
+#[test]
+fn fpu_integer_conversion() {
+    use crate::fpu::{Fpu, STATUS_INVALID};
+
+    let mut fpu = Fpu::new();
+    fpu.push(2.5);
+    assert_eq!(fpu.to_integer(16), 2);
+    fpu.push(-3.5);
+    assert_eq!(fpu.to_integer(16), -4);
+
+    // round toward zero
+    fpu.set_control_word(0x0fff);
+    assert_eq!(fpu.to_integer(16), -3);
+
+    fpu.push(40000.0);
+    assert_eq!(fpu.to_integer(16), -32768);
+    assert_ne!(fpu.status_word() & STATUS_INVALID, 0);
+    assert_eq!(fpu.to_integer(32), 40000);
+}
+
+#[test]
+fn fpu_memory_formats() {
+    use crate::fpu::{bcd_from_bytes, real_from_bytes, real_to_bytes, Fpu};
+
+    assert_eq!(
+        real_to_bytes(1.0, 10),
+        [0, 0, 0, 0, 0, 0, 0, 0x80, 0xff, 0x3f]
+    );
+    assert_eq!(real_to_bytes(-2.5, 4), (-2.5f32).to_le_bytes());
+    for value in [-3.5, 1e300, f64::MIN_POSITIVE / 4.0, 0.0, f64::INFINITY] {
+        assert_eq!(real_from_bytes(&real_to_bytes(value, 10)), value);
+    }
+    assert!(real_from_bytes(&real_to_bytes(f64::NAN, 10)).is_nan());
+
+    let mut fpu = Fpu::new();
+    fpu.push(-1234.0);
+    let bcd = fpu.to_bcd();
+    assert_eq!(bcd, [0x34, 0x12, 0, 0, 0, 0, 0, 0, 0, 0x80]);
+    assert_eq!(bcd_from_bytes(&bcd), -1234.0);
+}
+
+#[test]
+fn fpu_machine() {
+    use crate::{
+        fpu::{STATUS_C0, STATUS_C3},
+        machine::Machine,
+    };
+    use asmrs_assembler::assembler::{assemble, Options, Target};
+    use asmrs_parser::lexer::{token::Cpu, tokenize};
+
+    let source = "\
+fld dword [0x600]
+fild word [0x604]
+fmulp st(1), st
+fsqrt
+fistp word [0x606]
+fld1
+fldz
+fcompp
+fnstsw [0x608]
+fldpi
+fld st(0)
+fadd st, st(1)
+fstp qword [0x60a]
+fbstp tword [0x612]
+hlt
+";
+    let tokens = tokenize(source.to_string()).unwrap();
+    let options = Options {
+        cpu: Cpu::I8086,
+        target: Target::Flat { origin: 0x500 },
+    };
+    let image = assemble(&tokens, &options).unwrap().image();
+
+    let mut machine = Machine::new(Cpu::I8086);
+    machine.memory.load(0x500, &image);
+    machine.memory.load(0x600, &2.25f32.to_le_bytes());
+    machine.memory.load(0x604, &4i16.to_le_bytes());
+    machine.registers.ip = 0x500;
+
+    machine.run(100).unwrap();
+    assert!(machine.halted);
+
+    // sqrt(2.25 * 4)
+    assert_eq!(machine.memory.read_word(0x606), 3);
+    // 0 < 1: C0 set, stack empty again
+    let status = machine.memory.read_word(0x608);
+    assert_eq!(status & (STATUS_C0 | STATUS_C3), STATUS_C0);
+    assert_eq!(status >> 11 & 0b111, 0);
+
+    let bytes = (0..8)
+        .map(|index| machine.memory.read_byte(0x60a + index))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        f64::from_le_bytes(bytes.try_into().unwrap()),
+        2.0 * std::f64::consts::PI
+    );
+    // pi rounded to an integer in packed BCD
+    assert_eq!(machine.memory.read_word(0x612), 0x0003);
+    assert!(machine
+        .fpu
+        .is_empty(asmrs_parser::lexer::token::FloatingPointRegister::St0));
+}
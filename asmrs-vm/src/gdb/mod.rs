@@ -0,0 +1,600 @@
+use crate::{
+    journal::Journal,
+    machine::{Access, AccessKind, Machine},
+    memory::{address, MEMORY_SIZE},
+};
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::TcpStream,
+    ops::Range,
+};
+
+mod test;
+
+/// Registers of gdb's i386 register file: EAX, ECX, EDX, EBX, ESP, EBP, ESI, EDI, EIP,
+/// EFLAGS, CS, SS, DS, ES, FS, GS. Every register is sent as 32 bits; FS and GS do not
+/// exist before the 386 and read as zero.
+const REGISTER_COUNT: usize = 16;
+
+/// Instructions executed between checks for an interrupt from the front-end
+const POLL_INTERVAL: u64 = 10_000;
+
+/// Signal numbers used in stop replies
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+
+/// Accesses a watchpoint reacts to (`Z2`, `Z3` and `Z4`)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Write,
+    Read,
+    Access,
+}
+
+impl WatchKind {
+    fn matches(&self, kind: AccessKind) -> bool {
+        match self {
+            WatchKind::Write => kind == AccessKind::Write,
+            WatchKind::Read => kind == AccessKind::Read,
+            WatchKind::Access => true,
+        }
+    }
+
+    /// Stop reason reported to the front-end
+    fn reason(&self) -> &str {
+        match self {
+            WatchKind::Write => "watch",
+            WatchKind::Read => "rwatch",
+            WatchKind::Access => "awatch",
+        }
+    }
+}
+
+/// Breakpoint set with `Z0` (software) or `Z1` (hardware)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    /// Linear address
+    pub address: usize,
+    pub hardware: bool,
+}
+
+impl Breakpoint {
+    /// Stop reason reported to the front-end, as announced in `qSupported`
+    fn reason(&self) -> &str {
+        if self.hardware {
+            "hwbreak"
+        } else {
+            "swbreak"
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    /// Linear address range
+    pub range: Range<usize>,
+    pub kind: WatchKind,
+}
+
+/// What the connection has to do after a packet was handled
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Send the reply
+    Reply(String),
+    /// Execute one instruction (`step`) or until something stops the machine, then send the
+    /// stop reply
+    Resume { step: bool },
+    /// Undo one instruction or until a breakpoint or watchpoint, then send the stop reply
+    Reverse { step: bool },
+    /// Close the connection, after replying if a reply is given
+    Close(Option<String>),
+}
+
+/// GDB remote serial protocol stub for a machine.
+///
+/// Addresses in memory packets, breakpoints and watchpoints are linear (segment * 16 +
+/// offset) so the front-end sees the whole 1 MiB address space; EIP holds IP only.
+/// Software and hardware breakpoints are both kept by the stub instead of patching memory.
+pub struct GdbStub {
+    pub machine: Machine,
+    pub breakpoints: Vec<Breakpoint>,
+    pub watchpoints: Vec<Watchpoint>,
+    /// History for reverse execution (`bs` and `bc`)
+    pub journal: Journal,
+}
+
+impl GdbStub {
+    pub fn new(machine: Machine) -> GdbStub {
+        Self {
+            machine,
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            journal: Journal::default(),
+        }
+    }
+
+    /// Handles the payload of one packet.
+    pub fn handle(&mut self, packet: &str) -> Action {
+        let reply = |text: &str| Action::Reply(text.to_string());
+        // payloads are decoded lossily, the command may be any character
+        let command_length = packet.chars().next().map_or(0, char::len_utf8);
+        let (command, arguments) = packet.split_at(command_length);
+
+        match command {
+            "?" => Action::Reply(format!("S{:02x}", SIGTRAP)),
+            "g" => Action::Reply(
+                (0..REGISTER_COUNT)
+                    .map(|register| hex_u32(self.register(register)))
+                    .collect(),
+            ),
+            "G" => {
+                let values = (0..REGISTER_COUNT)
+                    .map(|register| parse_register(arguments.get(register * 8..register * 8 + 8)?))
+                    .collect::<Option<Vec<_>>>();
+
+                match values {
+                    Some(values) if arguments.len() == REGISTER_COUNT * 8 => {
+                        for (register, value) in values.into_iter().enumerate() {
+                            self.set_register(register, value);
+                        }
+                        reply("OK")
+                    }
+                    _ => reply("E01"),
+                }
+            }
+            "p" => match usize::from_str_radix(arguments, 16) {
+                Ok(register) if register < REGISTER_COUNT => {
+                    Action::Reply(hex_u32(self.register(register)))
+                }
+                _ => reply("E01"),
+            },
+            "P" => {
+                let parsed = arguments.split_once('=').and_then(|(register, value)| {
+                    Some((
+                        usize::from_str_radix(register, 16).ok()?,
+                        parse_register(value)?,
+                    ))
+                });
+
+                match parsed {
+                    Some((register, value)) if register < REGISTER_COUNT => {
+                        self.set_register(register, value);
+                        reply("OK")
+                    }
+                    _ => reply("E01"),
+                }
+            }
+            "m" => match parse_range(arguments) {
+                Some(range) => Action::Reply(
+                    range
+                        .map(|address| format!("{:02x}", self.machine.memory.read_byte(address)))
+                        .collect(),
+                ),
+                None => reply("E01"),
+            },
+            "M" => {
+                let parsed = arguments
+                    .split_once(':')
+                    .and_then(|(range, data)| Some((parse_range(range)?, parse_hex(data)?)));
+
+                match parsed {
+                    Some((range, data)) if range.len() == data.len() => {
+                        self.machine.memory.load(range.start, &data);
+                        reply("OK")
+                    }
+                    _ => reply("E01"),
+                }
+            }
+            "c" | "s" => {
+                if !arguments.is_empty() {
+                    match parse_u32(arguments) {
+                        Some(address) if (address as usize) < MEMORY_SIZE => {
+                            self.jump(address as usize)
+                        }
+                        _ => return reply("E01"),
+                    }
+                }
+                Action::Resume {
+                    step: command == "s",
+                }
+            }
+            "b" if arguments == "s" || arguments == "c" => Action::Reverse {
+                step: arguments == "s",
+            },
+            "Z" | "z" => self.point(command == "Z", arguments),
+            "H" | "T" => reply("OK"),
+            "D" => Action::Close(Some("OK".to_string())),
+            "k" => Action::Close(None),
+            "q" if arguments.starts_with("Supported") => {
+                reply("PacketSize=1000;swbreak+;hwbreak+;ReverseStep+;ReverseContinue+")
+            }
+            "q" if arguments == "Attached" => reply("1"),
+            _ => reply(""),
+        }
+    }
+
+    /// Inserts or removes a breakpoint or watchpoint: `TYPE,ADDRESS,KIND`.
+    fn point(&mut self, insert: bool, arguments: &str) -> Action {
+        let mut fields = arguments.split(',');
+        let (Some(kind), Some(address), Some(length)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            return Action::Reply("E01".to_string());
+        };
+        let (Some(address), Some(length)) = (parse_u32(address), parse_u32(length)) else {
+            return Action::Reply("E01".to_string());
+        };
+        let address = address as usize;
+
+        let watch = match kind {
+            "0" | "1" => {
+                let breakpoint = Breakpoint {
+                    address,
+                    hardware: kind == "1",
+                };
+                if insert {
+                    self.breakpoints.push(breakpoint);
+                } else if let Some(index) = self.breakpoints.iter().position(|b| *b == breakpoint) {
+                    self.breakpoints.remove(index);
+                }
+                return Action::Reply("OK".to_string());
+            }
+            "2" => WatchKind::Write,
+            "3" => WatchKind::Read,
+            "4" => WatchKind::Access,
+            _ => return Action::Reply(String::new()),
+        };
+
+        let watchpoint = Watchpoint {
+            range: address..address + (length as usize).max(1),
+            kind: watch,
+        };
+        if insert {
+            self.watchpoints.push(watchpoint);
+        } else if let Some(index) = self.watchpoints.iter().position(|w| *w == watchpoint) {
+            self.watchpoints.remove(index);
+        }
+
+        Action::Reply("OK".to_string())
+    }
+
+    /// Executes one instruction, or instructions until a breakpoint, watchpoint, `hlt`, an
+    /// error or `interrupted` (polled every few thousand instructions) stops the machine.
+    /// Returns the stop reply; errors are preceded by a console output packet payload
+    /// (`O...`) describing them.
+    pub fn resume(&mut self, step: bool, mut interrupted: impl FnMut() -> bool) -> Vec<String> {
+        let mut executed = 0u64;
+
+        loop {
+            if self.machine.halted && !self.machine.waiting() {
+                return vec!["W00".to_string()];
+            }
+
+            if let Err(error) = self.journal.step(&mut self.machine) {
+                let message = format!("{}\n", error);
+                return vec![
+                    format!("O{}", hex_bytes(message.as_bytes())),
+                    format!("S{:02x}", SIGILL),
+                ];
+            }
+
+            if let Some(reply) = self.watchpoint(self.machine.accesses()) {
+                return vec![reply];
+            }
+
+            if let Some(reply) = self.breakpoint() {
+                return vec![reply];
+            }
+            if step {
+                return vec![format!("S{:02x}", SIGTRAP)];
+            }
+
+            executed += 1;
+            if executed.is_multiple_of(POLL_INTERVAL) && interrupted() {
+                return vec![format!("S{:02x}", SIGINT)];
+            }
+        }
+    }
+
+    /// Undoes one instruction, or instructions until a breakpoint or a write to a
+    /// watchpoint. Reaching the start of the history is reported as `replaylog:begin`.
+    pub fn reverse(&mut self, step: bool) -> String {
+        loop {
+            let Some(entry) = self.journal.undo(&mut self.machine) else {
+                return format!("T{:02x}replaylog:begin;", SIGTRAP);
+            };
+
+            if let Some(reply) = self.watchpoint(&entry.writes) {
+                return reply;
+            }
+
+            if let Some(reply) = self.breakpoint() {
+                return reply;
+            }
+            if step {
+                return format!("S{:02x}", SIGTRAP);
+            }
+        }
+    }
+
+    /// Stop reply for a breakpoint at CS:IP
+    fn breakpoint(&self) -> Option<String> {
+        let registers = &self.machine.registers;
+        let ip = address(registers.cs, registers.ip);
+        self.breakpoints
+            .iter()
+            .find(|breakpoint| breakpoint.address == ip)
+            .map(|breakpoint| format!("T{:02x}{}:;", SIGTRAP, breakpoint.reason()))
+    }
+
+    /// Continues at a linear address: within the current code segment when it reaches it,
+    /// otherwise from the segment of its paragraph.
+    fn jump(&mut self, linear: usize) {
+        let registers = &mut self.machine.registers;
+        let base = address(registers.cs, 0);
+        match linear.checked_sub(base) {
+            Some(offset) if offset <= 0xffff => registers.ip = offset as u16,
+            _ => {
+                registers.cs = (linear >> 4) as u16;
+                registers.ip = (linear & 0xf) as u16;
+            }
+        }
+    }
+
+    /// Stop reply for the first of `accesses` hitting a watchpoint
+    fn watchpoint(&self, accesses: &[Access]) -> Option<String> {
+        accesses.iter().find_map(|access| {
+            let accessed = access.address..access.address + access.size as usize;
+            self.watchpoints
+                .iter()
+                .find(|watchpoint| {
+                    watchpoint.kind.matches(access.kind)
+                        && accessed.start < watchpoint.range.end
+                        && watchpoint.range.start < accessed.end
+                })
+                .map(|watchpoint| {
+                    format!(
+                        "T{:02x}{}:{:x};",
+                        SIGTRAP,
+                        watchpoint.kind.reason(),
+                        access.address.max(watchpoint.range.start)
+                    )
+                })
+        })
+    }
+
+    fn register(&self, register: usize) -> u32 {
+        let registers = &self.machine.registers;
+        let value = match register {
+            0 => registers.ax,
+            1 => registers.cx,
+            2 => registers.dx,
+            3 => registers.bx,
+            4 => registers.sp,
+            5 => registers.bp,
+            6 => registers.si,
+            7 => registers.di,
+            8 => registers.ip,
+            9 => registers.flags,
+            10 => registers.cs,
+            11 => registers.ss,
+            12 => registers.ds,
+            13 => registers.es,
+            _ => 0,
+        };
+        value as u32
+    }
+
+    /// Writes the low 16 bits of `value`; writes to FS and GS are ignored.
+    fn set_register(&mut self, register: usize, value: u32) {
+        let registers = &mut self.machine.registers;
+        let value = value as u16;
+        match register {
+            0 => registers.ax = value,
+            1 => registers.cx = value,
+            2 => registers.dx = value,
+            3 => registers.bx = value,
+            4 => registers.sp = value,
+            5 => registers.bp = value,
+            6 => registers.si = value,
+            7 => registers.di = value,
+            8 => registers.ip = value,
+            9 => registers.flags = value | 0x0002,
+            10 => registers.cs = value,
+            11 => registers.ss = value,
+            12 => registers.ds = value,
+            13 => registers.es = value,
+            _ => {}
+        }
+    }
+
+    /// Serves one front-end on `stream` until it detaches, kills the machine or disconnects.
+    pub fn serve(&mut self, mut stream: TcpStream) -> io::Result<()> {
+        while let Some(packet) = read_packet(&mut stream)? {
+            let replies = match self.handle(&packet) {
+                Action::Reply(reply) => vec![reply],
+                Action::Resume { step } => {
+                    // a 0x03 byte from the front-end interrupts a running machine
+                    let probe = stream.try_clone()?;
+                    self.resume(step, || interrupt_pending(&probe))
+                }
+                Action::Reverse { step } => vec![self.reverse(step)],
+                Action::Close(reply) => {
+                    if let Some(reply) = reply {
+                        send_packet(&mut stream, &reply)?;
+                    }
+                    return Ok(());
+                }
+            };
+
+            for reply in replies {
+                send_packet(&mut stream, &reply)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Frames a payload: `$payload#checksum`, escaping `#`, `$`, `}` and `*`.
+pub fn frame(payload: &str) -> Vec<u8> {
+    let mut escaped = Vec::new();
+    for byte in payload.bytes() {
+        if matches!(byte, b'#' | b'$' | b'}' | b'*') {
+            escaped.extend([b'}', byte ^ 0x20]);
+        } else {
+            escaped.push(byte);
+        }
+    }
+
+    let checksum = escaped
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    let mut framed = vec![b'$'];
+    framed.extend(escaped);
+    framed.extend(format!("#{:02x}", checksum).bytes());
+    framed
+}
+
+/// Reads the next packet, acknowledging it (`+`) or asking for retransmission (`-`) when
+/// the checksum does not match. Acknowledgements and interrupts outside a packet are
+/// skipped. Returns `None` when the connection is closed.
+pub fn read_packet(stream: &mut (impl Read + Write)) -> io::Result<Option<String>> {
+    let mut byte = [0u8];
+
+    loop {
+        // skip to the start of a packet
+        loop {
+            if stream.read(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            if byte[0] == b'$' {
+                break;
+            }
+        }
+
+        let mut payload = Vec::new();
+        let mut checksum = 0u8;
+        loop {
+            if stream.read(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            if byte[0] == b'#' {
+                break;
+            }
+            checksum = checksum.wrapping_add(byte[0]);
+            payload.push(byte[0]);
+        }
+
+        let mut expected = [0u8; 2];
+        stream.read_exact(&mut expected)?;
+        let expected = std::str::from_utf8(&expected)
+            .ok()
+            .and_then(|text| u8::from_str_radix(text, 16).ok());
+
+        if expected != Some(checksum) {
+            stream.write_all(b"-")?;
+            continue;
+        }
+        stream.write_all(b"+")?;
+
+        return Ok(Some(
+            String::from_utf8_lossy(&unescape(&payload)).into_owned(),
+        ));
+    }
+}
+
+/// Sends a packet and waits for the acknowledgement, resending on `-`.
+pub fn send_packet(stream: &mut (impl Read + Write), payload: &str) -> io::Result<()> {
+    let framed = frame(payload);
+    let mut byte = [0u8];
+
+    loop {
+        stream.write_all(&framed)?;
+        stream.flush()?;
+
+        loop {
+            if stream.read(&mut byte)? == 0 {
+                return Err(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "connection closed",
+                ));
+            }
+            match byte[0] {
+                b'+' => return Ok(()),
+                b'-' => break,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Whether the front-end sent an interrupt (0x03) without blocking
+fn interrupt_pending(stream: &TcpStream) -> bool {
+    let mut byte = [0u8];
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let pending = matches!(stream.peek(&mut byte), Ok(1) if byte[0] == 0x03);
+    if pending {
+        let mut reader = stream;
+        let _ = reader.read(&mut byte);
+    }
+    let _ = stream.set_nonblocking(false);
+    pending
+}
+
+fn unescape(payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut escaped = false;
+    for byte in payload {
+        match (escaped, byte) {
+            (false, b'}') => escaped = true,
+            (true, _) => {
+                bytes.push(byte ^ 0x20);
+                escaped = false;
+            }
+            _ => bytes.push(*byte),
+        }
+    }
+    bytes
+}
+
+/// 32-bit register value in target (little-endian) byte order
+fn hex_u32(value: u32) -> String {
+    hex_bytes(&value.to_le_bytes())
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Parses a 32-bit register value in target byte order.
+fn parse_register(text: &str) -> Option<u32> {
+    let bytes: [u8; 4] = parse_hex(text)?.try_into().ok()?;
+    Some(u32::from_le_bytes(bytes))
+}
+
+fn parse_u32(text: &str) -> Option<u32> {
+    u32::from_str_radix(text, 16).ok()
+}
+
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+/// Parses `ADDRESS,LENGTH`, limited to the 1 MiB address space.
+fn parse_range(text: &str) -> Option<Range<usize>> {
+    let (start, length) = text.split_once(',')?;
+    let start = usize::from_str_radix(start, 16).ok()?;
+    let length = usize::from_str_radix(length, 16).ok()?;
+    let end = start.checked_add(length)?;
+    (end <= MEMORY_SIZE).then_some(start..end)
+}
@@ -0,0 +1,180 @@
+/// Stub for a machine with `code` at 0000:0100 and the stack at 0000:FFFE
+#[cfg(test)]
+fn stub(code: &[u8]) -> crate::gdb::GdbStub {
+    use crate::{gdb::GdbStub, machine::test_machine};
+
+    GdbStub::new(test_machine(code))
+}
+
+/// Connection reading scripted input and collecting everything written
+#[cfg(test)]
+struct Connection {
+    input: std::io::Cursor<Vec<u8>>,
+    output: Vec<u8>,
+}
+
+#[cfg(test)]
+impl std::io::Read for Connection {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        self.input.read(buffer)
+    }
+}
+
+#[cfg(test)]
+impl std::io::Write for Connection {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        self.output.write(buffer)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn gdb_packets() {
+    use crate::gdb::{frame, read_packet, send_packet};
+
+    assert_eq!(frame("OK"), b"$OK#9a");
+    assert_eq!(frame("a#b"), b"$a}\x03b#43");
+
+    // an ack left from the last reply, a corrupted packet, then the resent one
+    let mut connection = Connection {
+        input: std::io::Cursor::new(b"+$g#00$g#67$m1}\x03,2#00".to_vec()),
+        output: Vec::new(),
+    };
+    assert_eq!(read_packet(&mut connection).unwrap(), Some("g".to_string()));
+    assert_eq!(connection.output, b"-+");
+    assert_eq!(read_packet(&mut connection).unwrap(), None);
+
+    let mut connection = Connection {
+        input: std::io::Cursor::new(b"-+".to_vec()),
+        output: Vec::new(),
+    };
+    send_packet(&mut connection, "OK").unwrap();
+    assert_eq!(connection.output, b"$OK#9a$OK#9a");
+}
+
+#[test]
+fn gdb_registers_memory() {
+    use crate::gdb::Action;
+
+    let mut stub = stub(&[0x90]);
+    stub.machine.registers.ax = 0x1234;
+    stub.machine.registers.cs = 0xf000;
+
+    let Action::Reply(registers) = stub.handle("g") else {
+        panic!("expected a reply");
+    };
+    assert_eq!(registers.len(), 16 * 8);
+    assert_eq!(&registers[..8], "34120000");
+    assert_eq!(&registers[64..72], "00010000");
+    assert_eq!(&registers[80..88], "00f00000");
+
+    assert_eq!(stub.handle("P1=cdab0000"), Action::Reply("OK".to_string()));
+    assert_eq!(stub.machine.registers.cx, 0xabcd);
+    assert_eq!(stub.handle("p1"), Action::Reply("cdab0000".to_string()));
+    assert_eq!(stub.handle("p10"), Action::Reply("E01".to_string()));
+
+    let mut registers = "0".repeat(16 * 8);
+    registers.replace_range(24..32, "22110000");
+    assert_eq!(
+        stub.handle(&format!("G{}", registers)),
+        Action::Reply("OK".to_string())
+    );
+    assert_eq!(stub.machine.registers.bx, 0x1122);
+    assert_eq!(stub.machine.registers.ax, 0);
+    assert_eq!(stub.machine.registers.flags, 0x0002);
+
+    assert_eq!(
+        stub.handle("M200,3:010203"),
+        Action::Reply("OK".to_string())
+    );
+    assert_eq!(
+        stub.handle("m1ff,5"),
+        Action::Reply("f0010203f0".to_string())
+    );
+    assert_eq!(stub.handle("mfffff,2"), Action::Reply("E01".to_string()));
+    assert_eq!(
+        stub.handle("m1,ffffffffffffffff"),
+        Action::Reply("E01".to_string())
+    );
+    // lossily decoded payloads may start with a multi-byte character
+    assert_eq!(stub.handle("\u{fffd}00"), Action::Reply(String::new()));
+    assert_eq!(stub.handle("M200,2:01"), Action::Reply("E01".to_string()));
+    assert_eq!(stub.handle("vMustReplyEmpty"), Action::Reply(String::new()));
+}
+
+#[test]
+fn gdb_execution() {
+    use crate::gdb::Action;
+
+    // mov sp, 200h / call 108h / hlt / nop / mov ax, 1234h / ret
+    let mut stub = stub(&[
+        0xbc, 0x00, 0x02, 0xe8, 0x02, 0x00, 0xf4, 0x90, 0xb8, 0x34, 0x12, 0xc3,
+    ]);
+
+    assert_eq!(stub.handle("s"), Action::Resume { step: true });
+    assert_eq!(stub.resume(true, || false), ["S05"]);
+    assert_eq!(stub.machine.registers.ip, 0x103);
+
+    assert_eq!(stub.handle("Z0,10b,1"), Action::Reply("OK".to_string()));
+    assert_eq!(stub.handle("Z2,1fe,2"), Action::Reply("OK".to_string()));
+    assert_eq!(stub.handle("c"), Action::Resume { step: false });
+    assert_eq!(stub.resume(false, || false), ["T05watch:1fe;"]);
+    assert_eq!(stub.machine.registers.ip, 0x108);
+
+    assert_eq!(stub.resume(false, || false), ["T05swbreak:;"]);
+    assert_eq!(stub.machine.registers.ip, 0x10b);
+
+    assert_eq!(stub.handle("z0,10b,1"), Action::Reply("OK".to_string()));
+    assert_eq!(stub.handle("z2,1fe,2"), Action::Reply("OK".to_string()));
+    assert!(stub.breakpoints.is_empty() && stub.watchpoints.is_empty());
+    assert_eq!(stub.resume(false, || false), ["W00"]);
+
+    // a failing instruction (an undefined opcode) is reported on the console, then as SIGILL
+    let mut stub = self::stub(&[0x0f]);
+    let replies = stub.resume(false, || false);
+    assert!(replies[0].starts_with('O'));
+    assert_eq!(replies[1], "S04");
+
+    // hardware breakpoints are reported as such
+    let mut stub = self::stub(&[0x90, 0x90, 0xf4]);
+    stub.handle("Z1,101,1");
+    assert_eq!(stub.resume(false, || false), ["T05hwbreak:;"]);
+
+    // addresses to continue at are linear, CS changes when IP cannot reach them
+    assert_eq!(stub.handle("s102"), Action::Resume { step: true });
+    assert_eq!(stub.machine.registers.ip, 0x102);
+    stub.handle("c12345");
+    assert_eq!(
+        (stub.machine.registers.cs, stub.machine.registers.ip),
+        (0x1234, 0x5)
+    );
+    assert_eq!(stub.handle("c100000"), Action::Reply("E01".to_string()));
+
+    // jmp $ only stops when interrupted
+    let mut stub = self::stub(&[0xeb, 0xfe]);
+    assert_eq!(stub.resume(false, || true), ["S02"]);
+}
+
+#[test]
+fn gdb_reverse() {
+    use crate::gdb::Action;
+
+    // mov sp, 200h / call 108h / hlt / nop / mov ax, 1234h / ret
+    let mut stub = stub(&[
+        0xbc, 0x00, 0x02, 0xe8, 0x02, 0x00, 0xf4, 0x90, 0xb8, 0x34, 0x12, 0xc3,
+    ]);
+    assert_eq!(stub.resume(false, || false), ["W00"]);
+
+    assert_eq!(stub.handle("bs"), Action::Reverse { step: true });
+    assert_eq!(stub.reverse(true), "S05");
+    assert_eq!(stub.machine.registers.ip, 0x106);
+
+    stub.handle("Z2,1fe,2");
+    assert_eq!(stub.reverse(false), "T05watch:1fe;");
+    assert_eq!(stub.machine.registers.ip, 0x103);
+    assert_eq!(stub.reverse(false), "T05replaylog:begin;");
+    assert_eq!(stub.machine.registers.ip, 0x100);
+}
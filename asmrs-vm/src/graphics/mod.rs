@@ -0,0 +1,398 @@
+use crate::{
+    bios::{INSTRUCTIONS_PER_TICK, VIDEO_MODE},
+    machine::Machine,
+    memory::{address, Memory},
+    pic::Pic,
+    port::PortDevice,
+    screen::TEXT_SEGMENT,
+};
+use std::{cell::RefCell, rc::Rc};
+
+mod test;
+
+/// Segment of the VGA graphics memory (mode 13h)
+pub const GRAPHICS_SEGMENT: u16 = 0xa000;
+
+/// DAC palette index for reads (writes) and DAC state (reads)
+pub const DAC_READ_INDEX_PORT: u16 = 0x3c7;
+/// DAC palette index for writes
+pub const DAC_WRITE_INDEX_PORT: u16 = 0x3c8;
+/// DAC palette data: red, green and blue in turn, 6 bits each
+pub const DAC_DATA_PORT: u16 = 0x3c9;
+/// CGA mode control register
+pub const CGA_MODE_PORT: u16 = 0x3d8;
+/// CGA color select register: background or foreground color, intensity and palette
+pub const CGA_COLOR_PORT: u16 = 0x3d9;
+/// Input status: bit 3 is set during the vertical retrace, bit 0 while the display is off
+pub const INPUT_STATUS_PORT: u16 = 0x3da;
+
+/// Machine steps per frame: the VGA refreshes 70 times a second, 3.85 frames per timer
+/// tick.
+pub const STEPS_PER_FRAME: u64 = INSTRUCTIONS_PER_TICK * 182 / 700;
+/// Steps at the end of each frame spent in the vertical retrace
+const RETRACE_STEPS: u64 = STEPS_PER_FRAME / 16;
+
+/// Red, green and blue of the 16 CGA colors
+pub const CGA_COLORS: [[u8; 3]; 16] = [
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0xaa],
+    [0x00, 0xaa, 0x00],
+    [0x00, 0xaa, 0xaa],
+    [0xaa, 0x00, 0x00],
+    [0xaa, 0x00, 0xaa],
+    [0xaa, 0x55, 0x00],
+    [0xaa, 0xaa, 0xaa],
+    [0x55, 0x55, 0x55],
+    [0x55, 0x55, 0xff],
+    [0x55, 0xff, 0x55],
+    [0x55, 0xff, 0xff],
+    [0xff, 0x55, 0x55],
+    [0xff, 0x55, 0xff],
+    [0xff, 0xff, 0x55],
+    [0xff, 0xff, 0xff],
+];
+
+/// Levels of the gray ramp in the default VGA palette
+const GRAYS: [u8; 16] = [0, 5, 8, 11, 14, 17, 20, 24, 28, 32, 36, 40, 45, 50, 56, 63];
+
+/// Image file format of exported frames
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    /// Binary portable pixmap (P6)
+    Ppm,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Ppm => "ppm",
+        }
+    }
+}
+
+/// Picture shown in a graphics mode
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    /// Red, green and blue of each pixel, row by row
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl Frame {
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn encode(&self, format: ImageFormat) -> Vec<u8> {
+        match format {
+            ImageFormat::Png => self.png(),
+            ImageFormat::Ppm => self.ppm(),
+        }
+    }
+
+    pub fn ppm(&self) -> Vec<u8> {
+        let mut bytes = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        bytes.extend(self.pixels.iter().flatten());
+        bytes
+    }
+
+    /// PNG with uncompressed (stored) deflate blocks, which every decoder reads.
+    pub fn png(&self) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        // 8 bits per channel, RGB, deflate, no filter method, no interlace
+        header.extend([8, 2, 0, 0, 0]);
+
+        // every scan line starts with filter type 0
+        let mut data = Vec::with_capacity(self.height * (self.width * 3 + 1));
+        for row in self.pixels.chunks(self.width.max(1)) {
+            data.push(0);
+            data.extend(row.iter().flatten());
+        }
+
+        let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut bytes, b"IHDR", &header);
+        chunk(&mut bytes, b"IDAT", &zlib_stored(&data));
+        chunk(&mut bytes, b"IEND", &[]);
+        bytes
+    }
+}
+
+/// Appends a PNG chunk with its length and CRC.
+fn chunk(bytes: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    bytes.extend((data.len() as u32).to_be_bytes());
+    let start = bytes.len();
+    bytes.extend(kind);
+    bytes.extend(data);
+    let crc = crc32(&bytes[start..]);
+    bytes.extend(crc.to_be_bytes());
+}
+
+/// zlib stream of `data` in stored blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        bytes.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let length = block.len() as u16;
+        bytes.push(last as u8);
+        bytes.extend(length.to_le_bytes());
+        bytes.extend((!length).to_le_bytes());
+        bytes.extend(block);
+    }
+    bytes.extend(adler32(data).to_be_bytes());
+    bytes
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let crc = data.iter().fold(0xffff_ffffu32, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    });
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + *byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    b << 16 | a
+}
+
+/// Palette the VGA BIOS loads: the 16 CGA colors, a gray ramp, then (approximating the
+/// hue rings of the real palette) a 6x6x6 color cube. Components have 6 bits.
+pub fn default_palette() -> [[u8; 3]; 256] {
+    let mut palette = [[0; 3]; 256];
+    for (index, color) in CGA_COLORS.iter().enumerate() {
+        palette[index] = color.map(|component| component >> 2);
+    }
+    for (index, gray) in GRAYS.iter().enumerate() {
+        palette[16 + index] = [*gray; 3];
+    }
+    let level = |value: usize| (value * 63 / 5) as u8;
+    for index in 0..216 {
+        palette[32 + index] = [level(index / 36), level(index / 6 % 6), level(index % 6)];
+    }
+    palette
+}
+
+/// Whether the BIOS video `mode` is one of the graphics modes shown by `Graphics`
+pub fn graphics_mode(mode: u8) -> bool {
+    matches!(mode & 0x7f, 0x04..=0x06 | 0x13)
+}
+
+/// Blanks the video memory of a graphics mode.
+pub fn clear(machine: &mut Machine, mode: u8) {
+    match mode {
+        0x04..=0x06 => machine.write_block(address(TEXT_SEGMENT, 0), &[0; 0x4000]),
+        0x13 => machine.write_block(address(GRAPHICS_SEGMENT, 0), &[0; 320 * 200]),
+        _ => {}
+    }
+}
+
+/// Video card registers of the graphics modes: the VGA DAC palette and the CGA color
+/// select register, with the retrace status counting frames on the machine's clock.
+/// The mode is the one the BIOS recorded (`int 10h` function 00h).
+#[derive(Clone, Debug)]
+pub struct Graphics {
+    /// DAC palette: red, green and blue with 6 bits each
+    pub palette: [[u8; 3]; 256],
+    /// CGA color select register
+    pub color_select: u8,
+    /// CGA mode control register
+    pub mode_control: u8,
+    read_index: u8,
+    write_index: u8,
+    /// Component of the current DAC color the next data access transfers
+    component: usize,
+    /// The last index written was the read index
+    reading: bool,
+    /// Machine clock at the last update
+    clock: u64,
+}
+
+impl Graphics {
+    pub fn new() -> Graphics {
+        Self {
+            palette: default_palette(),
+            color_select: 0,
+            mode_control: 0,
+            read_index: 0,
+            write_index: 0,
+            component: 0,
+            reading: false,
+            clock: 0,
+        }
+    }
+
+    /// Frames completed so far
+    pub fn frames(&self) -> u64 {
+        self.clock / STEPS_PER_FRAME
+    }
+
+    /// Whether the display is in its vertical retrace
+    pub fn retrace(&self) -> bool {
+        self.clock % STEPS_PER_FRAME >= STEPS_PER_FRAME - RETRACE_STEPS
+    }
+
+    /// Picture of the graphics mode the BIOS set, or nothing in text modes
+    pub fn frame(&self, memory: &Memory) -> Option<Frame> {
+        match memory.read_byte(VIDEO_MODE) & 0x7f {
+            0x04 | 0x05 => Some(self.cga_frame(memory, 320, 2)),
+            0x06 => Some(self.cga_frame(memory, 640, 1)),
+            0x13 => {
+                let pixels = memory
+                    .read(address(GRAPHICS_SEGMENT, 0), 320 * 200)
+                    .iter()
+                    .map(|color| self.palette[*color as usize].map(scale))
+                    .collect();
+                Some(Frame {
+                    width: 320,
+                    height: 200,
+                    pixels,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// CGA picture of `width` pixels with `bits` per pixel; even lines are at B800:0000,
+    /// odd lines at B800:2000.
+    fn cga_frame(&self, memory: &Memory, width: usize, bits: usize) -> Frame {
+        let colors = self.cga_colors(memory.read_byte(VIDEO_MODE) & 0x7f);
+        let per_byte = 8 / bits;
+        let mut pixels = Vec::with_capacity(width * 200);
+
+        for y in 0..200 {
+            let line = address(TEXT_SEGMENT, 0) + (y & 1) * 0x2000 + (y >> 1) * 80;
+            for x in 0..width {
+                let byte = memory.read_byte(line + x / per_byte);
+                let shift = 8 - bits * (x % per_byte + 1);
+                let color = (byte >> shift) & ((1 << bits) - 1);
+                pixels.push(CGA_COLORS[colors[color as usize] as usize]);
+            }
+        }
+
+        Frame {
+            width,
+            height: 200,
+            pixels,
+        }
+    }
+
+    /// Moves to the next DAC component; returns whether the color is complete.
+    fn next_component(&mut self) -> bool {
+        self.component = (self.component + 1) % 3;
+        self.component == 0
+    }
+
+    /// CGA colors of the pixel values in `mode`, as selected by the color select register
+    fn cga_colors(&self, mode: u8) -> [u8; 4] {
+        let color = self.color_select & 0x0f;
+        if mode == 0x06 {
+            return [0, color, 0, 0];
+        }
+
+        let intensity = (self.color_select >> 1) & 0x08;
+        let [first, second, third] = match (mode, self.color_select & 0x20 != 0) {
+            // the monochrome burst mode shows cyan, red and white
+            (0x05, _) => [3, 4, 7],
+            (_, true) => [3, 5, 7],
+            (_, false) => [2, 4, 6],
+        };
+        [
+            color,
+            first | intensity,
+            second | intensity,
+            third | intensity,
+        ]
+    }
+}
+
+impl Default for Graphics {
+    fn default() -> Self {
+        Graphics::new()
+    }
+}
+
+impl PortDevice for Graphics {
+    fn read_byte(&mut self, port: u16) -> u8 {
+        match port {
+            DAC_READ_INDEX_PORT if self.reading => 0x00,
+            DAC_READ_INDEX_PORT => 0x03,
+            DAC_WRITE_INDEX_PORT => self.write_index,
+            DAC_DATA_PORT => {
+                let value = self.palette[self.read_index as usize][self.component];
+                if self.next_component() {
+                    self.read_index = self.read_index.wrapping_add(1);
+                }
+                value
+            }
+            CGA_MODE_PORT => self.mode_control,
+            CGA_COLOR_PORT => self.color_select,
+            // the display is off during both retraces; horizontal ones come every step
+            _ => {
+                let retrace = self.retrace();
+                (retrace as u8) << 3 | (retrace || self.clock & 1 != 0) as u8
+            }
+        }
+    }
+
+    fn write_byte(&mut self, port: u16, value: u8) {
+        match port {
+            DAC_READ_INDEX_PORT => {
+                self.read_index = value;
+                self.component = 0;
+                self.reading = true;
+            }
+            DAC_WRITE_INDEX_PORT => {
+                self.write_index = value;
+                self.component = 0;
+                self.reading = false;
+            }
+            DAC_DATA_PORT => {
+                self.palette[self.write_index as usize][self.component] = value & 0x3f;
+                if self.next_component() {
+                    self.write_index = self.write_index.wrapping_add(1);
+                }
+            }
+            CGA_MODE_PORT => self.mode_control = value,
+            CGA_COLOR_PORT => self.color_select = value,
+            _ => {}
+        }
+    }
+
+    fn update(&mut self, clock: u64, _pic: &mut Pic) {
+        self.clock = clock;
+    }
+}
+
+/// 8-bit value of a 6-bit DAC component
+fn scale(component: u8) -> u8 {
+    (component << 2) | (component >> 4)
+}
+
+/// Maps the video card registers on ports 3C7h to 3C9h and 3D8h to 3DAh and returns it.
+pub fn install(machine: &mut Machine) -> Rc<RefCell<Graphics>> {
+    let graphics = Rc::new(RefCell::new(Graphics::new()));
+    machine
+        .ports
+        .map_shared(DAC_READ_INDEX_PORT..=DAC_DATA_PORT, graphics.clone());
+    machine
+        .ports
+        .map_shared(CGA_MODE_PORT..=INPUT_STATUS_PORT, graphics.clone());
+    graphics
+}
@@ -0,0 +1,118 @@
+#[test]
+fn graphics_images() {
+    use crate::graphics::{adler32, crc32, Frame, ImageFormat};
+
+    assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+    let frame = Frame {
+        width: 2,
+        height: 1,
+        pixels: vec![[0xff, 0, 0], [0, 0, 0xff]],
+    };
+    assert_eq!(frame.pixel(1, 0), [0, 0, 0xff]);
+    assert_eq!(
+        frame.encode(ImageFormat::Ppm),
+        b"P6\n2 1\n255\n\xff\x00\x00\x00\x00\xff"
+    );
+
+    let png = frame.encode(ImageFormat::Png);
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    assert_eq!(&png[8..16], b"\x00\x00\x00\x0dIHDR");
+    assert_eq!(&png[16..29], [0, 0, 0, 2, 0, 0, 0, 1, 8, 2, 0, 0, 0]);
+    // one stored block holding the filter byte and the pixels
+    assert_eq!(&png[33..41], b"\x00\x00\x00\x12IDAT");
+    assert_eq!(&png[41..48], [0x78, 0x01, 0x01, 0x07, 0x00, 0xf8, 0xff]);
+    assert_eq!(&png[48..55], [0, 0xff, 0, 0, 0, 0, 0xff]);
+    assert_eq!(
+        &png[png.len() - 12..],
+        b"\x00\x00\x00\x00IEND\xae\x42\x60\x82"
+    );
+}
+
+#[test]
+fn graphics_modes() {
+    use crate::{
+        bios::{Video, VIDEO_INTERRUPT},
+        graphics::{self, DAC_DATA_PORT, DAC_WRITE_INDEX_PORT, INPUT_STATUS_PORT, STEPS_PER_FRAME},
+        machine::Machine,
+        pic::Pic,
+        port::PortDevice,
+    };
+    use asmrs_parser::lexer::token::Cpu;
+
+    let mut machine = Machine::new(Cpu::I8086);
+    machine.registers.sp = 0xfffe;
+    machine
+        .interrupts
+        .set(VIDEO_INTERRUPT, Video::new(Box::new(std::io::sink())));
+    let graphics = graphics::install(&mut machine);
+    assert_eq!(graphics.borrow().frame(&machine.memory), None);
+
+    // mode 13h with color 1 set through the ports and color 2 through the BIOS
+    machine.memory.write_byte(0xa0000, 0xff);
+    machine.registers.ax = 0x0013;
+    machine.interrupt(VIDEO_INTERRUPT).unwrap();
+    machine.memory.load(0xa0000, &[1, 2, 0x0f, 0x1f]);
+    machine.write_port(DAC_WRITE_INDEX_PORT, 1, 1).unwrap();
+    for component in [63, 0, 0] {
+        machine.write_port(DAC_DATA_PORT, 1, component).unwrap();
+    }
+    machine.registers.ax = 0x1010;
+    machine.registers.bx = 2;
+    machine.registers.cx = 0x2010;
+    machine.registers.dx = 0x0800;
+    machine.interrupt(VIDEO_INTERRUPT).unwrap();
+
+    let frame = graphics.borrow().frame(&machine.memory).unwrap();
+    assert_eq!((frame.width, frame.height), (320, 200));
+    assert_eq!(frame.pixel(0, 0), [0xff, 0, 0]);
+    assert_eq!(frame.pixel(1, 0), [0x20, 0x82, 0x41]);
+    assert_eq!(frame.pixel(2, 0), [0xff, 0xff, 0xff]);
+    assert_eq!(frame.pixel(3, 0), [0xff, 0xff, 0xff]);
+    assert_eq!(frame.pixel(4, 0), [0, 0, 0]);
+
+    machine.registers.ax = 0x1015;
+    machine.registers.bx = 1;
+    machine.interrupt(VIDEO_INTERRUPT).unwrap();
+    assert_eq!((machine.registers.dx >> 8, machine.registers.cx), (63, 0));
+
+    // CGA 320x200: even lines first, then odd lines from 2000h
+    machine.registers.ax = 0x0004;
+    machine.interrupt(VIDEO_INTERRUPT).unwrap();
+    machine.memory.write_byte(0xb8000, 0b0001_1011);
+    machine.memory.write_byte(0xba000, 0b1100_0000);
+    let frame = graphics.borrow().frame(&machine.memory).unwrap();
+    let row = (0..4).map(|x| frame.pixel(x, 0)).collect::<Vec<_>>();
+    assert_eq!(
+        row,
+        [
+            [0x00, 0x00, 0x00],
+            [0x55, 0xff, 0xff],
+            [0xff, 0x55, 0xff],
+            [0xff, 0xff, 0xff]
+        ]
+    );
+    assert_eq!(frame.pixel(0, 1), [0xff, 0xff, 0xff]);
+
+    // blue background and the green, red and brown palette without intensity
+    machine.registers.ax = 0x0b00;
+    machine.registers.bx = 0x0001;
+    machine.interrupt(VIDEO_INTERRUPT).unwrap();
+    machine.registers.bx = 0x0100;
+    machine.interrupt(VIDEO_INTERRUPT).unwrap();
+    assert_eq!(graphics.borrow().color_select, 0x01);
+    let frame = graphics.borrow().frame(&machine.memory).unwrap();
+    assert_eq!(frame.pixel(0, 0), [0x00, 0x00, 0xaa]);
+    assert_eq!(frame.pixel(1, 0), [0x00, 0xaa, 0x00]);
+
+    // the vertical retrace ends each frame
+    let mut graphics = graphics.borrow_mut();
+    let mut pic = Pic::new();
+    graphics.update(STEPS_PER_FRAME + 10, &mut pic);
+    assert_eq!(graphics.frames(), 1);
+    assert_eq!(graphics.read_byte(INPUT_STATUS_PORT) & 0x08, 0);
+    graphics.update(2 * STEPS_PER_FRAME - 1, &mut pic);
+    assert_eq!(graphics.read_byte(INPUT_STATUS_PORT), 0x09);
+}
@@ -0,0 +1,103 @@
+use crate::{
+    machine::Machine,
+    memory::{address, Memory},
+};
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
+
+mod test;
+
+/// Segment of the stubs the interrupt vector table points to initially
+pub const STUB_SEGMENT: u16 = 0xf000;
+
+/// Offset of the stub of vector 0; the stub of vector N follows N bytes later
+pub const STUB_OFFSET: u16 = 0xe000;
+
+/// `iret`, the byte of every stub
+const IRET: u8 = 0xcf;
+
+/// Address (segment, offset) of the stub of `vector`. Executing a stub calls the Rust
+/// handler of its vector and returns like `iret`, so programs can chain to the previous
+/// vector after hooking one.
+pub fn stub(vector: u8) -> (u16, u16) {
+    (STUB_SEGMENT, STUB_OFFSET + vector as u16)
+}
+
+/// Writes the interrupt vector table at 0000:0000 with every vector pointing at its stub.
+pub fn write_vector_table(memory: &mut Memory) {
+    for vector in 0..=255 {
+        let (segment, offset) = stub(vector);
+        memory.write_word(vector as usize * 4, offset);
+        memory.write_word(vector as usize * 4 + 2, segment);
+        memory.write_byte(address(segment, offset), IRET);
+    }
+}
+
+/// Service for an interrupt, implemented in Rust. Handlers see the machine as it is after
+/// the `int` instruction and return registers in it like a real service routine. They run
+/// while the vector still points at its stub; a program hooking the vector replaces them.
+///
+/// Closures `FnMut(&mut Machine, u8) -> Result<(), String>` are handlers as well.
+pub trait InterruptHandler {
+    /// Services `int vector`. An error stops the machine.
+    fn interrupt(&mut self, machine: &mut Machine, vector: u8) -> Result<(), String>;
+}
+
+impl<F: FnMut(&mut Machine, u8) -> Result<(), String>> InterruptHandler for F {
+    fn interrupt(&mut self, machine: &mut Machine, vector: u8) -> Result<(), String> {
+        self(machine, vector)
+    }
+}
+
+/// Shared handler; one handler may serve several vectors.
+pub type Handler = Rc<RefCell<dyn InterruptHandler>>;
+
+/// Rust handlers by interrupt vector. Clones share the handlers.
+#[derive(Clone)]
+pub struct Interrupts {
+    handlers: Vec<Option<Handler>>,
+}
+
+impl Interrupts {
+    pub fn new() -> Interrupts {
+        Self {
+            handlers: vec![None; 256],
+        }
+    }
+
+    /// Installs `handler` for `vector`, replacing the previous one.
+    pub fn set(&mut self, vector: u8, handler: impl InterruptHandler + 'static) {
+        self.set_shared(vector, Rc::new(RefCell::new(handler)));
+    }
+
+    /// Installs a handler that may also serve other vectors.
+    pub fn set_shared(&mut self, vector: u8, handler: Handler) {
+        self.handlers[vector as usize] = Some(handler);
+    }
+
+    /// Removes the handler of `vector` and returns it.
+    pub fn remove(&mut self, vector: u8) -> Option<Handler> {
+        self.handlers[vector as usize].take()
+    }
+
+    pub fn get(&self, vector: u8) -> Option<Handler> {
+        self.handlers[vector as usize].clone()
+    }
+}
+
+impl Default for Interrupts {
+    fn default() -> Self {
+        Interrupts::new()
+    }
+}
+
+impl Debug for Interrupts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let vectors = self
+            .handlers
+            .iter()
+            .enumerate()
+            .filter(|(_, handler)| handler.is_some())
+            .map(|(vector, _)| vector);
+        f.debug_set().entries(vectors).finish()
+    }
+}
@@ -0,0 +1,156 @@
+#[test]
+fn interrupt_handlers() {
+    use crate::machine::{test_machine, Machine};
+
+    // int 80h / into / int3 / hlt
+    let mut machine = test_machine(&[0xcd, 0x80, 0xce, 0xcc, 0xf4]);
+
+    // without a Rust handler, int goes through the vector table to the stub's iret
+    assert_eq!(machine.run(10).unwrap(), 6);
+    assert!(machine.halted);
+    machine.registers.ip = 0x100;
+    machine.halted = false;
+
+    machine
+        .interrupts
+        .set(0x80, |machine: &mut Machine, vector| {
+            machine.registers.ax = vector as u16;
+            Ok(())
+        });
+    let handler = machine.interrupts.get(0x80).unwrap();
+    machine.interrupts.set_shared(0x03, handler);
+
+    // into only interrupts on overflow
+    assert_eq!(machine.run(10).unwrap(), 4);
+    assert_eq!(machine.registers.ax, 0x03);
+    assert_eq!(format!("{:?}", machine.interrupts), "{3, 128}");
+
+    machine.interrupts.remove(0x03);
+    assert!(machine.interrupts.get(0x03).is_none());
+}
+
+/// Machine with `code` at 0000:0100, `handler` at 0000:0600 installed for `vector` and
+/// the stack at 0000:FFFE
+#[cfg(test)]
+fn machine(code: &[u8], vector: u8, handler: &[u8]) -> crate::machine::Machine {
+    let mut machine = crate::machine::test_machine(code);
+    machine.memory.load(0x600, handler);
+    machine.memory.write_word(vector as usize * 4, 0x600);
+    machine.memory.write_word(vector as usize * 4 + 2, 0);
+    machine
+}
+
+#[test]
+fn interrupt_vector_table() {
+    use crate::{
+        interrupt::stub,
+        machine::Machine,
+        registers::{FLAG_CARRY, FLAG_INTERRUPT},
+    };
+
+    // sti / int 60h / int 10h / hlt
+    // 0000:0600: mov bx, 1234h / iret
+    let mut machine = machine(
+        &[0xfb, 0xcd, 0x60, 0xcd, 0x10, 0xf4],
+        0x60,
+        &[0xbb, 0x34, 0x12, 0xcf],
+    );
+    assert_eq!(stub(0x10), (0xf000, 0xe010));
+    assert!(machine.vectored(0x60));
+    assert!(!machine.vectored(0x10));
+
+    machine.run(2).unwrap();
+    assert_eq!((machine.registers.cs, machine.registers.ip), (0, 0x600));
+    assert!(!machine.flag(FLAG_INTERRUPT));
+    assert_eq!(machine.memory.read_word(0xfff8), 0x103);
+    machine.run(2).unwrap();
+    assert_eq!(machine.registers.bx, 0x1234);
+    assert_eq!(machine.registers.sp, 0xfffe);
+    assert!(machine.flag(FLAG_INTERRUPT));
+
+    // a hook chaining to the stub reaches the Rust handler
+    // 0000:0610: inc cx / jmp far f000:e010
+    machine
+        .memory
+        .load(0x610, &[0x41, 0xea, 0x10, 0xe0, 0x00, 0xf0]);
+    machine.memory.write_word(0x40, 0x610);
+    machine.memory.write_word(0x42, 0);
+    machine
+        .interrupts
+        .set(0x10, |machine: &mut Machine, _vector| {
+            machine.registers.ax = 0x55;
+            machine.set_flag(FLAG_CARRY, true);
+            Ok(())
+        });
+
+    machine.run(10).unwrap();
+    assert!(machine.halted);
+    assert_eq!(machine.registers.ip, 0x106);
+    assert_eq!((machine.registers.ax, machine.registers.cx), (0x55, 1));
+    assert!(machine.flag(FLAG_CARRY));
+    assert!(machine.flag(FLAG_INTERRUPT));
+}
+
+#[test]
+fn interrupt_exceptions() {
+    use crate::registers::FLAG_TRAP;
+
+    // nop / nop / hlt, single stepped
+    // 0000:0600: inc cx / iret
+    let mut machine = machine(&[0x90, 0x90, 0xf4], 0x01, &[0x41, 0xcf]);
+    machine.registers.flags |= FLAG_TRAP;
+    machine.step().unwrap();
+    assert_eq!(machine.registers.ip, 0x600);
+    assert!(!machine.flag(FLAG_TRAP));
+
+    machine.run(10).unwrap();
+    assert_eq!(machine.registers.cx, 2);
+    assert_eq!(machine.instructions, 7);
+
+    // mov ax, 10 / mov bl, 0 / div bl / hlt
+    // 0000:0600: mov dx, 7 / iret
+    let mut divider = self::machine(
+        &[0xb8, 0x0a, 0x00, 0xb3, 0x00, 0xf6, 0xf3, 0xf4],
+        0x00,
+        &[0xba, 0x07, 0x00, 0xcf],
+    );
+    divider.run(10).unwrap();
+    assert_eq!(divider.registers.dx, 7);
+    assert_eq!(divider.registers.ip, 0x108);
+}
+
+#[test]
+fn interrupt_hardware() {
+    use crate::registers::FLAG_INTERRUPT;
+
+    // sti / hlt / jmp $-1
+    // 0000:0600: inc cx / iret
+    let mut machine = machine(&[0xfb, 0xf4, 0xeb, 0xfd], 0x08, &[0x41, 0xcf]);
+    machine.pic.request(0);
+    assert!(!machine.interrupt_pending());
+
+    // the request waits for the instruction after sti, then wakes the halted machine
+    machine.step().unwrap();
+    assert_eq!(machine.registers.ip, 0x101);
+    machine.run(10).unwrap();
+    assert!(machine.halted);
+    assert_eq!(machine.registers.cx, 1);
+    assert_eq!(machine.registers.ip, 0x102);
+    assert!(machine.flag(FLAG_INTERRUPT));
+
+    // the same line waits for the end of interrupt
+    machine.pic.request(0);
+    assert_eq!(machine.run(10).unwrap(), 0);
+    assert_eq!(machine.pic.end_of_interrupt(), Some(0));
+    machine.run(10).unwrap();
+    assert_eq!(machine.registers.cx, 2);
+
+    // masked or without a handler, nothing runs
+    machine.pic.end_of_interrupt();
+    machine.pic.imr = 0x01;
+    machine.pic.request(0);
+    machine.pic.request(1);
+    machine.run(10).unwrap();
+    assert_eq!(machine.registers.cx, 2);
+    assert_eq!((machine.pic.irr, machine.pic.isr), (0x01, 0));
+}
@@ -0,0 +1,164 @@
+use crate::{
+    fpu::Fpu,
+    machine::{Access, AccessKind, ExecutionError, Machine},
+    pic::Pic,
+    registers::Registers,
+};
+use std::collections::VecDeque;
+
+mod test;
+
+/// Instructions kept by default, roughly 200 bytes each
+pub const DEFAULT_JOURNAL_CAPACITY: usize = 100_000;
+
+/// State an instruction changed, enough to undo it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// Number of instructions executed before this one
+    pub instruction: u64,
+    /// Steps taken before this one
+    pub clock: u64,
+    /// Registers before the instruction, CS:IP pointing at it
+    pub registers: Registers,
+    /// Coprocessor before the instruction
+    pub fpu: Fpu,
+    pub halted: bool,
+    /// Interrupt controller before the instruction, which may have taken an interrupt
+    pub pic: Pic,
+    /// Memory writes in execution order, with the values they replaced
+    pub writes: Vec<Access>,
+}
+
+impl Entry {
+    /// Whether one of the writes touches the linear address range `start..end`
+    pub fn wrote(&self, start: usize, end: usize) -> Option<&Access> {
+        self.writes
+            .iter()
+            .rev()
+            .find(|write| write.address < end && start < write.address + write.size as usize)
+    }
+}
+
+/// Execution journal for reverse debugging: the register and memory deltas of the last
+/// `capacity` instructions. When the journal is full the oldest entry is dropped, so the
+/// earliest reachable state (the checkpoint) moves forward and memory use stays bounded.
+///
+/// Devices behind `Machine::ports` are not journaled. An instruction that reads or writes
+/// one of their ports cannot be undone, so the journal starts over after it, making it the
+/// checkpoint. Devices keep counting time from where they are when the clock is rewound,
+/// and Rust interrupt handlers keep their own state (the BIOS cursor, consumed keys).
+#[derive(Clone, Debug)]
+pub struct Journal {
+    entries: VecDeque<Entry>,
+    capacity: usize,
+}
+
+impl Journal {
+    pub fn new(capacity: usize) -> Journal {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Recorded entries, oldest first
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &Entry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Instruction count of the earliest state `undo` can return to
+    pub fn checkpoint(&self) -> Option<u64> {
+        self.entries.front().map(|entry| entry.instruction)
+    }
+
+    /// Steps the machine and records what the instruction changed. Failed instructions
+    /// leave the machine unchanged and are not recorded; port I/O clears the journal.
+    pub fn step(&mut self, machine: &mut Machine) -> Result<(), ExecutionError> {
+        let (registers, fpu, pic) = (
+            machine.registers.clone(),
+            machine.fpu.clone(),
+            machine.pic.clone(),
+        );
+        let (halted, instruction, clock) = (machine.halted, machine.instructions, machine.clock);
+
+        machine.step()?;
+
+        if machine.port_io() {
+            self.entries.clear();
+            return Ok(());
+        }
+        if self.capacity == 0 {
+            return Ok(());
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(Entry {
+            instruction,
+            clock,
+            registers,
+            fpu,
+            halted,
+            pic,
+            writes: machine
+                .accesses()
+                .iter()
+                .filter(|access| access.kind == AccessKind::Write)
+                .copied()
+                .collect(),
+        });
+
+        Ok(())
+    }
+
+    /// Reverts the last recorded instruction and returns its entry, or `None` at the
+    /// checkpoint.
+    pub fn undo(&mut self, machine: &mut Machine) -> Option<Entry> {
+        let entry = self.entries.pop_back()?;
+
+        for write in entry.writes.iter().rev() {
+            match write.size {
+                1 => machine
+                    .memory
+                    .write_byte(write.address, write.previous as u8),
+                _ => machine.memory.write_word(write.address, write.previous),
+            }
+        }
+
+        machine.registers = entry.registers.clone();
+        machine.fpu = entry.fpu.clone();
+        machine.halted = entry.halted;
+        machine.instructions = entry.instruction;
+        machine.clock = entry.clock;
+        machine.pic = entry.pic.clone();
+
+        Some(entry)
+    }
+
+    /// Latest recorded instruction writing to the linear address
+    pub fn last_write(&self, address: usize) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.wrote(address, address + 1).is_some())
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Journal::new(DEFAULT_JOURNAL_CAPACITY)
+    }
+}
@@ -0,0 +1,122 @@
+#[test]
+fn journal_undo() {
+    use crate::{journal::Journal, machine::Machine};
+    use asmrs_parser::lexer::token::Cpu;
+
+    // mov sp, 200h / call 108h / hlt / nop / mov ax, 1234h / ret
+    let mut machine = Machine::new(Cpu::I8086);
+    machine.memory.load(
+        0x100,
+        &[
+            0xbc, 0x00, 0x02, 0xe8, 0x02, 0x00, 0xf4, 0x90, 0xb8, 0x34, 0x12, 0xc3,
+        ],
+    );
+    machine.registers.ip = 0x100;
+    let start = machine.clone();
+
+    let mut journal = Journal::new(10);
+    for _ in 0..5 {
+        journal.step(&mut machine).unwrap();
+    }
+    assert!(machine.halted);
+    assert_eq!(journal.len(), 5);
+    assert_eq!(journal.checkpoint(), Some(0));
+
+    // the call pushed the return address, nothing wrote it later
+    let entry = journal.last_write(0x1ff).unwrap();
+    assert_eq!(entry.instruction, 1);
+    assert_eq!(entry.registers.ip, 0x103);
+    assert_eq!(entry.writes[0].value, 0x106);
+    assert!(journal.last_write(0x200).is_none());
+
+    let entry = journal.undo(&mut machine).unwrap();
+    assert_eq!(entry.registers.ip, 0x106);
+    assert!(!machine.halted);
+    assert_eq!(machine.instructions, 4);
+
+    while journal.undo(&mut machine).is_some() {}
+    assert_eq!(machine.registers, start.registers);
+    assert_eq!(machine.instructions, 0);
+    assert_eq!(
+        machine.memory.read_word(0x1fe),
+        start.memory.read_word(0x1fe)
+    );
+}
+
+#[test]
+fn journal_capacity() {
+    use crate::{journal::Journal, machine::Machine};
+    use asmrs_parser::lexer::token::Cpu;
+
+    // inc ax / jmp $-1
+    let mut machine = Machine::new(Cpu::I8086);
+    machine.memory.load(0x100, &[0x40, 0xeb, 0xfd]);
+    machine.registers.ip = 0x100;
+
+    let mut journal = Journal::new(4);
+    for _ in 0..10 {
+        journal.step(&mut machine).unwrap();
+    }
+    assert_eq!(journal.len(), 4);
+    assert_eq!(journal.checkpoint(), Some(6));
+
+    while journal.undo(&mut machine).is_some() {}
+    assert_eq!(machine.instructions, 6);
+    assert_eq!(machine.registers.ax, 3);
+
+    // a failed instruction is not recorded: div cl with cl = 0 and no handler
+    machine.memory.load(0x100, &[0xf6, 0xf1]);
+    assert!(journal.step(&mut machine).is_err());
+    assert!(journal.is_empty());
+}
+
+#[test]
+fn journal_ports() {
+    use crate::{journal::Journal, machine::Machine};
+    use asmrs_parser::lexer::token::Cpu;
+
+    // mov al, 0feh / out 21h, al / in al, 60h / nop
+    let mut machine = Machine::new(Cpu::I8086);
+    machine
+        .memory
+        .load(0x100, &[0xb0, 0xfe, 0xe6, 0x21, 0xe4, 0x60, 0x90]);
+    machine.registers.ip = 0x100;
+
+    // the interrupt controller and the clock are restored
+    let mut journal = Journal::new(10);
+    journal.step(&mut machine).unwrap();
+    journal.step(&mut machine).unwrap();
+    assert_eq!((machine.pic.imr, machine.clock), (0xfe, 2));
+    journal.undo(&mut machine).unwrap();
+    assert_eq!((machine.pic.imr, machine.clock), (0, 1));
+
+    // other ports belong to devices, the journal starts over behind them
+    journal.step(&mut machine).unwrap();
+    journal.step(&mut machine).unwrap();
+    assert!(journal.is_empty());
+    journal.step(&mut machine).unwrap();
+    assert_eq!(journal.checkpoint(), Some(3));
+}
+
+#[test]
+fn journal_fpu() {
+    use crate::{journal::Journal, machine::test_machine};
+    use asmrs_parser::lexer::token::FloatingPointRegister;
+
+    // fld1 / fldz
+    let mut machine = test_machine(&[0xd9, 0xe8, 0xd9, 0xee]);
+    let mut journal = Journal::new(10);
+    journal.step(&mut machine).unwrap();
+    journal.step(&mut machine).unwrap();
+    assert_eq!(machine.fpu.top(), 6);
+    assert_eq!(machine.fpu.value(FloatingPointRegister::St1), 1.0);
+
+    // the pushes are undone with the registers
+    journal.undo(&mut machine).unwrap();
+    assert_eq!(machine.fpu.top(), 7);
+    assert_eq!(machine.fpu.value(FloatingPointRegister::St0), 1.0);
+    journal.undo(&mut machine).unwrap();
+    assert_eq!(machine.registers.ip, 0x100);
+    assert_eq!(machine.fpu.top(), 0);
+    assert!(machine.fpu.is_empty(FloatingPointRegister::St0));
+}
@@ -0,0 +1,464 @@
+use crate::{
+    bios::{scan_code, shifted, Keyboard, INSTRUCTIONS_PER_TICK, KEYBOARD_INTERRUPT},
+    machine::Machine,
+    pic::Pic,
+    port::PortDevice,
+};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io::{self, Read},
+    rc::Rc,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+mod test;
+
+/// Output buffer (reads) and keyboard commands (writes)
+pub const KEYBOARD_DATA_PORT: u16 = 0x60;
+/// Status register (reads) and controller commands (writes)
+pub const KEYBOARD_STATUS_PORT: u16 = 0x64;
+
+/// Interrupt request line of the keyboard
+pub const KEYBOARD_IRQ: u8 = 1;
+/// Vector of IRQ 1 as set up by the PC BIOS
+pub const KEYBOARD_IRQ_VECTOR: u8 = 0x09;
+
+/// Machine steps per millisecond of script time, at the speed of the BIOS clock
+pub const STEPS_PER_MILLISECOND: u64 = INSTRUCTIONS_PER_TICK * 182 / 10_000;
+
+/// Status: a byte waits in the output buffer
+const STATUS_OUTPUT_FULL: u8 = 0x01;
+/// Status: the system passed its self test
+const STATUS_SYSTEM: u8 = 0x04;
+/// Status: the last write went to the command port
+const STATUS_COMMAND: u8 = 0x08;
+/// Status: the keyboard is not locked
+const STATUS_UNLOCKED: u8 = 0x10;
+
+/// Command byte: raise IRQ 1 for keyboard bytes
+const COMMAND_IRQ: u8 = 0x01;
+/// Command byte: the keyboard interface is disabled
+const COMMAND_DISABLED: u8 = 0x10;
+
+/// Keyboard acknowledge
+const ACK: u8 = 0xfa;
+
+/// Keys without a character, by script name: whether they send the E0h prefix and their
+/// make code
+const KEY_NAMES: [(&str, bool, u8); 35] = [
+    ("esc", false, 0x01),
+    ("escape", false, 0x01),
+    ("backspace", false, 0x0e),
+    ("tab", false, 0x0f),
+    ("enter", false, 0x1c),
+    ("ctrl", false, 0x1d),
+    ("shift", false, 0x2a),
+    ("lshift", false, 0x2a),
+    ("rshift", false, 0x36),
+    ("alt", false, 0x38),
+    ("space", false, 0x39),
+    ("capslock", false, 0x3a),
+    ("f1", false, 0x3b),
+    ("f2", false, 0x3c),
+    ("f3", false, 0x3d),
+    ("f4", false, 0x3e),
+    ("f5", false, 0x3f),
+    ("f6", false, 0x40),
+    ("f7", false, 0x41),
+    ("f8", false, 0x42),
+    ("f9", false, 0x43),
+    ("f10", false, 0x44),
+    ("f11", false, 0x57),
+    ("f12", false, 0x58),
+    ("home", true, 0x47),
+    ("up", true, 0x48),
+    ("pgup", true, 0x49),
+    ("left", true, 0x4b),
+    ("right", true, 0x4d),
+    ("end", true, 0x4f),
+    ("down", true, 0x50),
+    ("pgdn", true, 0x51),
+    ("insert", true, 0x52),
+    ("delete", true, 0x53),
+    ("numlock", false, 0x45),
+];
+
+/// 8042 keyboard controller with a keyboard sending set 1 scan codes. Scan codes come
+/// from a script, timed on the machine's clock, or from the host stdin; a new byte enters
+/// the output buffer once the previous one was read, raising IRQ 1.
+pub struct Controller {
+    /// Scan code bytes with the clock they are due at, in order
+    pub queue: VecDeque<(u64, u8)>,
+    /// Byte waiting to be read from port 60h
+    pub output: Option<u8>,
+    /// Controller command byte
+    pub command_byte: u8,
+    /// The keyboard sends scan codes (enabled with F4h, disabled with F5h)
+    pub scanning: bool,
+    /// Keyboard LEDs set with EDh
+    pub leds: u8,
+    /// Controller and keyboard replies, sent before scan codes
+    replies: VecDeque<u8>,
+    /// Last byte put in the output buffer, read again while it is empty
+    last: u8,
+    /// Controller command waiting for its parameter on port 60h
+    command: Option<u8>,
+    /// Keyboard command waiting for its parameter
+    keyboard_command: Option<u8>,
+    /// The last write went to the command port
+    command_written: bool,
+    output_port: u8,
+    /// Host stdin typed as keys, read on a separate thread
+    stdin: Option<Receiver<u8>>,
+    clock: u64,
+}
+
+impl Controller {
+    /// Controller with nothing to send
+    pub fn new() -> Controller {
+        Self {
+            queue: VecDeque::new(),
+            output: None,
+            command_byte: 0x45,
+            scanning: true,
+            leds: 0,
+            replies: VecDeque::new(),
+            last: 0,
+            command: None,
+            keyboard_command: None,
+            command_written: false,
+            output_port: 0xdf,
+            stdin: None,
+            clock: 0,
+        }
+    }
+
+    /// Controller sending the keys of a script (see `parse_script`)
+    pub fn from_script(text: &str) -> Result<Controller, String> {
+        let mut controller = Self::new();
+        controller.queue = parse_script(text)?;
+        Ok(controller)
+    }
+
+    /// Controller typing the bytes of the host stdin as they come
+    pub fn stdin() -> Controller {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for byte in io::stdin().lock().bytes() {
+                let Ok(byte) = byte else { break };
+                if sender.send(byte).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut controller = Self::new();
+        controller.stdin = Some(receiver);
+        controller
+    }
+
+    /// Whether everything was sent and read, and no more input can come
+    pub fn finished(&self) -> bool {
+        self.queue.is_empty()
+            && self.output.is_none()
+            && self.replies.is_empty()
+            && self.stdin.is_none()
+    }
+
+    /// Queues the keystrokes of host input typed now.
+    fn poll_stdin(&mut self) {
+        let Some(receiver) = &self.stdin else {
+            return;
+        };
+
+        let mut typed = Vec::new();
+        loop {
+            match receiver.try_recv() {
+                Ok(byte) => typed.push(byte),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.stdin = None;
+                    break;
+                }
+            }
+        }
+        for byte in typed {
+            let byte = if byte == b'\n' { b'\r' } else { byte };
+            if let Some(bytes) = keystrokes(byte) {
+                self.queue
+                    .extend(bytes.into_iter().map(|code| (self.clock, code)));
+            }
+        }
+    }
+
+    fn reply(&mut self, bytes: &[u8]) {
+        self.replies.extend(bytes);
+    }
+
+    /// Controller command written to port 64h
+    fn controller_command(&mut self, command: u8) {
+        match command {
+            // read the command byte / the output port
+            0x20 => self.reply(&[self.command_byte]),
+            0xd0 => self.reply(&[self.output_port]),
+            // the parameter follows on port 60h
+            0x60 | 0xd1 => self.command = Some(command),
+            // self test, interface test
+            0xaa => self.reply(&[0x55]),
+            0xab => self.reply(&[0x00]),
+            0xad => self.command_byte |= COMMAND_DISABLED,
+            0xae => self.command_byte &= !COMMAND_DISABLED,
+            // auxiliary device, reset pulses and the rest are ignored
+            _ => {}
+        }
+    }
+
+    /// Command written to port 60h for the keyboard
+    fn keyboard_command(&mut self, value: u8) {
+        if let Some(command) = self.keyboard_command.take() {
+            if command == 0xed {
+                self.leds = value & 0x07;
+            }
+            return self.reply(&[ACK]);
+        }
+
+        match value {
+            // set LEDs, typematic rate: a parameter follows
+            0xed | 0xf3 => {
+                self.keyboard_command = Some(value);
+                self.reply(&[ACK]);
+            }
+            0xee => self.reply(&[0xee]),
+            // identify: MF2 keyboard
+            0xf2 => self.reply(&[ACK, 0xab, 0x83]),
+            0xf4 => {
+                self.scanning = true;
+                self.reply(&[ACK]);
+            }
+            0xf5 => {
+                self.scanning = false;
+                self.reply(&[ACK]);
+            }
+            // reset and self test
+            0xff => {
+                self.scanning = true;
+                self.reply(&[ACK, 0xaa]);
+            }
+            _ => self.reply(&[ACK]),
+        }
+    }
+}
+
+impl Default for Controller {
+    fn default() -> Self {
+        Controller::new()
+    }
+}
+
+impl PortDevice for Controller {
+    fn read_byte(&mut self, port: u16) -> u8 {
+        if port == KEYBOARD_STATUS_PORT {
+            let mut status = STATUS_SYSTEM | STATUS_UNLOCKED;
+            if self.output.is_some() {
+                status |= STATUS_OUTPUT_FULL;
+            }
+            if self.command_written {
+                status |= STATUS_COMMAND;
+            }
+            return status;
+        }
+        self.output.take().unwrap_or(self.last)
+    }
+
+    fn write_byte(&mut self, port: u16, value: u8) {
+        self.command_written = port == KEYBOARD_STATUS_PORT;
+        if port == KEYBOARD_STATUS_PORT {
+            return self.controller_command(value);
+        }
+
+        match self.command.take() {
+            Some(0x60) => self.command_byte = value,
+            Some(_) => self.output_port = value,
+            None => self.keyboard_command(value),
+        }
+    }
+
+    fn update(&mut self, clock: u64, pic: &mut Pic) {
+        self.clock = clock;
+        self.poll_stdin();
+        if self.output.is_some() {
+            return;
+        }
+
+        let due = self.queue.front().is_some_and(|(due, _)| *due <= clock);
+        let byte = match self.replies.pop_front() {
+            Some(byte) => byte,
+            None if due && self.scanning && self.command_byte & COMMAND_DISABLED == 0 => {
+                match self.queue.pop_front() {
+                    Some((_, byte)) => byte,
+                    None => return,
+                }
+            }
+            None => return,
+        };
+
+        self.output = Some(byte);
+        self.last = byte;
+        if self.command_byte & COMMAND_IRQ != 0 {
+            pic.request(KEYBOARD_IRQ);
+        }
+    }
+}
+
+/// Scan code bytes typing `character` on a US keyboard, with shift or ctrl held as needed
+pub fn keystrokes(character: u8) -> Option<Vec<u8>> {
+    let (code, modifier) = match character {
+        // backspace, tab and enter have keys of their own, other control characters take ctrl
+        0x08 | b'\t' | b'\r' => (scan_code(character), None),
+        0x01..=0x1a => (scan_code(character), Some(0x1d)),
+        _ if shifted(character) => (scan_code(character), Some(0x2a)),
+        _ => (scan_code(character), None),
+    };
+    if code == 0 {
+        return None;
+    }
+
+    Some(match modifier {
+        Some(modifier) => vec![modifier, code, code | 0x80, modifier | 0x80],
+        None => vec![code, code | 0x80],
+    })
+}
+
+/// Prefix and make code of a key named in a script: a name like `enter` or `f1`, a single
+/// character, or a make code in hex like `0x1e`
+fn key(name: &str) -> Result<(bool, u8), String> {
+    let lower = name.to_lowercase();
+    if let Some((_, extended, code)) = KEY_NAMES.iter().find(|(key, ..)| *key == lower) {
+        return Ok((*extended, *code));
+    }
+    if let Some(hex) = lower.strip_prefix("0x") {
+        return u8::from_str_radix(hex, 16)
+            .map(|code| (false, code & 0x7f))
+            .map_err(|_| format!("Invalid scan code: '{}'", name));
+    }
+    match name.as_bytes() {
+        [character] if scan_code(character.to_ascii_lowercase()) != 0 => {
+            Ok((false, scan_code(character.to_ascii_lowercase())))
+        }
+        _ => Err(format!("Unknown key: '{}'", name)),
+    }
+}
+
+/// Text of a `type` command: the rest of the line, optionally quoted, with `\n` (enter),
+/// `\t`, `\b`, `\e` (escape), `\\` and `\"` escapes
+fn unescape(text: &str) -> Result<Vec<u8>, String> {
+    let text = text
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text);
+
+    let mut bytes = Vec::new();
+    let mut characters = text.bytes();
+    while let Some(byte) = characters.next() {
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        bytes.push(match characters.next() {
+            Some(b'n') => b'\r',
+            Some(b't') => b'\t',
+            Some(b'b') => 0x08,
+            Some(b'e') => 0x1b,
+            Some(byte @ (b'\\' | b'"')) => byte,
+            _ => return Err(format!("Invalid escape in '{}'", text)),
+        });
+    }
+    Ok(bytes)
+}
+
+/// Parses a keyboard script into scan code bytes and the clock they are due at. Each line
+/// holds a command; lines starting with `#` are comments.
+///
+/// - `type TEXT`: press and release the keys typing TEXT
+/// - `press KEY`, `down KEY`, `up KEY`: press and release, press or release a key
+/// - `wait MS`: send the following keys MS milliseconds of machine time later
+pub fn parse_script(text: &str) -> Result<VecDeque<(u64, u8)>, String> {
+    let mut queue = VecDeque::new();
+    let mut due = 0;
+
+    for (number, line) in text.lines().enumerate() {
+        let error = |message: String| format!("Line {}: {}", number + 1, message);
+        let line = match line.trim_start().strip_prefix('#') {
+            Some(_) => "",
+            None => line.trim(),
+        };
+        if line.is_empty() {
+            continue;
+        }
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+
+        let bytes = match command {
+            "type" => {
+                let mut bytes = Vec::new();
+                for character in unescape(argument).map_err(error)? {
+                    bytes.extend(keystrokes(character).ok_or_else(|| {
+                        error(format!("Cannot type '{}'", character.escape_ascii()))
+                    })?);
+                }
+                bytes
+            }
+            "press" | "down" | "up" => {
+                let (extended, code) = key(argument).map_err(error)?;
+                let codes = match command {
+                    "press" => vec![code, code | 0x80],
+                    "down" => vec![code],
+                    _ => vec![code | 0x80],
+                };
+                codes
+                    .into_iter()
+                    .flat_map(|code| {
+                        if extended {
+                            vec![0xe0, code]
+                        } else {
+                            vec![code]
+                        }
+                    })
+                    .collect()
+            }
+            "wait" => {
+                let milliseconds = argument
+                    .parse::<u64>()
+                    .map_err(|_| error(format!("Invalid delay: '{}'", argument)))?;
+                due += milliseconds * STEPS_PER_MILLISECOND;
+                continue;
+            }
+            _ => return Err(error(format!("Unknown command: '{}'", command))),
+        };
+        queue.extend(bytes.into_iter().map(|byte| (due, byte)));
+    }
+
+    Ok(queue)
+}
+
+/// Maps `controller` on ports 60h and 64h and installs keyboard services reading it on
+/// IRQ 1 and `int 16h`. Returns the controller.
+pub fn install(machine: &mut Machine, controller: Controller) -> Rc<RefCell<Controller>> {
+    let controller = Rc::new(RefCell::new(controller));
+    machine
+        .ports
+        .map_shared(KEYBOARD_DATA_PORT..=KEYBOARD_DATA_PORT, controller.clone());
+    machine.ports.map_shared(
+        KEYBOARD_STATUS_PORT..=KEYBOARD_STATUS_PORT,
+        controller.clone(),
+    );
+
+    let keyboard = Rc::new(RefCell::new(Keyboard::controller(controller.clone())));
+    machine
+        .interrupts
+        .set_shared(KEYBOARD_IRQ_VECTOR, keyboard.clone());
+    machine.interrupts.set_shared(KEYBOARD_INTERRUPT, keyboard);
+    controller
+}
@@ -0,0 +1,139 @@
+#[test]
+fn keyboard_script() {
+    use crate::keyboard::{keystrokes, parse_script, STEPS_PER_MILLISECOND};
+
+    assert_eq!(keystrokes(b'a'), Some(vec![0x1e, 0x9e]));
+    assert_eq!(keystrokes(b'A'), Some(vec![0x2a, 0x1e, 0x9e, 0xaa]));
+    assert_eq!(keystrokes(0x03), Some(vec![0x1d, 0x2e, 0xae, 0x9d]));
+    assert_eq!(keystrokes(b'\r'), Some(vec![0x1c, 0x9c]));
+    assert_eq!(keystrokes(0xe9), None);
+
+    let script = "# greeting\n\ntype \"Hi\\n\"\nwait 10\npress left\nup 0x1d\n";
+    let queue = parse_script(script).unwrap();
+    let bytes = queue.iter().map(|(_, byte)| *byte).collect::<Vec<_>>();
+    assert_eq!(
+        bytes,
+        [0x2a, 0x23, 0xa3, 0xaa, 0x17, 0x97, 0x1c, 0x9c, 0xe0, 0x4b, 0xe0, 0xcb, 0x9d]
+    );
+    assert_eq!(queue[7].0, 0);
+    assert_eq!(queue[8].0, 10 * STEPS_PER_MILLISECOND);
+
+    assert_eq!(
+        parse_script("type ok\njump").unwrap_err(),
+        "Line 2: Unknown command: 'jump'"
+    );
+    assert_eq!(
+        parse_script("press hyper").unwrap_err(),
+        "Line 1: Unknown key: 'hyper'"
+    );
+    assert_eq!(
+        parse_script("wait soon").unwrap_err(),
+        "Line 1: Invalid delay: 'soon'"
+    );
+}
+
+#[test]
+fn keyboard_controller() {
+    use crate::{
+        keyboard::{Controller, KEYBOARD_DATA_PORT, KEYBOARD_STATUS_PORT},
+        pic::Pic,
+        port::PortDevice,
+    };
+
+    let mut controller = Controller::from_script("type a").unwrap();
+    let mut pic = Pic::new();
+    assert_eq!(controller.read_byte(KEYBOARD_STATUS_PORT) & 0x01, 0);
+
+    // a scan code fills the output buffer and raises IRQ 1
+    controller.update(1, &mut pic);
+    assert_eq!(controller.read_byte(KEYBOARD_STATUS_PORT) & 0x01, 0x01);
+    assert_eq!(pic.pending(), Some(1));
+    assert_eq!(controller.read_byte(KEYBOARD_DATA_PORT), 0x1e);
+    assert_eq!(controller.read_byte(KEYBOARD_STATUS_PORT) & 0x01, 0);
+
+    // replies come before scan codes
+    controller.write_byte(KEYBOARD_STATUS_PORT, 0xaa);
+    controller.update(2, &mut pic);
+    assert_eq!(controller.read_byte(KEYBOARD_DATA_PORT), 0x55);
+    controller.write_byte(KEYBOARD_DATA_PORT, 0xf2);
+    let mut replies = Vec::new();
+    for clock in 3..6 {
+        controller.update(clock, &mut pic);
+        replies.push(controller.read_byte(KEYBOARD_DATA_PORT));
+    }
+    assert_eq!(replies, [0xfa, 0xab, 0x83]);
+
+    // set the LEDs, then turn off IRQ 1 through the command byte
+    controller.write_byte(KEYBOARD_DATA_PORT, 0xed);
+    controller.write_byte(KEYBOARD_DATA_PORT, 0x04);
+    assert_eq!(controller.leds, 0x04);
+    controller.write_byte(KEYBOARD_STATUS_PORT, 0x60);
+    controller.write_byte(KEYBOARD_DATA_PORT, 0x44);
+    assert_eq!(controller.command_byte, 0x44);
+    assert_eq!(controller.read_byte(KEYBOARD_STATUS_PORT) & 0x08, 0);
+
+    let mut pic = Pic::new();
+    for clock in 6..8 {
+        controller.update(clock, &mut pic);
+        assert_eq!(controller.read_byte(KEYBOARD_DATA_PORT), 0xfa);
+    }
+    controller.update(8, &mut pic);
+    assert_eq!(controller.read_byte(KEYBOARD_DATA_PORT), 0x9e);
+    assert_eq!(pic.pending(), None);
+    assert!(controller.finished());
+}
+
+#[test]
+fn keyboard_machine() {
+    use crate::{
+        keyboard::{self, Controller, STEPS_PER_MILLISECOND},
+        machine::Machine,
+    };
+    use asmrs_parser::lexer::token::Cpu;
+
+    // sti / mov ah, 0 / int 16h / stosw / jmp short back to mov / (hlt)
+    let mut machine = Machine::new(Cpu::I8086);
+    machine.memory.load(
+        0x500,
+        &[0xfb, 0xb4, 0x00, 0xcd, 0x16, 0xab, 0xeb, 0xf9, 0xf4],
+    );
+    machine.registers.ip = 0x500;
+    machine.registers.sp = 0xfffe;
+    machine.registers.di = 0x700;
+
+    let script = "wait 5\ntype x\nwait 5\ntype X\nwait 5\npress up\n";
+    keyboard::install(&mut machine, Controller::from_script(script).unwrap());
+
+    // int 16h waits with the machine running until the keys arrive
+    machine.run(4 * STEPS_PER_MILLISECOND).unwrap();
+    assert_eq!(machine.registers.di, 0x700);
+
+    machine.run(20 * STEPS_PER_MILLISECOND).unwrap_err();
+    assert_eq!(machine.registers.di, 0x706);
+    assert_eq!(machine.memory.read_word(0x700), 0x2d78);
+    assert_eq!(machine.memory.read_word(0x702), 0x2d58);
+    assert_eq!(machine.memory.read_word(0x704), 0x4800);
+}
+
+#[test]
+fn keyboard_polling() {
+    use crate::{
+        keyboard::{self, Controller},
+        machine::Machine,
+    };
+    use asmrs_parser::lexer::token::Cpu;
+
+    // cli / in al, 64h / test al, 1 / jz back to in / in al, 60h / hlt
+    let mut machine = Machine::new(Cpu::I8086);
+    machine.memory.load(
+        0x500,
+        &[0xfa, 0xe4, 0x64, 0xa8, 0x01, 0x74, 0xfa, 0xe4, 0x60, 0xf4],
+    );
+    machine.registers.ip = 0x500;
+    machine.registers.sp = 0xfffe;
+    keyboard::install(&mut machine, Controller::from_script("press esc").unwrap());
+
+    machine.run(100).unwrap();
+    assert!(machine.halted);
+    assert_eq!(machine.registers.ax & 0xff, 0x01);
+}
@@ -0,0 +1,18 @@
+pub mod bios;
+pub mod debugger;
+pub mod dos;
+pub mod fpu;
+pub mod gdb;
+pub mod graphics;
+pub mod interrupt;
+pub mod journal;
+pub mod keyboard;
+pub mod loader;
+pub mod machine;
+pub mod memory;
+pub mod pic;
+pub mod pit;
+pub mod port;
+pub mod registers;
+pub mod screen;
+pub mod trace;
@@ -0,0 +1,414 @@
+use crate::{
+    memory::{address, Memory},
+    registers::{Registers, FLAG_INTERRUPT},
+};
+use std::{error::Error, fmt::Display};
+
+mod test;
+
+/// Segment of the PSP when the caller does not choose one
+pub const DEFAULT_PSP_SEGMENT: u16 = 0x1000;
+
+/// Size of the program segment prefix
+pub const PSP_SIZE: usize = 0x100;
+
+/// First segment above conventional memory, stored in the PSP as end of allocation
+pub const MEMORY_END_SEGMENT: u16 = 0xa000;
+
+/// Longest command tail that fits into the PSP (without the terminating CR)
+pub const COMMAND_TAIL_LENGTH: usize = 126;
+
+/// Loads a .COM image behind a PSP at `psp_segment` and sets up registers like MS-DOS 5+:
+/// CS=DS=ES=SS=PSP, IP=100h, SP=FFFEh with a zero word pushed (so `ret` reaches `int 20h`),
+/// AX=BX=0, CX=00FFh, DX=PSP, SI=100h, DI=FFFEh, BP=091Ch, interrupts enabled.
+pub fn load_com(
+    memory: &mut Memory,
+    registers: &mut Registers,
+    image: &[u8],
+    psp_segment: u16,
+    command_tail: &str,
+) -> Result<(), LoadError> {
+    // the image shares the segment with the PSP and the return address on the stack
+    if PSP_SIZE + image.len() > 0xfffe {
+        return Err(LoadError::new(format!(
+            "Image of {} bytes does not fit into a .COM segment.",
+            image.len()
+        )));
+    }
+
+    write_psp(memory, psp_segment, command_tail)?;
+    memory.load(address(psp_segment, PSP_SIZE as u16), image);
+    memory.write_word(address(psp_segment, 0xfffe), 0);
+
+    *registers = Registers {
+        ax: 0,
+        bx: 0,
+        cx: 0x00ff,
+        dx: psp_segment,
+        sp: 0xfffe,
+        bp: 0x091c,
+        si: PSP_SIZE as u16,
+        di: 0xfffe,
+        ip: PSP_SIZE as u16,
+        cs: psp_segment,
+        ds: psp_segment,
+        ss: psp_segment,
+        es: psp_segment,
+        flags: 0x0002 | FLAG_INTERRUPT,
+    };
+
+    Ok(())
+}
+
+/// Header fields of an MZ executable needed for loading
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MzHeader {
+    /// Bytes used in the last 512 byte page (0 if it is full)
+    pub last_page_size: u16,
+    /// Number of 512 byte pages, the last one possibly partial
+    pub pages: u16,
+    pub relocations: u16,
+    pub header_paragraphs: u16,
+    pub min_alloc: u16,
+    pub max_alloc: u16,
+    pub ss: u16,
+    pub sp: u16,
+    pub ip: u16,
+    pub cs: u16,
+    pub relocation_offset: u16,
+}
+
+impl MzHeader {
+    /// Parses and validates the header at the start of `file`.
+    pub fn parse(file: &[u8]) -> Result<MzHeader, LoadError> {
+        if file.len() < 0x1c || !matches!(&file[..2], b"MZ" | b"ZM") {
+            return Err(LoadError::new("Missing MZ signature.".to_string()));
+        }
+
+        let word = |offset: usize| u16::from_le_bytes([file[offset], file[offset + 1]]);
+
+        let header = MzHeader {
+            last_page_size: word(0x02),
+            pages: word(0x04),
+            relocations: word(0x06),
+            header_paragraphs: word(0x08),
+            min_alloc: word(0x0a),
+            max_alloc: word(0x0c),
+            ss: word(0x0e),
+            sp: word(0x10),
+            ip: word(0x14),
+            cs: word(0x16),
+            relocation_offset: word(0x18),
+        };
+
+        let relocation_end = header.relocation_offset as usize + header.relocations as usize * 4;
+        if header.image_end() > file.len()
+            || header.header_size() > header.image_end()
+            || relocation_end > file.len()
+        {
+            return Err(LoadError::new("Truncated MZ executable.".to_string()));
+        }
+
+        Ok(header)
+    }
+
+    pub fn header_size(&self) -> usize {
+        self.header_paragraphs as usize * 16
+    }
+
+    /// End of the load image within the file
+    pub fn image_end(&self) -> usize {
+        let size = self.pages as usize * 512;
+        match self.last_page_size {
+            0 => size,
+            last => size.saturating_sub(512 - last as usize),
+        }
+    }
+}
+
+/// Loads an MZ executable behind a PSP at `psp_segment`. The image is placed at the load
+/// segment PSP+10h, relocations are applied by adding the load segment, and registers are
+/// set up from the header: CS:IP and SS:SP relative to the load segment, DS=ES=DX=PSP,
+/// AX=BX=0, CX=00FFh, interrupts enabled.
+pub fn load_exe(
+    memory: &mut Memory,
+    registers: &mut Registers,
+    file: &[u8],
+    psp_segment: u16,
+    command_tail: &str,
+) -> Result<(), LoadError> {
+    let header = MzHeader::parse(file)?;
+    let image = &file[header.header_size()..header.image_end()];
+    let load_segment = psp_segment.wrapping_add((PSP_SIZE / 16) as u16);
+
+    let end = load_segment as usize + image.len().div_ceil(16) + header.min_alloc as usize;
+    if end > MEMORY_END_SEGMENT as usize {
+        return Err(LoadError::new(format!(
+            "Executable needs memory up to segment {:04X}h, above conventional memory.",
+            end
+        )));
+    }
+
+    write_psp(memory, psp_segment, command_tail)?;
+    memory.load(address(load_segment, 0), image);
+
+    for index in 0..header.relocations as usize {
+        let entry = header.relocation_offset as usize + index * 4;
+        let offset = u16::from_le_bytes([file[entry], file[entry + 1]]);
+        let segment = u16::from_le_bytes([file[entry + 2], file[entry + 3]]);
+
+        let target = address(load_segment.wrapping_add(segment), offset);
+        let value = memory.read_word(target).wrapping_add(load_segment);
+        memory.write_word(target, value);
+    }
+
+    *registers = Registers {
+        ax: 0,
+        bx: 0,
+        cx: 0x00ff,
+        dx: psp_segment,
+        sp: header.sp,
+        ip: header.ip,
+        cs: load_segment.wrapping_add(header.cs),
+        ds: psp_segment,
+        ss: load_segment.wrapping_add(header.ss),
+        es: psp_segment,
+        flags: 0x0002 | FLAG_INTERRUPT,
+        ..Registers::default()
+    };
+
+    Ok(())
+}
+
+/// Address the BIOS loads the boot sector to (0000:7C00)
+pub const BOOT_SECTOR_ADDRESS: u16 = 0x7c00;
+
+/// Boots a raw disk image the way the BIOS does: sector 0 is loaded to 0000:7C00 and
+/// entered with DL set to the boot drive (00h for the first floppy, 80h for the first
+/// hard disk). The stack is placed directly below the boot sector.
+pub fn load_boot_sector(
+    memory: &mut Memory,
+    registers: &mut Registers,
+    disk: &[u8],
+    drive: u8,
+) -> Result<(), LoadError> {
+    if disk.len() < 512 {
+        return Err(LoadError::new(
+            "Disk image is smaller than one sector.".to_string(),
+        ));
+    }
+
+    if disk[510..512] != [0x55, 0xaa] {
+        return Err(LoadError::new(
+            "Boot sector is missing the 55h AAh signature.".to_string(),
+        ));
+    }
+
+    memory.load(address(0, BOOT_SECTOR_ADDRESS), &disk[..512]);
+
+    *registers = Registers {
+        dx: drive as u16,
+        sp: BOOT_SECTOR_ADDRESS,
+        ip: BOOT_SECTOR_ADDRESS,
+        flags: 0x0002 | FLAG_INTERRUPT,
+        ..Registers::default()
+    };
+
+    Ok(())
+}
+
+/// Loads Intel HEX records into memory. Data records (00) are placed relative to the last
+/// extended segment (02) or linear (04) address; a start segment (03) or start linear (05)
+/// record sets CS:IP.
+pub fn load_intel_hex(
+    memory: &mut Memory,
+    registers: &mut Registers,
+    text: &str,
+) -> Result<(), LoadError> {
+    let mut base = 0usize;
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let error = |message: &str| LoadError::new(format!("Line {}: {}", line_index + 1, message));
+
+        let bytes = line
+            .strip_prefix(':')
+            .and_then(hex_bytes)
+            .ok_or_else(|| error("Invalid Intel HEX record."))?;
+
+        if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+            return Err(error("Record length does not match its byte count."));
+        }
+
+        if bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(error("Checksum mismatch."));
+        }
+
+        let offset = u16::from_be_bytes([bytes[1], bytes[2]]) as usize;
+        let data = &bytes[4..bytes.len() - 1];
+        let word = |index: usize| u16::from_be_bytes([data[index], data[index + 1]]);
+
+        match (bytes[3], data.len()) {
+            (0x00, _) => memory.load(base + offset, data),
+            (0x01, _) => return Ok(()),
+            (0x02, 2) => base = (word(0) as usize) << 4,
+            (0x03, 4) => {
+                registers.cs = word(0);
+                registers.ip = word(2);
+            }
+            (0x04, 2) => base = (word(0) as usize) << 16,
+            (0x05, 4) => {
+                let entry = ((word(0) as u32) << 16) | word(2) as u32;
+                (registers.cs, registers.ip) = linear_to_far(entry);
+            }
+            _ => return Err(error("Unsupported record type.")),
+        }
+    }
+
+    Err(LoadError::new(
+        "Missing Intel HEX end of file record.".to_string(),
+    ))
+}
+
+/// Loads Motorola S-records into memory. S1/S2/S3 data records are placed at their linear
+/// address; S7/S8/S9 termination records set CS:IP to the start address.
+pub fn load_s_record(
+    memory: &mut Memory,
+    registers: &mut Registers,
+    text: &str,
+) -> Result<(), LoadError> {
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let error = |message: &str| LoadError::new(format!("Line {}: {}", line_index + 1, message));
+
+        let (r#type, bytes) = line
+            .strip_prefix('S')
+            .and_then(|line| line.split_at_checked(1))
+            .and_then(|(r#type, line)| Some((r#type, hex_bytes(line)?)))
+            .ok_or_else(|| error("Invalid S-record."))?;
+
+        if bytes.is_empty() || bytes.len() != bytes[0] as usize + 1 {
+            return Err(error("Record length does not match its byte count."));
+        }
+
+        if bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0xff {
+            return Err(error("Checksum mismatch."));
+        }
+
+        let address_length = match r#type {
+            "0" | "1" | "5" | "9" => 2,
+            "2" | "6" | "8" => 3,
+            "3" | "7" => 4,
+            _ => return Err(error("Unsupported record type.")),
+        };
+
+        if bytes.len() < address_length + 2 {
+            return Err(error("Record is too short for its address."));
+        }
+
+        let address = bytes[1..=address_length]
+            .iter()
+            .fold(0u32, |address, byte| (address << 8) | *byte as u32);
+        let data = &bytes[address_length + 1..bytes.len() - 1];
+
+        match r#type {
+            "1" | "2" | "3" => memory.load(address as usize, data),
+            "7" | "8" | "9" => {
+                (registers.cs, registers.ip) = linear_to_far(address);
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+
+    Err(LoadError::new(
+        "Missing S-record termination record.".to_string(),
+    ))
+}
+
+/// Splits a linear address below 1 MiB into a 64 KiB aligned segment and an offset.
+fn linear_to_far(address: u32) -> (u16, u16) {
+    (((address & 0xf0000) >> 4) as u16, address as u16)
+}
+
+/// Parses pairs of hex digits.
+fn hex_bytes(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&text[index..index + 2], 16).ok())
+        .collect()
+}
+
+/// Writes a program segment prefix at `segment:0000`: `int 20h` at offset 0, the end of
+/// memory, the job file table with the standard handles, the `int 21h`/`retf` dispatcher
+/// at 50h, two empty FCBs and the command tail at 80h.
+pub fn write_psp(memory: &mut Memory, segment: u16, command_tail: &str) -> Result<(), LoadError> {
+    if command_tail.len() > COMMAND_TAIL_LENGTH {
+        return Err(LoadError::new(format!(
+            "Command tail exceeds {} characters.",
+            COMMAND_TAIL_LENGTH
+        )));
+    }
+
+    let base = address(segment, 0);
+    memory.load(base, &[0; PSP_SIZE]);
+
+    // int 20h
+    memory.load(base, &[0xcd, 0x20]);
+    memory.write_word(base + 0x02, MEMORY_END_SEGMENT);
+
+    // job file table: stdin, stdout, stderr on CON, stdaux on AUX, stdprn on PRN
+    let mut handles = [0xff; 20];
+    handles[..5].copy_from_slice(&[0x01, 0x01, 0x01, 0x00, 0x02]);
+    memory.load(base + 0x18, &handles);
+    memory.write_word(base + 0x32, handles.len() as u16);
+    memory.write_word(base + 0x34, 0x18);
+    memory.write_word(base + 0x36, segment);
+
+    // int 21h, retf
+    memory.load(base + 0x50, &[0xcd, 0x21, 0xcb]);
+
+    // unopened FCBs: default drive, blank name
+    for fcb in [0x5c, 0x6c] {
+        memory.write_byte(base + fcb, 0);
+        memory.load(base + fcb + 1, &[b' '; 11]);
+    }
+
+    memory.write_byte(base + 0x80, command_tail.len() as u8);
+    memory.load(base + 0x81, command_tail.as_bytes());
+    memory.write_byte(base + 0x81 + command_tail.len(), b'\r');
+
+    Ok(())
+}
+
+#[derive(Clone, Debug)]
+pub struct LoadError {
+    message: String,
+}
+
+impl LoadError {
+    /// Creates a new Load Error with the given message.
+    pub fn new(message: String) -> LoadError {
+        Self { message }
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Load Error: {}", self.message)
+    }
+}
+
+impl Error for LoadError {}
@@ -0,0 +1,195 @@
+#[test]
+fn load_com_registers() {
+    use crate::{
+        loader::load_com,
+        memory::{address, Memory},
+        registers::Registers,
+    };
+
+    let mut memory = Memory::new();
+    let mut registers = Registers::default();
+
+    let output = load_com(&mut memory, &mut registers, &[0xb4, 0x4c], 0x2000, "");
+    assert!(output.is_ok());
+
+    assert_eq!(registers.cs, 0x2000);
+    assert_eq!(registers.ds, 0x2000);
+    assert_eq!(registers.ss, 0x2000);
+    assert_eq!(registers.ip, 0x100);
+    assert_eq!(registers.sp, 0xfffe);
+    assert_eq!(memory.read_word(address(0x2000, 0xfffe)), 0);
+    assert_eq!(memory.read(address(0x2000, 0x100), 2), vec![0xb4, 0x4c]);
+}
+
+#[test]
+fn load_com_psp() {
+    use crate::{
+        loader::{load_com, MEMORY_END_SEGMENT},
+        memory::{address, Memory},
+        registers::Registers,
+    };
+
+    let mut memory = Memory::new();
+    let mut registers = Registers::default();
+    load_com(&mut memory, &mut registers, &[0xc3], 0x2000, " /v file.txt").unwrap();
+
+    let psp = address(0x2000, 0);
+    assert_eq!(memory.read(psp, 2), vec![0xcd, 0x20]);
+    assert_eq!(memory.read_word(psp + 2), MEMORY_END_SEGMENT);
+    assert_eq!(memory.read(psp + 0x50, 3), vec![0xcd, 0x21, 0xcb]);
+    assert_eq!(memory.read_byte(psp + 0x80), 12);
+    assert_eq!(memory.read(psp + 0x81, 13), b" /v file.txt\r".to_vec());
+}
+
+#[test]
+fn load_com_errors() {
+    use crate::{loader::load_com, memory::Memory, registers::Registers};
+
+    let mut memory = Memory::new();
+    let mut registers = Registers::default();
+
+    let image = vec![0x90; 0xff00];
+    assert!(load_com(&mut memory, &mut registers, &image, 0x2000, "").is_err());
+
+    let tail = "x".repeat(127);
+    assert!(load_com(&mut memory, &mut registers, &[0xc3], 0x2000, &tail).is_err());
+}
+
+#[test]
+fn load_exe_relocations() {
+    use crate::{
+        loader::load_exe,
+        memory::{address, Memory},
+        registers::Registers,
+    };
+
+    // header of 2 paragraphs with one relocation at 0001:0002
+    let mut file = vec![0; 32];
+    let fields: [(usize, u16); 11] = [
+        (0x00, 0x5a4d),
+        (0x02, 32 + 20),
+        (0x04, 1),
+        (0x06, 1),
+        (0x08, 2),
+        (0x0a, 0x10),
+        (0x0c, 0xffff),
+        (0x0e, 0x0001),
+        (0x10, 0x0080),
+        (0x16, 0x0000),
+        (0x18, 0x1c),
+    ];
+    for (offset, value) in fields {
+        file[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+    file[0x1c..0x20].copy_from_slice(&[0x02, 0x00, 0x01, 0x00]);
+    // image: 16 bytes of code, then data segment with a segment reference at offset 2
+    file.extend([0x90; 16]);
+    file.extend([0x00, 0x00, 0x01, 0x00]);
+
+    let mut memory = Memory::new();
+    let mut registers = Registers::default();
+    let output = load_exe(&mut memory, &mut registers, &file, 0x2000, "");
+    assert!(output.is_ok());
+
+    assert_eq!(registers.cs, 0x2010);
+    assert_eq!(registers.ip, 0);
+    assert_eq!(registers.ss, 0x2011);
+    assert_eq!(registers.sp, 0x80);
+    assert_eq!(registers.ds, 0x2000);
+    assert_eq!(memory.read_byte(address(0x2010, 0)), 0x90);
+    // segment 0001h relocated to 2011h
+    assert_eq!(memory.read_word(address(0x2011, 2)), 0x2011);
+}
+
+#[test]
+fn load_exe_errors() {
+    use crate::{loader::load_exe, memory::Memory, registers::Registers};
+
+    let mut memory = Memory::new();
+    let mut registers = Registers::default();
+
+    assert!(load_exe(&mut memory, &mut registers, b"not an exe", 0x2000, "").is_err());
+
+    // claims 4 pages but contains only the header
+    let mut file = vec![0; 32];
+    file[..2].copy_from_slice(b"MZ");
+    file[0x04] = 4;
+    file[0x08] = 2;
+    assert!(load_exe(&mut memory, &mut registers, &file, 0x2000, "").is_err());
+}
+
+#[test]
+fn load_boot_sector_drive() {
+    use crate::{
+        loader::load_boot_sector,
+        memory::{address, Memory},
+        registers::Registers,
+    };
+
+    let mut disk = vec![0; 1024];
+    disk[0] = 0xfa;
+    disk[510..512].copy_from_slice(&[0x55, 0xaa]);
+    disk[512] = 0xff;
+
+    let mut memory = Memory::new();
+    let mut registers = Registers::default();
+    let output = load_boot_sector(&mut memory, &mut registers, &disk, 0x80);
+    assert!(output.is_ok());
+
+    assert_eq!(registers.cs, 0);
+    assert_eq!(registers.ip, 0x7c00);
+    assert_eq!(registers.dx & 0xff, 0x80);
+    assert_eq!(memory.read_byte(address(0, 0x7c00)), 0xfa);
+    assert_eq!(memory.read_word(address(0, 0x7dfe)), 0xaa55);
+    // only sector 0 is loaded
+    assert_eq!(memory.read_byte(address(0, 0x7e00)), 0);
+
+    disk[511] = 0;
+    assert!(load_boot_sector(&mut memory, &mut registers, &disk, 0).is_err());
+}
+
+#[test]
+fn load_intel_hex_segments() {
+    use crate::{
+        loader::load_intel_hex,
+        memory::{address, Memory},
+        registers::Registers,
+    };
+
+    let text = ":020000021000EC\n\
+                :02FFFE001122CE\n\
+                :020000022000DC\n\
+                :0100000033CC\n\
+                :0400000310000100E8\n\
+                :00000001FF\n";
+
+    let mut memory = Memory::new();
+    let mut registers = Registers::default();
+    let output = load_intel_hex(&mut memory, &mut registers, text);
+    assert!(output.is_ok());
+
+    assert_eq!(memory.read(address(0x1000, 0xfffe), 2), vec![0x11, 0x22]);
+    assert_eq!(memory.read_byte(address(0x2000, 0)), 0x33);
+    assert_eq!((registers.cs, registers.ip), (0x1000, 0x0100));
+
+    let corrupted = ":0100000033CD\n:00000001FF\n";
+    assert!(load_intel_hex(&mut memory, &mut registers, corrupted).is_err());
+    assert!(load_intel_hex(&mut memory, &mut registers, ":0100000033CC\n").is_err());
+}
+
+#[test]
+fn load_s_record_entry() {
+    use crate::{loader::load_s_record, memory::Memory, registers::Registers};
+
+    let text = "S008000061736D7273D1\nS2050FFFF0EA12\nS8040FFFF0FD\n";
+
+    let mut memory = Memory::new();
+    let mut registers = Registers::default();
+    let output = load_s_record(&mut memory, &mut registers, text);
+    assert!(output.is_ok());
+
+    assert_eq!(memory.read_byte(0xffff0), 0xea);
+    assert_eq!((registers.cs, registers.ip), (0xf000, 0xfff0));
+
+    assert!(load_s_record(&mut memory, &mut registers, "S1060100B83412FB\n").is_err());
+}
@@ -0,0 +1,1689 @@
+use crate::{
+    fpu::{self, Arithmetic, Fpu},
+    interrupt::{stub, write_vector_table, Interrupts, STUB_OFFSET, STUB_SEGMENT},
+    memory::{address, Memory},
+    pic::{Pic, PIC_COMMAND_PORT, PIC_DATA_PORT},
+    port::Ports,
+    registers::{
+        Registers, FLAG_AUXILIARY, FLAG_CARRY, FLAG_DIRECTION, FLAG_INTERRUPT, FLAG_OVERFLOW,
+        FLAG_PARITY, FLAG_SIGN, FLAG_TRAP, FLAG_ZERO,
+    },
+};
+use asmrs_disassembler::decoder::{self, decode, Instruction, Operand};
+use asmrs_parser::lexer::token::{
+    Condition, Cpu, FloatingPointRegister, GeneralPurposeRegister, InstructionType, OperandSize,
+    RegisterType, SegmentRegister, SpecialPurposeRegister,
+};
+use std::{error::Error, fmt::Display};
+
+mod test;
+
+/// Bytes of the 8087 environment (`fstenv`): control, status and tag word followed by
+/// the instruction and operand pointers
+const FPU_ENVIRONMENT_SIZE: usize = 14;
+
+/// Longest possible instruction, prefixes included
+const MAX_INSTRUCTION_LENGTH: u16 = 15;
+
+/// Flags `popf` and `sahf` can change (bit 1 always reads as set)
+const FLAGS_WRITABLE: u16 = 0x0fd5;
+
+/// Divide error exception
+const DIVIDE_ERROR: u8 = 0x00;
+/// Single step trap, taken after each instruction while TF is set
+const SINGLE_STEP: u8 = 0x01;
+/// `bound` range exceeded exception (80186 and later)
+const BOUND_RANGE: u8 = 0x05;
+
+/// Direction of a memory access
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// Memory access made by the last instruction
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Access {
+    pub kind: AccessKind,
+    /// Linear address
+    pub address: usize,
+    /// 1 for bytes, 2 for words
+    pub size: u8,
+    pub value: u16,
+    /// Value before a write (equal to `value` for reads)
+    pub previous: u16,
+}
+
+/// Processor and memory of the virtual machine, executing one instruction per `step`.
+#[derive(Clone, Debug)]
+pub struct Machine {
+    pub registers: Registers,
+    pub memory: Memory,
+    pub fpu: Fpu,
+    /// Instruction set executed
+    pub cpu: Cpu,
+    /// Set by `hlt`; an interrupt resumes execution when IF is set
+    pub halted: bool,
+    /// Instructions executed so far
+    pub instructions: u64,
+    /// Steps taken so far, executed or halted; devices keep time with it
+    pub clock: u64,
+    /// Rust services called through the stubs of the interrupt vector table
+    pub interrupts: Interrupts,
+    /// Interrupt controller feeding hardware interrupts to the processor
+    pub pic: Pic,
+    /// Devices answering `in` and `out`
+    pub ports: Ports,
+    accesses: Vec<Access>,
+    /// Set when the last step reached a device through `ports`
+    port_io: bool,
+    /// Set by a handler that has to wait; its interrupt is taken again at the next step
+    retry: bool,
+}
+
+impl Machine {
+    /// Creates a machine with zeroed registers (FLAGS bit 1 set) and memory, except for the
+    /// interrupt vector table pointing at the handler stubs.
+    pub fn new(cpu: Cpu) -> Machine {
+        let mut memory = Memory::new();
+        write_vector_table(&mut memory);
+
+        Self {
+            registers: Registers {
+                flags: 0x0002,
+                ..Registers::default()
+            },
+            memory,
+            fpu: Fpu::new(),
+            cpu,
+            halted: false,
+            instructions: 0,
+            clock: 0,
+            interrupts: Interrupts::new(),
+            pic: Pic::new(),
+            ports: Ports::new(),
+            accesses: Vec::new(),
+            port_io: false,
+            retry: false,
+        }
+    }
+
+    /// Memory accesses of the last step, in order
+    pub fn accesses(&self) -> &[Access] {
+        &self.accesses
+    }
+
+    /// Whether the last step read or wrote a port outside the interrupt controller. The
+    /// devices there keep state of their own that the machine cannot restore.
+    pub fn port_io(&self) -> bool {
+        self.port_io
+    }
+
+    /// Decodes the instruction at `segment:offset` without executing it.
+    pub fn decode_at(&self, segment: u16, offset: u16) -> Result<Instruction, ExecutionError> {
+        let bytes = (0..MAX_INSTRUCTION_LENGTH)
+            .map(|index| {
+                self.memory
+                    .read_byte(address(segment, offset.wrapping_add(index)))
+            })
+            .collect::<Vec<_>>();
+
+        decode(&bytes, offset, self.cpu)
+            .map_err(|error| ExecutionError::new(error.to_string(), segment, offset))
+    }
+
+    /// Decodes the instruction at CS:IP.
+    pub fn fetch(&self) -> Result<Instruction, ExecutionError> {
+        self.decode_at(self.registers.cs, self.registers.ip)
+    }
+
+    /// Executes the instruction at CS:IP, then takes a single step trap or a pending
+    /// hardware interrupt. A halted machine stays at the instruction after `hlt` until an
+    /// interrupt arrives. On error CS:IP is left at the failing instruction.
+    pub fn step(&mut self) -> Result<(), ExecutionError> {
+        self.accesses.clear();
+        self.port_io = false;
+
+        let (cs, ip) = (self.registers.cs, self.registers.ip);
+        let error = |message| ExecutionError::new(message, cs, ip);
+
+        if self.halted {
+            self.tick();
+            return self.accept_interrupt().map_err(error);
+        }
+
+        // a stub whose vector has a Rust handler runs it instead of its `iret`
+        if let Some(vector) = self.stub_vector() {
+            self.service(vector).map_err(error)?;
+            self.instructions += 1;
+            self.tick();
+            return self.accept_interrupt().map_err(error);
+        }
+
+        let instruction = self.fetch()?;
+        self.registers.ip = ip.wrapping_add(instruction.bytes.len() as u16);
+
+        // interrupt instructions clear TF, the trap comes after the handler returns
+        let trap = self.flag(FLAG_TRAP)
+            && !matches!(
+                instruction.r#type,
+                InstructionType::Int | InstructionType::Int3 | InstructionType::Into
+            );
+        // interrupts wait one more instruction after `sti` and loads of SS
+        let shadow = (instruction.r#type == InstructionType::Sti && !self.flag(FLAG_INTERRUPT))
+            || (matches!(
+                instruction.r#type,
+                InstructionType::Mov | InstructionType::Pop
+            ) && instruction.operands.first()
+                == Some(&Operand::Register(RegisterType::Segment(
+                    SegmentRegister::Ss,
+                ))));
+
+        if let Err(message) = self.execute(&instruction) {
+            self.registers.cs = cs;
+            self.registers.ip = ip;
+            return Err(error(message));
+        }
+
+        if std::mem::take(&mut self.retry) {
+            self.registers.ip = ip;
+        }
+
+        self.instructions += 1;
+        self.tick();
+
+        let (cs, ip) = (self.registers.cs, self.registers.ip);
+        let error = |message| ExecutionError::new(message, cs, ip);
+        // without a handler the trap is ignored like the `iret` of its stub
+        if trap && !self.halted {
+            if self.vectored(SINGLE_STEP) {
+                self.enter_interrupt(SINGLE_STEP);
+            } else if self.interrupts.get(SINGLE_STEP).is_some() {
+                self.interrupt(SINGLE_STEP).map_err(error)?;
+            }
+        }
+        if !shadow {
+            self.accept_interrupt().map_err(error)?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs until `hlt` with nothing to wake the machine, or an error, at most `limit`
+    /// steps. Returns the number of instructions executed.
+    pub fn run(&mut self, limit: u64) -> Result<u64, ExecutionError> {
+        let start = self.instructions;
+
+        for _ in 0..limit {
+            if self.halted && !self.waiting() {
+                break;
+            }
+            self.step()?;
+        }
+
+        Ok(self.instructions - start)
+    }
+
+    /// Whether the machine is halted waiting for a hardware interrupt: one is pending, or
+    /// the program hooked the vector of an unmasked line. A `hlt` ending a program that
+    /// installed no handler stops the run even with IF set.
+    pub fn waiting(&self) -> bool {
+        self.halted
+            && (self.interrupt_pending()
+                || self.flag(FLAG_INTERRUPT)
+                    && (0..8).any(|line| {
+                        self.pic.imr & (1 << line) == 0
+                            && self.vectored(self.pic.base.wrapping_add(line))
+                    }))
+    }
+
+    /// Advances the clock and lets the devices catch up.
+    fn tick(&mut self) {
+        self.clock += 1;
+        self.ports.update(self.clock, &mut self.pic);
+    }
+
+    /// Whether a hardware interrupt would be taken now
+    pub fn interrupt_pending(&self) -> bool {
+        self.flag(FLAG_INTERRUPT) && self.pic.pending().is_some()
+    }
+
+    /// Takes a pending hardware interrupt: through the vector table when a program hooked
+    /// the vector, otherwise by calling the Rust handler. Without either, the interrupt is
+    /// ended right away like the dummy handler of a BIOS.
+    fn accept_interrupt(&mut self) -> Result<(), String> {
+        if !self.flag(FLAG_INTERRUPT) {
+            return Ok(());
+        }
+        let Some(vector) = self.pic.acknowledge() else {
+            return Ok(());
+        };
+
+        self.halted = false;
+        if self.vectored(vector) {
+            self.enter_interrupt(vector);
+        } else if self.interrupts.get(vector).is_some() {
+            self.interrupt(vector)?;
+        } else {
+            self.pic.end_of_interrupt();
+        }
+        Ok(())
+    }
+
+    /// Whether the vector table entry of `vector` was changed from its stub
+    pub fn vectored(&self, vector: u8) -> bool {
+        let entry = vector as usize * 4;
+        let pointer = (
+            self.memory.read_word(entry + 2),
+            self.memory.read_word(entry),
+        );
+        pointer != stub(vector)
+    }
+
+    /// Pushes FLAGS, CS and IP, clears IF and TF and jumps through the vector table.
+    pub fn enter_interrupt(&mut self, vector: u8) {
+        let entry = vector as usize * 4;
+        let offset = self.read_word(entry);
+        let segment = self.read_word(entry + 2);
+
+        self.push(self.registers.flags);
+        self.push(self.registers.cs);
+        self.push(self.registers.ip);
+        self.set_flag(FLAG_INTERRUPT, false);
+        self.set_flag(FLAG_TRAP, false);
+        self.registers.cs = segment;
+        self.registers.ip = offset;
+    }
+
+    /// `int vector`: the Rust handler while the vector points at its stub, otherwise the
+    /// vector table entry, which is the program's handler or the stub's `iret`.
+    fn software_interrupt(&mut self, vector: u8) -> Result<(), String> {
+        if self.vectored(vector) || self.interrupts.get(vector).is_none() {
+            self.enter_interrupt(vector);
+            Ok(())
+        } else {
+            self.interrupt(vector)
+        }
+    }
+
+    /// Raises a processor exception, failing with `message` when nothing handles it.
+    fn exception(&mut self, vector: u8, message: String) -> Result<(), String> {
+        if self.vectored(vector) || self.interrupts.get(vector).is_some() {
+            self.software_interrupt(vector)
+        } else {
+            Err(message)
+        }
+    }
+
+    /// Vector of the stub at CS:IP, if it has a Rust handler
+    fn stub_vector(&self) -> Option<u8> {
+        let offset = self.registers.ip.checked_sub(STUB_OFFSET)?;
+        let vector = u8::try_from(offset).ok()?;
+        (self.registers.cs == STUB_SEGMENT && self.interrupts.get(vector).is_some())
+            .then_some(vector)
+    }
+
+    /// Runs the Rust handler of a stub reached through the vector table and returns like
+    /// `iret`, keeping the status flags the handler returns.
+    fn service(&mut self, vector: u8) -> Result<(), String> {
+        self.interrupt(vector)?;
+
+        // stay at the stub with interrupts enabled, like a BIOS waiting in a loop
+        if std::mem::take(&mut self.retry) {
+            self.set_flag(FLAG_INTERRUPT, true);
+            return Ok(());
+        }
+
+        self.registers.ip = self.pop();
+        self.registers.cs = self.pop();
+        let flags = self.pop();
+        let restored = FLAG_INTERRUPT | FLAG_TRAP;
+        self.registers.flags = (self.registers.flags & !restored) | (flags & restored);
+        Ok(())
+    }
+
+    /// Reads a byte or word (`size` 1 or 2) from an I/O port. The interrupt controller
+    /// answers on its ports unless a device is mapped there.
+    pub fn read_port(&mut self, port: u16, size: u8) -> Result<u16, String> {
+        if size == 2 && (self.pic_port(port) || self.pic_port(port.wrapping_add(1))) {
+            let low = self.read_port(port, 1)?;
+            let high = self.read_port(port.wrapping_add(1), 1)?;
+            return Ok(high << 8 | low);
+        }
+
+        if self.pic_port(port) {
+            Ok(self.pic.read(port) as u16)
+        } else {
+            self.port_io = true;
+            self.ports.read(port, size)
+        }
+    }
+
+    /// Writes a byte or word (`size` 1 or 2) to an I/O port.
+    pub fn write_port(&mut self, port: u16, size: u8, value: u16) -> Result<(), String> {
+        if size == 2 && (self.pic_port(port) || self.pic_port(port.wrapping_add(1))) {
+            self.write_port(port, 1, value & 0xff)?;
+            return self.write_port(port.wrapping_add(1), 1, value >> 8);
+        }
+
+        if self.pic_port(port) {
+            self.pic.write(port, value as u8);
+            Ok(())
+        } else {
+            self.port_io = true;
+            self.ports.write(port, size, value)
+        }
+    }
+
+    fn pic_port(&self, port: u16) -> bool {
+        matches!(port, PIC_COMMAND_PORT | PIC_DATA_PORT) && self.ports.get(port).is_none()
+    }
+
+    /// Calls the Rust handler of `vector`.
+    pub fn interrupt(&mut self, vector: u8) -> Result<(), String> {
+        let handler = self
+            .interrupts
+            .get(vector)
+            .ok_or_else(|| format!("Interrupt {:02X}h has no handler.", vector))?;
+        let result = handler.borrow_mut().interrupt(self, vector);
+        result
+    }
+
+    /// Called by a Rust handler that has to wait, e.g. for a key: instead of returning,
+    /// the machine takes the interrupt again at the next step. Hardware interrupts come in
+    /// between while IF is set.
+    pub fn retry_interrupt(&mut self) {
+        self.retry = true;
+    }
+
+    /// Whether a handler asked for its interrupt to be taken again
+    pub fn retrying(&self) -> bool {
+        self.retry
+    }
+
+    pub fn flag(&self, flag: u16) -> bool {
+        self.registers.flags & flag != 0
+    }
+
+    pub fn set_flag(&mut self, flag: u16, value: bool) {
+        if value {
+            self.registers.flags |= flag;
+        } else {
+            self.registers.flags &= !flag;
+        }
+    }
+
+    /// Reads a byte and records the access.
+    pub fn read_byte(&mut self, address: usize) -> u8 {
+        let value = self.memory.read_byte(address);
+        self.record(AccessKind::Read, address, 1, value as u16, value as u16);
+        value
+    }
+
+    /// Reads a word and records the access.
+    pub fn read_word(&mut self, address: usize) -> u16 {
+        let value = self.memory.read_word(address);
+        self.record(AccessKind::Read, address, 2, value, value);
+        value
+    }
+
+    /// Writes a byte and records the access.
+    pub fn write_byte(&mut self, address: usize, value: u8) {
+        let previous = self.memory.read_byte(address);
+        self.memory.write_byte(address, value);
+        self.record(AccessKind::Write, address, 1, value as u16, previous as u16);
+    }
+
+    /// Writes `bytes` from the linear `address` on, recording every byte.
+    pub fn write_block(&mut self, address: usize, bytes: &[u8]) {
+        for (index, byte) in bytes.iter().enumerate() {
+            self.write_byte(address + index, *byte);
+        }
+    }
+
+    /// Writes a word and records the access.
+    pub fn write_word(&mut self, address: usize, value: u16) {
+        let previous = self.memory.read_word(address);
+        self.memory.write_word(address, value);
+        self.record(AccessKind::Write, address, 2, value, previous);
+    }
+
+    fn record(&mut self, kind: AccessKind, address: usize, size: u8, value: u16, previous: u16) {
+        self.accesses.push(Access {
+            kind,
+            address: address % crate::memory::MEMORY_SIZE,
+            size,
+            value,
+            previous,
+        });
+    }
+
+    /// Pushes a word onto SS:SP.
+    pub fn push(&mut self, value: u16) {
+        self.registers.sp = self.registers.sp.wrapping_sub(2);
+        self.write_word(address(self.registers.ss, self.registers.sp), value);
+    }
+
+    /// Pops a word from SS:SP.
+    pub fn pop(&mut self) -> u16 {
+        let value = self.read_word(address(self.registers.ss, self.registers.sp));
+        self.registers.sp = self.registers.sp.wrapping_add(2);
+        value
+    }
+
+    /// Segment and offset a memory operand refers to: the override, or SS for BP based
+    /// addressing and DS otherwise.
+    pub fn effective_address(&self, memory: &decoder::Memory) -> (u16, u16) {
+        let offset = [memory.base, memory.index]
+            .into_iter()
+            .flatten()
+            .fold(memory.displacement, |offset, register| {
+                offset.wrapping_add(self.registers.get(register))
+            });
+
+        let segment = match memory.segment {
+            Some(segment) => segment,
+            None if memory.base
+                == Some(RegisterType::SpecialPurpose(SpecialPurposeRegister::Bp)) =>
+            {
+                SegmentRegister::Ss
+            }
+            None => SegmentRegister::Ds,
+        };
+
+        (self.registers.get(RegisterType::Segment(segment)), offset)
+    }
+
+    /// Segment of the implicit source of string instructions and xlat: the override or DS.
+    /// The destination ES:DI cannot be overridden.
+    fn source_segment(&self, instruction: &Instruction) -> u16 {
+        let segment = instruction.segment.unwrap_or(SegmentRegister::Ds);
+        self.registers.get(RegisterType::Segment(segment))
+    }
+
+    fn read_operand(&mut self, operand: &Operand, wide: bool) -> u16 {
+        match operand {
+            Operand::Register(register) => self.registers.get(*register),
+            Operand::Immediate(value) | Operand::Target(value) => *value,
+            Operand::Memory(memory) => {
+                let (segment, offset) = self.effective_address(memory);
+                if wide {
+                    self.read_word(address(segment, offset))
+                } else {
+                    self.read_byte(address(segment, offset)) as u16
+                }
+            }
+            Operand::Far { offset, .. } => *offset,
+        }
+    }
+
+    fn write_operand(&mut self, operand: &Operand, wide: bool, value: u16) -> Result<(), String> {
+        match operand {
+            Operand::Register(register) => self.registers.set(*register, value),
+            Operand::Memory(memory) => {
+                let (segment, offset) = self.effective_address(memory);
+                if wide {
+                    self.write_word(address(segment, offset), value);
+                } else {
+                    self.write_byte(address(segment, offset), value as u8);
+                }
+            }
+            _ => return Err("Operand is not writable.".to_string()),
+        }
+        Ok(())
+    }
+
+    /// Reads a far pointer (offset, segment) from a memory operand.
+    fn read_far_pointer(&mut self, operand: &Operand) -> Result<(u16, u16), String> {
+        let Operand::Memory(memory) = operand else {
+            return Err("Expected memory operand.".to_string());
+        };
+        let (segment, offset) = self.effective_address(memory);
+        let pointer_offset = self.read_word(address(segment, offset));
+        let pointer_segment = self.read_word(address(segment, offset.wrapping_add(2)));
+        Ok((pointer_offset, pointer_segment))
+    }
+
+    fn execute(&mut self, instruction: &Instruction) -> Result<(), String> {
+        use InstructionType as I;
+
+        let operands = &instruction.operands;
+        let wide = width(operands);
+        let operand = |index: usize| {
+            operands
+                .get(index)
+                .ok_or_else(|| format!("'{}' is missing an operand.", instruction.r#type))
+        };
+
+        match instruction.r#type {
+            I::Add | I::Or | I::Adc | I::Sbb | I::And | I::Sub | I::Xor | I::Cmp | I::Test => {
+                let destination = self.read_operand(operand(0)?, wide);
+                let source = self.read_operand(operand(1)?, wide);
+                let result = self.alu(instruction.r#type, destination, source, wide);
+                if !matches!(instruction.r#type, I::Cmp | I::Test) {
+                    self.write_operand(operand(0)?, wide, result)?;
+                }
+            }
+            I::Inc | I::Dec => {
+                let value = self.read_operand(operand(0)?, wide);
+                let carry = self.flag(FLAG_CARRY);
+                let r#type = if instruction.r#type == I::Inc {
+                    I::Add
+                } else {
+                    I::Sub
+                };
+                let result = self.alu(r#type, value, 1, wide);
+                self.set_flag(FLAG_CARRY, carry);
+                self.write_operand(operand(0)?, wide, result)?;
+            }
+            I::Neg => {
+                let value = self.read_operand(operand(0)?, wide);
+                let result = self.alu(I::Sub, 0, value, wide);
+                self.write_operand(operand(0)?, wide, result)?;
+            }
+            I::Not => {
+                let value = self.read_operand(operand(0)?, wide);
+                self.write_operand(operand(0)?, wide, !value)?;
+            }
+            I::Mul | I::Imul if operands.len() == 1 => {
+                let source = self.read_operand(operand(0)?, wide);
+                self.multiply(instruction.r#type == I::Imul, source, wide);
+            }
+            I::Imul => {
+                // 80186 three operand form: reg = r/m * immediate
+                let source = self.read_operand(operand(1)?, true) as i16 as i32;
+                let factor = self.read_operand(operand(2)?, true) as i16 as i32;
+                let product = source * factor;
+                let overflow = product != product as i16 as i32;
+                self.set_flag(FLAG_CARRY, overflow);
+                self.set_flag(FLAG_OVERFLOW, overflow);
+                self.write_operand(operand(0)?, true, product as u16)?;
+            }
+            I::Div | I::Idiv => {
+                let source = self.read_operand(operand(0)?, wide);
+                if let Err(message) = self.divide(instruction.r#type == I::Idiv, source, wide) {
+                    self.fault(instruction);
+                    self.exception(DIVIDE_ERROR, message)?;
+                }
+            }
+            I::Mov => {
+                let value = self.read_operand(operand(1)?, wide);
+                self.write_operand(operand(0)?, wide, value)?;
+            }
+            I::Xchg => {
+                let first = self.read_operand(operand(0)?, wide);
+                let second = self.read_operand(operand(1)?, wide);
+                self.write_operand(operand(0)?, wide, second)?;
+                self.write_operand(operand(1)?, wide, first)?;
+            }
+            I::Lea => {
+                let Operand::Memory(memory) = operand(1)? else {
+                    return Err("Expected memory operand.".to_string());
+                };
+                let (_, offset) = self.effective_address(memory);
+                self.write_operand(operand(0)?, true, offset)?;
+            }
+            I::Les | I::Lds => {
+                let (offset, segment) = self.read_far_pointer(operand(1)?)?;
+                self.write_operand(operand(0)?, true, offset)?;
+                if instruction.r#type == I::Les {
+                    self.registers.es = segment;
+                } else {
+                    self.registers.ds = segment;
+                }
+            }
+            I::Push => {
+                let value =
+                    match operand(0)? {
+                        // the 8086 pushes the already decremented stack pointer
+                        Operand::Register(RegisterType::SpecialPurpose(
+                            SpecialPurposeRegister::Sp,
+                        )) if self.cpu == Cpu::I8086 => self.registers.sp.wrapping_sub(2),
+                        operand => self.read_operand(operand, true),
+                    };
+                self.push(value);
+            }
+            I::Pop => {
+                let value = self.pop();
+                self.write_operand(operand(0)?, true, value)?;
+            }
+            I::Pusha => {
+                let sp = self.registers.sp;
+                for value in [
+                    self.registers.ax,
+                    self.registers.cx,
+                    self.registers.dx,
+                    self.registers.bx,
+                    sp,
+                    self.registers.bp,
+                    self.registers.si,
+                    self.registers.di,
+                ] {
+                    self.push(value);
+                }
+            }
+            I::Popa => {
+                self.registers.di = self.pop();
+                self.registers.si = self.pop();
+                self.registers.bp = self.pop();
+                self.pop();
+                self.registers.bx = self.pop();
+                self.registers.dx = self.pop();
+                self.registers.cx = self.pop();
+                self.registers.ax = self.pop();
+            }
+            I::Pushf => {
+                // the upper four bits read as set before the 80286
+                let high = if self.cpu < Cpu::I80286 { 0xf000 } else { 0 };
+                self.push(self.registers.flags | high);
+            }
+            I::Popf => {
+                let value = self.pop();
+                self.registers.flags = (value & FLAGS_WRITABLE) | 0x0002;
+            }
+            I::Sahf => {
+                let mask = FLAG_SIGN | FLAG_ZERO | FLAG_AUXILIARY | FLAG_PARITY | FLAG_CARRY;
+                self.registers.flags =
+                    (self.registers.flags & !mask) | (self.registers.ax >> 8 & mask);
+            }
+            I::Lahf => {
+                self.registers.ax =
+                    (self.registers.ax & 0x00ff) | (self.registers.flags & 0x00ff) << 8;
+            }
+            I::Cbw => self.registers.ax = self.registers.ax as u8 as i8 as i16 as u16,
+            I::Cwd => {
+                self.registers.dx = if self.registers.ax & 0x8000 != 0 {
+                    0xffff
+                } else {
+                    0
+                }
+            }
+            I::Nop | I::Wait => {}
+            I::Hlt => self.halted = true,
+            I::Clc => self.set_flag(FLAG_CARRY, false),
+            I::Stc => self.set_flag(FLAG_CARRY, true),
+            I::Cmc => self.set_flag(FLAG_CARRY, !self.flag(FLAG_CARRY)),
+            I::Cld => self.set_flag(FLAG_DIRECTION, false),
+            I::Std => self.set_flag(FLAG_DIRECTION, true),
+            I::Cli => self.set_flag(FLAG_INTERRUPT, false),
+            I::Sti => self.set_flag(FLAG_INTERRUPT, true),
+            I::Rol | I::Ror | I::Rcl | I::Rcr | I::Shl | I::Shr | I::Sar => {
+                let value = self.read_operand(operand(0)?, wide);
+                let mut count = self.read_operand(operand(1)?, false) as u8;
+                // the 80186 masks the count, the 8086 shifts as often as asked
+                if self.cpu >= Cpu::I80186 {
+                    count &= 0x1f;
+                }
+                let result = self.shift(instruction.r#type, value, count, wide);
+                self.write_operand(operand(0)?, wide, result)?;
+            }
+            I::Movsb
+            | I::Movsw
+            | I::Cmpsb
+            | I::Cmpsw
+            | I::Stosb
+            | I::Stosw
+            | I::Lodsb
+            | I::Lodsw
+            | I::Scasb
+            | I::Scasw
+            | I::Insb
+            | I::Insw
+            | I::Outsb
+            | I::Outsw => self.string(instruction)?,
+            I::In => {
+                // the port is an immediate or DX
+                let wide = width(&operands[..1]);
+                let port = self.read_operand(operand(1)?, false);
+                let value = self.read_port(port, if wide { 2 } else { 1 })?;
+                self.write_operand(operand(0)?, wide, value)?;
+            }
+            I::Out => {
+                let wide = width(&operands[1..]);
+                let port = self.read_operand(operand(0)?, false);
+                let value = self.read_operand(operand(1)?, wide);
+                self.write_port(port, if wide { 2 } else { 1 }, value)?;
+            }
+            I::Jmp => {
+                let (segment, offset) = self.branch_target(instruction)?;
+                self.registers.cs = segment;
+                self.registers.ip = offset;
+            }
+            I::Call => {
+                let (segment, offset) = self.branch_target(instruction)?;
+                if instruction.far || far(operands) {
+                    self.push(self.registers.cs);
+                }
+                self.push(self.registers.ip);
+                self.registers.cs = segment;
+                self.registers.ip = offset;
+            }
+            I::Ret | I::Retf => {
+                self.registers.ip = self.pop();
+                if instruction.r#type == I::Retf {
+                    self.registers.cs = self.pop();
+                }
+                if let Some(operand) = operands.first() {
+                    let release = self.read_operand(operand, true);
+                    self.registers.sp = self.registers.sp.wrapping_add(release);
+                }
+            }
+            I::Jcc(condition) => {
+                if self.condition(condition) {
+                    self.registers.ip = self.read_operand(operand(0)?, true);
+                }
+            }
+            I::Loop | I::Loope | I::Loopne => {
+                self.registers.cx = self.registers.cx.wrapping_sub(1);
+                let zero = self.flag(FLAG_ZERO);
+                let taken = self.registers.cx != 0
+                    && match instruction.r#type {
+                        I::Loope => zero,
+                        I::Loopne => !zero,
+                        _ => true,
+                    };
+                if taken {
+                    self.registers.ip = self.read_operand(operand(0)?, true);
+                }
+            }
+            I::Jcxz => {
+                if self.registers.cx == 0 {
+                    self.registers.ip = self.read_operand(operand(0)?, true);
+                }
+            }
+            I::Enter => {
+                let size = self.read_operand(operand(0)?, true);
+                let level = self.read_operand(operand(1)?, false) % 32;
+                self.push(self.registers.bp);
+                let frame = self.registers.sp;
+                for _ in 1..level {
+                    self.registers.bp = self.registers.bp.wrapping_sub(2);
+                    let value = self.read_word(address(self.registers.ss, self.registers.bp));
+                    self.push(value);
+                }
+                if level > 0 {
+                    self.push(frame);
+                }
+                self.registers.bp = frame;
+                self.registers.sp = self.registers.sp.wrapping_sub(size);
+            }
+            I::Leave => {
+                self.registers.sp = self.registers.bp;
+                self.registers.bp = self.pop();
+            }
+            I::Xlat => {
+                let offset = self.registers.bx.wrapping_add(self.registers.ax & 0x00ff);
+                let value = self.read_byte(address(self.source_segment(instruction), offset));
+                self.registers.set(al(), value as u16);
+            }
+            I::Daa | I::Das => self.decimal_adjust(instruction.r#type == I::Das),
+            I::Aaa | I::Aas => self.ascii_adjust(instruction.r#type == I::Aas),
+            I::Aam => {
+                let base = operands.first().map_or(10, |operand| match operand {
+                    Operand::Immediate(base) => *base as u8,
+                    _ => 10,
+                });
+                if base == 0 {
+                    self.fault(instruction);
+                    return self.exception(DIVIDE_ERROR, "Division by zero.".to_string());
+                }
+                let value = self.registers.ax as u8;
+                self.registers.ax = ((value / base) as u16) << 8 | (value % base) as u16;
+                self.set_result_flags(self.registers.ax & 0xff, false);
+            }
+            I::Aad => {
+                let base = operands.first().map_or(10, |operand| match operand {
+                    Operand::Immediate(base) => *base as u8,
+                    _ => 10,
+                });
+                let [low, high] = self.registers.ax.to_le_bytes();
+                let value = high.wrapping_mul(base).wrapping_add(low);
+                self.registers.ax = value as u16;
+                self.set_result_flags(value as u16, false);
+            }
+            I::Bound => {
+                let index = self.read_operand(operand(0)?, true) as i16;
+                let (lower, upper) = self.read_far_pointer(operand(1)?)?;
+                if index < lower as i16 || index > upper as i16 {
+                    self.fault(instruction);
+                    self.exception(BOUND_RANGE, "Array index out of bounds.".to_string())?;
+                }
+            }
+            I::Int => {
+                let vector = self.read_operand(operand(0)?, false) as u8;
+                self.software_interrupt(vector)?;
+            }
+            I::Int3 => self.software_interrupt(3)?,
+            I::Into => {
+                if self.flag(FLAG_OVERFLOW) {
+                    self.software_interrupt(4)?;
+                }
+            }
+            I::Iret => {
+                self.registers.ip = self.pop();
+                self.registers.cs = self.pop();
+                let value = self.pop();
+                self.registers.flags = (value & FLAGS_WRITABLE) | 0x0002;
+            }
+            r#type if r#type.is_floating_point() => self.floating_point(instruction)?,
+            r#type => return Err(format!("'{}' is not supported yet.", r#type)),
+        }
+
+        Ok(())
+    }
+
+    /// Computes an arithmetic or logic operation and sets the flags.
+    fn alu(&mut self, r#type: InstructionType, destination: u16, source: u16, wide: bool) -> u16 {
+        use InstructionType as I;
+
+        let (mask, sign) = masks(wide);
+        let (a, b) = (destination as u32 & mask, source as u32 & mask);
+        let carry = self.flag(FLAG_CARRY) as u32;
+
+        let result = match r#type {
+            I::Add | I::Adc => {
+                let carry = if r#type == I::Adc { carry } else { 0 };
+                let result = a + b + carry;
+                self.set_flag(FLAG_CARRY, result > mask);
+                self.set_flag(FLAG_OVERFLOW, (a ^ result) & (b ^ result) & sign != 0);
+                self.set_flag(FLAG_AUXILIARY, (a ^ b ^ result) & 0x10 != 0);
+                result
+            }
+            I::Sub | I::Sbb | I::Cmp => {
+                let borrow = if r#type == I::Sbb { carry } else { 0 };
+                let result = a.wrapping_sub(b).wrapping_sub(borrow);
+                self.set_flag(FLAG_CARRY, b + borrow > a);
+                self.set_flag(FLAG_OVERFLOW, (a ^ b) & (a ^ result) & sign != 0);
+                self.set_flag(FLAG_AUXILIARY, (a ^ b ^ result) & 0x10 != 0);
+                result
+            }
+            _ => {
+                let result = match r#type {
+                    I::And | I::Test => a & b,
+                    I::Or => a | b,
+                    _ => a ^ b,
+                };
+                self.set_flag(FLAG_CARRY, false);
+                self.set_flag(FLAG_OVERFLOW, false);
+                self.set_flag(FLAG_AUXILIARY, false);
+                result
+            }
+        };
+
+        let result = (result & mask) as u16;
+        self.set_result_flags(result, wide);
+        result
+    }
+
+    /// Sets ZF, SF and PF from a result.
+    fn set_result_flags(&mut self, result: u16, wide: bool) {
+        let (mask, sign) = masks(wide);
+        self.set_flag(FLAG_ZERO, result as u32 & mask == 0);
+        self.set_flag(FLAG_SIGN, result as u32 & sign != 0);
+        self.set_flag(FLAG_PARITY, (result as u8).count_ones().is_multiple_of(2));
+    }
+
+    fn multiply(&mut self, signed: bool, source: u16, wide: bool) {
+        let overflow = if wide {
+            let product = if signed {
+                (self.registers.ax as i16 as i32 * source as i16 as i32) as u32
+            } else {
+                self.registers.ax as u32 * source as u32
+            };
+            self.registers.ax = product as u16;
+            self.registers.dx = (product >> 16) as u16;
+            if signed {
+                product as i32 != product as i16 as i32
+            } else {
+                self.registers.dx != 0
+            }
+        } else {
+            let product = if signed {
+                (self.registers.ax as i8 as i16 * source as i8 as i16) as u16
+            } else {
+                (self.registers.ax & 0xff) * (source & 0xff)
+            };
+            self.registers.ax = product;
+            if signed {
+                product as i16 != product as i8 as i16
+            } else {
+                product > 0xff
+            }
+        };
+
+        self.set_flag(FLAG_CARRY, overflow);
+        self.set_flag(FLAG_OVERFLOW, overflow);
+    }
+
+    /// Points IP back at a faulting instruction; the 8086 returns after it instead.
+    fn fault(&mut self, instruction: &Instruction) {
+        if self.cpu != Cpu::I8086 {
+            self.registers.ip = instruction.address;
+        }
+    }
+
+    fn divide(&mut self, signed: bool, source: u16, wide: bool) -> Result<(), String> {
+        let error = || Err("Division overflow.".to_string());
+
+        if (wide && source == 0) || (!wide && source & 0xff == 0) {
+            return Err("Division by zero.".to_string());
+        }
+
+        if wide {
+            let dividend = (self.registers.dx as u32) << 16 | self.registers.ax as u32;
+            let (quotient, remainder) = if signed {
+                let (dividend, divisor) = (dividend as i32 as i64, source as i16 as i64);
+                let quotient = dividend / divisor;
+                if quotient != quotient as i16 as i64 {
+                    return error();
+                }
+                (quotient as u16, (dividend % divisor) as u16)
+            } else {
+                let quotient = dividend / source as u32;
+                if quotient > 0xffff {
+                    return error();
+                }
+                (quotient as u16, (dividend % source as u32) as u16)
+            };
+            self.registers.ax = quotient;
+            self.registers.dx = remainder;
+        } else {
+            let dividend = self.registers.ax;
+            let (quotient, remainder) = if signed {
+                let (dividend, divisor) = (dividend as i16 as i32, source as i8 as i32);
+                let quotient = dividend / divisor;
+                if quotient != quotient as i8 as i32 {
+                    return error();
+                }
+                (quotient as u8, (dividend % divisor) as u8)
+            } else {
+                let quotient = dividend / (source & 0xff);
+                if quotient > 0xff {
+                    return error();
+                }
+                (quotient as u8, (dividend % (source & 0xff)) as u8)
+            };
+            self.registers.ax = u16::from_le_bytes([quotient, remainder]);
+        }
+
+        Ok(())
+    }
+
+    fn shift(&mut self, r#type: InstructionType, value: u16, count: u8, wide: bool) -> u16 {
+        use InstructionType as I;
+
+        if count == 0 {
+            return value;
+        }
+
+        let (mask, sign) = masks(wide);
+        let (mask, sign) = (mask as u16, sign as u16);
+        let mut value = value & mask;
+        let mut carry = self.flag(FLAG_CARRY);
+
+        for _ in 0..count {
+            match r#type {
+                I::Rol => {
+                    carry = value & sign != 0;
+                    value = (value << 1 | carry as u16) & mask;
+                }
+                I::Ror => {
+                    carry = value & 1 != 0;
+                    value = value >> 1 | if carry { sign } else { 0 };
+                }
+                I::Rcl => {
+                    let out = value & sign != 0;
+                    value = (value << 1 | carry as u16) & mask;
+                    carry = out;
+                }
+                I::Rcr => {
+                    let out = value & 1 != 0;
+                    value = value >> 1 | if carry { sign } else { 0 };
+                    carry = out;
+                }
+                I::Shl => {
+                    carry = value & sign != 0;
+                    value = (value << 1) & mask;
+                }
+                I::Shr => {
+                    carry = value & 1 != 0;
+                    value >>= 1;
+                }
+                _ => {
+                    carry = value & 1 != 0;
+                    value = value >> 1 | (value & sign);
+                }
+            }
+        }
+
+        self.set_flag(FLAG_CARRY, carry);
+
+        let msb = value & sign != 0;
+        let overflow = match r#type {
+            I::Rol | I::Rcl | I::Shl => msb != carry,
+            I::Ror | I::Rcr => msb != (value & (sign >> 1) != 0),
+            // SHR: sign bit of the original operand, SAR: never
+            I::Shr => count == 1 && (value & (sign >> 1) != 0),
+            _ => false,
+        };
+        self.set_flag(FLAG_OVERFLOW, overflow);
+
+        // rotates leave the arithmetic flags alone
+        if matches!(r#type, I::Shl | I::Shr | I::Sar) {
+            self.set_result_flags(value, wide);
+        }
+
+        value
+    }
+
+    fn string(&mut self, instruction: &Instruction) -> Result<(), String> {
+        use InstructionType as I;
+
+        let wide = matches!(
+            instruction.r#type,
+            I::Movsw | I::Cmpsw | I::Stosw | I::Lodsw | I::Scasw | I::Insw | I::Outsw
+        );
+        let size = if wide { 2u16 } else { 1 };
+        let delta = if self.flag(FLAG_DIRECTION) {
+            size.wrapping_neg()
+        } else {
+            size
+        };
+        let repeat = matches!(instruction.prefix, Some(I::Rep | I::Repne));
+        let compares = matches!(
+            instruction.r#type,
+            I::Cmpsb | I::Cmpsw | I::Scasb | I::Scasw
+        );
+
+        loop {
+            if repeat && self.registers.cx == 0 {
+                break;
+            }
+
+            let source = address(self.source_segment(instruction), self.registers.si);
+            let destination = address(self.registers.es, self.registers.di);
+            let read = |machine: &mut Machine, address| {
+                if wide {
+                    machine.read_word(address)
+                } else {
+                    machine.read_byte(address) as u16
+                }
+            };
+            let write = |machine: &mut Machine, address, value: u16| {
+                if wide {
+                    machine.write_word(address, value)
+                } else {
+                    machine.write_byte(address, value as u8)
+                }
+            };
+            let accumulator = if wide {
+                self.registers.ax
+            } else {
+                self.registers.ax & 0xff
+            };
+
+            match instruction.r#type {
+                I::Movsb | I::Movsw => {
+                    let value = read(self, source);
+                    write(self, destination, value);
+                }
+                I::Cmpsb | I::Cmpsw => {
+                    let first = read(self, source);
+                    let second = read(self, destination);
+                    self.alu(I::Cmp, first, second, wide);
+                }
+                I::Stosb | I::Stosw => write(self, destination, accumulator),
+                I::Lodsb | I::Lodsw => {
+                    let value = read(self, source);
+                    let register = if wide { ax() } else { al() };
+                    self.registers.set(register, value);
+                }
+                I::Insb | I::Insw => {
+                    let value = self.read_port(self.registers.dx, size as u8)?;
+                    write(self, destination, value);
+                }
+                I::Outsb | I::Outsw => {
+                    let value = read(self, source);
+                    self.write_port(self.registers.dx, size as u8, value)?;
+                }
+                _ => {
+                    let value = read(self, destination);
+                    self.alu(I::Cmp, accumulator, value, wide);
+                }
+            }
+
+            if matches!(
+                instruction.r#type,
+                I::Movsb
+                    | I::Movsw
+                    | I::Cmpsb
+                    | I::Cmpsw
+                    | I::Lodsb
+                    | I::Lodsw
+                    | I::Outsb
+                    | I::Outsw
+            ) {
+                self.registers.si = self.registers.si.wrapping_add(delta);
+            }
+            if !matches!(
+                instruction.r#type,
+                I::Lodsb | I::Lodsw | I::Outsb | I::Outsw
+            ) {
+                self.registers.di = self.registers.di.wrapping_add(delta);
+            }
+
+            if !repeat {
+                break;
+            }
+
+            self.registers.cx = self.registers.cx.wrapping_sub(1);
+
+            // repe stops on a mismatch, repne on a match
+            if compares && self.flag(FLAG_ZERO) != (instruction.prefix == Some(I::Rep)) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Target CS:IP of a jump or call.
+    fn branch_target(&mut self, instruction: &Instruction) -> Result<(u16, u16), String> {
+        let operand = instruction
+            .operands
+            .first()
+            .ok_or_else(|| "Missing branch target.".to_string())?;
+
+        if instruction.far {
+            let (offset, segment) = self.read_far_pointer(operand)?;
+            return Ok((segment, offset));
+        }
+
+        Ok(match operand {
+            Operand::Far { segment, offset } => (*segment, *offset),
+            operand => (self.registers.cs, self.read_operand(operand, true)),
+        })
+    }
+
+    fn condition(&self, condition: Condition) -> bool {
+        use Condition as C;
+
+        let (carry, zero) = (self.flag(FLAG_CARRY), self.flag(FLAG_ZERO));
+        let (sign, overflow) = (self.flag(FLAG_SIGN), self.flag(FLAG_OVERFLOW));
+
+        match condition {
+            C::O => overflow,
+            C::No => !overflow,
+            C::B => carry,
+            C::Ae => !carry,
+            C::E => zero,
+            C::Ne => !zero,
+            C::Be => carry || zero,
+            C::A => !carry && !zero,
+            C::S => sign,
+            C::Ns => !sign,
+            C::P => self.flag(FLAG_PARITY),
+            C::Np => !self.flag(FLAG_PARITY),
+            C::L => sign != overflow,
+            C::Ge => sign == overflow,
+            C::Le => zero || sign != overflow,
+            C::G => !zero && sign == overflow,
+        }
+    }
+
+    fn decimal_adjust(&mut self, subtract: bool) {
+        let mut al = self.registers.ax as u8;
+        let original = al;
+        let carry = self.flag(FLAG_CARRY);
+
+        if al & 0x0f > 9 || self.flag(FLAG_AUXILIARY) {
+            al = if subtract {
+                al.wrapping_sub(6)
+            } else {
+                al.wrapping_add(6)
+            };
+            self.set_flag(FLAG_AUXILIARY, true);
+        } else {
+            self.set_flag(FLAG_AUXILIARY, false);
+        }
+
+        if original > 0x99 || carry {
+            al = if subtract {
+                al.wrapping_sub(0x60)
+            } else {
+                al.wrapping_add(0x60)
+            };
+            self.set_flag(FLAG_CARRY, true);
+        } else {
+            self.set_flag(FLAG_CARRY, false);
+        }
+
+        self.registers.set(self::al(), al as u16);
+        self.set_result_flags(al as u16, false);
+    }
+
+    fn ascii_adjust(&mut self, subtract: bool) {
+        let adjust = self.registers.ax & 0x0f > 9 || self.flag(FLAG_AUXILIARY);
+
+        if adjust {
+            let [low, high] = self.registers.ax.to_le_bytes();
+            let (low, high) = if subtract {
+                (low.wrapping_sub(6), high.wrapping_sub(1))
+            } else {
+                (low.wrapping_add(6), high.wrapping_add(1))
+            };
+            self.registers.ax = u16::from_le_bytes([low, high]);
+        }
+
+        self.set_flag(FLAG_AUXILIARY, adjust);
+        self.set_flag(FLAG_CARRY, adjust);
+        self.registers.ax &= 0xff0f;
+    }
+
+    /// Executes an 8087 instruction on `fpu`. The `wait` of the waiting forms is decoded
+    /// as an instruction of its own, so only the no-wait forms arrive here.
+    fn floating_point(&mut self, instruction: &Instruction) -> Result<(), String> {
+        use FloatingPointRegister as St;
+        use InstructionType as I;
+
+        let r#type = instruction.r#type;
+        let operands = instruction.operands.as_slice();
+        let invalid = || format!("Invalid operands for '{}'.", r#type);
+        let st = |operand: &Operand| match operand {
+            Operand::Register(RegisterType::FloatingPoint(register)) => Ok(*register),
+            _ => Err(invalid()),
+        };
+        let memory = || match operands {
+            [Operand::Memory(memory)] => Ok(memory),
+            _ => Err(invalid()),
+        };
+        let integer = matches!(
+            r#type,
+            I::Fiadd
+                | I::Fisub
+                | I::Fisubr
+                | I::Fimul
+                | I::Fidiv
+                | I::Fidivr
+                | I::Ficom
+                | I::Ficomp
+                | I::Fild
+        );
+
+        let arithmetic = match r#type {
+            I::Fadd | I::Faddp | I::Fiadd => Some(Arithmetic::Add),
+            I::Fsub | I::Fsubp | I::Fisub => Some(Arithmetic::Sub),
+            I::Fsubr | I::Fsubrp | I::Fisubr => Some(Arithmetic::SubReversed),
+            I::Fmul | I::Fmulp | I::Fimul => Some(Arithmetic::Mul),
+            I::Fdiv | I::Fdivp | I::Fidiv => Some(Arithmetic::Div),
+            I::Fdivr | I::Fdivrp | I::Fidivr => Some(Arithmetic::DivReversed),
+            _ => None,
+        };
+
+        if let Some(operation) = arithmetic {
+            let pop = matches!(
+                r#type,
+                I::Faddp | I::Fsubp | I::Fsubrp | I::Fmulp | I::Fdivp | I::Fdivrp
+            );
+            let (destination, source) = match operands {
+                [Operand::Memory(memory)] => (St::St0, self.load(memory, integer)?),
+                [destination, source] => (st(destination)?, {
+                    let source = st(source)?;
+                    self.fpu.st(source)
+                }),
+                _ => return Err(invalid()),
+            };
+            self.fpu.arithmetic(operation, destination, source, pop);
+            return Ok(());
+        }
+
+        match r#type {
+            I::Fcom | I::Fcomp | I::Ficom | I::Ficomp => {
+                let source = match operands {
+                    [] => self.fpu.st(St::St1),
+                    [Operand::Memory(memory)] => self.load(memory, integer)?,
+                    [register] => {
+                        let register = st(register)?;
+                        self.fpu.st(register)
+                    }
+                    _ => return Err(invalid()),
+                };
+                self.fpu.compare(source);
+                if matches!(r#type, I::Fcomp | I::Ficomp) {
+                    self.fpu.pop();
+                }
+            }
+            I::Fcompp => {
+                let source = self.fpu.st(St::St1);
+                self.fpu.compare(source);
+                self.fpu.pop();
+                self.fpu.pop();
+            }
+            I::Ftst => self.fpu.compare(0.0),
+            I::Fxam => self.fpu.examine(),
+            I::Fld | I::Fild => {
+                let value = match operands {
+                    [Operand::Memory(memory)] => self.load(memory, integer)?,
+                    [register] => {
+                        let register = st(register)?;
+                        self.fpu.st(register)
+                    }
+                    _ => return Err(invalid()),
+                };
+                self.fpu.push(value);
+            }
+            I::Fbld => {
+                let bytes = self.read_bytes(memory()?, 10);
+                self.fpu
+                    .push(fpu::bcd_from_bytes(&bytes.try_into().unwrap()));
+            }
+            I::Fst | I::Fstp => {
+                match operands {
+                    [Operand::Memory(memory)] => {
+                        let length = size_length(memory.size)?;
+                        let value = self.fpu.st(St::St0);
+                        self.write_bytes(memory, &fpu::real_to_bytes(value, length));
+                    }
+                    [register] => {
+                        let register = st(register)?;
+                        let value = self.fpu.st(St::St0);
+                        self.fpu.set_st(register, value);
+                    }
+                    _ => return Err(invalid()),
+                }
+                if r#type == I::Fstp {
+                    self.fpu.pop();
+                }
+            }
+            I::Fist | I::Fistp => {
+                let memory = memory()?;
+                let length = size_length(memory.size)?;
+                let value = self.fpu.to_integer(length as u32 * 8);
+                self.write_bytes(memory, &value.to_le_bytes()[..length]);
+                if r#type == I::Fistp {
+                    self.fpu.pop();
+                }
+            }
+            I::Fbstp => {
+                let bytes = self.fpu.to_bcd();
+                self.write_bytes(memory()?, &bytes);
+                self.fpu.pop();
+            }
+            I::Fxch => {
+                let register = match operands {
+                    [] => St::St1,
+                    [register] => st(register)?,
+                    _ => return Err(invalid()),
+                };
+                self.fpu.exchange(register);
+            }
+            I::Ffree => {
+                let [register] = operands else {
+                    return Err(invalid());
+                };
+                self.fpu.free(st(register)?);
+            }
+            I::Fld1 => self.fpu.push(1.0),
+            I::Fldl2t => self.fpu.push(std::f64::consts::LOG2_10),
+            I::Fldl2e => self.fpu.push(std::f64::consts::LOG2_E),
+            I::Fldpi => self.fpu.push(std::f64::consts::PI),
+            I::Fldlg2 => self.fpu.push(std::f64::consts::LOG10_2),
+            I::Fldln2 => self.fpu.push(std::f64::consts::LN_2),
+            I::Fldz => self.fpu.push(0.0),
+            I::Fchs => self.fpu.apply(|value| -value),
+            I::Fabs => self.fpu.apply(f64::abs),
+            I::Fsqrt => self.fpu.apply(f64::sqrt),
+            I::Frndint => {
+                let value = self.fpu.st(St::St0);
+                let rounded = self.fpu.round(value);
+                self.fpu.set_st(St::St0, rounded);
+            }
+            I::F2xm1 => self
+                .fpu
+                .apply(|value| (value * std::f64::consts::LN_2).exp_m1()),
+            I::Fyl2x | I::Fyl2xp1 => {
+                let x = self.fpu.pop();
+                let x = if r#type == I::Fyl2x {
+                    x.log2()
+                } else {
+                    x.ln_1p() / std::f64::consts::LN_2
+                };
+                self.fpu.apply(|y| y * x);
+            }
+            I::Fptan => {
+                self.fpu.apply(f64::tan);
+                self.fpu.push(1.0);
+            }
+            I::Fpatan => {
+                let x = self.fpu.pop();
+                self.fpu.apply(|y| y.atan2(x));
+            }
+            I::Fxtract => {
+                let value = self.fpu.st(St::St0);
+                let exponent = if value == 0.0 {
+                    f64::NEG_INFINITY
+                } else {
+                    value.abs().log2().floor()
+                };
+                self.fpu.set_st(St::St0, exponent);
+                self.fpu.push(value / 2f64.powf(exponent));
+            }
+            I::Fscale => {
+                let exponent = self.fpu.st(St::St1).trunc();
+                self.fpu.apply(|value| value * 2f64.powf(exponent));
+            }
+            I::Fprem => self.fpu.partial_remainder(),
+            I::Fincstp | I::Fdecstp => self.fpu.rotate(r#type == I::Fincstp),
+            I::Fnop => {}
+            // bit 7 of the control word masks the 8087 interrupt
+            I::Fneni | I::Feni => {
+                let control = self.fpu.control_word() & !0x80;
+                self.fpu.set_control_word(control);
+            }
+            I::Fndisi | I::Fdisi => {
+                let control = self.fpu.control_word() | 0x80;
+                self.fpu.set_control_word(control);
+            }
+            I::Fnclex | I::Fclex => self.fpu.clear_exceptions(),
+            I::Fninit | I::Finit => self.fpu.init(),
+            I::Fldcw => {
+                let bytes = self.read_bytes(memory()?, 2);
+                self.fpu
+                    .set_control_word(u16::from_le_bytes([bytes[0], bytes[1]]));
+            }
+            I::Fnstcw | I::Fstcw => {
+                let control = self.fpu.control_word();
+                self.write_bytes(memory()?, &control.to_le_bytes());
+            }
+            I::Fnstsw | I::Fstsw => {
+                let status = self.fpu.status_word();
+                self.write_bytes(memory()?, &status.to_le_bytes());
+            }
+            I::Fldenv | I::Frstor => {
+                let memory = memory()?;
+                let length = if r#type == I::Frstor {
+                    FPU_ENVIRONMENT_SIZE + 80
+                } else {
+                    FPU_ENVIRONMENT_SIZE
+                };
+                let bytes = self.read_bytes(memory, length);
+                let word =
+                    |index: usize| u16::from_le_bytes([bytes[index * 2], bytes[index * 2 + 1]]);
+                let (control, status, tag) = (word(0), word(1), word(2));
+
+                // the tag word is loaded last, storing the registers would change it
+                self.fpu.set_environment(control, status, tag);
+                for (index, real) in bytes[FPU_ENVIRONMENT_SIZE..].chunks(10).enumerate() {
+                    let register = St::from_index(index as u8).unwrap();
+                    self.fpu.set_st(register, fpu::real_from_bytes(real));
+                }
+                self.fpu.set_environment(control, status, tag);
+            }
+            I::Fnstenv | I::Fstenv | I::Fnsave | I::Fsave => {
+                // instruction and operand pointers are not kept and stored as zero
+                let mut bytes = [
+                    self.fpu.control_word(),
+                    self.fpu.status_word(),
+                    self.fpu.tag_word(),
+                ]
+                .iter()
+                .flat_map(|word| word.to_le_bytes())
+                .collect::<Vec<_>>();
+                bytes.resize(FPU_ENVIRONMENT_SIZE, 0);
+
+                let save = matches!(r#type, I::Fnsave | I::Fsave);
+                if save {
+                    for index in 0..8 {
+                        let value = self.fpu.value(St::from_index(index).unwrap());
+                        bytes.extend(fpu::real_to_bytes(value, 10));
+                    }
+                }
+
+                self.write_bytes(memory()?, &bytes);
+                if save {
+                    self.fpu.init();
+                }
+            }
+            _ => return Err(format!("'{}' is not supported yet.", r#type)),
+        }
+
+        Ok(())
+    }
+
+    /// Loads a real or, for `integer`, a signed integer from memory, sized by the operand.
+    fn load(&mut self, memory: &decoder::Memory, integer: bool) -> Result<f64, String> {
+        let length = size_length(memory.size)?;
+        let bytes = self.read_bytes(memory, length);
+
+        Ok(match (integer, length) {
+            (true, 2) => i16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            (true, 4) => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            (true, 8) => i64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            (false, 4 | 8 | 10) => fpu::real_from_bytes(&bytes),
+            _ => return Err(format!("Cannot load a {}-byte operand.", length)),
+        })
+    }
+
+    /// Reads `length` bytes of a memory operand, recording each access.
+    fn read_bytes(&mut self, memory: &decoder::Memory, length: usize) -> Vec<u8> {
+        let (segment, offset) = self.effective_address(memory);
+        (0..length)
+            .map(|index| self.read_byte(address(segment, offset.wrapping_add(index as u16))))
+            .collect()
+    }
+
+    /// Writes `bytes` to a memory operand, recording each access.
+    fn write_bytes(&mut self, memory: &decoder::Memory, bytes: &[u8]) {
+        let (segment, offset) = self.effective_address(memory);
+        for (index, byte) in bytes.iter().enumerate() {
+            self.write_byte(address(segment, offset.wrapping_add(index as u16)), *byte);
+        }
+    }
+}
+
+/// 8086 machine with `code` at 0000:0100 and the stack at 0000:FFFE, for tests
+#[cfg(test)]
+pub(crate) fn test_machine(code: &[u8]) -> Machine {
+    let mut machine = Machine::new(Cpu::I8086);
+    machine.memory.load(0x100, code);
+    machine.registers.ip = 0x100;
+    machine.registers.sp = 0xfffe;
+    machine
+}
+
+/// Bytes of a coprocessor memory operand
+fn size_length(size: Option<OperandSize>) -> Result<usize, String> {
+    match size {
+        Some(OperandSize::Byte) => Ok(1),
+        Some(OperandSize::Word) => Ok(2),
+        Some(OperandSize::Dword) => Ok(4),
+        Some(OperandSize::Qword) => Ok(8),
+        Some(OperandSize::Tword) => Ok(10),
+        None => Err("Coprocessor operand has no size.".to_string()),
+    }
+}
+
+/// Whether the instruction works on words: decided by the first register or sized memory
+/// operand, words if there is none.
+fn width(operands: &[Operand]) -> bool {
+    for operand in operands {
+        match operand {
+            Operand::Register(RegisterType::GeneralPurpose(register)) => {
+                return !matches!(
+                    register,
+                    GeneralPurposeRegister::Al
+                        | GeneralPurposeRegister::Ah
+                        | GeneralPurposeRegister::Bl
+                        | GeneralPurposeRegister::Bh
+                        | GeneralPurposeRegister::Cl
+                        | GeneralPurposeRegister::Ch
+                        | GeneralPurposeRegister::Dl
+                        | GeneralPurposeRegister::Dh
+                )
+            }
+            Operand::Register(_) => return true,
+            Operand::Memory(memory) if memory.size.is_some() => {
+                return memory.size != Some(OperandSize::Byte)
+            }
+            _ => {}
+        }
+    }
+    true
+}
+
+fn far(operands: &[Operand]) -> bool {
+    matches!(operands.first(), Some(Operand::Far { .. }))
+}
+
+/// Value mask and sign bit of an operand size
+fn masks(wide: bool) -> (u32, u32) {
+    if wide {
+        (0xffff, 0x8000)
+    } else {
+        (0xff, 0x80)
+    }
+}
+
+fn al() -> RegisterType {
+    RegisterType::GeneralPurpose(GeneralPurposeRegister::Al)
+}
+
+fn ax() -> RegisterType {
+    RegisterType::GeneralPurpose(GeneralPurposeRegister::Ax)
+}
+
+#[derive(Clone, Debug)]
+pub struct ExecutionError {
+    message: String,
+    segment: u16,
+    offset: u16,
+}
+
+impl ExecutionError {
+    /// Creates a new Execution Error for the instruction at `segment:offset`.
+    pub fn new(message: String, segment: u16, offset: u16) -> ExecutionError {
+        Self {
+            message,
+            segment,
+            offset,
+        }
+    }
+
+    /// Address of the failing instruction
+    pub fn location(&self) -> (u16, u16) {
+        (self.segment, self.offset)
+    }
+}
+
+impl Display for ExecutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Execution Error: at {:04X}:{:04X}: {}",
+            self.segment, self.offset, self.message
+        )
+    }
+}
+
+impl Error for ExecutionError {}
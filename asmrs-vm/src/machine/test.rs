@@ -0,0 +1,141 @@
+#[test]
+fn machine_loop() {
+    use crate::machine::test_machine;
+
+    // mov cx, 5 / xor ax, ax / add ax, cx / loop $-2 / hlt
+    let mut machine = test_machine(&[0xb9, 0x05, 0x00, 0x31, 0xc0, 0x01, 0xc8, 0xe2, 0xfc, 0xf4]);
+
+    assert_eq!(machine.run(100).unwrap(), 13);
+    assert!(machine.halted);
+    assert_eq!(machine.registers.ax, 15);
+    assert_eq!(machine.registers.cx, 0);
+    assert_eq!(machine.registers.ip, 0x10a);
+}
+
+#[test]
+fn machine_call() {
+    use crate::machine::test_machine;
+
+    // mov sp, 200h / call 108h / hlt / nop / mov ax, 1234h / ret
+    let mut machine = test_machine(&[
+        0xbc, 0x00, 0x02, 0xe8, 0x02, 0x00, 0xf4, 0x90, 0xb8, 0x34, 0x12, 0xc3,
+    ]);
+
+    machine.step().unwrap();
+    machine.step().unwrap();
+    assert_eq!(machine.registers.ip, 0x108);
+    assert_eq!(machine.registers.sp, 0x1fe);
+    assert_eq!(machine.memory.read_word(0x1fe), 0x106);
+
+    machine.run(10).unwrap();
+    assert_eq!(machine.registers.ax, 0x1234);
+    assert_eq!(machine.registers.sp, 0x200);
+    assert_eq!(machine.registers.ip, 0x107);
+}
+
+#[test]
+fn machine_string() {
+    use crate::machine::{test_machine, AccessKind};
+
+    // mov si, 200h / mov di, 300h / mov cx, 2 / cld / rep movsw / hlt
+    let mut machine = test_machine(&[
+        0xbe, 0x00, 0x02, 0xbf, 0x00, 0x03, 0xb9, 0x02, 0x00, 0xfc, 0xf3, 0xa5, 0xf4,
+    ]);
+    machine.memory.load(0x200, &[0x11, 0x22, 0x33, 0x44]);
+
+    machine.run(4).unwrap();
+    machine.step().unwrap();
+
+    assert_eq!(machine.memory.read(0x300, 4), [0x11, 0x22, 0x33, 0x44]);
+    assert_eq!(
+        (
+            machine.registers.si,
+            machine.registers.di,
+            machine.registers.cx
+        ),
+        (0x204, 0x304, 0)
+    );
+
+    let accesses = machine.accesses();
+    assert_eq!(accesses.len(), 4);
+    assert_eq!(accesses[1].kind, AccessKind::Write);
+    assert_eq!((accesses[1].address, accesses[1].value), (0x300, 0x2211));
+}
+
+#[test]
+fn machine_string_override() {
+    use crate::machine::test_machine;
+
+    // mov si, 100h / cs lodsb / mov di, 200h / cs movsb / mov bx, 100h / mov al, 3 /
+    // cs xlat / hlt
+    let mut machine = test_machine(&[
+        0xbe, 0x00, 0x01, 0x2e, 0xac, 0xbf, 0x00, 0x02, 0x2e, 0xa4, 0xbb, 0x00, 0x01, 0xb0, 0x03,
+        0x2e, 0xd7, 0xf4,
+    ]);
+    machine.registers.ds = 0x1000;
+    machine.registers.es = 0x2000;
+    machine.memory.load(0x10100, &[0xff; 4]);
+    machine.memory.load(0x20200, &[0xff]);
+
+    machine.step().unwrap();
+    machine.step().unwrap();
+    assert_eq!(machine.registers.ax & 0xff, 0xbe);
+
+    // the destination stays ES:DI
+    machine.run(2).unwrap();
+    assert_eq!(machine.memory.read(0x20200, 1), [0x00]);
+
+    machine.run(10).unwrap();
+    assert!(machine.halted);
+    assert_eq!(machine.registers.ax & 0xff, 0x2e);
+}
+
+#[test]
+fn machine_flags() {
+    use crate::{
+        machine::test_machine,
+        registers::{FLAG_AUXILIARY, FLAG_CARRY, FLAG_OVERFLOW, FLAG_SIGN, FLAG_ZERO},
+    };
+
+    // mov al, 7fh / add al, 1 / sub al, 81h / mov al, 15h / add al, 27h / daa
+    // mov ax, 8001h / shl ax, 1
+    let mut machine = test_machine(&[
+        0xb0, 0x7f, 0x04, 0x01, 0x2c, 0x81, 0xb0, 0x15, 0x04, 0x27, 0x27, 0xb8, 0x01, 0x80, 0xd1,
+        0xe0,
+    ]);
+
+    machine.run(2).unwrap();
+    assert_eq!(machine.registers.ax & 0xff, 0x80);
+    assert!(machine.flag(FLAG_OVERFLOW) && machine.flag(FLAG_SIGN) && machine.flag(FLAG_AUXILIARY));
+    assert!(!machine.flag(FLAG_CARRY) && !machine.flag(FLAG_ZERO));
+
+    machine.step().unwrap();
+    assert_eq!(machine.registers.ax & 0xff, 0xff);
+    assert!(machine.flag(FLAG_CARRY) && machine.flag(FLAG_SIGN) && !machine.flag(FLAG_OVERFLOW));
+
+    machine.run(3).unwrap();
+    assert_eq!(machine.registers.ax & 0xff, 0x42);
+
+    machine.run(2).unwrap();
+    assert_eq!(machine.registers.ax, 0x0002);
+    assert!(machine.flag(FLAG_CARRY) && machine.flag(FLAG_OVERFLOW));
+}
+
+#[test]
+fn machine_errors() {
+    use crate::machine::test_machine;
+
+    // mov bl, 0 / div bl
+    let mut machine = test_machine(&[0xb3, 0x00, 0xf6, 0xf3]);
+    machine.step().unwrap();
+    let error = machine.step().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Execution Error: at 0000:0102: Division by zero."
+    );
+    assert_eq!(machine.registers.ip, 0x102);
+
+    // undefined opcode
+    let mut machine = test_machine(&[0x0f]);
+    assert_eq!(machine.step().unwrap_err().location(), (0, 0x100));
+}
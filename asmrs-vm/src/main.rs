@@ -1,3 +1,655 @@
-fn main() {
-    println!("Hello, world!");
+use asmrs_assembler::debug::DebugInfo;
+use asmrs_parser::lexer::{parse_number_argument, token::Cpu};
+use asmrs_vm::{
+    bios::{self, Disk, Video, VIDEO_INTERRUPT, VIDEO_MODE},
+    debugger::Debugger,
+    dos::{Dos, DOS_INTERRUPT, TERMINATE_INTERRUPT},
+    gdb::GdbStub,
+    graphics::{self, graphics_mode, Graphics, ImageFormat, STEPS_PER_FRAME},
+    keyboard::{self, Controller},
+    loader::{
+        load_boot_sector, load_com, load_exe, load_intel_hex, load_s_record, LoadError,
+        DEFAULT_PSP_SEGMENT, PSP_SIZE,
+    },
+    machine::{AccessKind, Machine},
+    pit,
+    port::UnhandledPorts,
+    screen::{Terminal, TextScreen},
+    trace::{label_range, TraceFormat, Tracer},
+};
+use std::{
+    cell::RefCell,
+    env, fs,
+    io::{self, BufRead, BufWriter, Write},
+    net::TcpListener,
+    ops::Range,
+    path::PathBuf,
+    process::ExitCode,
+    rc::Rc,
+};
+
+const USAGE: &str = "Usage: asmrs-vm [--format com|exe|boot|ihex|srec] [--cpu 8086|186|286] \
+[--floppy FILE] [--hdd FILE] [--sandbox DIR] [--ports ignore|log|fault] [--screen] [--screenshot FILE] [--frame FILE] [--frames DIR] [--frame-interval N] [--frame-format png|ppm] [--keyboard SCRIPT] [--debug] [--symbols FILE] [--gdb PORT] [--trace FILE] [--trace-format text|binary] \
+[--trace-range START-END] [--trace-label LABEL] [--trace-limit N] <program> [arguments]...";
+
+/// DOS services kept by `main` for the program's return code
+type SharedDos = Rc<RefCell<Dos>>;
+/// Video card kept by `main` for exporting frames
+type SharedGraphics = Rc<RefCell<Graphics>>;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ProgramFormat {
+    Com,
+    Exe,
+    /// Raw disk image booted from its first sector
+    Boot,
+    IntelHex,
+    SRecord,
+}
+
+struct Arguments {
+    program: String,
+    /// Command tail passed to DOS programs
+    arguments: Vec<String>,
+    format: ProgramFormat,
+    cpu: Cpu,
+    /// Disk image served as drive 00h by int 13h (a booted image is used by default)
+    floppy: Option<String>,
+    /// Disk image served as drive 80h
+    hdd: Option<String>,
+    /// Host directory DOS programs see as their current directory
+    sandbox: String,
+    /// What accesses to ports without a device do
+    ports: UnhandledPorts,
+    /// Show the text buffer on the terminal while running
+    screen: bool,
+    /// File receiving the text screen at exit
+    screenshot: Option<String>,
+    /// File receiving the graphics frame at exit
+    frame: Option<String>,
+    frames: Option<FrameOptions>,
+    /// Keyboard script typed through the keyboard controller, `-` for the host stdin
+    keyboard: Option<String>,
+    /// Start the interactive debugger instead of running
+    debug: bool,
+    /// Debug info sidecar used for labels and source lines
+    symbols: Option<String>,
+    /// Local port to wait on for a GDB front-end
+    gdb: Option<u16>,
+    trace: Option<TraceOptions>,
+}
+
+/// Graphics frames saved while running
+struct FrameOptions {
+    directory: String,
+    /// Save every Nth frame
+    interval: u64,
+    format: ImageFormat,
+}
+
+struct TraceOptions {
+    path: String,
+    format: TraceFormat,
+    /// Linear address ranges of the instructions to record
+    ranges: Vec<Range<usize>>,
+    /// Labels whose code is recorded, resolved through the symbols
+    labels: Vec<String>,
+    limit: Option<u64>,
+}
+
+fn main() -> ExitCode {
+    let arguments = match parse_arguments(env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (machine, dos, graphics) = match load(&arguments) {
+        Ok(loaded) => loaded,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(port) = arguments.gdb {
+        return match gdb_session(GdbStub::new(machine), port) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(message) => {
+                eprintln!("{}", message);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let mut debug = match arguments.symbols.as_deref().map(read_symbols).transpose() {
+        Ok(debug) => debug,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // the assembler addresses .com and .exe programs from the segment they are loaded at
+    if let Some(debug) = &mut debug {
+        match arguments.format {
+            ProgramFormat::Com => debug.relocate(DEFAULT_PSP_SEGMENT),
+            ProgramFormat::Exe => debug.relocate(DEFAULT_PSP_SEGMENT + (PSP_SIZE / 16) as u16),
+            _ => {}
+        }
+    }
+
+    if arguments.debug {
+        return debug_session(Debugger::new(machine, debug));
+    }
+
+    let mut machine = machine;
+    let mut result = match &arguments.trace {
+        Some(options) => run_traced(&mut machine, options, debug.as_ref()),
+        None if arguments.screen || arguments.frames.is_some() => run_in_frames(
+            &mut machine,
+            arguments.screen,
+            &graphics,
+            arguments.frames.as_ref(),
+        ),
+        None => machine
+            .run(u64::MAX)
+            .map(|_| ())
+            .map_err(|error| error.to_string()),
+    };
+
+    if let Some(path) = &arguments.screenshot {
+        let text = TextScreen::capture(&machine.memory).text();
+        if let Err(error) = fs::write(path, text) {
+            let message = format!("Could not write '{}': {}", path, error);
+            result = result.and(Err(message));
+        }
+    }
+
+    if let Some(path) = &arguments.frame {
+        let format = if path.to_lowercase().ends_with(".ppm") {
+            ImageFormat::Ppm
+        } else {
+            ImageFormat::Png
+        };
+        let saved = match graphics.borrow().frame(&machine.memory) {
+            Some(frame) => fs::write(path, frame.encode(format))
+                .map_err(|error| format!("Could not write '{}': {}", path, error)),
+            None => Err(format!("No graphics mode to save in '{}'.", path)),
+        };
+        result = result.and(saved);
+    }
+
+    for access in &machine.ports.unhandled {
+        match access.kind {
+            AccessKind::Read => eprintln!("Unhandled port read: {:04X}h", access.port),
+            AccessKind::Write => eprintln!(
+                "Unhandled port write: {:04X}h = {:0width$X}h",
+                access.port,
+                access.value,
+                width = access.size as usize * 2
+            ),
+        }
+    }
+
+    match result {
+        // DOS programs report their return code
+        Ok(()) => match dos.and_then(|dos| dos.borrow().exit_code) {
+            Some(code) => ExitCode::from(code),
+            None => ExitCode::SUCCESS,
+        },
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs until `hlt` or an error a frame at a time, redrawing the text screen on the
+/// terminal and saving graphics frames as asked.
+fn run_in_frames(
+    machine: &mut Machine,
+    screen: bool,
+    graphics: &SharedGraphics,
+    frames: Option<&FrameOptions>,
+) -> Result<(), String> {
+    let write_error = |error: io::Error| format!("Could not write the screen: {}", error);
+    let mut terminal = screen.then(|| Terminal::new(Box::new(io::stdout())));
+
+    let result = loop {
+        let ran = machine.run(STEPS_PER_FRAME);
+        let text = !graphics_mode(machine.memory.read_byte(VIDEO_MODE));
+
+        if let Some(terminal) = terminal.as_mut().filter(|_| text) {
+            terminal.render(&machine.memory).map_err(write_error)?;
+        }
+
+        if let Some(options) = frames {
+            let graphics = graphics.borrow();
+            let number = graphics.frames();
+            if number.is_multiple_of(options.interval) {
+                if let Some(frame) = graphics.frame(&machine.memory) {
+                    let path = PathBuf::from(&options.directory).join(format!(
+                        "frame-{:05}.{}",
+                        number,
+                        options.format.extension()
+                    ));
+                    fs::write(&path, frame.encode(options.format)).map_err(|error| {
+                        format!("Could not write '{}': {}", path.display(), error)
+                    })?;
+                }
+            }
+        }
+
+        if let Err(error) = ran {
+            break Err(error.to_string());
+        }
+        if machine.halted && !machine.waiting() {
+            break Ok(());
+        }
+    };
+
+    if let Some(terminal) = terminal.as_mut() {
+        terminal.finish().map_err(write_error)?;
+    }
+    result
+}
+
+/// Runs until `hlt` or an error, writing a trace record per instruction.
+fn run_traced(
+    machine: &mut Machine,
+    options: &TraceOptions,
+    debug: Option<&DebugInfo>,
+) -> Result<(), String> {
+    let file = fs::File::create(&options.path)
+        .map_err(|error| format!("Could not write '{}': {}", options.path, error))?;
+    let write_error = |error: io::Error| format!("Could not write '{}': {}", options.path, error);
+
+    let mut tracer = Tracer::new(BufWriter::new(file), options.format).map_err(write_error)?;
+    tracer.ranges = options.ranges.clone();
+    tracer.limit = options.limit;
+    for label in &options.labels {
+        let range = debug
+            .and_then(|debug| label_range(debug, label))
+            .ok_or_else(|| format!("Unknown label '{}'.", label))?;
+        tracer.ranges.push(range);
+    }
+
+    while !machine.halted || machine.waiting() {
+        // steps spent halted are not instructions
+        if machine.halted {
+            machine.step().map_err(|error| error.to_string())?;
+            continue;
+        }
+        let before = machine.registers.clone();
+        let instruction = machine.fetch().map_err(|error| error.to_string())?;
+        machine.step().map_err(|error| error.to_string())?;
+        tracer
+            .record(&before, &instruction, machine)
+            .map_err(write_error)?;
+    }
+
+    tracer.finish().map_err(write_error)?;
+    Ok(())
+}
+
+/// Reads debugger commands from stdin until `quit` or end of input.
+fn debug_session(mut debugger: Debugger) -> ExitCode {
+    println!("{}", debugger.current());
+
+    let stdin = io::stdin();
+    loop {
+        print!("(asmrs) ");
+        io::stdout().flush().ok();
+
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) => return ExitCode::SUCCESS,
+            Ok(_) => {}
+            Err(error) => {
+                eprintln!("Could not read command: {}", error);
+                return ExitCode::FAILURE;
+            }
+        }
+
+        if matches!(line.trim(), "quit" | "q") {
+            return ExitCode::SUCCESS;
+        }
+
+        match debugger.execute(&line) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{}", output),
+            Err(message) => println!("{}", message),
+        }
+    }
+}
+
+/// Waits for one GDB front-end on the local port and serves it until it detaches.
+fn gdb_session(mut stub: GdbStub, port: u16) -> Result<(), String> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|error| format!("Could not listen on port {}: {}", port, error))?;
+    eprintln!("Waiting for GDB on 127.0.0.1:{}", port);
+
+    let (stream, _) = listener
+        .accept()
+        .map_err(|error| format!("Could not accept connection: {}", error))?;
+    stream.set_nodelay(true).ok();
+
+    stub.serve(stream)
+        .map_err(|error| format!("GDB connection failed: {}", error))
+}
+
+/// Creates a machine with the program loaded as its format requires. DOS programs get the
+/// DOS services, which are returned for their exit code.
+fn load(arguments: &Arguments) -> Result<(Machine, Option<SharedDos>, SharedGraphics), String> {
+    let path = &arguments.program;
+    let bytes = read(path)?;
+    let text = || String::from_utf8_lossy(&bytes).into_owned();
+    let tail = arguments.arguments.join(" ");
+
+    let mut machine = Machine::new(arguments.cpu);
+    let (memory, registers) = (&mut machine.memory, &mut machine.registers);
+
+    match arguments.format {
+        ProgramFormat::Com => load_com(memory, registers, &bytes, DEFAULT_PSP_SEGMENT, &tail),
+        ProgramFormat::Exe => load_exe(memory, registers, &bytes, DEFAULT_PSP_SEGMENT, &tail),
+        ProgramFormat::Boot => load_boot_sector(memory, registers, &bytes, 0),
+        ProgramFormat::IntelHex => load_intel_hex(memory, registers, &text()),
+        ProgramFormat::SRecord => load_s_record(memory, registers, &text()),
+    }
+    .map_err(|error: LoadError| format!("{}: {}", path, error))?;
+
+    let mut disks = Vec::new();
+    match &arguments.floppy {
+        Some(floppy) => disks.push(Disk::new(0x00, read(floppy)?)),
+        None if arguments.format == ProgramFormat::Boot => disks.push(Disk::new(0x00, bytes)),
+        None => {}
+    }
+    if let Some(hdd) = &arguments.hdd {
+        disks.push(Disk::new(0x80, read(hdd)?));
+    }
+    bios::install(&mut machine, disks);
+    if arguments.screen {
+        // teletype output reaches the terminal through the text buffer
+        machine
+            .interrupts
+            .set(VIDEO_INTERRUPT, Video::new(Box::new(io::sink())));
+    }
+    pit::install(&mut machine);
+    let graphics = graphics::install(&mut machine);
+    match arguments.keyboard.as_deref() {
+        Some("-") => {
+            keyboard::install(&mut machine, Controller::stdin());
+        }
+        Some(script) => {
+            let text = String::from_utf8_lossy(&read(script)?).into_owned();
+            let controller =
+                Controller::from_script(&text).map_err(|error| format!("{}: {}", script, error))?;
+            keyboard::install(&mut machine, controller);
+        }
+        None => {}
+    }
+    machine.ports.policy = arguments.ports;
+
+    let dos = match arguments.format {
+        ProgramFormat::Com | ProgramFormat::Exe => {
+            let dos = Rc::new(RefCell::new(Dos::new(
+                PathBuf::from(&arguments.sandbox),
+                DEFAULT_PSP_SEGMENT,
+            )));
+            machine.interrupts.set_shared(DOS_INTERRUPT, dos.clone());
+            machine
+                .interrupts
+                .set_shared(TERMINATE_INTERRUPT, dos.clone());
+            Some(dos)
+        }
+        _ => None,
+    };
+
+    Ok((machine, dos, graphics))
+}
+
+fn read(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|error| format!("Could not read '{}': {}", path, error))
+}
+
+fn read_symbols(path: &str) -> Result<DebugInfo, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|error| format!("Could not read '{}': {}", path, error))?;
+    DebugInfo::parse(&text).map_err(|error| format!("{}: {}", path, error))
+}
+
+fn parse_arguments(mut args: impl Iterator<Item = String>) -> Result<Arguments, String> {
+    let mut program = None;
+    let mut format = None;
+    let mut cpu = Cpu::I8086;
+    let mut floppy = None;
+    let mut hdd = None;
+    let mut sandbox = None;
+    let mut ports = UnhandledPorts::Ignore;
+    let mut screen = false;
+    let mut screenshot = None;
+    let mut frame = None;
+    let mut frames = None;
+    let mut frame_interval = None;
+    let mut frame_format = None;
+    let mut keyboard = None;
+    let mut debug = false;
+    let mut symbols = None;
+    let mut gdb = None;
+    let mut trace = None;
+    let mut trace_format = TraceFormat::Text;
+    let mut trace_ranges = Vec::new();
+    let mut trace_labels = Vec::new();
+    let mut trace_limit = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Expected value after '{}'.", arg))
+        };
+
+        match arg.as_str() {
+            "--format" => {
+                format = Some(match value()?.as_str() {
+                    "com" => ProgramFormat::Com,
+                    "exe" => ProgramFormat::Exe,
+                    "boot" => ProgramFormat::Boot,
+                    "ihex" => ProgramFormat::IntelHex,
+                    "srec" => ProgramFormat::SRecord,
+                    other => {
+                        return Err(format!(
+                            "Unknown format: '{}'. Expected com, exe, boot, ihex or srec.",
+                            other
+                        ))
+                    }
+                })
+            }
+            "--cpu" => cpu = value()?.parse()?,
+            "--floppy" => floppy = Some(value()?),
+            "--hdd" => hdd = Some(value()?),
+            "--sandbox" => sandbox = Some(value()?),
+            "--ports" => {
+                ports = match value()?.as_str() {
+                    "ignore" => UnhandledPorts::Ignore,
+                    "log" => UnhandledPorts::Log,
+                    "fault" => UnhandledPorts::Fault,
+                    other => {
+                        return Err(format!(
+                            "Unknown port policy: '{}'. Expected ignore, log or fault.",
+                            other
+                        ))
+                    }
+                }
+            }
+            "--screen" => screen = true,
+            "--screenshot" => screenshot = Some(value()?),
+            "--frame" => frame = Some(value()?),
+            "--frames" => frames = Some(value()?),
+            "--frame-interval" => {
+                let interval = value()?;
+                frame_interval = Some(
+                    interval
+                        .parse::<u64>()
+                        .ok()
+                        .filter(|interval| *interval > 0)
+                        .ok_or_else(|| format!("Invalid frame interval: '{}'", interval))?,
+                );
+            }
+            "--frame-format" => {
+                frame_format = Some(match value()?.as_str() {
+                    "png" => ImageFormat::Png,
+                    "ppm" => ImageFormat::Ppm,
+                    other => {
+                        return Err(format!(
+                            "Unknown frame format: '{}'. Expected png or ppm.",
+                            other
+                        ))
+                    }
+                })
+            }
+            "--keyboard" => keyboard = Some(value()?),
+            "--debug" => debug = true,
+            "--symbols" => symbols = Some(value()?),
+            "--gdb" => {
+                let port = value()?;
+                gdb = Some(
+                    port.parse()
+                        .map_err(|_| format!("Invalid port: '{}'", port))?,
+                );
+            }
+            "--trace" => trace = Some(value()?),
+            "--trace-format" => {
+                trace_format = match value()?.as_str() {
+                    "text" => TraceFormat::Text,
+                    "binary" => TraceFormat::Binary,
+                    other => {
+                        return Err(format!(
+                            "Unknown trace format: '{}'. Expected text or binary.",
+                            other
+                        ))
+                    }
+                }
+            }
+            "--trace-range" => {
+                let range = value()?;
+                let (start, end) = range
+                    .split_once('-')
+                    .ok_or_else(|| format!("Expected START-END, found '{}'.", range))?;
+                trace_ranges.push(
+                    parse_number_argument(start)? as usize..parse_number_argument(end)? as usize,
+                );
+            }
+            "--trace-label" => trace_labels.push(value()?),
+            "--trace-limit" => trace_limit = Some(parse_number_argument(&value()?)? as u64),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option: '{}'", arg)),
+            _ => {
+                // everything after the program belongs to it
+                program = Some(arg);
+                break;
+            }
+        }
+    }
+
+    let program = program.ok_or_else(|| "Expected program file.".to_string())?;
+    let format = match format {
+        Some(format) => format,
+        None => infer_format(&program)?,
+    };
+
+    if gdb.is_some() && debug {
+        return Err("Use either the debugger (--debug) or a GDB front-end (--gdb).".to_string());
+    }
+
+    if trace.is_some() && (debug || gdb.is_some()) {
+        return Err("Tracing (--trace) runs the program without a debugger.".to_string());
+    }
+
+    let shown = screen || screenshot.is_some() || frame.is_some() || frames.is_some();
+    if shown && (debug || gdb.is_some()) {
+        return Err(
+            "The screen and frames are only kept when running without a debugger.".to_string(),
+        );
+    }
+
+    if (screen || frames.is_some()) && trace.is_some() {
+        return Err(
+            "Use either the screen or frames (--screen, --frames) or tracing (--trace)."
+                .to_string(),
+        );
+    }
+
+    if frames.is_none() && (frame_interval.is_some() || frame_format.is_some()) {
+        return Err("Frame options need a frame directory (--frames).".to_string());
+    }
+
+    let traced = trace_format != TraceFormat::Text
+        || !trace_ranges.is_empty()
+        || !trace_labels.is_empty()
+        || trace_limit.is_some();
+    if trace.is_none() && traced {
+        return Err("Trace options need a trace file (--trace).".to_string());
+    }
+
+    if symbols.is_some() && !debug && trace_labels.is_empty() {
+        return Err(
+            "Symbols are only used by the debugger (--debug) and --trace-label.".to_string(),
+        );
+    }
+
+    if !trace_labels.is_empty() && symbols.is_none() {
+        return Err("Trace labels need debug info (--symbols).".to_string());
+    }
+
+    Ok(Arguments {
+        program,
+        arguments: args.collect(),
+        format,
+        cpu,
+        floppy,
+        hdd,
+        sandbox: sandbox.unwrap_or_else(|| ".".to_string()),
+        ports,
+        screen,
+        screenshot,
+        frame,
+        frames: frames.map(|directory| FrameOptions {
+            directory,
+            interval: frame_interval.unwrap_or(1),
+            format: frame_format.unwrap_or(ImageFormat::Png),
+        }),
+        keyboard,
+        debug,
+        symbols,
+        gdb,
+        trace: trace.map(|path| TraceOptions {
+            path,
+            format: trace_format,
+            ranges: trace_ranges,
+            labels: trace_labels,
+            limit: trace_limit,
+        }),
+    })
+}
+
+/// Format implied by the file extension
+fn infer_format(path: &str) -> Result<ProgramFormat, String> {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "com" => Ok(ProgramFormat::Com),
+        "exe" => Ok(ProgramFormat::Exe),
+        "img" | "ima" => Ok(ProgramFormat::Boot),
+        "hex" | "ihex" => Ok(ProgramFormat::IntelHex),
+        "srec" | "s19" | "s28" | "s37" => Ok(ProgramFormat::SRecord),
+        _ => Err(format!(
+            "Cannot infer the format of '{}'; use --format.",
+            path
+        )),
+    }
 }
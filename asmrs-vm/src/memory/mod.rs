@@ -0,0 +1,64 @@
+mod test;
+
+/// Size of the 8086 address space (20 address lines)
+pub const MEMORY_SIZE: usize = 0x100000;
+
+/// Linear address of `segment:offset`, wrapping at 1 MiB like the 8086.
+pub fn address(segment: u16, offset: u16) -> usize {
+    (((segment as usize) << 4) + offset as usize) % MEMORY_SIZE
+}
+
+/// Flat 1 MiB memory, addressed linearly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Memory {
+    bytes: Vec<u8>,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Memory {
+    /// Creates zeroed memory.
+    pub fn new() -> Memory {
+        Self {
+            bytes: vec![0; MEMORY_SIZE],
+        }
+    }
+
+    pub fn read_byte(&self, address: usize) -> u8 {
+        self.bytes[address % MEMORY_SIZE]
+    }
+
+    pub fn write_byte(&mut self, address: usize, value: u8) {
+        self.bytes[address % MEMORY_SIZE] = value;
+    }
+
+    /// Reads a little endian word.
+    pub fn read_word(&self, address: usize) -> u16 {
+        u16::from_le_bytes([self.read_byte(address), self.read_byte(address + 1)])
+    }
+
+    /// Writes a little endian word.
+    pub fn write_word(&mut self, address: usize, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        self.write_byte(address, low);
+        self.write_byte(address + 1, high);
+    }
+
+    /// Copies `bytes` to memory starting at `address`.
+    pub fn load(&mut self, address: usize, bytes: &[u8]) {
+        for (index, byte) in bytes.iter().enumerate() {
+            self.write_byte(address + index, *byte);
+        }
+    }
+
+    /// Reads `length` bytes starting at `address`.
+    pub fn read(&self, address: usize, length: usize) -> Vec<u8> {
+        (0..length)
+            .map(|index| self.read_byte(address + index))
+            .collect()
+    }
+}
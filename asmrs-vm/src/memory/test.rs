@@ -0,0 +1,25 @@
+#[test]
+fn memory_address() {
+    use crate::memory::address;
+
+    assert_eq!(address(0x1234, 0x5678), 0x179b8);
+    // wraps around at 1 MiB
+    assert_eq!(address(0xffff, 0x0010), 0);
+}
+
+#[test]
+fn memory_words() {
+    use crate::memory::{Memory, MEMORY_SIZE};
+
+    let mut memory = Memory::new();
+    memory.write_word(0x100, 0xbeef);
+    assert_eq!(memory.read_byte(0x100), 0xef);
+    assert_eq!(memory.read_byte(0x101), 0xbe);
+    assert_eq!(memory.read_word(0x100), 0xbeef);
+
+    memory.write_word(MEMORY_SIZE - 1, 0x1234);
+    assert_eq!(memory.read_byte(0), 0x12);
+
+    memory.load(0x200, b"asmrs");
+    assert_eq!(memory.read(0x200, 5), b"asmrs".to_vec());
+}
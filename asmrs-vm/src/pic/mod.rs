@@ -0,0 +1,216 @@
+mod test;
+
+/// Command port (ICW1, OCW2 and OCW3; reads IRR or ISR)
+pub const PIC_COMMAND_PORT: u16 = 0x20;
+/// Data port (ICW2 to ICW4 during initialization, then the mask)
+pub const PIC_DATA_PORT: u16 = 0x21;
+
+/// Non-specific end of interrupt command
+pub const EOI: u8 = 0x20;
+
+/// Vector of IRQ 0 as set up by the PC BIOS
+pub const DEFAULT_BASE_VECTOR: u8 = 0x08;
+
+/// Initialization word the chip waits for after ICW1
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Initialization {
+    Ready,
+    Icw2,
+    Icw3,
+    Icw4,
+}
+
+/// 8259A programmable interrupt controller. Devices raise IRQ lines with `request`; the
+/// processor takes the highest priority unmasked request with `acknowledge` between
+/// instructions, and handlers end it with an EOI command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pic {
+    /// Interrupt request register: raised lines waiting for service
+    pub irr: u8,
+    /// In-service register: lines whose handlers have not sent an EOI yet
+    pub isr: u8,
+    /// Interrupt mask register (OCW1)
+    pub imr: u8,
+    /// Vector of IRQ 0 (ICW2)
+    pub base: u8,
+    /// Line with the lowest priority, 7 unless rotated
+    pub lowest: u8,
+    /// End interrupts on acknowledge (ICW4)
+    pub auto_eoi: bool,
+    /// Rotate priorities on automatic EOIs
+    rotate_auto_eoi: bool,
+    initialization: Initialization,
+    /// ICW1 announced ICW3 (cascade) and ICW4
+    cascade: bool,
+    icw4: bool,
+    /// Command port reads return the ISR instead of the IRR (OCW3)
+    read_isr: bool,
+}
+
+impl Pic {
+    /// Creates a controller initialized like the PC BIOS does: IRQ 0 at vector 08h, fixed
+    /// priorities and nothing masked.
+    pub fn new() -> Pic {
+        Self {
+            irr: 0,
+            isr: 0,
+            imr: 0,
+            base: DEFAULT_BASE_VECTOR,
+            lowest: 7,
+            auto_eoi: false,
+            rotate_auto_eoi: false,
+            initialization: Initialization::Ready,
+            cascade: false,
+            icw4: false,
+            read_isr: false,
+        }
+    }
+
+    /// Raises IRQ `line`; edge triggered, so it stays requested until acknowledged.
+    pub fn request(&mut self, line: u8) {
+        self.irr |= 1 << (line & 7);
+    }
+
+    /// Withdraws a request that was not acknowledged yet.
+    pub fn withdraw(&mut self, line: u8) {
+        self.irr &= !(1 << (line & 7));
+    }
+
+    /// Priority of `line`, 0 being the highest
+    fn priority(&self, line: u8) -> u8 {
+        line.wrapping_sub(self.lowest).wrapping_sub(1) & 7
+    }
+
+    /// Highest priority line set in `register`
+    fn highest(&self, register: u8) -> Option<u8> {
+        (0..8)
+            .filter(|line| register & (1 << line) != 0)
+            .min_by_key(|line| self.priority(*line))
+    }
+
+    /// Unmasked request that would interrupt the processor: one with a higher priority
+    /// than every line in service.
+    pub fn pending(&self) -> Option<u8> {
+        let line = self.highest(self.irr & !self.imr)?;
+        match self.highest(self.isr) {
+            Some(serviced) if self.priority(serviced) <= self.priority(line) => None,
+            _ => Some(line),
+        }
+    }
+
+    /// Takes the pending request into service and returns its vector.
+    pub fn acknowledge(&mut self) -> Option<u8> {
+        let line = self.pending()?;
+        self.irr &= !(1 << line);
+
+        if self.auto_eoi {
+            if self.rotate_auto_eoi {
+                self.lowest = line;
+            }
+        } else {
+            self.isr |= 1 << line;
+        }
+
+        Some(self.base.wrapping_add(line))
+    }
+
+    /// Ends the highest priority interrupt in service and returns its line.
+    pub fn end_of_interrupt(&mut self) -> Option<u8> {
+        let line = self.highest(self.isr)?;
+        self.isr &= !(1 << line);
+        Some(line)
+    }
+
+    /// Reads the command port (IRR or ISR as selected by OCW3) or the data port (IMR).
+    pub fn read(&self, port: u16) -> u8 {
+        match port & 1 {
+            0 if self.read_isr => self.isr,
+            0 => self.irr,
+            _ => self.imr,
+        }
+    }
+
+    /// Writes an initialization or operation command word.
+    pub fn write(&mut self, port: u16, value: u8) {
+        if port & 1 == 0 {
+            if value & 0x10 != 0 {
+                // ICW1 restarts initialization
+                self.isr = 0;
+                self.imr = 0;
+                self.lowest = 7;
+                self.auto_eoi = false;
+                self.rotate_auto_eoi = false;
+                self.read_isr = false;
+                self.cascade = value & 0x02 == 0;
+                self.icw4 = value & 0x01 != 0;
+                self.initialization = Initialization::Icw2;
+            } else if value & 0x08 != 0 {
+                // OCW3: register read on the command port
+                if value & 0x02 != 0 {
+                    self.read_isr = value & 0x01 != 0;
+                }
+            } else {
+                self.command(value);
+            }
+            return;
+        }
+
+        self.initialization = match self.initialization {
+            Initialization::Icw2 => {
+                self.base = value & 0xf8;
+                self.next_initialization(Initialization::Icw2)
+            }
+            // cascade wiring has no effect with a single controller
+            Initialization::Icw3 => self.next_initialization(Initialization::Icw3),
+            Initialization::Icw4 => {
+                self.auto_eoi = value & 0x02 != 0;
+                Initialization::Ready
+            }
+            Initialization::Ready => {
+                self.imr = value;
+                Initialization::Ready
+            }
+        };
+    }
+
+    fn next_initialization(&self, current: Initialization) -> Initialization {
+        match current {
+            Initialization::Icw2 if self.cascade => Initialization::Icw3,
+            Initialization::Icw2 | Initialization::Icw3 if self.icw4 => Initialization::Icw4,
+            _ => Initialization::Ready,
+        }
+    }
+
+    /// OCW2: end of interrupt and priority rotation
+    fn command(&mut self, value: u8) {
+        let level = value & 7;
+
+        match value >> 5 {
+            // non-specific EOI, optionally rotating
+            0b001 => {
+                self.end_of_interrupt();
+            }
+            0b101 => {
+                if let Some(line) = self.end_of_interrupt() {
+                    self.lowest = line;
+                }
+            }
+            // specific EOI, optionally rotating
+            0b011 => self.isr &= !(1 << level),
+            0b111 => {
+                self.isr &= !(1 << level);
+                self.lowest = level;
+            }
+            0b100 => self.rotate_auto_eoi = true,
+            0b000 => self.rotate_auto_eoi = false,
+            0b110 => self.lowest = level,
+            _ => {}
+        }
+    }
+}
+
+impl Default for Pic {
+    fn default() -> Self {
+        Pic::new()
+    }
+}
@@ -0,0 +1,70 @@
+#[test]
+fn pic_priority() {
+    use crate::pic::{Pic, EOI, PIC_COMMAND_PORT, PIC_DATA_PORT};
+
+    let mut pic = Pic::new();
+    pic.request(3);
+    pic.request(1);
+    assert_eq!(pic.pending(), Some(1));
+    assert_eq!(pic.acknowledge(), Some(0x09));
+
+    // lower priorities wait for the EOI, higher ones nest
+    assert_eq!(pic.pending(), None);
+    pic.request(0);
+    assert_eq!(pic.acknowledge(), Some(0x08));
+    assert_eq!(pic.isr, 0b0000_0011);
+
+    pic.write(PIC_COMMAND_PORT, EOI);
+    pic.write(PIC_COMMAND_PORT, EOI);
+    assert_eq!(pic.isr, 0);
+    assert_eq!(pic.acknowledge(), Some(0x0b));
+    pic.write(PIC_COMMAND_PORT, 0x63); // specific EOI for IRQ 3
+    assert_eq!(pic.isr, 0);
+
+    // masking
+    pic.write(PIC_DATA_PORT, 0b0001_0000);
+    assert_eq!(pic.read(PIC_DATA_PORT), 0b0001_0000);
+    pic.request(4);
+    assert_eq!(pic.pending(), None);
+    assert_eq!(pic.read(PIC_COMMAND_PORT), 0b0001_0000);
+    pic.write(PIC_DATA_PORT, 0);
+    assert_eq!(pic.acknowledge(), Some(0x0c));
+    pic.write(PIC_COMMAND_PORT, 0x0b); // OCW3: read ISR
+    assert_eq!(pic.read(PIC_COMMAND_PORT), 0b0001_0000);
+
+    // rotating on EOI makes IRQ 4 the lowest priority
+    pic.write(PIC_COMMAND_PORT, 0xa0);
+    pic.request(4);
+    pic.request(6);
+    assert_eq!(pic.lowest, 4);
+    assert_eq!(pic.acknowledge(), Some(0x0e));
+}
+
+#[test]
+fn pic_initialization() {
+    use crate::pic::{Pic, PIC_COMMAND_PORT, PIC_DATA_PORT};
+
+    // single controller, ICW4 with automatic EOI, vectors from 50h
+    let mut pic = Pic::new();
+    pic.imr = 0xff;
+    pic.write(PIC_COMMAND_PORT, 0x13);
+    assert_eq!(pic.imr, 0);
+    pic.write(PIC_DATA_PORT, 0x50);
+    pic.write(PIC_DATA_PORT, 0x03);
+    pic.write(PIC_DATA_PORT, 0xfe);
+    assert_eq!((pic.base, pic.auto_eoi, pic.imr), (0x50, true, 0xfe));
+
+    pic.request(0);
+    pic.request(1);
+    assert_eq!(pic.acknowledge(), Some(0x50));
+    assert_eq!(pic.isr, 0);
+    assert_eq!(pic.acknowledge(), None);
+
+    // cascaded controllers expect ICW3 before ICW4
+    pic.write(PIC_COMMAND_PORT, 0x11);
+    pic.write(PIC_DATA_PORT, 0x08);
+    pic.write(PIC_DATA_PORT, 0x04);
+    pic.write(PIC_DATA_PORT, 0x01);
+    pic.write(PIC_DATA_PORT, 0xfb);
+    assert_eq!((pic.base, pic.auto_eoi, pic.imr), (0x08, false, 0xfb));
+}
@@ -0,0 +1,469 @@
+use crate::{bios::INSTRUCTIONS_PER_TICK, machine::Machine, pic::Pic, port::PortDevice};
+use std::{cell::RefCell, rc::Rc};
+
+mod test;
+
+/// Data port of channel 0; channels 1 and 2 follow
+pub const PIT_CHANNEL_PORT: u16 = 0x40;
+/// Control word port
+pub const PIT_CONTROL_PORT: u16 = 0x43;
+/// Port B of the system board: channel 2 gate (bit 0) and speaker data (bit 1); reads
+/// add the refresh toggle (bit 4) and the channel 2 output (bit 5)
+pub const SYSTEM_PORT_B: u16 = 0x61;
+
+/// Input clocks per BIOS timer tick (a count of 0 on channel 0). The timer runs at
+/// 1.19318 MHz on a PC; here a tick lasts `INSTRUCTIONS_PER_TICK` machine steps.
+pub const CLOCKS_PER_TICK: u64 = 0x10000;
+
+/// Input clocks per period of the refresh request toggling port B bit 4 (15 µs)
+const REFRESH_CLOCKS: u64 = 18;
+
+/// Bytes of the count transferred through the data port
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AccessMode {
+    Low,
+    High,
+    /// Low byte, then high byte
+    Word,
+}
+
+/// One counter of the timer
+#[derive(Clone, Debug)]
+pub struct Channel {
+    /// Counter mode 0 to 5
+    pub mode: u8,
+    pub access: AccessMode,
+    /// Counts in binary coded decimal instead of binary
+    pub bcd: bool,
+    /// Count written by the program, 0 meaning the maximum
+    pub initial: u16,
+    /// Counting element
+    pub count: u32,
+    pub out: bool,
+    pub gate: bool,
+    /// A complete count was written since the mode was set
+    armed: bool,
+    /// The counting element was loaded since the mode was set
+    counting: bool,
+    /// A complete count waits to be loaded on the next clock
+    load: bool,
+    /// A gate trigger (modes 1 and 5) or rising gate (modes 2 and 3) waits for the next clock
+    trigger: bool,
+    /// A count was written but not loaded yet (status bit 6)
+    null_count: bool,
+    /// The terminal count of a one-shot mode was reached
+    expired: bool,
+    /// Remaining clocks of the current half period in mode 3
+    half: u32,
+    /// Next data port write is the high byte of a word count
+    write_high: bool,
+    /// Next data port read is the high byte of a word count
+    read_high: bool,
+    latch: Option<u16>,
+    status: Option<u8>,
+}
+
+impl Channel {
+    fn new(gate: bool) -> Channel {
+        Self {
+            mode: 0,
+            access: AccessMode::Word,
+            bcd: false,
+            initial: 0,
+            count: 0,
+            out: false,
+            gate,
+            armed: false,
+            counting: false,
+            load: false,
+            trigger: false,
+            null_count: true,
+            expired: false,
+            half: 0,
+            write_high: false,
+            read_high: false,
+            latch: None,
+            status: None,
+        }
+    }
+
+    /// Clocks of a full count
+    fn period(&self) -> u32 {
+        match (self.initial, self.bcd) {
+            (0, false) => 0x10000,
+            (0, true) => 10000,
+            (count, false) => count as u32,
+            (count, true) => from_bcd(count),
+        }
+    }
+
+    /// Modulus of the counting element
+    fn modulus(&self) -> u32 {
+        if self.bcd {
+            10000
+        } else {
+            0x10000
+        }
+    }
+
+    /// Sets the mode and access of a control word; the counter waits for a count.
+    fn control(&mut self, value: u8) {
+        self.access = match (value >> 4) & 3 {
+            1 => AccessMode::Low,
+            2 => AccessMode::High,
+            _ => AccessMode::Word,
+        };
+        // modes 6 and 7 are aliases of 2 and 3
+        self.mode = match (value >> 1) & 7 {
+            mode @ 6..=7 => mode - 4,
+            mode => mode,
+        };
+        self.bcd = value & 1 != 0;
+        self.out = self.mode != 0;
+        self.armed = false;
+        self.counting = false;
+        self.load = false;
+        self.trigger = false;
+        self.null_count = true;
+        self.write_high = false;
+        self.read_high = false;
+        self.latch = None;
+        self.status = None;
+    }
+
+    /// Latches the current count for reading (counter latch command).
+    fn latch_count(&mut self) {
+        if self.latch.is_none() {
+            let count = self.count as u16;
+            self.latch = Some(if self.bcd { to_bcd(count) } else { count });
+            self.read_high = false;
+        }
+    }
+
+    /// Latches the status byte (read-back command of the 8254).
+    fn latch_status(&mut self) {
+        if self.status.is_none() {
+            let access = match self.access {
+                AccessMode::Low => 1,
+                AccessMode::High => 2,
+                AccessMode::Word => 3,
+            };
+            self.status = Some(
+                (self.out as u8) << 7
+                    | (self.null_count as u8) << 6
+                    | access << 4
+                    | self.mode << 1
+                    | self.bcd as u8,
+            );
+        }
+    }
+
+    fn read(&mut self) -> u8 {
+        if let Some(status) = self.status.take() {
+            return status;
+        }
+
+        let value = match self.latch {
+            Some(latch) => latch,
+            None if self.bcd => to_bcd(self.count as u16),
+            None => self.count as u16,
+        };
+        let [low, high] = value.to_le_bytes();
+
+        let (byte, done) = match self.access {
+            AccessMode::Low => (low, true),
+            AccessMode::High => (high, true),
+            AccessMode::Word if self.read_high => (high, true),
+            AccessMode::Word => (low, false),
+        };
+        self.read_high = !done;
+        if done {
+            self.latch = None;
+        }
+        byte
+    }
+
+    fn write(&mut self, value: u8) {
+        let count = match self.access {
+            AccessMode::Low => value as u16,
+            AccessMode::High => (value as u16) << 8,
+            AccessMode::Word if self.write_high => {
+                self.write_high = false;
+                self.initial & 0x00ff | (value as u16) << 8
+            }
+            AccessMode::Word => {
+                self.write_high = true;
+                self.initial = self.initial & 0xff00 | value as u16;
+                // the first byte stops counting in mode 0
+                if self.mode == 0 {
+                    self.counting = false;
+                }
+                return;
+            }
+        };
+
+        self.initial = count;
+        self.armed = true;
+        self.null_count = true;
+        match self.mode {
+            // a new count restarts the counter
+            0 | 4 => {
+                self.out = self.mode != 0;
+                self.counting = false;
+                self.load = true;
+            }
+            // periodic modes take a new count at the end of the period
+            2 | 3 if self.counting => {}
+            2 | 3 => self.load = true,
+            // one-shot modes wait for a gate trigger
+            _ => {}
+        }
+    }
+
+    fn set_gate(&mut self, gate: bool) {
+        if gate && !self.gate {
+            match self.mode {
+                1 | 5 if self.armed => self.trigger = true,
+                2 | 3 if self.counting => self.trigger = true,
+                _ => {}
+            }
+        } else if !gate && self.gate && matches!(self.mode, 2 | 3) {
+            self.out = true;
+        }
+        self.gate = gate;
+    }
+
+    /// Loads the initial count into the counting element.
+    fn reload(&mut self) {
+        self.count = self.period() % self.modulus();
+        self.counting = true;
+        self.load = false;
+        self.trigger = false;
+        self.null_count = false;
+        self.expired = false;
+        if self.mode == 3 {
+            self.out = true;
+            self.half = self.period().div_ceil(2);
+        }
+    }
+
+    /// Decrements the counting element, wrapping at zero.
+    fn decrement(&mut self, by: u32) {
+        self.count = (self.count + self.modulus() - by) % self.modulus();
+    }
+
+    /// Advances the counter by one input clock.
+    fn clock(&mut self) {
+        match self.mode {
+            0 | 4 => {
+                if self.load {
+                    self.reload();
+                    return;
+                }
+                if !self.counting || !self.gate {
+                    return;
+                }
+                // the strobe of mode 4 lasts one clock
+                if self.mode == 4 && !self.out {
+                    self.out = true;
+                }
+                self.decrement(1);
+                if self.count == 0 && !self.expired {
+                    self.expired = true;
+                    self.out = self.mode == 0;
+                }
+            }
+            1 | 5 => {
+                if self.trigger {
+                    self.reload();
+                    self.out = self.mode == 5;
+                    return;
+                }
+                if !self.counting {
+                    return;
+                }
+                if self.mode == 5 && !self.out {
+                    self.out = true;
+                }
+                self.decrement(1);
+                if self.count == 0 && !self.expired {
+                    self.expired = true;
+                    self.out = self.mode == 1;
+                }
+            }
+            2 => {
+                if self.load || self.trigger {
+                    self.reload();
+                    return;
+                }
+                if !self.counting || !self.gate {
+                    return;
+                }
+                // the output is low for the clock after the count reached 1
+                if !self.out {
+                    self.reload();
+                    self.out = true;
+                    return;
+                }
+                self.decrement(1);
+                if self.count == 1 {
+                    self.out = false;
+                }
+            }
+            _ => {
+                if self.load || self.trigger {
+                    self.reload();
+                    return;
+                }
+                if !self.counting || !self.gate {
+                    return;
+                }
+                self.half = self.half.saturating_sub(1);
+                self.count = self.half * 2;
+                if self.half == 0 {
+                    // high for the larger half of odd counts
+                    self.out = !self.out;
+                    self.half = if self.out {
+                        self.period().div_ceil(2)
+                    } else {
+                        (self.period() / 2).max(1)
+                    };
+                    self.count = self.period() % self.modulus();
+                    self.null_count = false;
+                }
+            }
+        }
+    }
+}
+
+/// 8253/8254 programmable interval timer with the channel 2 gate on port B. Channel 0
+/// raises IRQ 0 on every rising edge of its output. The input clock is derived from the
+/// machine's steps, so timing is reproducible.
+#[derive(Clone, Debug)]
+pub struct Pit {
+    pub channels: [Channel; 3],
+    /// Speaker data bit of port B
+    pub speaker: bool,
+    /// Input clocks elapsed
+    pub clocks: u64,
+}
+
+impl Pit {
+    /// Creates a timer programmed like the PC BIOS leaves it: channel 0 in mode 3 with
+    /// the maximum count (18.2 Hz), channel 1 in mode 2 for memory refresh and channel 2
+    /// in mode 3 for an 896 Hz beep with its gate closed.
+    pub fn new() -> Pit {
+        let mut pit = Self {
+            channels: [Channel::new(true), Channel::new(true), Channel::new(false)],
+            speaker: false,
+            clocks: 0,
+        };
+
+        for (channel, control, count) in [(0, 0x36, 0), (1, 0x74, 18), (2, 0xb6, 1331)] {
+            pit.channels[channel].control(control);
+            pit.channels[channel].write(count as u8);
+            pit.channels[channel].write((count >> 8) as u8);
+        }
+
+        pit
+    }
+
+    /// Output of channel 2, driving the speaker while port B enables it
+    pub fn speaker_on(&self) -> bool {
+        self.speaker && self.channels[2].out
+    }
+
+    /// Advances all channels by `clocks` input clocks.
+    pub fn advance(&mut self, clocks: u64, pic: &mut Pic) {
+        for _ in 0..clocks {
+            let out = self.channels[0].out;
+            for channel in &mut self.channels {
+                channel.clock();
+            }
+            if !out && self.channels[0].out {
+                pic.request(0);
+            }
+            self.clocks += 1;
+        }
+    }
+}
+
+impl Default for Pit {
+    fn default() -> Self {
+        Pit::new()
+    }
+}
+
+impl PortDevice for Pit {
+    fn read_byte(&mut self, port: u16) -> u8 {
+        match port {
+            SYSTEM_PORT_B => {
+                let refresh = (self.clocks / REFRESH_CLOCKS) % 2 == 1;
+                self.channels[2].gate as u8
+                    | (self.speaker as u8) << 1
+                    | (refresh as u8) << 4
+                    | (self.channels[2].out as u8) << 5
+            }
+            PIT_CONTROL_PORT => 0xff,
+            _ => self.channels[(port - PIT_CHANNEL_PORT) as usize % 3].read(),
+        }
+    }
+
+    fn write_byte(&mut self, port: u16, value: u8) {
+        match port {
+            SYSTEM_PORT_B => {
+                self.channels[2].set_gate(value & 1 != 0);
+                self.speaker = value & 2 != 0;
+            }
+            PIT_CONTROL_PORT => match value >> 6 {
+                // read-back: bit 5 clear latches counts, bit 4 clear latches status
+                3 => {
+                    for (index, channel) in self.channels.iter_mut().enumerate() {
+                        if value & (2 << index) != 0 {
+                            if value & 0x20 == 0 {
+                                channel.latch_count();
+                            }
+                            if value & 0x10 == 0 {
+                                channel.latch_status();
+                            }
+                        }
+                    }
+                }
+                channel if value & 0x30 == 0 => self.channels[channel as usize].latch_count(),
+                channel => self.channels[channel as usize].control(value),
+            },
+            _ => self.channels[(port - PIT_CHANNEL_PORT) as usize % 3].write(value),
+        }
+    }
+
+    fn update(&mut self, clock: u64, pic: &mut Pic) {
+        let target = clock * CLOCKS_PER_TICK / INSTRUCTIONS_PER_TICK;
+        if target > self.clocks {
+            self.advance(target - self.clocks, pic);
+        }
+    }
+}
+
+/// Maps a timer on ports 40h to 43h and port B and returns it.
+pub fn install(machine: &mut Machine) -> Rc<RefCell<Pit>> {
+    let pit = Rc::new(RefCell::new(Pit::new()));
+    machine
+        .ports
+        .map_shared(PIT_CHANNEL_PORT..=PIT_CONTROL_PORT, pit.clone());
+    machine
+        .ports
+        .map_shared(SYSTEM_PORT_B..=SYSTEM_PORT_B, pit.clone());
+    pit
+}
+
+fn from_bcd(value: u16) -> u32 {
+    (0..4).rev().fold(0, |number, digit| {
+        number * 10 + ((value >> (digit * 4)) & 0xf) as u32
+    })
+}
+
+fn to_bcd(value: u16) -> u16 {
+    (0..4).fold(0, |bcd, digit| {
+        bcd | (value / 10u16.pow(digit) % 10) << (digit * 4)
+    })
+}
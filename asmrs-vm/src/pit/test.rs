@@ -0,0 +1,123 @@
+#[cfg(test)]
+fn clock(pit: &mut crate::pit::Pit, clocks: u64) -> bool {
+    let mut pic = crate::pic::Pic::new();
+    pit.advance(clocks, &mut pic);
+    pic.irr & 1 != 0
+}
+
+#[test]
+fn pit_modes() {
+    use crate::{
+        pit::{Pit, PIT_CHANNEL_PORT, PIT_CONTROL_PORT},
+        port::PortDevice,
+    };
+
+    let mut pit = Pit::new();
+
+    // mode 0: the output rises when the count runs out
+    pit.write_byte(PIT_CONTROL_PORT, 0x30);
+    pit.write_byte(PIT_CHANNEL_PORT, 5);
+    pit.write_byte(PIT_CHANNEL_PORT, 0);
+    assert!(!clock(&mut pit, 5));
+    assert!(!pit.channels[0].out);
+    assert!(clock(&mut pit, 1));
+    assert!(pit.channels[0].out);
+
+    // mode 2: low for one clock every count
+    pit.write_byte(PIT_CONTROL_PORT, 0x34);
+    pit.write_byte(PIT_CHANNEL_PORT, 3);
+    pit.write_byte(PIT_CHANNEL_PORT, 0);
+    clock(&mut pit, 3);
+    assert!(!pit.channels[0].out);
+    assert!(clock(&mut pit, 1));
+    assert_eq!(pit.channels[0].count, 3);
+
+    // mode 3: a square wave
+    pit.write_byte(PIT_CONTROL_PORT, 0x36);
+    pit.write_byte(PIT_CHANNEL_PORT, 4);
+    pit.write_byte(PIT_CHANNEL_PORT, 0);
+    let mut outputs = Vec::new();
+    for _ in 0..9 {
+        clock(&mut pit, 1);
+        outputs.push(pit.channels[0].out as u8);
+    }
+    assert_eq!(outputs, [1, 1, 0, 0, 1, 1, 0, 0, 1]);
+
+    // BCD counts
+    pit.write_byte(PIT_CONTROL_PORT, 0x31);
+    pit.write_byte(PIT_CHANNEL_PORT, 0x00);
+    pit.write_byte(PIT_CHANNEL_PORT, 0x10);
+    clock(&mut pit, 2);
+    assert_eq!(pit.channels[0].count, 999);
+    assert_eq!(pit.read_byte(PIT_CHANNEL_PORT), 0x99);
+    assert_eq!(pit.read_byte(PIT_CHANNEL_PORT), 0x09);
+}
+
+#[test]
+fn pit_latches() {
+    use crate::{
+        pit::{Pit, PIT_CHANNEL_PORT, PIT_CONTROL_PORT, SYSTEM_PORT_B},
+        port::PortDevice,
+    };
+
+    let mut pit = Pit::new();
+    pit.write_byte(PIT_CONTROL_PORT, 0x34);
+    pit.write_byte(PIT_CHANNEL_PORT, 0x00);
+    pit.write_byte(PIT_CHANNEL_PORT, 0x01);
+    clock(&mut pit, 0x11);
+
+    // the latched count survives further clocks until it is read
+    pit.write_byte(PIT_CONTROL_PORT, 0x00);
+    clock(&mut pit, 5);
+    assert_eq!(pit.read_byte(PIT_CHANNEL_PORT), 0xf0);
+    assert_eq!(pit.read_byte(PIT_CHANNEL_PORT), 0x00);
+    assert_eq!(pit.read_byte(PIT_CHANNEL_PORT), 0xeb);
+
+    // read-back: status then count
+    pit.read_byte(PIT_CHANNEL_PORT);
+    pit.write_byte(PIT_CONTROL_PORT, 0xc2);
+    clock(&mut pit, 1);
+    assert_eq!(pit.read_byte(PIT_CHANNEL_PORT), 0xb4);
+    assert_eq!(pit.read_byte(PIT_CHANNEL_PORT), 0xeb);
+    assert_eq!(pit.read_byte(PIT_CHANNEL_PORT), 0x00);
+
+    // port B opens the gate of channel 2 and enables the speaker
+    assert_eq!(pit.read_byte(SYSTEM_PORT_B) & 0x03, 0);
+    pit.write_byte(SYSTEM_PORT_B, 0x03);
+    assert!(pit.channels[2].gate);
+    clock(&mut pit, 1);
+    assert!(pit.speaker_on());
+    assert_eq!(pit.read_byte(SYSTEM_PORT_B) & 0x23, 0x23);
+}
+
+#[test]
+fn pit_interrupts() {
+    use crate::{bios::INSTRUCTIONS_PER_TICK, machine::Machine, pit};
+    use asmrs_parser::lexer::token::Cpu;
+
+    // sti / hlt / jmp short back to hlt
+    let mut machine = Machine::new(Cpu::I8086);
+    machine.memory.load(0x500, &[0xfb, 0xf4, 0xeb, 0xfd]);
+    machine.registers.ip = 0x500;
+    machine.registers.sp = 0xfffe;
+
+    // inc byte [700h] / mov al, 20h / out 20h, al / iret
+    machine.memory.load(
+        0x600,
+        &[0xfe, 0x06, 0x00, 0x07, 0xb0, 0x20, 0xe6, 0x20, 0xcf],
+    );
+    machine.memory.load(0x20, &[0x00, 0x06, 0x00, 0x00]);
+
+    pit::install(&mut machine);
+
+    // the machine waits in `hlt` for the timer, every tick
+    machine.run(3 * INSTRUCTIONS_PER_TICK + 10).unwrap();
+    assert_eq!(machine.memory.read_byte(0x700), 3);
+    assert_eq!(machine.clock, 3 * INSTRUCTIONS_PER_TICK + 10);
+    assert!(machine.instructions < 30);
+    assert!(machine.waiting());
+
+    // masking IRQ 0 leaves nothing to wait for
+    machine.pic.imr = 0x01;
+    assert_eq!(machine.run(u64::MAX).unwrap(), 0);
+}
@@ -0,0 +1,182 @@
+use crate::{machine::AccessKind, pic::Pic};
+use std::{cell::RefCell, collections::BTreeSet, fmt::Debug, ops::RangeInclusive, rc::Rc};
+
+mod test;
+
+/// Value read from a port nothing answers on (the data bus floats high)
+pub const FLOATING_BUS: u8 = 0xff;
+
+/// Peripheral in the I/O space, implemented in Rust. Word accesses default to two byte
+/// accesses, the low byte at `port` and the high byte at `port + 1`.
+pub trait PortDevice {
+    fn read_byte(&mut self, port: u16) -> u8;
+
+    fn write_byte(&mut self, port: u16, value: u8);
+
+    fn read_word(&mut self, port: u16) -> u16 {
+        let low = self.read_byte(port);
+        let high = self.read_byte(port.wrapping_add(1));
+        u16::from_le_bytes([low, high])
+    }
+
+    fn write_word(&mut self, port: u16, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        self.write_byte(port, low);
+        self.write_byte(port.wrapping_add(1), high);
+    }
+
+    /// Catches up with the machine after each step. `clock` counts the steps taken,
+    /// executed or halted; devices keep time with it and raise IRQ lines on `pic`.
+    fn update(&mut self, _clock: u64, _pic: &mut Pic) {}
+}
+
+/// Shared device; one device may serve several port ranges.
+pub type Device = Rc<RefCell<dyn PortDevice>>;
+
+/// What `in` and `out` do on ports no device serves
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum UnhandledPorts {
+    /// Reads return FFh and writes are dropped
+    #[default]
+    Ignore,
+    /// Like `Ignore`, recording the first access per port and direction
+    Log,
+    /// The access stops the machine with an error
+    Fault,
+}
+
+/// Access to a port no device serves
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PortAccess {
+    pub kind: AccessKind,
+    pub port: u16,
+    /// 1 for bytes, 2 for words
+    pub size: u8,
+    /// Value written; the floating bus for reads
+    pub value: u16,
+}
+
+/// I/O space dispatching port accesses to the devices mapped on them. Clones share the
+/// devices.
+#[derive(Clone, Default)]
+pub struct Ports {
+    devices: Vec<(RangeInclusive<u16>, Device)>,
+    pub policy: UnhandledPorts,
+    /// Unhandled accesses recorded by `UnhandledPorts::Log`, in order
+    pub unhandled: Vec<PortAccess>,
+    logged: BTreeSet<(u16, bool)>,
+}
+
+impl Ports {
+    pub fn new() -> Ports {
+        Self::default()
+    }
+
+    /// Maps `device` on `ports`, in front of devices mapped earlier.
+    pub fn map(&mut self, ports: RangeInclusive<u16>, device: impl PortDevice + 'static) {
+        self.map_shared(ports, Rc::new(RefCell::new(device)));
+    }
+
+    /// Maps a device that may also serve other ranges.
+    pub fn map_shared(&mut self, ports: RangeInclusive<u16>, device: Device) {
+        self.devices.insert(0, (ports, device));
+    }
+
+    /// Removes the mappings covering `port` and returns their devices.
+    pub fn unmap(&mut self, port: u16) -> Vec<Device> {
+        let (removed, kept) = self
+            .devices
+            .drain(..)
+            .partition::<Vec<_>, _>(|(ports, _)| ports.contains(&port));
+        self.devices = kept;
+        removed.into_iter().map(|(_, device)| device).collect()
+    }
+
+    /// Device serving `port`, the latest mapping winning
+    pub fn get(&self, port: u16) -> Option<Device> {
+        self.devices
+            .iter()
+            .find(|(ports, _)| ports.contains(&port))
+            .map(|(_, device)| device.clone())
+    }
+
+    /// Reads a byte or word (`size` 1 or 2) from `port`.
+    pub fn read(&mut self, port: u16, size: u8) -> Result<u16, String> {
+        match self.get(port) {
+            Some(device) if size == 2 => Ok(device.borrow_mut().read_word(port)),
+            Some(device) => Ok(device.borrow_mut().read_byte(port) as u16),
+            None => {
+                let value = if size == 2 {
+                    u16::from_le_bytes([FLOATING_BUS; 2])
+                } else {
+                    FLOATING_BUS as u16
+                };
+                self.unhandled(AccessKind::Read, port, size, value)?;
+                Ok(value)
+            }
+        }
+    }
+
+    /// Writes a byte or word (`size` 1 or 2) to `port`.
+    pub fn write(&mut self, port: u16, size: u8, value: u16) -> Result<(), String> {
+        match self.get(port) {
+            Some(device) if size == 2 => device.borrow_mut().write_word(port, value),
+            Some(device) => device.borrow_mut().write_byte(port, value as u8),
+            None => self.unhandled(AccessKind::Write, port, size, value)?,
+        }
+        Ok(())
+    }
+
+    /// Updates every device once, however many ranges it serves.
+    pub fn update(&mut self, clock: u64, pic: &mut Pic) {
+        for (index, (_, device)) in self.devices.iter().enumerate() {
+            let mapped_before = self.devices[..index]
+                .iter()
+                .any(|(_, other)| Rc::ptr_eq(other, device));
+            if !mapped_before {
+                device.borrow_mut().update(clock, pic);
+            }
+        }
+    }
+
+    fn unhandled(
+        &mut self,
+        kind: AccessKind,
+        port: u16,
+        size: u8,
+        value: u16,
+    ) -> Result<(), String> {
+        match self.policy {
+            UnhandledPorts::Ignore => Ok(()),
+            UnhandledPorts::Log => {
+                if self.logged.insert((port, kind == AccessKind::Write)) {
+                    self.unhandled.push(PortAccess {
+                        kind,
+                        port,
+                        size,
+                        value,
+                    });
+                }
+                Ok(())
+            }
+            UnhandledPorts::Fault => Err(format!("Port {:04X}h has no device.", port)),
+        }
+    }
+}
+
+impl Debug for Ports {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ports")
+            .field(
+                "devices",
+                &self
+                    .devices
+                    .iter()
+                    .map(|(ports, _)| ports)
+                    .collect::<Vec<_>>(),
+            )
+            .field("policy", &self.policy)
+            .field("unhandled", &self.unhandled)
+            .finish()
+    }
+}
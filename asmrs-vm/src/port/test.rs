@@ -0,0 +1,128 @@
+/// Four byte registers on consecutive ports from 60h
+#[cfg(test)]
+#[derive(Default)]
+struct Registers([u8; 4]);
+
+#[cfg(test)]
+impl crate::port::PortDevice for Registers {
+    fn read_byte(&mut self, port: u16) -> u8 {
+        self.0[(port - 0x60) as usize & 3]
+    }
+
+    fn write_byte(&mut self, port: u16, value: u8) {
+        self.0[(port - 0x60) as usize & 3] = value;
+    }
+}
+
+/// Read-only port returning a constant
+#[cfg(test)]
+struct Constant(u8);
+
+#[cfg(test)]
+impl crate::port::PortDevice for Constant {
+    fn read_byte(&mut self, _port: u16) -> u8 {
+        self.0
+    }
+
+    fn write_byte(&mut self, _port: u16, _value: u8) {}
+}
+
+#[test]
+fn port_bus() {
+    use crate::{
+        machine::AccessKind,
+        port::{PortAccess, Ports, UnhandledPorts},
+    };
+
+    let mut ports = Ports::new();
+    ports.map(0x60..=0x63, Registers::default());
+
+    ports.write(0x60, 2, 0x1234).unwrap();
+    ports.write(0x63, 1, 0x56).unwrap();
+    assert_eq!(ports.read(0x61, 1), Ok(0x12));
+    assert_eq!(ports.read(0x62, 2), Ok(0x5600));
+
+    // unhandled ports float, are logged once or fault
+    assert_eq!(ports.read(0x80, 2), Ok(0xffff));
+    ports.policy = UnhandledPorts::Log;
+    ports.write(0x80, 1, 1).unwrap();
+    ports.write(0x80, 1, 2).unwrap();
+    ports.read(0x80, 1).unwrap();
+    assert_eq!(
+        ports.unhandled,
+        [
+            PortAccess {
+                kind: AccessKind::Write,
+                port: 0x80,
+                size: 1,
+                value: 1,
+            },
+            PortAccess {
+                kind: AccessKind::Read,
+                port: 0x80,
+                size: 1,
+                value: 0xff,
+            },
+        ]
+    );
+    ports.policy = UnhandledPorts::Fault;
+    assert_eq!(
+        ports.write(0x80, 1, 0),
+        Err("Port 0080h has no device.".to_string())
+    );
+
+    // later mappings win until they are removed
+    ports.map(0x62..=0x62, Constant(0x77));
+    assert_eq!(ports.read(0x62, 1), Ok(0x77));
+    assert_eq!(ports.unmap(0x62).len(), 2);
+    assert!(ports.get(0x60).is_none());
+}
+
+#[test]
+fn port_instructions() {
+    use crate::{machine::Machine, port::UnhandledPorts};
+    use asmrs_parser::lexer::token::Cpu;
+    use std::{cell::RefCell, rc::Rc};
+
+    // mov ax, 1234h / out 60h, ax / mov dx, 62h / mov al, 56h / out dx, al
+    // in al, 61h / mov dx, 60h / in ax, dx / mov al, 0ffh / out 21h, al / in al, 21h / hlt
+    let code = [
+        0xb8, 0x34, 0x12, 0xe7, 0x60, 0xba, 0x62, 0x00, 0xb0, 0x56, 0xee, 0xe4, 0x61, 0xba, 0x60,
+        0x00, 0xed, 0xb0, 0xff, 0xe6, 0x21, 0xe4, 0x21, 0xf4,
+    ];
+    let mut machine = Machine::new(Cpu::I80186);
+    machine.memory.load(0x500, &code);
+    machine.registers.ip = 0x500;
+
+    let device = Rc::new(RefCell::new(Registers::default()));
+    machine.ports.map_shared(0x60..=0x63, device.clone());
+
+    machine.run(6).unwrap();
+    assert_eq!(device.borrow().0, [0x34, 0x12, 0x56, 0]);
+    assert_eq!(machine.registers.ax & 0xff, 0x12);
+    machine.run(2).unwrap();
+    assert_eq!(machine.registers.ax, 0x1234);
+
+    // the interrupt controller is on ports 20h and 21h
+    machine.run(10).unwrap();
+    assert_eq!(machine.pic.imr, 0xff);
+    assert_eq!(machine.registers.ax, 0x12ff);
+
+    // rep outsb from DS:SI / in al, 80h
+    machine.memory.load(0x600, &[0xf3, 0x6e, 0xe4, 0x80]);
+    machine.memory.load(0x700, b"abc");
+    machine.registers.ip = 0x600;
+    machine.registers.si = 0x700;
+    machine.registers.cx = 3;
+    machine.registers.dx = 0x61;
+    machine.halted = false;
+    machine.step().unwrap();
+    assert_eq!(device.borrow().0[1], b'c');
+    assert_eq!(machine.registers.si, 0x703);
+
+    machine.ports.policy = UnhandledPorts::Fault;
+    assert_eq!(
+        machine.step().unwrap_err().to_string(),
+        "Execution Error: at 0000:0602: Port 0080h has no device."
+    );
+}
@@ -0,0 +1,134 @@
+use asmrs_parser::lexer::token::{
+    GeneralPurposeRegister, RegisterType, SegmentRegister, SpecialPurposeRegister,
+};
+
+mod test;
+
+/// Carry flag
+pub const FLAG_CARRY: u16 = 1 << 0;
+/// Parity flag
+pub const FLAG_PARITY: u16 = 1 << 2;
+/// Auxiliary carry flag
+pub const FLAG_AUXILIARY: u16 = 1 << 4;
+/// Zero flag
+pub const FLAG_ZERO: u16 = 1 << 6;
+/// Sign flag
+pub const FLAG_SIGN: u16 = 1 << 7;
+/// Trap (single step) flag
+pub const FLAG_TRAP: u16 = 1 << 8;
+/// Interrupt enable flag
+pub const FLAG_INTERRUPT: u16 = 1 << 9;
+/// Direction flag
+pub const FLAG_DIRECTION: u16 = 1 << 10;
+/// Overflow flag
+pub const FLAG_OVERFLOW: u16 = 1 << 11;
+
+/// Flags with their names, most significant first
+pub const FLAG_NAMES: [(u16, &str); 9] = [
+    (FLAG_OVERFLOW, "OF"),
+    (FLAG_DIRECTION, "DF"),
+    (FLAG_INTERRUPT, "IF"),
+    (FLAG_TRAP, "TF"),
+    (FLAG_SIGN, "SF"),
+    (FLAG_ZERO, "ZF"),
+    (FLAG_AUXILIARY, "AF"),
+    (FLAG_PARITY, "PF"),
+    (FLAG_CARRY, "CF"),
+];
+
+/// Register file of the 8086.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Registers {
+    pub ax: u16,
+    pub bx: u16,
+    pub cx: u16,
+    pub dx: u16,
+    pub sp: u16,
+    pub bp: u16,
+    pub si: u16,
+    pub di: u16,
+    pub ip: u16,
+    pub cs: u16,
+    pub ds: u16,
+    pub ss: u16,
+    pub es: u16,
+    pub flags: u16,
+}
+
+impl Registers {
+    /// Reads a register. 8-bit registers are zero extended.
+    pub fn get(&self, register: RegisterType) -> u16 {
+        use GeneralPurposeRegister as G;
+
+        match register {
+            RegisterType::GeneralPurpose(register) => match register {
+                G::Al => self.ax & 0xff,
+                G::Ah => self.ax >> 8,
+                G::Ax => self.ax,
+                G::Bl => self.bx & 0xff,
+                G::Bh => self.bx >> 8,
+                G::Bx => self.bx,
+                G::Cl => self.cx & 0xff,
+                G::Ch => self.cx >> 8,
+                G::Cx => self.cx,
+                G::Dl => self.dx & 0xff,
+                G::Dh => self.dx >> 8,
+                G::Dx => self.dx,
+            },
+            RegisterType::Segment(register) => match register {
+                SegmentRegister::Cs => self.cs,
+                SegmentRegister::Ds => self.ds,
+                SegmentRegister::Ss => self.ss,
+                SegmentRegister::Es => self.es,
+            },
+            RegisterType::SpecialPurpose(register) => match register {
+                SpecialPurposeRegister::Sp => self.sp,
+                SpecialPurposeRegister::Bp => self.bp,
+                SpecialPurposeRegister::Si => self.si,
+                SpecialPurposeRegister::Di => self.di,
+                SpecialPurposeRegister::Ip => self.ip,
+            },
+            RegisterType::FloatingPoint(_) => 0,
+        }
+    }
+
+    /// Writes a register. 8-bit registers take the low byte of `value`.
+    pub fn set(&mut self, register: RegisterType, value: u16) {
+        use GeneralPurposeRegister as G;
+
+        let low = |word: u16| (word & 0xff00) | (value & 0xff);
+        let high = |word: u16| (word & 0x00ff) | (value << 8);
+
+        match register {
+            RegisterType::GeneralPurpose(register) => match register {
+                G::Al => self.ax = low(self.ax),
+                G::Ah => self.ax = high(self.ax),
+                G::Ax => self.ax = value,
+                G::Bl => self.bx = low(self.bx),
+                G::Bh => self.bx = high(self.bx),
+                G::Bx => self.bx = value,
+                G::Cl => self.cx = low(self.cx),
+                G::Ch => self.cx = high(self.cx),
+                G::Cx => self.cx = value,
+                G::Dl => self.dx = low(self.dx),
+                G::Dh => self.dx = high(self.dx),
+                G::Dx => self.dx = value,
+            },
+            RegisterType::Segment(register) => match register {
+                SegmentRegister::Cs => self.cs = value,
+                SegmentRegister::Ds => self.ds = value,
+                SegmentRegister::Ss => self.ss = value,
+                SegmentRegister::Es => self.es = value,
+            },
+            RegisterType::SpecialPurpose(register) => match register {
+                SpecialPurposeRegister::Sp => self.sp = value,
+                SpecialPurposeRegister::Bp => self.bp = value,
+                SpecialPurposeRegister::Si => self.si = value,
+                SpecialPurposeRegister::Di => self.di = value,
+                SpecialPurposeRegister::Ip => self.ip = value,
+            },
+            // the coprocessor stack lives in `Fpu`
+            RegisterType::FloatingPoint(_) => {}
+        }
+    }
+}
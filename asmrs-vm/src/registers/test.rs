@@ -0,0 +1,25 @@
+#[test]
+fn registers_byte_halves() {
+    use crate::registers::Registers;
+    use asmrs_parser::lexer::token::{GeneralPurposeRegister, RegisterType};
+
+    let mut registers = Registers::default();
+    registers.set(
+        RegisterType::GeneralPurpose(GeneralPurposeRegister::Ax),
+        0x1234,
+    );
+    registers.set(
+        RegisterType::GeneralPurpose(GeneralPurposeRegister::Ah),
+        0xff,
+    );
+    assert_eq!(registers.ax, 0xff34);
+    registers.set(
+        RegisterType::GeneralPurpose(GeneralPurposeRegister::Al),
+        0x1ab,
+    );
+    assert_eq!(registers.ax, 0xffab);
+    assert_eq!(
+        registers.get(RegisterType::GeneralPurpose(GeneralPurposeRegister::Ah)),
+        0xff
+    );
+}
@@ -0,0 +1,213 @@
+use crate::{
+    bios::Video,
+    machine::Machine,
+    memory::{address, Memory},
+};
+use std::io::{self, Write};
+
+mod test;
+
+/// Segment of the color text buffer: a character and an attribute byte per cell
+pub const TEXT_SEGMENT: u16 = 0xb800;
+
+/// Attribute of blank cells: light gray on black
+pub const DEFAULT_ATTRIBUTE: u8 = 0x07;
+
+const COLUMNS: usize = Video::COLUMNS as usize;
+const ROWS: usize = Video::ROWS as usize;
+
+/// Glyphs of code page 437 for the control characters
+const LOW_GLYPHS: &str = " ☺☻♥♦♣♠•◘○◙♂♀♪♫☼►◄↕‼¶§▬↨↑↓→←∟↔▲▼";
+/// Glyphs of code page 437 from 80h
+const HIGH_GLYPHS: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■ ";
+
+/// ANSI color number of each CGA color (the two orders swap blue and red)
+const ANSI_COLORS: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+/// Linear address of the cell at `row` and `column` of the text buffer
+pub fn cell_address(row: u8, column: u8) -> usize {
+    address(TEXT_SEGMENT, 0) + (row as usize * COLUMNS + column as usize) * 2
+}
+
+/// Fills the text buffer with blanks.
+pub fn clear(machine: &mut Machine) {
+    scroll(
+        machine,
+        (0, 0),
+        (ROWS as u8 - 1, COLUMNS as u8 - 1),
+        0,
+        DEFAULT_ATTRIBUTE,
+    );
+}
+
+/// Scrolls the window from `top_left` to `bottom_right` (rows and columns, inclusive) up
+/// by `lines`, or down for negative counts, blanking the uncovered lines with `attribute`.
+/// A count of 0 or at least the window height blanks the whole window.
+pub fn scroll(
+    machine: &mut Machine,
+    top_left: (u8, u8),
+    bottom_right: (u8, u8),
+    lines: i8,
+    attribute: u8,
+) {
+    let (top, left) = top_left;
+    let bottom = bottom_right.0.min(ROWS as u8 - 1);
+    let right = bottom_right.1.min(COLUMNS as u8 - 1);
+    if top > bottom || left > right {
+        return;
+    }
+
+    let height = bottom - top + 1;
+    let count = match lines.unsigned_abs() {
+        0 => height,
+        count => count.min(height),
+    };
+    let width = (right - left + 1) as usize * 2;
+
+    let rows: Vec<u8> = if lines >= 0 {
+        (top..=bottom).collect()
+    } else {
+        (top..=bottom).rev().collect()
+    };
+    for (index, row) in rows.iter().enumerate() {
+        let destination = cell_address(*row, left);
+        match rows.get(index + count as usize) {
+            Some(source) => {
+                let cells = machine.memory.read(cell_address(*source, left), width);
+                machine.write_block(destination, &cells);
+            }
+            None => {
+                for offset in (0..width).step_by(2) {
+                    machine.write_byte(destination + offset, b' ');
+                    machine.write_byte(destination + offset + 1, attribute);
+                }
+            }
+        }
+    }
+}
+
+/// Unicode character shown for a byte of code page 437
+pub fn glyph(character: u8) -> char {
+    match character {
+        0x00..=0x1f => LOW_GLYPHS.chars().nth(character as usize).unwrap_or(' '),
+        0x7f => '⌂',
+        0x80..=0xff => HIGH_GLYPHS
+            .chars()
+            .nth(character as usize - 0x80)
+            .unwrap_or(' '),
+        _ => character as char,
+    }
+}
+
+/// Snapshot of the 80x25 text buffer at B800:0000
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextScreen {
+    /// Character and attribute of each cell, row by row
+    pub cells: Vec<(u8, u8)>,
+}
+
+impl TextScreen {
+    pub fn capture(memory: &Memory) -> TextScreen {
+        let cells = memory
+            .read(cell_address(0, 0), ROWS * COLUMNS * 2)
+            .chunks(2)
+            .map(|cell| (cell[0], cell[1]))
+            .collect();
+        Self { cells }
+    }
+
+    /// Character and attribute at `row` and `column`
+    pub fn cell(&self, row: u8, column: u8) -> (u8, u8) {
+        self.cells[row as usize * COLUMNS + column as usize]
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[(u8, u8)]> {
+        self.cells.chunks(COLUMNS)
+    }
+
+    /// Characters of the screen, a line per row without trailing blanks and without
+    /// attributes, for comparing against expected output.
+    pub fn text(&self) -> String {
+        self.rows()
+            .map(|row| {
+                let line = row
+                    .iter()
+                    .map(|(character, _)| glyph(*character))
+                    .collect::<String>();
+                format!("{}\n", line.trim_end())
+            })
+            .collect()
+    }
+
+    /// The screen drawn from the top left corner of an ANSI terminal with its colors.
+    /// Blinking attributes blink.
+    pub fn ansi(&self) -> String {
+        let mut text = String::from("\x1b[H");
+
+        for (index, row) in self.rows().enumerate() {
+            let mut current = None;
+            for (character, attribute) in row {
+                if current != Some(*attribute) {
+                    text.push_str(&sgr(*attribute));
+                    current = Some(*attribute);
+                }
+                text.push(glyph(*character));
+            }
+            text.push_str("\x1b[0m");
+            if index + 1 < ROWS {
+                text.push_str("\r\n");
+            }
+        }
+
+        text
+    }
+}
+
+/// Select graphic rendition sequence for a CGA attribute
+fn sgr(attribute: u8) -> String {
+    let foreground =
+        ANSI_COLORS[attribute as usize & 7] + if attribute & 0x08 != 0 { 90 } else { 30 };
+    let background = ANSI_COLORS[(attribute >> 4) as usize & 7] + 40;
+    let blink = if attribute & 0x80 != 0 { ";5" } else { "" };
+    format!("\x1b[0;{};{}{}m", foreground, background, blink)
+}
+
+/// Shows the text buffer on an ANSI terminal, redrawing it when it changed.
+pub struct Terminal {
+    output: Box<dyn Write>,
+    shown: Option<TextScreen>,
+}
+
+impl Terminal {
+    pub fn new(output: Box<dyn Write>) -> Terminal {
+        Self {
+            output,
+            shown: None,
+        }
+    }
+
+    /// Draws the screen in `memory` unless the terminal shows it already.
+    pub fn render(&mut self, memory: &Memory) -> io::Result<()> {
+        let screen = TextScreen::capture(memory);
+        if self.shown.as_ref() == Some(&screen) {
+            return Ok(());
+        }
+
+        if self.shown.is_none() {
+            self.output.write_all(b"\x1b[2J")?;
+        }
+        self.output.write_all(screen.ansi().as_bytes())?;
+        self.output.flush()?;
+        self.shown = Some(screen);
+        Ok(())
+    }
+
+    /// Moves below the screen so later output does not overwrite it.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.shown.is_some() {
+            self.output.write_all(b"\r\n")?;
+            self.output.flush()?;
+        }
+        Ok(())
+    }
+}
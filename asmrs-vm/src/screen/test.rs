@@ -0,0 +1,84 @@
+#[test]
+fn screen_snapshot() {
+    use crate::{
+        machine::test_machine,
+        screen::{cell_address, clear, glyph, TextScreen},
+    };
+
+    let mut machine = test_machine(&[]);
+    clear(&mut machine);
+    machine.memory.load(cell_address(0, 0), b"H\x1fi\x4e");
+    machine
+        .memory
+        .load(cell_address(2, 78), &[0xc9, 0x07, 0xcd, 0x07]);
+
+    let screen = TextScreen::capture(&machine.memory);
+    assert_eq!(screen.cell(0, 1), (b'i', 0x4e));
+    assert_eq!(glyph(0x01), '☺');
+    assert_eq!(glyph(0xb0), '░');
+    assert_eq!(
+        screen.text(),
+        format!("Hi\n\n{}╔═\n{}", " ".repeat(78), "\n".repeat(22))
+    );
+
+    // white on blue, then yellow on red; every row ends resetting the colors
+    let ansi = screen.ansi();
+    assert!(ansi.starts_with("\x1b[H\x1b[0;97;44mH\x1b[0;93;41mi\x1b[0;37;40m "));
+    assert_eq!(ansi.matches("\x1b[0m").count(), 25);
+    assert!(!ansi.ends_with('\n'));
+}
+
+#[test]
+fn screen_bios() {
+    use crate::{
+        bios::{Video, VIDEO_INTERRUPT},
+        machine::Machine,
+        screen::TextScreen,
+    };
+    use asmrs_parser::lexer::token::Cpu;
+
+    // mov ax, 3 / int 10h / mov ax, 0e41h / int 10h / mov ax, 0942h / mov bl, 1fh
+    // mov cx, 3 / int 10h / hlt
+    let code = [
+        0xb8, 0x03, 0x00, 0xcd, 0x10, 0xb8, 0x41, 0x0e, 0xcd, 0x10, 0xb8, 0x42, 0x09, 0xb3, 0x1f,
+        0xb9, 0x03, 0x00, 0xcd, 0x10, 0xf4,
+    ];
+    let mut machine = Machine::new(Cpu::I8086);
+    machine.memory.load(0x500, &code);
+    machine.registers.ip = 0x500;
+    machine.registers.sp = 0xfffe;
+    machine
+        .interrupts
+        .set(VIDEO_INTERRUPT, Video::new(Box::new(std::io::sink())));
+
+    machine.run(100).unwrap();
+    let screen = TextScreen::capture(&machine.memory);
+    assert_eq!(screen.text().lines().next(), Some("ABBB"));
+    assert_eq!(screen.cell(0, 0), (b'A', 0x07));
+    assert_eq!(screen.cell(0, 3), (b'B', 0x1f));
+
+    // teletype scrolls at the bottom of the screen
+    let mut video = Video::new(Box::new(std::io::sink()));
+    for line in 0..26 {
+        for character in format!("{}\r\n", line).bytes() {
+            video.teletype(&mut machine, character).unwrap();
+        }
+    }
+    let text = TextScreen::capture(&machine.memory).text();
+    assert_eq!(text.lines().next(), Some("2"));
+    assert_eq!(text.lines().nth(23), Some("25"));
+    assert_eq!(video.cursor, (24, 0));
+
+    // scroll the rows 0 to 1 down, blanking with white on blue
+    machine.registers.ax = 0x0701;
+    machine.registers.bx = 0x1700;
+    machine.registers.cx = 0x0000;
+    machine.registers.dx = 0x014f;
+    machine.interrupt(VIDEO_INTERRUPT).unwrap();
+    let screen = TextScreen::capture(&machine.memory);
+    assert_eq!(
+        screen.text().lines().take(3).collect::<Vec<_>>(),
+        ["", "2", "4"]
+    );
+    assert_eq!(screen.cell(0, 79), (b' ', 0x17));
+}
@@ -0,0 +1,245 @@
+use crate::{
+    machine::{Access, AccessKind, Machine},
+    memory::address,
+    registers::{Registers, FLAG_NAMES},
+};
+use asmrs_assembler::debug::DebugInfo;
+use asmrs_disassembler::{decoder::Instruction, disassembly::format_instruction};
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+    ops::Range,
+};
+
+mod test;
+
+/// Magic number at the start of a binary trace
+pub const TRACE_MAGIC: &[u8; 4] = b"ATRC";
+
+/// Version of the binary trace layout
+pub const TRACE_VERSION: u16 = 1;
+
+/// Registers compared between records, in the order of the binary change mask. IP is
+/// left out as every record starts with CS:IP; FLAGS is bit 12.
+const REGISTER_NAMES: [&str; 12] = [
+    "AX", "BX", "CX", "DX", "SP", "BP", "SI", "DI", "CS", "DS", "SS", "ES",
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// One line per instruction:
+    /// `CS:IP  BYTES  DISASSEMBLY  REG=VALUE... +FLAG -FLAG... R:ADDRESS=VALUE W:ADDRESS=VALUE`
+    Text,
+    /// Magic, version, then per instruction: CS, IP, byte count and bytes, a 16-bit mask
+    /// of the changed registers followed by their new values, access count and accesses
+    /// (kind: bit 0 write, bit 1 word; 32-bit address; 16-bit value). Little endian; the
+    /// disassembly is left out as it follows from the bytes.
+    Binary,
+}
+
+/// Writes one record per executed instruction.
+pub struct Tracer<W: Write> {
+    output: W,
+    format: TraceFormat,
+    /// Linear address ranges of the instructions to record; empty records everything
+    pub ranges: Vec<Range<usize>>,
+    /// Maximum number of records; later instructions are not recorded
+    pub limit: Option<u64>,
+    /// Records written so far
+    pub records: u64,
+}
+
+impl<W: Write> Tracer<W> {
+    /// Creates a tracer, writing the header of binary traces.
+    pub fn new(mut output: W, format: TraceFormat) -> io::Result<Tracer<W>> {
+        if format == TraceFormat::Binary {
+            output.write_all(TRACE_MAGIC)?;
+            output.write_all(&TRACE_VERSION.to_le_bytes())?;
+        }
+
+        Ok(Self {
+            output,
+            format,
+            ranges: Vec::new(),
+            limit: None,
+            records: 0,
+        })
+    }
+
+    /// Whether the record limit was reached
+    pub fn full(&self) -> bool {
+        self.limit.is_some_and(|limit| self.records >= limit)
+    }
+
+    /// Records `instruction`, executed with the registers `before`, resulting in the
+    /// current state and accesses of `machine`.
+    pub fn record(
+        &mut self,
+        before: &Registers,
+        instruction: &Instruction,
+        machine: &Machine,
+    ) -> io::Result<()> {
+        let linear = address(before.cs, before.ip);
+        let selected =
+            self.ranges.is_empty() || self.ranges.iter().any(|range| range.contains(&linear));
+        if !selected || self.full() {
+            return Ok(());
+        }
+
+        let after = &machine.registers;
+        let changed = register_values(before)
+            .into_iter()
+            .zip(register_values(after))
+            .map(|(before, after)| (before != after).then_some(after))
+            .collect::<Vec<_>>();
+
+        match self.format {
+            TraceFormat::Text => {
+                let mut line = format!(
+                    "{:04X}:{:04X}  {:<14}  {:<28}",
+                    before.cs,
+                    before.ip,
+                    instruction
+                        .bytes
+                        .iter()
+                        .map(|byte| format!("{:02X}", byte))
+                        .collect::<String>(),
+                    format_instruction(instruction, &BTreeSet::new())
+                );
+
+                for (name, value) in REGISTER_NAMES.iter().zip(&changed) {
+                    if let Some(value) = value {
+                        line.push_str(&format!(" {}={:04X}", name, value));
+                    }
+                }
+
+                for (flag, name) in FLAG_NAMES {
+                    match (before.flags & flag != 0, after.flags & flag != 0) {
+                        (false, true) => line.push_str(&format!(" +{}", name)),
+                        (true, false) => line.push_str(&format!(" -{}", name)),
+                        _ => {}
+                    }
+                }
+
+                for access in machine.accesses() {
+                    line.push_str(&format!(" {}", format_access(access)));
+                }
+
+                writeln!(self.output, "{}", line.trim_end())?;
+            }
+            TraceFormat::Binary => {
+                let mut record = Vec::new();
+                record.extend(before.cs.to_le_bytes());
+                record.extend(before.ip.to_le_bytes());
+                record.push(instruction.bytes.len() as u8);
+                record.extend(&instruction.bytes);
+
+                let mut mask = 0u16;
+                let mut values = Vec::new();
+                for (bit, value) in changed.iter().enumerate() {
+                    if let Some(value) = value {
+                        mask |= 1 << bit;
+                        values.extend(value.to_le_bytes());
+                    }
+                }
+                if before.flags != after.flags {
+                    mask |= 1 << REGISTER_NAMES.len();
+                    values.extend(after.flags.to_le_bytes());
+                }
+                record.extend(mask.to_le_bytes());
+                record.extend(values);
+
+                record.push(machine.accesses().len() as u8);
+                for access in machine.accesses() {
+                    let write = (access.kind == AccessKind::Write) as u8;
+                    let word = ((access.size == 2) as u8) << 1;
+                    record.push(write | word);
+                    record.extend((access.address as u32).to_le_bytes());
+                    record.extend(access.value.to_le_bytes());
+                }
+
+                self.output.write_all(&record)?;
+            }
+        }
+
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Flushes and returns the output.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.output.flush()?;
+        Ok(self.output)
+    }
+}
+
+/// Linear address range of a label for filtering: the scope of that name, or from the
+/// symbol to the next symbol in its segment (or the end of the segment). Local labels are
+/// named as the maps print them (`start.loop`), or bare (`.loop`) for the first one.
+pub fn label_range(debug: &DebugInfo, name: &str) -> Option<Range<usize>> {
+    let (name, parent) = match name
+        .split_once('.')
+        .filter(|(parent, _)| !parent.is_empty())
+    {
+        Some((parent, local)) => {
+            let parent = debug
+                .scopes
+                .iter()
+                .position(|scope| scope.name == parent && scope.parent.is_none())?;
+            (format!(".{}", local), Some(parent))
+        }
+        None => (name.to_string(), None),
+    };
+
+    if let Some(scope) = debug
+        .scopes
+        .iter()
+        .find(|scope| scope.name == name && (parent.is_none() || scope.parent == parent))
+    {
+        return Some(address(scope.segment, scope.start)..address(scope.segment, scope.end));
+    }
+
+    let symbol = debug
+        .symbols
+        .iter()
+        .find(|symbol| symbol.name == name && (parent.is_none() || symbol.scope == parent))?;
+    let end = debug
+        .symbols
+        .iter()
+        .filter(|other| other.segment == symbol.segment && other.offset > symbol.offset)
+        .map(|other| address(other.segment, other.offset))
+        .min()
+        .unwrap_or(address(symbol.segment, 0) + 0x10000);
+
+    Some(address(symbol.segment, symbol.offset)..end)
+}
+
+fn register_values(registers: &Registers) -> [u16; 12] {
+    [
+        registers.ax,
+        registers.bx,
+        registers.cx,
+        registers.dx,
+        registers.sp,
+        registers.bp,
+        registers.si,
+        registers.di,
+        registers.cs,
+        registers.ds,
+        registers.ss,
+        registers.es,
+    ]
+}
+
+/// `R:ADDRESS=VALUE` or `W:ADDRESS=VALUE`, with two digits for bytes and four for words
+fn format_access(access: &Access) -> String {
+    let kind = match access.kind {
+        AccessKind::Read => 'R',
+        AccessKind::Write => 'W',
+    };
+
+    match access.size {
+        1 => format!("{}:{:05X}={:02X}", kind, access.address, access.value),
+        _ => format!("{}:{:05X}={:04X}", kind, access.address, access.value),
+    }
+}
@@ -0,0 +1,114 @@
+/// Runs `code` at 0000:0100 until `hlt`, recording every instruction.
+#[cfg(test)]
+fn trace(
+    code: &[u8],
+    tracer: &mut crate::trace::Tracer<Vec<u8>>,
+) -> Result<(), crate::machine::ExecutionError> {
+    let mut machine = crate::machine::test_machine(code);
+
+    while !machine.halted {
+        let before = machine.registers.clone();
+        let instruction = machine.fetch()?;
+        machine.step()?;
+        tracer.record(&before, &instruction, &machine).unwrap();
+    }
+
+    Ok(())
+}
+
+/// mov sp, 200h / call 108h / hlt / nop / xor ax, ax / ret
+#[cfg(test)]
+const CALL: [u8; 11] = [
+    0xbc, 0x00, 0x02, 0xe8, 0x02, 0x00, 0xf4, 0x90, 0x31, 0xc0, 0xc3,
+];
+
+#[test]
+fn trace_text() {
+    use crate::trace::{TraceFormat, Tracer};
+
+    let mut tracer = Tracer::new(Vec::new(), TraceFormat::Text).unwrap();
+    trace(&CALL, &mut tracer).unwrap();
+    assert_eq!(tracer.records, 5);
+
+    let text = String::from_utf8(tracer.finish().unwrap()).unwrap();
+    assert_eq!(
+        text.lines().collect::<Vec<_>>(),
+        [
+            "0000:0100  BC0002          mov sp, 0x200                SP=0200",
+            "0000:0103  E80200          call 0x108                   SP=01FE W:001FE=0106",
+            "0000:0108  31C0            xor ax, ax                   +ZF +PF",
+            "0000:010A  C3              ret                          SP=0200 R:001FE=0106",
+            "0000:0106  F4              hlt",
+        ]
+    );
+}
+
+#[test]
+fn trace_binary() {
+    use crate::trace::{TraceFormat, Tracer};
+
+    let mut tracer = Tracer::new(Vec::new(), TraceFormat::Binary).unwrap();
+    tracer.limit = Some(2);
+    trace(&CALL, &mut tracer).unwrap();
+    assert_eq!(tracer.records, 2);
+
+    assert_eq!(
+        tracer.finish().unwrap(),
+        [
+            b'A', b'T', b'R', b'C', 1, 0, // header
+            0, 0, 0, 1, 3, 0xbc, 0, 2, 0x10, 0, 0, 2, 0, // mov sp, 200h
+            0, 0, 3, 1, 3, 0xe8, 2, 0, 0x10, 0, 0xfe, 1, 1, 3, 0xfe, 1, 0, 0, 6, 1, // call
+        ]
+    );
+}
+
+#[test]
+fn trace_filter() {
+    use crate::trace::{label_range, TraceFormat, Tracer};
+    use asmrs_assembler::debug::{DebugInfo, DebugSymbol};
+
+    let mut debug = DebugInfo::new();
+    for (name, offset) in [("start", 0x100), ("function", 0x108), ("end", 0x10b)] {
+        debug.push_symbol(DebugSymbol {
+            name: name.to_string(),
+            segment: 0,
+            offset,
+            scope: None,
+        });
+    }
+    assert_eq!(label_range(&debug, "function"), Some(0x108..0x10b));
+    assert_eq!(label_range(&debug, "end"), Some(0x10b..0x10000));
+    assert_eq!(label_range(&debug, "missing"), None);
+
+    let mut tracer = Tracer::new(Vec::new(), TraceFormat::Text).unwrap();
+    tracer.ranges = vec![label_range(&debug, "function").unwrap()];
+    trace(&CALL, &mut tracer).unwrap();
+
+    let text = String::from_utf8(tracer.finish().unwrap()).unwrap();
+    assert_eq!(text.lines().count(), 2);
+    assert!(text.starts_with("0000:0108"));
+}
+
+#[test]
+fn trace_filter_local() {
+    use crate::trace::label_range;
+    use asmrs_assembler::{
+        assembler::{assemble, Options, Target},
+        debug::DebugInfo,
+    };
+    use asmrs_parser::lexer::{token::Cpu, tokenize};
+
+    let source = "start:\n.loop:\n    loop .loop\nnext:\n    nop\n.loop:\n    loop .loop\n";
+    let options = Options {
+        cpu: Cpu::I8086,
+        target: Target::Flat { origin: 0x100 },
+    };
+    let assembly = assemble(&tokenize(source.to_string()).unwrap(), &options).unwrap();
+    let debug = DebugInfo::from_assembly(&assembly, "main.asmrs");
+
+    assert_eq!(label_range(&debug, "start.loop"), Some(0x100..0x102));
+    assert_eq!(label_range(&debug, "next.loop"), Some(0x103..0x105));
+    assert_eq!(label_range(&debug, ".loop"), Some(0x100..0x102));
+    assert_eq!(label_range(&debug, "missing.loop"), None);
+    assert_eq!(label_range(&debug, "start.missing"), None);
+}